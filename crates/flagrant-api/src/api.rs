@@ -1,51 +1,396 @@
-use axum::{Json, extract::Path};
-use flagrant::models::{environment, identity, project};
-use flagrant_types::FeatureResponse;
+use std::{collections::BTreeMap, convert::Infallible};
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use flagrant::{
+    errors::FlagrantError,
+    evaluator::SegmentCache,
+    models::{environment, identity, project, traits},
+};
+use flagrant_types::{
+    Environment, FeatureResponse, FeatureVisibility, Project, TraitValue,
+    payload::{EvaluationPayload, IdentityTraitPayload},
+};
+use futures_util::{Stream, stream};
+use serde_json::Value;
+use sqlx::{SqliteConnection, SqlitePool};
+use tokio::sync::broadcast::{Receiver, error::RecvError};
 
 use crate::{
+    changes::{Change, ChangeBus},
     errors::ServiceError,
-    extractors::{DbConnection, Identity},
+    extractors::{Audience, DbConnection, Identity},
 };
 
 /// Returns feature values for a given identity.
 ///
 /// Requires the `X-Flagrant-Identity` header to identify the caller and
 /// determine which variant value to return for each active feature.
+/// Features limited to backends are left out for requests authenticated
+/// with a public client key.
+///
+/// With JWT identities configured, identity comes with a token in the
+/// `Authorization: Bearer` header instead, and the API key - if needed - in the
+/// `X-Flagrant-Key` header. Claims mapped onto traits are taken into account when
+/// evaluating segments, the persisted ones get stored along with the identity.
+///
+/// Query parameters are taken as traits of the identity (`?platform=ios&version=5.2.0`),
+/// merged over the stored ones. Whether they get stored too is up to the trait
+/// persistence policy of the project. Claims take precedence over traits of the same
+/// names.
 #[utoipa::path(
     get,
     path = "/api/v1/projects/{project}/envs/{environment}/features",
     params(
         ("project" = String, Path, description = "Project name"),
         ("environment" = String, Path, description = "Environment name"),
-        ("X-Flagrant-Identity" = String, Header, description = "Caller identity used for variant assignment")
+        ("X-Flagrant-Identity" = Option<String>, Header, description = "Caller identity used for variant assignment"),
+        ("Authorization" = Option<String>, Header, description = "Bearer token holding the caller identity, if JWT identities are configured")
     ),
     responses(
         (status = 200, description = "Feature values for the identity", body = Vec<FeatureResponse>),
-        (status = 401, description = "Missing X-Flagrant-Identity header")
+        (status = 400, description = "Invalid trait name or value"),
+        (status = 401, description = "Missing identity, or identity token failed verification")
     ),
     tag = "api"
 )]
 pub async fn get_features(
     DbConnection(mut conn): DbConnection,
+    State(segments): State<SegmentCache>,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Query(request_traits): Query<BTreeMap<String, String>>,
+    identity: Identity,
+    audience: Audience,
+) -> Result<Json<Vec<FeatureResponse>>, ServiceError> {
+    let features = evaluate_with_request_traits(
+        &mut conn,
+        &segments,
+        project_name,
+        env_name,
+        identity,
+        query_traits(request_traits),
+        audience,
+    )
+    .await?;
+
+    Ok(Json(features))
+}
+
+/// Returns feature values for a given identity, same as the `GET` request does, with
+/// traits of the identity sent in the request body instead of the query string. String
+/// values get their type inferred, `null` unsets a trait for the request.
+#[utoipa::path(
+    post,
+    path = "/api/v1/projects/{project}/envs/{environment}/features",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("X-Flagrant-Identity" = Option<String>, Header, description = "Caller identity used for variant assignment"),
+        ("Authorization" = Option<String>, Header, description = "Bearer token holding the caller identity, if JWT identities are configured")
+    ),
+    request_body = EvaluationPayload,
+    responses(
+        (status = 200, description = "Feature values for the identity", body = Vec<FeatureResponse>),
+        (status = 400, description = "Invalid trait name or value"),
+        (status = 401, description = "Missing identity, or identity token failed verification")
+    ),
+    tag = "api"
+)]
+pub async fn evaluate_features(
+    DbConnection(mut conn): DbConnection,
+    State(segments): State<SegmentCache>,
     Path((project_name, env_name)): Path<(String, String)>,
-    Identity(identity): Identity,
+    identity: Identity,
+    audience: Audience,
+    Json(payload): Json<EvaluationPayload>,
 ) -> Result<Json<Vec<FeatureResponse>>, ServiceError> {
+    let features = evaluate_with_request_traits(
+        &mut conn,
+        &segments,
+        project_name,
+        env_name,
+        identity,
+        json_traits(payload.traits)?,
+        audience,
+    )
+    .await?;
+
+    Ok(Json(features))
+}
+
+/// Streams feature values for a given identity as Server-Sent Events.
+///
+/// Identity and its traits are resolved the same way as for the one-off `GET` request,
+/// traits sent in the query string stay in effect for the whole stream. The first
+/// `features` event carries values as of subscription time, every following one is sent
+/// when a change in the project (features, variant weights, segments, overrides...) has
+/// made any of the values differ from the last sent ones.
+#[utoipa::path(
+    get,
+    path = "/api/v1/projects/{project}/envs/{environment}/features/stream",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("X-Flagrant-Identity" = Option<String>, Header, description = "Caller identity used for variant assignment"),
+        ("Authorization" = Option<String>, Header, description = "Bearer token holding the caller identity, if JWT identities are configured")
+    ),
+    responses(
+        (status = 200, description = "Stream of `features` events, each holding feature values for the identity", body = Vec<FeatureResponse>, content_type = "text/event-stream"),
+        (status = 401, description = "Missing identity, or identity token failed verification")
+    ),
+    tag = "api"
+)]
+pub async fn stream_features(
+    State(pool): State<SqlitePool>,
+    State(segments): State<SegmentCache>,
+    State(changes): State<ChangeBus>,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Query(request_traits): Query<BTreeMap<String, String>>,
+    identity: Identity,
+    audience: Audience,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ServiceError> {
+    // Subscribed before the first evaluation, so that no change gets missed in between.
+    let changes = changes.subscribe();
+
+    let mut conn = pool.acquire().await?;
     let project = project::get_by_name(&mut conn, project_name).await?;
     let env = environment::get_by_name(&mut conn, &project, env_name).await?;
-    let identity = identity::get_or_create_by_value(&mut conn, &env, identity).await?;
-    let variants = identity::get_identity_variants(&mut conn, &env, &identity)
-        .await?
+    let Identity {
+        value,
+        transient_traits,
+        persisted_traits,
+    } = with_request_traits(&mut conn, &project, identity, query_traits(request_traits)).await?;
+
+    persist_traits(&mut conn, &env, &value, persisted_traits).await?;
+    let features = evaluate(
+        &mut conn,
+        &segments,
+        &env,
+        value.clone(),
+        &transient_traits,
+        audience,
+    )
+    .await?;
+
+    // Connection goes back to the pool, stream may stay open for long.
+    drop(conn);
+
+    let subscription = Subscription {
+        pool,
+        segments,
+        project: project.name,
+        env,
+        value,
+        transient_traits,
+        audience,
+        changes,
+        pending: Some(features),
+        last: None,
+    };
+    Ok(Sse::new(stream::unfold(subscription, Subscription::next)).keep_alive(KeepAlive::default()))
+}
+
+/// State of a single feature stream.
+struct Subscription {
+    pool: SqlitePool,
+    segments: SegmentCache,
+    project: String,
+    env: Environment,
+    value: String,
+    transient_traits: Vec<IdentityTraitPayload>,
+    audience: Audience,
+    changes: Receiver<Change>,
+    /// Feature values waiting to be sent.
+    pending: Option<Vec<FeatureResponse>>,
+    /// Feature values sent last.
+    last: Option<Vec<FeatureResponse>>,
+}
+
+impl Subscription {
+    /// Waits for feature values to change and returns an event with the new ones.
+    /// Returns `None`, which closes the stream, if values can't be evaluated anymore.
+    async fn next(mut self) -> Option<(Result<Event, Infallible>, Self)> {
+        loop {
+            if let Some(features) = self.pending.take() {
+                let event = Event::default()
+                    .event("features")
+                    .json_data(&features)
+                    .inspect_err(
+                        |error| tracing::error!(error = ?error, "Could not serialize features"),
+                    )
+                    .ok()?;
+                self.last = Some(features);
+                return Some((Ok(event), self));
+            }
+            match self.changes.recv().await {
+                Ok(change) if !change.affects(&self.project) => continue,
+                // Lagging behind means some changes have been missed, each one
+                // might have been relevant.
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return None,
+            }
+            match self.evaluate().await {
+                Ok(features) if self.last.as_ref() != Some(&features) => {
+                    self.pending = Some(features)
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    tracing::warn!(error = ?error, "Could not re-evaluate streamed features");
+                    return None;
+                }
+            }
+        }
+    }
+
+    async fn evaluate(&self) -> anyhow::Result<Vec<FeatureResponse>> {
+        let mut conn = self.pool.acquire().await?;
+        evaluate(
+            &mut conn,
+            &self.segments,
+            &self.env,
+            self.value.clone(),
+            &self.transient_traits,
+            self.audience,
+        )
+        .await
+    }
+}
+
+/// Evaluates feature values for the `identity`, along with traits sent with the request.
+async fn evaluate_with_request_traits(
+    conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    project_name: String,
+    env_name: String,
+    identity: Identity,
+    request_traits: Vec<IdentityTraitPayload>,
+    audience: Audience,
+) -> anyhow::Result<Vec<FeatureResponse>> {
+    let project = project::get_by_name(conn, project_name).await?;
+    let env = environment::get_by_name(conn, &project, env_name).await?;
+    let Identity {
+        value,
+        transient_traits,
+        persisted_traits,
+    } = with_request_traits(conn, &project, identity, request_traits).await?;
+
+    persist_traits(conn, &env, &value, persisted_traits).await?;
+    evaluate(conn, segments, &env, value, &transient_traits, audience).await
+}
+
+/// Adds traits sent along with a request to the ones of `identity`, split into persisted
+/// and transient ones by the trait persistence policy of the `project`. Traits taken from
+/// token claims are trusted over the ones sent along, which get dropped on name clashes.
+async fn with_request_traits(
+    conn: &mut SqliteConnection,
+    project: &Project,
+    mut identity: Identity,
+    request_traits: Vec<IdentityTraitPayload>,
+) -> anyhow::Result<Identity> {
+    let request_traits = request_traits
+        .into_iter()
+        .filter(|t| {
+            !identity
+                .transient_traits
+                .iter()
+                .chain(&identity.persisted_traits)
+                .any(|c| c.name == t.name)
+        })
+        .collect();
+
+    let (persisted, transient) =
+        traits::split_by_persistence(conn, project, request_traits).await?;
+
+    identity.persisted_traits.extend(persisted);
+    identity.transient_traits.extend(transient);
+    Ok(identity)
+}
+
+/// Turns traits of a query string into trait payloads, inferring their types.
+fn query_traits(traits: BTreeMap<String, String>) -> Vec<IdentityTraitPayload> {
+    traits
+        .into_iter()
+        .map(|(name, value)| IdentityTraitPayload {
+            name,
+            value: Some(TraitValue::build(&value)),
+        })
+        .collect()
+}
+
+/// Turns traits of a JSON request body into trait payloads. Strings get their type
+/// inferred, same as in the query string, `null` unsets a trait.
+fn json_traits(traits: BTreeMap<String, Value>) -> anyhow::Result<Vec<IdentityTraitPayload>> {
+    traits
         .into_iter()
-        // get_identity_variants always distributes, so feature_value should always be Some.
-        // filter_map drops any entries where distribution unexpectedly produced None.
-        .filter_map(|v| {
-            Some(FeatureResponse {
-                feature_id: v.feature_id,
-                name: v.feature_name,
-                value: v.feature_value?,
-            })
+        .map(|(name, value)| {
+            let value = match value {
+                Value::Null => None,
+                Value::String(s) => Some(TraitValue::build(&s)),
+                Value::Bool(b) => Some(TraitValue::Bool(b)),
+                Value::Number(n) => match n.as_i64().and_then(|i| i32::try_from(i).ok()) {
+                    Some(i) => Some(TraitValue::Int(i)),
+                    None => n.as_f64().map(|f| TraitValue::Float(f as f32)),
+                },
+                Value::Array(_) | Value::Object(_) => {
+                    return Err(FlagrantError::BadRequest(
+                        "Trait values have to be strings, numbers, booleans or null",
+                    )
+                    .into());
+                }
+            };
+            Ok(IdentityTraitPayload { name, value })
+        })
+        .collect()
+}
+
+/// Stores traits along with the identity of given `value`.
+pub(crate) async fn persist_traits(
+    conn: &mut SqliteConnection,
+    env: &Environment,
+    value: &str,
+    traits: Vec<IdentityTraitPayload>,
+) -> anyhow::Result<()> {
+    if !traits.is_empty() {
+        let identity = identity::get_or_create_by_value(conn, env, value.to_owned()).await?;
+        identity::upsert_traits(conn, env, &identity, traits).await?;
+    }
+    Ok(())
+}
+
+/// Evaluates feature values for the identity of given `value`, leaving out features
+/// not meant for the `audience`.
+pub(crate) async fn evaluate(
+    conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    env: &Environment,
+    value: String,
+    transient_traits: &[IdentityTraitPayload],
+    audience: Audience,
+) -> anyhow::Result<Vec<FeatureResponse>> {
+    let identity = identity::get_or_create_by_value(conn, env, value).await?;
+    let features = identity::get_identity_variants_with_traits(
+        conn,
+        segments,
+        env,
+        &identity,
+        transient_traits,
+    )
+    .await?
+    .into_iter()
+    .filter(|v| audience == Audience::Backend || v.feature_visibility != FeatureVisibility::Backend)
+    // get_identity_variants always distributes, so feature_value should always be Some.
+    // filter_map drops any entries where distribution unexpectedly produced None.
+    .filter_map(|v| {
+        Some(FeatureResponse {
+            feature_id: v.feature_id,
+            name: v.feature_name,
+            value: v.feature_value?,
         })
-        .collect::<Vec<_>>();
+    })
+    .collect::<Vec<_>>();
 
-    Ok(Json(variants))
+    Ok(features)
 }
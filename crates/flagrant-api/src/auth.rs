@@ -0,0 +1,223 @@
+//! Authentication of requests with API keys.
+//!
+//! Requests authenticate with a key sent in the `Authorization: Bearer <key>` header.
+//! The master key (`FLAGRANT_MASTER_KEY` environment variable) grants access to
+//! everything. Keys created through the API are scoped to a project, optionally to one
+//! of its environments, and come in two kinds: client keys, valid for the public client
+//! API only, and management keys, valid for both the management and the client API.
+//!
+//! Requests without a key are rejected, unless authentication gets explicitly disabled
+//! with `FLAGRANT_AUTH_DISABLED=1`, which is meant for local setups only.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use flagrant::{
+    errors::FlagrantError,
+    models::{api_key, environment, project},
+};
+use flagrant_types::{ApiKey, ApiKeyKind};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    errors::ServiceError,
+    extractors::{Audience, BearerKey},
+};
+
+/// Name of environment variable holding the master key.
+const MASTER_KEY_VAR: &str = "FLAGRANT_MASTER_KEY";
+
+/// Name of environment variable opting out of authentication.
+const AUTH_DISABLED_VAR: &str = "FLAGRANT_AUTH_DISABLED";
+
+/// Path parameters which may carry an environment name or id, depending on a route.
+const ENVIRONMENT_PARAMS: [&str; 3] = ["environment", "env_id", "environment_id"];
+
+static MASTER_KEY: OnceLock<Option<String>> = OnceLock::new();
+static AUTH_DISABLED: OnceLock<bool> = OnceLock::new();
+
+/// Outcome of a successful authentication.
+enum Access {
+    /// Unrestricted access, either with the master key or with authentication
+    /// disabled.
+    Unrestricted,
+    /// Access restricted to the scope of given API key.
+    Scoped(ApiKey),
+}
+
+/// Returns the master key, if one is configured.
+pub fn master_key() -> Option<&'static str> {
+    MASTER_KEY
+        .get_or_init(|| std::env::var(MASTER_KEY_VAR).ok().filter(|k| !k.is_empty()))
+        .as_deref()
+}
+
+/// Returns `true` if authentication has been explicitly disabled. Requests without
+/// a key get unrestricted access then, requests with one are still authenticated.
+pub fn auth_disabled() -> bool {
+    *AUTH_DISABLED.get_or_init(|| {
+        std::env::var(AUTH_DISABLED_VAR).is_ok_and(|v| matches!(v.as_str(), "1" | "true"))
+    })
+}
+
+/// Guards routes not scoped to any project (listing and creating projects), which
+/// require the master key.
+pub async fn master(
+    State(pool): State<SqlitePool>,
+    BearerKey(key): BearerKey,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServiceError> {
+    let mut conn = pool.acquire().await?;
+    match authenticate(&mut conn, key.as_deref()).await? {
+        Access::Unrestricted => Ok(next.run(request).await),
+        Access::Scoped(_) => Err(FlagrantError::Forbidden("Master key required").into()),
+    }
+}
+
+/// Guards the management API, accessible with a management key of the project (and
+/// environment, if the key is limited to one) given in the request path.
+pub async fn management(
+    State(pool): State<SqlitePool>,
+    BearerKey(key): BearerKey,
+    Path(params): Path<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServiceError> {
+    let mut conn = pool.acquire().await?;
+    if let Access::Scoped(api_key) = authenticate(&mut conn, key.as_deref()).await? {
+        if api_key.kind != ApiKeyKind::Management {
+            return Err(FlagrantError::Forbidden("Management key required").into());
+        }
+        check_scope(&mut conn, &api_key, &params).await?;
+    }
+    Ok(next.run(request).await)
+}
+
+/// Guards the public client API, accessible with any kind of key of the project (and
+/// environment, if the key is limited to one) given in the request path.
+///
+/// Tells the handlers which [`Audience`] request comes from - client keys are meant
+/// to be public, management (and master) keys are considered to be used by backends.
+pub async fn client(
+    State(pool): State<SqlitePool>,
+    BearerKey(key): BearerKey,
+    Path(params): Path<HashMap<String, String>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ServiceError> {
+    let mut conn = pool.acquire().await?;
+    let audience = client_audience(&mut conn, key.as_deref(), &params).await?;
+    request.extensions_mut().insert(audience);
+    Ok(next.run(request).await)
+}
+
+/// Authenticates `key` for the client API of project (and environment) given in
+/// `params`, returning the [`Audience`] it is meant for.
+pub async fn client_audience(
+    conn: &mut SqliteConnection,
+    key: Option<&str>,
+    params: &HashMap<String, String>,
+) -> anyhow::Result<Audience> {
+    match authenticate(conn, key).await? {
+        Access::Scoped(api_key) => {
+            check_scope(conn, &api_key, params).await?;
+            match api_key.kind {
+                ApiKeyKind::Client => Ok(Audience::Public),
+                ApiKeyKind::Management => Ok(Audience::Backend),
+            }
+        }
+        Access::Unrestricted => Ok(Audience::Backend),
+    }
+}
+
+async fn authenticate(conn: &mut SqliteConnection, key: Option<&str>) -> anyhow::Result<Access> {
+    let master = master_key();
+    match key {
+        Some(key) if master.is_some_and(|master| constant_time_eq(master, key)) => {
+            Ok(Access::Unrestricted)
+        }
+        Some(key) => match api_key::authenticate(conn, key).await? {
+            Some(api_key) => Ok(Access::Scoped(api_key)),
+            None => Err(FlagrantError::Unauthorized("Invalid API key").into()),
+        },
+        None if auth_disabled() => Ok(Access::Unrestricted),
+        None => Err(FlagrantError::Unauthorized("Missing API key").into()),
+    }
+}
+
+/// Checks that project and environment given in the request path fall within the scope
+/// of `api_key`. Keys limited to an environment are valid only for routes of that
+/// environment, not for project-wide ones.
+async fn check_scope(
+    conn: &mut SqliteConnection,
+    api_key: &ApiKey,
+    params: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let project = project::get_by_id(conn, api_key.project_id).await?;
+    if params.get("project") != Some(&project.name) {
+        return Err(FlagrantError::Forbidden("API key not valid for this project").into());
+    }
+    if let Some(environment_id) = api_key.environment_id {
+        let environment = environment::get_by_id(conn, environment_id).await?;
+        let matches = ENVIRONMENT_PARAMS
+            .iter()
+            .find_map(|param| params.get(*param))
+            .is_some_and(|env| *env == environment.name || *env == environment.id.to_string());
+
+        if !matches {
+            return Err(FlagrantError::Forbidden("API key not valid for this environment").into());
+        }
+    }
+    Ok(())
+}
+
+/// Compares two keys in time independent of the position of first mismatch.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        http::StatusCode,
+        middleware::from_fn_with_state,
+        routing::{get, post},
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_without_key_are_rejected() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let router = Router::new()
+            .route("/projects/", post(|| async { StatusCode::CREATED }))
+            .route_layer(from_fn_with_state(pool.clone(), master))
+            .merge(
+                Router::new()
+                    .route("/projects/:project/envs", get(|| async { StatusCode::OK }))
+                    .route_layer(from_fn_with_state(pool, management)),
+            );
+
+        for (method, uri) in [("POST", "/projects/"), ("GET", "/projects/secret/envs")] {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Bus of change notifications, telling the streaming client API when feature values
+//! it has served might have become stale.
+//!
+//! Notifications are intentionally coarse - they tell which project has changed, not
+//! what exactly. Subscribers re-evaluate features on each notification and decide on
+//! their own whether anything has changed for them.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use flagrant::evaluator::SegmentCache;
+use tokio::sync::broadcast;
+
+/// How many notifications may pile up for a subscriber before it starts to lag behind.
+/// Lagging subscribers lose notifications, which is fine as long as they re-evaluate.
+const CHANGES_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Something has changed within the project of given name.
+    Project(String),
+    /// Something has changed within unknown projects, possibly all of them.
+    Any,
+}
+
+impl Change {
+    /// Returns true if change might have affected project of given name.
+    pub fn affects(&self, project: &str) -> bool {
+        match self {
+            Change::Project(name) => name == project,
+            Change::Any => true,
+        }
+    }
+}
+
+/// Handle to the bus - clones of a handle publish to and subscribe to the same bus.
+#[derive(Clone)]
+pub struct ChangeBus(broadcast::Sender<Change>);
+
+impl Default for ChangeBus {
+    fn default() -> Self {
+        ChangeBus(broadcast::channel(CHANGES_CAPACITY).0)
+    }
+}
+
+impl ChangeBus {
+    /// Notifies all the subscribers about a change.
+    pub fn publish(&self, change: Change) {
+        // Sending fails only when there is no one listening, which is fine.
+        let _ = self.0.send(change);
+    }
+
+    /// Subscribes to change notifications published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Change> {
+        self.0.subscribe()
+    }
+}
+
+/// Publishes a change of the project given in the request path, once the management
+/// API has successfully handled a request which might have changed anything. Read-only
+/// requests are not published. Segments compiled for evaluation get dropped before,
+/// so that subscribers re-evaluate against the change.
+pub async fn notify(
+    State(segments): State<SegmentCache>,
+    State(changes): State<ChangeBus>,
+    Path(params): Path<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let read_only = request.method().is_safe();
+    let response = next.run(request).await;
+
+    if !read_only && response.status().is_success() {
+        segments.invalidate();
+        if let Some(project) = params.get("project") {
+            changes.publish(Change::Project(project.to_owned()));
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        http::{Method, StatusCode},
+        middleware::from_fn_with_state,
+        routing::get,
+    };
+    use tokio::sync::broadcast::error::TryRecvError;
+    use tower::ServiceExt;
+
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::state::AppState;
+
+    /// Returns changes received by `changes` so far.
+    fn received(changes: &mut broadcast::Receiver<Change>) -> Vec<Change> {
+        let mut received = Vec::new();
+        while let Ok(change) = changes.try_recv() {
+            received.push(change);
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn subscribers_get_published_changes() {
+        let bus = ChangeBus::default();
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        bus.publish(Change::Project("bus".to_owned()));
+
+        let expected = vec![Change::Project("bus".to_owned())];
+        assert_eq!(received(&mut first), expected);
+        assert_eq!(received(&mut second), expected);
+    }
+
+    #[tokio::test]
+    async fn lagging_subscribers_learn_about_missed_changes() {
+        let bus = ChangeBus::default();
+        let mut changes = bus.subscribe();
+        for _ in 0..=CHANGES_CAPACITY {
+            bus.publish(Change::Any);
+        }
+        assert!(matches!(changes.try_recv(), Err(TryRecvError::Lagged(1))));
+    }
+
+    #[tokio::test]
+    async fn successful_writes_get_published() {
+        let state = AppState::new(SqlitePool::connect_lazy("sqlite::memory:").unwrap());
+        let mut changes = state.changes.subscribe();
+        let router = Router::new()
+            .route(
+                "/projects/:project/features",
+                get(|| async { StatusCode::OK })
+                    .post(|| async { StatusCode::CREATED })
+                    .put(|| async { StatusCode::BAD_REQUEST }),
+            )
+            .route_layer(from_fn_with_state(state, notify));
+
+        for method in [Method::GET, Method::PUT, Method::POST] {
+            let request = Request::builder()
+                .method(method)
+                .uri("/projects/notified/features")
+                .body(Body::empty())
+                .unwrap();
+            router.clone().oneshot(request).await.unwrap();
+        }
+        assert_eq!(
+            received(&mut changes),
+            vec![Change::Project("notified".to_owned())]
+        );
+    }
+}
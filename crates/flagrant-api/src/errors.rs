@@ -8,8 +8,9 @@ use flagrant::errors::FlagrantError;
 // Make our own error that wraps `anyhow::Error`.
 pub struct ServiceError(anyhow::Error);
 
-impl IntoResponse for ServiceError {
-    fn into_response(self) -> Response<Body> {
+impl ServiceError {
+    /// Logs the error and tells which status and message it should be reported with.
+    pub fn status_and_message(&self) -> (StatusCode, String) {
         match self.0.downcast_ref::<FlagrantError>() {
             Some(FlagrantError::UnexpectedFailure(error, cause)) => {
                 tracing::error!(cause = ?cause, error);
@@ -28,6 +29,14 @@ impl IntoResponse for ServiceError {
                 (StatusCode::UNAUTHORIZED, error.to_string())
             }
             Some(FlagrantError::NotFound(error)) => (StatusCode::NOT_FOUND, error.to_string()),
+            Some(FlagrantError::Unauthorized(error)) => {
+                (StatusCode::UNAUTHORIZED, error.to_string())
+            }
+            Some(FlagrantError::Forbidden(error)) => (StatusCode::FORBIDDEN, error.to_string()),
+            Some(FlagrantError::InvalidToken(error, cause)) => {
+                tracing::warn!(cause = ?cause, error);
+                (StatusCode::UNAUTHORIZED, error.to_string())
+            }
             _ => {
                 tracing::error!(error = ?self.0, "Unexpected error");
                 (
@@ -36,7 +45,12 @@ impl IntoResponse for ServiceError {
                 )
             }
         }
-        .into_response()
+    }
+}
+
+impl IntoResponse for ServiceError {
+    fn into_response(self) -> Response<Body> {
+        self.status_and_message().into_response()
     }
 }
 
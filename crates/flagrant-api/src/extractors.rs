@@ -1,16 +1,42 @@
 use axum::{
     async_trait,
     extract::{FromRef, FromRequestParts},
-    http::request::Parts,
+    http::{header::AUTHORIZATION, request::Parts},
 };
 use flagrant::errors::FlagrantError;
+use flagrant_types::payload::IdentityTraitPayload;
 use sqlx::{Sqlite, SqlitePool, pool::PoolConnection};
 
-use crate::errors::ServiceError;
+use crate::{errors::ServiceError, jwt};
 
 pub struct DbConnection(pub PoolConnection<Sqlite>);
 
-pub struct Identity(pub String);
+/// Identity of the caller of the client API, either taken from the `X-Flagrant-Identity`
+/// header or - if JWT identities are configured - from a verified token.
+pub struct Identity {
+    pub value: String,
+    /// Traits taken from token claims, meant for a single request only.
+    pub transient_traits: Vec<IdentityTraitPayload>,
+    /// Traits taken from token claims, to be stored along with the identity.
+    pub persisted_traits: Vec<IdentityTraitPayload>,
+}
+
+/// API key sent along with a request in the `X-Flagrant-Key` header, or in the
+/// `Authorization: Bearer <key>` header unless it holds a JWT identity token.
+pub struct BearerKey(pub Option<String>);
+
+/// Audience of the client API a request comes from, as told by its API key and set
+/// by the `auth::client` middleware. Public audience (browsers, mobile apps) doesn't
+/// get to see features limited to backends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Audience {
+    Public,
+    Backend,
+}
+
+/// Name of whoever applies a change, recorded along with change revisions.
+/// Optional - changes applied anonymously are recorded with no author.
+pub struct Author(pub Option<String>);
 
 #[async_trait]
 impl<S> FromRequestParts<S> for Identity
@@ -20,13 +46,97 @@ where
     type Rejection = ServiceError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(config) = jwt::config() {
+            let Some(token) = bearer(parts).filter(|t| is_jwt(t)) else {
+                return Err(FlagrantError::NoIdentity("No identity token found").into());
+            };
+            return Ok(config.verify(token)?);
+        }
         if let Some(Ok(header)) = parts.headers.get("X-Flagrant-Identity").map(|h| h.to_str()) {
-            return Ok(Identity(header.to_owned()));
+            return Ok(Identity {
+                value: header.to_owned(),
+                transient_traits: Vec::new(),
+                persisted_traits: Vec::new(),
+            });
         }
         Err(FlagrantError::NoIdentity("No identity header found").into())
     }
 }
 
+#[async_trait]
+impl<S> FromRequestParts<S> for Author
+where
+    S: Send + Sync,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let author = parts
+            .headers
+            .get("X-Flagrant-Author")
+            .and_then(|h| h.to_str().ok())
+            .filter(|h| !h.is_empty())
+            .map(str::to_owned);
+
+        Ok(Author(author))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Audience
+where
+    S: Send + Sync,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Requests which didn't go through authentication are not trusted with anything
+        // more than what a public key would give.
+        Ok(parts
+            .extensions
+            .get::<Audience>()
+            .copied()
+            .unwrap_or(Audience::Public))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for BearerKey
+where
+    S: Send + Sync,
+{
+    type Rejection = ServiceError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let key = parts
+            .headers
+            .get("X-Flagrant-Key")
+            .and_then(|h| h.to_str().ok())
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .or_else(|| bearer(parts).filter(|k| !is_jwt(k)))
+            .map(str::to_owned);
+
+        Ok(BearerKey(key))
+    }
+}
+
+/// Returns the `Authorization: Bearer` header value, if any.
+fn bearer(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// Tells JWT tokens (three dot-separated parts) apart from API keys, which have no dots.
+fn is_jwt(value: &str) -> bool {
+    value.split('.').count() == 3
+}
+
 #[async_trait]
 impl<S> FromRequestParts<S> for DbConnection
 where
@@ -0,0 +1,91 @@
+use axum::{Json, extract::Path};
+use flagrant::models::{api_key, environment, project};
+use flagrant_types::{
+    ApiKey,
+    payload::{ApiKeyCreatedResponse, NewApiKeyPayload},
+};
+
+use crate::{errors::ServiceError, extractors::DbConnection};
+
+/// Lists API keys of a project.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/api-keys",
+    params(
+        ("project" = String, Path, description = "Project name")
+    ),
+    responses(
+        (status = 200, description = "List of API keys", body = Vec<ApiKey>)
+    ),
+    tag = "api-keys"
+)]
+pub async fn list(
+    DbConnection(mut conn): DbConnection,
+    Path(project_name): Path<String>,
+) -> Result<Json<Vec<ApiKey>>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let keys = api_key::list(&mut conn, &project).await?;
+
+    Ok(Json(keys))
+}
+
+/// Creates a new API key, scoped to a project and optionally to one of its environments.
+///
+/// The key itself is returned only once, in response to this request.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/api-keys",
+    params(
+        ("project" = String, Path, description = "Project name")
+    ),
+    request_body = NewApiKeyPayload,
+    responses(
+        (status = 200, description = "Created API key along with the key itself", body = ApiKeyCreatedResponse)
+    ),
+    tag = "api-keys"
+)]
+pub async fn create(
+    DbConnection(mut conn): DbConnection,
+    Path(project_name): Path<String>,
+    Json(payload): Json<NewApiKeyPayload>,
+) -> Result<Json<ApiKeyCreatedResponse>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let environment = match payload.environment_id {
+        Some(environment_id) => Some(environment::get_by_id(&mut conn, environment_id).await?),
+        None => None,
+    };
+    let (api_key, key) = api_key::create(
+        &mut conn,
+        &project,
+        environment.as_ref(),
+        payload.name,
+        payload.kind,
+    )
+    .await?;
+
+    Ok(Json(ApiKeyCreatedResponse { api_key, key }))
+}
+
+/// Revokes an API key.
+#[utoipa::path(
+    delete,
+    path = "/projects/{project}/api-keys/{api_key_id}",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("api_key_id" = i32, Path, description = "API key ID")
+    ),
+    responses(
+        (status = 200, description = "API key revoked"),
+        (status = 404, description = "API key not found")
+    ),
+    tag = "api-keys"
+)]
+pub async fn delete(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, api_key_id)): Path<(String, i32)>,
+) -> Result<Json<()>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    api_key::delete(&mut conn, &project, api_key_id).await?;
+
+    Ok(Json(()))
+}
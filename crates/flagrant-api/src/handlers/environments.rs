@@ -2,12 +2,18 @@ use axum::{
     Json,
     extract::{Path, Query},
 };
-use flagrant::models::{environment, project};
-use flagrant_types::{Environment, payload::NewEnvironmentPayload};
+use flagrant::models::{environment, project, revision};
+use flagrant_types::{
+    Environment, Revision, RevisionEntity,
+    payload::{FreezePayload, NewEnvironmentPayload},
+};
 use serde::Deserialize;
 use utoipa::IntoParams;
 
-use crate::{errors::ServiceError, extractors::DbConnection};
+use crate::{
+    errors::ServiceError,
+    extractors::{Author, DbConnection},
+};
 
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct EnvQueryParams {
@@ -132,3 +138,84 @@ pub async fn list(
 
     Ok(Json(envs))
 }
+
+/// Engages the kill switch of an environment: all of its features - or the ones tagged with
+/// given tag only - serve their control value until the environment thaws.
+#[utoipa::path(
+    put,
+    path = "/projects/{project}/envs/{environment}/freeze",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name")
+    ),
+    request_body = FreezePayload,
+    responses(
+        (status = 200, description = "Frozen environment", body = Environment),
+        (status = 400, description = "No feature tagged with given tag")
+    ),
+    tag = "environments"
+)]
+pub async fn freeze(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Author(author): Author,
+    Json(payload): Json<FreezePayload>,
+) -> Result<Json<Environment>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let frozen = environment::freeze(&mut conn, &env, payload.tag, author.as_deref()).await?;
+
+    Ok(Json(frozen))
+}
+
+/// Releases the kill switch of an environment, letting its features serve their variants
+/// again.
+#[utoipa::path(
+    delete,
+    path = "/projects/{project}/envs/{environment}/freeze",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name")
+    ),
+    responses(
+        (status = 200, description = "Thawed environment", body = Environment)
+    ),
+    tag = "environments"
+)]
+pub async fn thaw(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Author(author): Author,
+) -> Result<Json<Environment>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let thawed = environment::thaw(&mut conn, &env, author.as_deref()).await?;
+
+    Ok(Json(thawed))
+}
+
+/// Lists recorded changes of an environment's kill switch, newest first.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/history",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name")
+    ),
+    responses(
+        (status = 200, description = "Environment revisions", body = Vec<Revision>)
+    ),
+    tag = "environments"
+)]
+pub async fn history(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name)): Path<(String, String)>,
+) -> Result<Json<Vec<Revision>>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let revisions =
+        revision::list_for_entity(&mut conn, RevisionEntity::Environment, env.id, Some(env.id))
+            .await?;
+
+    Ok(Json(revisions))
+}
@@ -2,15 +2,18 @@ use axum::{
     Json,
     extract::{Path, Query},
 };
-use flagrant::models::{environment, feature, identity, project, segment};
+use flagrant::models::{environment, feature, identity, project, revision, segment};
 use flagrant_types::{
-    Feature, FeatureOverride,
+    Feature, FeatureOverride, Revision, RevisionEntity,
     payload::{FeaturePatch, NewFeaturePayload},
 };
 use serde::Deserialize;
 use utoipa::IntoParams;
 
-use crate::{errors::ServiceError, extractors::DbConnection};
+use crate::{
+    errors::ServiceError,
+    extractors::{Author, DbConnection},
+};
 
 /// Query parameters for feature listing.
 #[derive(Debug, Deserialize, IntoParams)]
@@ -59,7 +62,8 @@ impl<'de> Deserialize<'de> for FeatureId {
 ///
 /// The feature is created as inactive by default, with the enabled state
 /// determined by the payload. The feature value becomes the environment's
-/// control variant.
+/// control variant. Features limited to backends are served by the client API
+/// to requests authenticated with management keys only.
 #[utoipa::path(
     post,
     path = "/projects/{project}/envs/{environment}/features",
@@ -80,13 +84,14 @@ pub async fn create(
 ) -> Result<Json<Feature>, ServiceError> {
     let project = project::get_by_name(&mut conn, project_name).await?;
     let env = environment::get_by_name(&mut conn, &project, env_name).await?;
-    let feature = feature::create(
+    let feature = feature::create_with_visibility(
         &mut conn,
         &env,
         payload.name,
         payload.description,
         payload.value,
         payload.is_enabled,
+        payload.visibility,
     )
     .await?;
 
@@ -252,6 +257,7 @@ pub async fn delete(
 )]
 pub async fn patch(
     DbConnection(mut conn): DbConnection,
+    Author(author): Author,
     Path((project_name, env_name, feature_id)): Path<(String, String, i32)>,
     Json(patch): Json<FeaturePatch>,
 ) -> Result<Json<Feature>, ServiceError> {
@@ -259,10 +265,69 @@ pub async fn patch(
     let env = environment::get_by_name(&mut conn, &project, env_name).await?;
     let feature = feature::get_by_id(&mut conn, &env, feature_id).await?;
 
-    let updated = feature::patch(&mut conn, &env, &feature, patch).await?;
+    let updated = feature::patch(&mut conn, &env, &feature, patch, author.as_deref()).await?;
     Ok(Json(updated))
 }
 
+/// Returns history of changes applied to a feature in given environment, newest first.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/features/{feature_id}/history",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("feature_id" = i32, Path, description = "Feature ID")
+    ),
+    responses(
+        (status = 200, description = "Feature revisions", body = Vec<Revision>)
+    ),
+    tag = "features"
+)]
+pub async fn history(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, feature_id)): Path<(String, String, i32)>,
+) -> Result<Json<Vec<Revision>>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let feature = feature::get_by_id(&mut conn, &env, feature_id).await?;
+    let revisions =
+        revision::list_for_entity(&mut conn, RevisionEntity::Feature, feature.id, Some(env.id))
+            .await?;
+
+    Ok(Json(revisions))
+}
+
+/// Reverts a feature to the state it had right before given revision was applied.
+///
+/// Restoration is applied within a single transaction and recorded as a new revision.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/features/{feature_id}/history/{revision_id}/revert",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("feature_id" = i32, Path, description = "Feature ID"),
+        ("revision_id" = i32, Path, description = "Revision ID")
+    ),
+    responses(
+        (status = 200, description = "Reverted feature with restored state", body = Feature)
+    ),
+    tag = "features"
+)]
+pub async fn revert(
+    DbConnection(mut conn): DbConnection,
+    Author(author): Author,
+    Path((project_name, env_name, feature_id, revision_id)): Path<(String, String, i32, i32)>,
+) -> Result<Json<Feature>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let feature = feature::get_by_id(&mut conn, &env, feature_id).await?;
+
+    let reverted =
+        feature::revert(&mut conn, &env, &feature, revision_id, author.as_deref()).await?;
+    Ok(Json(reverted))
+}
+
 /// Returns explicit variant overrides (pinned identities) for a feature.
 #[utoipa::path(
     get,
@@ -1,4 +1,7 @@
-use crate::{errors::ServiceError, extractors::DbConnection};
+use crate::{
+    errors::ServiceError,
+    extractors::{Author, DbConnection},
+};
 use axum::{
     Json,
     extract::{Path, Query},
@@ -7,6 +10,7 @@ use flagrant::models::identity::TraitCondition;
 use flagrant::models::{environment, identity, project};
 use flagrant_types::{
     IdentityVariant, IdentityWithTraits,
+    explain::FeatureExplanation,
     payload::{IdentityPatch, NewIdentityPayload},
 };
 use serde::Deserialize;
@@ -190,13 +194,14 @@ pub async fn create(
 )]
 pub async fn update(
     DbConnection(mut conn): DbConnection,
+    Author(author): Author,
     Path((project_name, env_name, identity_value)): Path<(String, String, String)>,
     Json(patch): Json<IdentityPatch>,
 ) -> Result<Json<IdentityWithTraits>, ServiceError> {
     let project = project::get_by_name(&mut conn, project_name).await?;
     let env = environment::get_by_name(&mut conn, &project, env_name).await?;
     let identity = identity::get_by_value(&mut conn, &env, identity_value).await?;
-    let identity = identity::patch(&mut conn, &env, identity, patch).await?;
+    let identity = identity::patch(&mut conn, &env, identity, patch, author.as_deref()).await?;
 
     Ok(Json(identity))
 }
@@ -226,6 +231,44 @@ pub async fn get_variants(
     Ok(Json(variants))
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub(crate) struct ExplainParams {
+    /// Name of the only feature to explain
+    feature: Option<String>,
+}
+
+/// Explains, feature by feature, why an identity gets the values it does: pinned by an
+/// override, pending migration, matching a segment (along with the groups and rules of
+/// segments evaluated) or distributed organically. Doesn't change anything.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/identities/{identity}/explain",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("identity" = String, Path, description = "Identity value"),
+        ExplainParams
+    ),
+    responses(
+        (status = 200, description = "Resolution path of each feature", body = Vec<FeatureExplanation>),
+        (status = 404, description = "Identity or feature not found")
+    ),
+    tag = "identities"
+)]
+pub async fn explain(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, identity_value)): Path<(String, String, String)>,
+    Query(params): Query<ExplainParams>,
+) -> Result<Json<Vec<FeatureExplanation>>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let identity = identity::get_by_value(&mut conn, &env, identity_value).await?;
+    let explanations =
+        identity::explain(&mut conn, &env, &identity, params.feature.as_deref()).await?;
+
+    Ok(Json(explanations))
+}
+
 /// Deletes an identity and all its trait associations and variant assignments.
 #[utoipa::path(
     delete,
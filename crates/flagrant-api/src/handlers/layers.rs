@@ -0,0 +1,201 @@
+use axum::{Json, extract::Path};
+use flagrant::models::{environment, layer, project};
+use flagrant_types::{
+    Environment, Layer,
+    payload::{LayerSharePayload, NewLayerPayload},
+};
+use serde::Deserialize;
+use sqlx::SqliteConnection;
+
+use crate::{errors::ServiceError, extractors::DbConnection};
+
+/// Layer identifier - either its numeric ID or its name.
+#[derive(Debug)]
+pub(crate) enum LayerId {
+    Id(i32),
+    Name(String),
+}
+
+impl<'de> Deserialize<'de> for LayerId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        match s.parse::<i32>() {
+            Ok(id) => Ok(LayerId::Id(id)),
+            Err(_) => Ok(LayerId::Name(s)),
+        }
+    }
+}
+
+async fn resolve_layer(
+    conn: &mut SqliteConnection,
+    env: &Environment,
+    layer_id: LayerId,
+) -> anyhow::Result<Layer> {
+    match layer_id {
+        LayerId::Id(id) => layer::get_by_id(conn, env, id).await,
+        LayerId::Name(name) => layer::get_by_name(conn, env, name).await,
+    }
+}
+
+/// Lists exclusion layers of an environment, along with shares of their features.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/layers",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name")
+    ),
+    responses(
+        (status = 200, description = "List of exclusion layers", body = Vec<Layer>)
+    ),
+    tag = "layers"
+)]
+pub async fn list(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name)): Path<(String, String)>,
+) -> Result<Json<Vec<Layer>>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let layers = layer::list(&mut conn, &env).await?;
+
+    Ok(Json(layers))
+}
+
+/// Creates a new exclusion layer, with no features yet.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/layers",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name")
+    ),
+    request_body = NewLayerPayload,
+    responses(
+        (status = 200, description = "Created exclusion layer", body = Layer)
+    ),
+    tag = "layers"
+)]
+pub async fn create(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Json(payload): Json<NewLayerPayload>,
+) -> Result<Json<Layer>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let layer = layer::create(&mut conn, &env, payload.name, payload.description).await?;
+
+    Ok(Json(layer))
+}
+
+/// Fetches an exclusion layer by its ID or name.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/layers/{layer}",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("layer" = String, Path, description = "Layer ID or name")
+    ),
+    responses(
+        (status = 200, description = "Exclusion layer with shares of its features", body = Layer)
+    ),
+    tag = "layers"
+)]
+pub async fn fetch_by_id_or_name(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, layer_id)): Path<(String, String, LayerId)>,
+) -> Result<Json<Layer>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let layer = resolve_layer(&mut conn, &env, layer_id).await?;
+
+    Ok(Json(layer))
+}
+
+/// Removes an exclusion layer. Its features stay as they are, they just stop excluding
+/// each other.
+#[utoipa::path(
+    delete,
+    path = "/projects/{project}/envs/{environment}/layers/{layer}",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("layer" = String, Path, description = "Layer ID or name")
+    ),
+    responses(
+        (status = 200, description = "Exclusion layer removed")
+    ),
+    tag = "layers"
+)]
+pub async fn delete(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, layer_id)): Path<(String, String, LayerId)>,
+) -> Result<Json<()>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let layer = resolve_layer(&mut conn, &env, layer_id).await?;
+    layer::delete(&mut conn, &env, layer.id).await?;
+
+    Ok(Json(()))
+}
+
+/// Gives a feature a share of the traffic of an exclusion layer, adding the feature to
+/// the layer unless it's there already.
+#[utoipa::path(
+    put,
+    path = "/projects/{project}/envs/{environment}/layers/{layer}/features/{feature_id}",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("layer" = String, Path, description = "Layer ID or name"),
+        ("feature_id" = i32, Path, description = "Feature ID")
+    ),
+    request_body = LayerSharePayload,
+    responses(
+        (status = 200, description = "Updated exclusion layer", body = Layer),
+        (status = 400, description = "Share doesn't fit, or feature belongs to another layer")
+    ),
+    tag = "layers"
+)]
+pub async fn set_share(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, layer_id, feature_id)): Path<(String, String, LayerId, i32)>,
+    Json(payload): Json<LayerSharePayload>,
+) -> Result<Json<Layer>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let layer = resolve_layer(&mut conn, &env, layer_id).await?;
+    let layer = layer::set_share(&mut conn, &env, &layer, feature_id, payload.share).await?;
+
+    Ok(Json(layer))
+}
+
+/// Removes a feature from an exclusion layer, releasing its share.
+#[utoipa::path(
+    delete,
+    path = "/projects/{project}/envs/{environment}/layers/{layer}/features/{feature_id}",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("layer" = String, Path, description = "Layer ID or name"),
+        ("feature_id" = i32, Path, description = "Feature ID")
+    ),
+    responses(
+        (status = 200, description = "Updated exclusion layer", body = Layer)
+    ),
+    tag = "layers"
+)]
+pub async fn unset_share(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, layer_id, feature_id)): Path<(String, String, LayerId, i32)>,
+) -> Result<Json<Layer>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let layer = resolve_layer(&mut conn, &env, layer_id).await?;
+    let layer = layer::unset_share(&mut conn, &env, &layer, feature_id).await?;
+
+    Ok(Json(layer))
+}
@@ -1,8 +1,13 @@
+pub mod api_keys;
 pub mod environments;
 pub mod features;
 pub mod identities;
+pub mod layers;
 pub mod projects;
+pub mod rollouts;
+pub mod schedules;
 pub mod segments;
+pub mod snapshots;
 pub mod tags;
 pub mod traits;
 pub mod variants;
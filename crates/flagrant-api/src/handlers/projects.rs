@@ -2,7 +2,7 @@ use axum::{Json, extract::Path};
 use flagrant::models::project;
 use flagrant_types::{
     Project,
-    payload::{NewProjectPayload, ProjectCreatedResponse},
+    payload::{NewProjectPayload, ProjectCreatedResponse, ProjectPatch},
 };
 
 use crate::{errors::ServiceError, extractors::DbConnection};
@@ -45,6 +45,31 @@ pub async fn fetch(
     Ok(Json(project))
 }
 
+/// Patches a project, e.g. changes whether traits sent along with client API requests
+/// get stored with identities.
+#[utoipa::path(
+    patch,
+    path = "/projects/{project}",
+    params(
+        ("project" = String, Path, description = "Project name")
+    ),
+    request_body = ProjectPatch,
+    responses(
+        (status = 200, description = "Patched project", body = Project)
+    ),
+    tag = "projects"
+)]
+pub async fn patch(
+    DbConnection(mut conn): DbConnection,
+    Path(project_name): Path<String>,
+    Json(patch): Json<ProjectPatch>,
+) -> Result<Json<Project>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let project = project::patch(&mut conn, project, patch).await?;
+
+    Ok(Json(project))
+}
+
 /// Creates a new project with a default environment.
 #[utoipa::path(
     post,
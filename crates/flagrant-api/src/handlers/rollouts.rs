@@ -0,0 +1,170 @@
+use axum::{Json, extract::Path};
+use flagrant::models::{environment, project, rollout};
+use flagrant_types::{RolloutPlan, payload::NewRolloutPlanPayload};
+
+use crate::{
+    errors::ServiceError,
+    extractors::{Author, DbConnection},
+};
+
+/// Returns the most recent rollout plan of each feature variant.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/features/{feature_id}/rollouts",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("feature_id" = i32, Path, description = "Feature ID")
+    ),
+    responses(
+        (status = 200, description = "Rollout plans", body = Vec<RolloutPlan>)
+    ),
+    tag = "rollouts"
+)]
+pub async fn list(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, feature_id)): Path<(String, String, i32)>,
+) -> Result<Json<Vec<RolloutPlan>>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let plans = rollout::list_for_feature(&mut conn, &env, feature_id).await?;
+
+    Ok(Json(plans))
+}
+
+/// Returns the most recent rollout plan of a variant.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/variants/{variant_id}/rollout",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("variant_id" = i32, Path, description = "Variant ID")
+    ),
+    responses(
+        (status = 200, description = "Rollout plan", body = RolloutPlan),
+        (status = 404, description = "Variant has never been rolled out")
+    ),
+    tag = "rollouts"
+)]
+pub async fn fetch(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, variant_id)): Path<(String, String, i32)>,
+) -> Result<Json<RolloutPlan>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let plan = rollout::get_for_variant(&mut conn, &env, variant_id).await?;
+
+    Ok(Json(plan))
+}
+
+/// Starts a progressive rollout of a variant.
+///
+/// Variant weight is set to the start weight right away, and then gets bumped by a step
+/// by a background task every interval, until it reaches the target weight.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/variants/{variant_id}/rollout",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("variant_id" = i32, Path, description = "Variant ID")
+    ),
+    request_body = NewRolloutPlanPayload,
+    responses(
+        (status = 200, description = "Started rollout plan", body = RolloutPlan),
+        (status = 400, description = "Invalid plan or variant already being rolled out")
+    ),
+    tag = "rollouts"
+)]
+pub async fn create(
+    DbConnection(mut conn): DbConnection,
+    Author(author): Author,
+    Path((project_name, env_name, variant_id)): Path<(String, String, i32)>,
+    Json(payload): Json<NewRolloutPlanPayload>,
+) -> Result<Json<RolloutPlan>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let plan = rollout::create(&mut conn, &env, variant_id, payload, author.as_deref()).await?;
+
+    Ok(Json(plan))
+}
+
+/// Pauses an active rollout of a variant.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/variants/{variant_id}/rollout/pause",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("variant_id" = i32, Path, description = "Variant ID")
+    ),
+    responses(
+        (status = 200, description = "Paused rollout plan", body = RolloutPlan),
+        (status = 400, description = "Rollout is not active")
+    ),
+    tag = "rollouts"
+)]
+pub async fn pause(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, variant_id)): Path<(String, String, i32)>,
+) -> Result<Json<RolloutPlan>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let plan = rollout::pause(&mut conn, &env, variant_id).await?;
+
+    Ok(Json(plan))
+}
+
+/// Resumes a paused rollout of a variant.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/variants/{variant_id}/rollout/resume",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("variant_id" = i32, Path, description = "Variant ID")
+    ),
+    responses(
+        (status = 200, description = "Resumed rollout plan", body = RolloutPlan),
+        (status = 400, description = "Rollout is not paused")
+    ),
+    tag = "rollouts"
+)]
+pub async fn resume(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, variant_id)): Path<(String, String, i32)>,
+) -> Result<Json<RolloutPlan>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let plan = rollout::resume(&mut conn, &env, variant_id).await?;
+
+    Ok(Json(plan))
+}
+
+/// Aborts a rollout of a variant, rolling variant back to the weight rollout started at.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/variants/{variant_id}/rollout/abort",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("variant_id" = i32, Path, description = "Variant ID")
+    ),
+    responses(
+        (status = 200, description = "Aborted rollout plan", body = RolloutPlan),
+        (status = 400, description = "Rollout is already completed or aborted")
+    ),
+    tag = "rollouts"
+)]
+pub async fn abort(
+    DbConnection(mut conn): DbConnection,
+    Author(author): Author,
+    Path((project_name, env_name, variant_id)): Path<(String, String, i32)>,
+) -> Result<Json<RolloutPlan>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let plan = rollout::abort(&mut conn, &env, variant_id, author.as_deref()).await?;
+
+    Ok(Json(plan))
+}
@@ -0,0 +1,127 @@
+use axum::{
+    Json,
+    extract::{Path, Query},
+};
+use flagrant::models::{environment, project, schedule};
+use flagrant_types::{
+    RevisionEntity, ScheduleStatus, ScheduledChange, payload::NewScheduledChangePayload,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+    errors::ServiceError,
+    extractors::{Author, DbConnection},
+};
+
+/// Query parameters for scheduled changes listing.
+#[derive(Debug, Deserialize, IntoParams)]
+pub(crate) struct ScheduleQueryParams {
+    /// Filter by status (pending by default)
+    status: Option<ScheduleStatus>,
+    /// Narrow down to changes of given feature
+    feature_id: Option<i32>,
+    /// Narrow down to changes of given segment
+    segment_id: Option<i32>,
+}
+
+/// Returns changes scheduled within an environment, the nearest first.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/schedules",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ScheduleQueryParams
+    ),
+    responses(
+        (status = 200, description = "Scheduled changes", body = Vec<ScheduledChange>)
+    ),
+    tag = "schedules"
+)]
+pub async fn list(
+    DbConnection(mut conn): DbConnection,
+    Query(params): Query<ScheduleQueryParams>,
+    Path((project_name, env_name)): Path<(String, String)>,
+) -> Result<Json<Vec<ScheduledChange>>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let entity = match (params.feature_id, params.segment_id) {
+        (Some(feature_id), _) => Some((RevisionEntity::Feature, feature_id)),
+        (_, Some(segment_id)) => Some((RevisionEntity::Segment, segment_id)),
+        _ => None,
+    };
+    let changes = schedule::list(
+        &mut conn,
+        &env,
+        params.status.unwrap_or(ScheduleStatus::Pending),
+        entity,
+    )
+    .await?;
+
+    Ok(Json(changes))
+}
+
+/// Schedules a feature patch, or a segment overrides patch, to be applied at given time.
+///
+/// Once due, change gets applied by a background task and recorded as a revision
+/// attributed to the author who scheduled it.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/schedules",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name")
+    ),
+    request_body = NewScheduledChangePayload,
+    responses(
+        (status = 200, description = "Scheduled change", body = ScheduledChange),
+        (status = 400, description = "Time in the past or patch not allowed to be scheduled")
+    ),
+    tag = "schedules"
+)]
+pub async fn create(
+    DbConnection(mut conn): DbConnection,
+    Author(author): Author,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Json(payload): Json<NewScheduledChangePayload>,
+) -> Result<Json<ScheduledChange>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let scheduled = schedule::create(
+        &mut conn,
+        &env,
+        payload.change,
+        payload.apply_at,
+        author.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(scheduled))
+}
+
+/// Cancels a pending scheduled change.
+#[utoipa::path(
+    delete,
+    path = "/projects/{project}/envs/{environment}/schedules/{scheduled_change_id}",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        ("scheduled_change_id" = i32, Path, description = "Scheduled change ID")
+    ),
+    responses(
+        (status = 200, description = "Cancelled change", body = ScheduledChange),
+        (status = 400, description = "Change is no longer pending")
+    ),
+    tag = "schedules"
+)]
+pub async fn cancel(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name, scheduled_change_id)): Path<(String, String, i32)>,
+) -> Result<Json<ScheduledChange>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let cancelled = schedule::cancel(&mut conn, &env, scheduled_change_id).await?;
+
+    Ok(Json(cancelled))
+}
@@ -14,7 +14,10 @@ use serde::Deserialize;
 use sqlx::SqliteConnection;
 use utoipa::IntoParams;
 
-use crate::{errors::ServiceError, extractors::DbConnection};
+use crate::{
+    errors::ServiceError,
+    extractors::{Author, DbConnection},
+};
 
 #[derive(Debug, Deserialize, IntoParams)]
 pub(crate) struct SegmentQueryParams {
@@ -182,19 +185,21 @@ pub async fn delete(
 /// Applies a batch of staged operations to a segment.
 pub async fn patch_segment(
     DbConnection(mut conn): DbConnection,
+    Author(author): Author,
     Path((project_name, segment_id)): Path<(String, SegmentId)>,
     Json(payload): Json<SegmentPatch>,
 ) -> Result<Json<Segment>, ServiceError> {
     let project = project::get_by_name(&mut conn, project_name).await?;
     let seg = resolve_segment(&mut conn, &project, segment_id).await?;
-    let updated = segment::patch(&mut conn, &project, seg, payload).await?;
+    let updated = segment::patch(&mut conn, &project, seg, payload, author.as_deref()).await?;
     Ok(Json(updated))
 }
 
 /// Adds a group to a segment.
 ///
 /// The first group added is the head (connector must be omitted or null).
-/// Subsequent groups require a connector (`and` or `and_not`).
+/// Subsequent groups require a connector (`and`, `and_not`, `or` or `or_not`). AND-ed
+/// groups bind tighter than OR-ed ones.
 #[utoipa::path(
     post,
     path = "/projects/{project}/segments/{segment_id}/groups",
@@ -0,0 +1,87 @@
+use axum::{
+    Json,
+    extract::{Path, Query},
+};
+use flagrant::models::{environment, project, snapshot};
+use flagrant_types::snapshot::{Snapshot, SnapshotImportSummary};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+    errors::ServiceError,
+    extractors::{Author, DbConnection},
+};
+
+/// Snapshots carrying identities may easily outgrow axum's default 2MB limit of request body.
+pub(crate) const SNAPSHOT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub(crate) struct SnapshotQueryParams {
+    /// Include identities along with their traits and pinned variants
+    identities: Option<bool>,
+}
+
+/// Captures an environment (features, segments, traits and optionally identities)
+/// as a single versioned snapshot document.
+#[utoipa::path(
+    get,
+    path = "/projects/{project}/envs/{environment}/snapshot",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name"),
+        SnapshotQueryParams
+    ),
+    responses(
+        (status = 200, description = "Environment snapshot", body = Snapshot)
+    ),
+    tag = "snapshots"
+)]
+pub async fn export(
+    DbConnection(mut conn): DbConnection,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Query(params): Query<SnapshotQueryParams>,
+) -> Result<Json<Snapshot>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let snapshot = snapshot::export(
+        &mut conn,
+        &project,
+        &env,
+        params.identities.unwrap_or_default(),
+    )
+    .await?;
+
+    Ok(Json(snapshot))
+}
+
+/// Restores a snapshot into an environment, possibly of a different project than the
+/// snapshot was taken from.
+///
+/// Features and segments are matched by name. Those missing get created, existing ones
+/// are brought to the captured state. Restoration happens within a single transaction.
+#[utoipa::path(
+    post,
+    path = "/projects/{project}/envs/{environment}/snapshot",
+    params(
+        ("project" = String, Path, description = "Project name"),
+        ("environment" = String, Path, description = "Environment name")
+    ),
+    request_body = Snapshot,
+    responses(
+        (status = 200, description = "Summary of restored entities", body = SnapshotImportSummary),
+        (status = 400, description = "Unsupported or inconsistent snapshot")
+    ),
+    tag = "snapshots"
+)]
+pub async fn import(
+    DbConnection(mut conn): DbConnection,
+    Author(author): Author,
+    Path((project_name, env_name)): Path<(String, String)>,
+    Json(snapshot): Json<Snapshot>,
+) -> Result<Json<SnapshotImportSummary>, ServiceError> {
+    let project = project::get_by_name(&mut conn, project_name).await?;
+    let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+    let summary = snapshot::import(&mut conn, &env, snapshot, author.as_deref()).await?;
+
+    Ok(Json(summary))
+}
@@ -0,0 +1,228 @@
+//! Identities resolved from JSON Web Tokens.
+//!
+//! By default the public client API takes the identity from the `X-Flagrant-Identity`
+//! header, as is. Once a key to verify tokens with is configured, identity has to come
+//! with a token instead (`Authorization: Bearer <jwt>`), so that callers can't pretend
+//! to be someone else. Tokens are verified with either an HMAC secret or the keys of a
+//! local JWKS file:
+//!
+//! | Variable                        | Meaning                                                |
+//! |---------------------------------|--------------------------------------------------------|
+//! | `FLAGRANT_JWT_SECRET`           | HMAC secret (HS256, HS384, HS512)                      |
+//! | `FLAGRANT_JWT_JWKS`             | Path of a JWKS file, keys picked by token `kid`        |
+//! | `FLAGRANT_JWT_IDENTITY_CLAIM`   | Claim holding the identity, `sub` by default           |
+//! | `FLAGRANT_JWT_ISSUER`           | Required `iss` claim, if set                           |
+//! | `FLAGRANT_JWT_AUDIENCE`         | Required `aud` claim, if set                           |
+//! | `FLAGRANT_JWT_TRAITS`           | Claims used as traits for a single request only        |
+//! | `FLAGRANT_JWT_PERSISTED_TRAITS` | Claims stored as identity traits                       |
+//!
+//! Traits are given as a comma-separated list of `claim` or `claim=trait` entries, where
+//! claims nested within objects are reached with dots, e.g. `plan,org.id=account_id`.
+//! Claims missing in a token, or holding anything else than a string, number or boolean,
+//! are skipped.
+
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, bail};
+use flagrant::errors::FlagrantError;
+use flagrant_types::{TraitValue, payload::IdentityTraitPayload};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, jwk::JwkSet};
+use serde_json::Value;
+
+use crate::extractors::Identity;
+
+const SECRET_VAR: &str = "FLAGRANT_JWT_SECRET";
+const JWKS_VAR: &str = "FLAGRANT_JWT_JWKS";
+const IDENTITY_CLAIM_VAR: &str = "FLAGRANT_JWT_IDENTITY_CLAIM";
+const ISSUER_VAR: &str = "FLAGRANT_JWT_ISSUER";
+const AUDIENCE_VAR: &str = "FLAGRANT_JWT_AUDIENCE";
+const TRAITS_VAR: &str = "FLAGRANT_JWT_TRAITS";
+const PERSISTED_TRAITS_VAR: &str = "FLAGRANT_JWT_PERSISTED_TRAITS";
+
+static CONFIG: OnceLock<Option<JwtConfig>> = OnceLock::new();
+
+pub struct JwtConfig {
+    key: VerificationKey,
+    identity_claim: String,
+    issuer: Option<String>,
+    audience: Option<String>,
+    transient_traits: Vec<ClaimTrait>,
+    persisted_traits: Vec<ClaimTrait>,
+}
+
+enum VerificationKey {
+    Secret(DecodingKey),
+    Jwks(JwkSet),
+}
+
+/// Claim (path) mapped onto a trait of given name.
+struct ClaimTrait {
+    claim: String,
+    name: String,
+}
+
+/// Reads JWT configuration from environment variables. Meant to be called once, at
+/// startup, so that a broken configuration gets reported before any request comes.
+pub fn init() -> anyhow::Result<()> {
+    let config = JwtConfig::from_env()?;
+    if config.is_some() {
+        tracing::info!("Identities taken from JWT tokens");
+    }
+    CONFIG
+        .set(config)
+        .map_err(|_| anyhow!("JWT configuration already initialized"))
+}
+
+/// Returns JWT configuration, if identities are to be taken from tokens.
+pub fn config() -> Option<&'static JwtConfig> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+impl JwtConfig {
+    fn from_env() -> anyhow::Result<Option<JwtConfig>> {
+        let key = match (var(SECRET_VAR), var(JWKS_VAR)) {
+            (Some(_), Some(_)) => {
+                bail!("Either {SECRET_VAR} or {JWKS_VAR} should be set, not both")
+            }
+            (Some(secret), None) => {
+                VerificationKey::Secret(DecodingKey::from_secret(secret.as_bytes()))
+            }
+            (None, Some(path)) => {
+                let jwks = std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("Could not read JWKS file {path}: {e}"))?;
+                VerificationKey::Jwks(
+                    serde_json::from_str(&jwks)
+                        .map_err(|e| anyhow!("Could not parse JWKS file {path}: {e}"))?,
+                )
+            }
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(JwtConfig {
+            key,
+            identity_claim: var(IDENTITY_CLAIM_VAR).unwrap_or_else(|| "sub".to_owned()),
+            issuer: var(ISSUER_VAR),
+            audience: var(AUDIENCE_VAR),
+            transient_traits: claim_traits(var(TRAITS_VAR)),
+            persisted_traits: claim_traits(var(PERSISTED_TRAITS_VAR)),
+        }))
+    }
+
+    /// Verifies given `token` and resolves identity (along with its traits) out of it.
+    pub fn verify(&self, token: &str) -> anyhow::Result<Identity> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|e| FlagrantError::InvalidToken("Malformed token", e.into()))?;
+
+        // Key type has to match the algorithm declared by token, which is checked
+        // along with the signature - an HMAC secret won't verify RSA-signed tokens,
+        // neither will public keys verify HMAC-signed ones.
+        let key = match &self.key {
+            VerificationKey::Secret(key) => key.clone(),
+            VerificationKey::Jwks(jwks) => {
+                // Symmetric keys are not meant to be published, hence not expected in JWKS.
+                if matches!(
+                    header.alg,
+                    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+                ) {
+                    return Err(FlagrantError::InvalidToken(
+                        "Unsupported algorithm",
+                        anyhow!("{:?}", header.alg),
+                    )
+                    .into());
+                }
+                let jwk = match &header.kid {
+                    Some(kid) => jwks.find(kid),
+                    None if jwks.keys.len() == 1 => jwks.keys.first(),
+                    None => None,
+                }
+                .ok_or_else(|| {
+                    FlagrantError::InvalidToken(
+                        "Unknown signing key",
+                        anyhow!("kid: {:?}", header.kid),
+                    )
+                })?;
+                DecodingKey::from_jwk(jwk)
+                    .map_err(|e| FlagrantError::InvalidToken("Unsupported signing key", e.into()))?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let claims = jsonwebtoken::decode::<Value>(token, &key, &validation)
+            .map_err(|e| FlagrantError::InvalidToken("Token verification failed", e.into()))?
+            .claims;
+
+        let value = match claim(&claims, &self.identity_claim) {
+            Some(Value::String(s)) if !s.is_empty() => s.clone(),
+            Some(Value::Number(n)) => n.to_string(),
+            _ => {
+                return Err(FlagrantError::InvalidToken(
+                    "Token holds no identity",
+                    anyhow!("claim: {}", self.identity_claim),
+                )
+                .into());
+            }
+        };
+        Ok(Identity {
+            value,
+            transient_traits: traits(&claims, &self.transient_traits),
+            persisted_traits: traits(&claims, &self.persisted_traits),
+        })
+    }
+}
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Parses comma-separated `claim` or `claim=trait` entries.
+fn claim_traits(spec: Option<String>) -> Vec<ClaimTrait> {
+    spec.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((claim, name)) => ClaimTrait {
+                claim: claim.trim().to_owned(),
+                name: name.trim().to_owned(),
+            },
+            None => ClaimTrait {
+                claim: entry.to_owned(),
+                name: entry.to_owned(),
+            },
+        })
+        .collect()
+}
+
+/// Returns claim of given dot-separated `path`.
+fn claim<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(claims, |value, key| value.get(key))
+}
+
+fn traits(claims: &Value, mapping: &[ClaimTrait]) -> Vec<IdentityTraitPayload> {
+    mapping
+        .iter()
+        .filter_map(|ct| {
+            let value = match claim(claims, &ct.claim)? {
+                Value::String(s) => TraitValue::Str(s.clone()),
+                Value::Bool(b) => TraitValue::Bool(*b),
+                Value::Number(n) => match n.as_i64().and_then(|i| i32::try_from(i).ok()) {
+                    Some(i) => TraitValue::Int(i),
+                    None => TraitValue::Float(n.as_f64()? as f32),
+                },
+                _ => return None,
+            };
+            Some(IdentityTraitPayload {
+                name: ct.name.clone(),
+                value: Some(value),
+            })
+        })
+        .collect()
+}
@@ -1,13 +1,20 @@
+use state::AppState;
 use tower_http::compression::CompressionLayer;
 use tower_http::trace::TraceLayer;
 use tracing::init_tracing;
 
 mod api;
+mod auth;
+mod changes;
 mod errors;
 mod extractors;
 mod handlers;
+mod jwt;
 mod openapi;
 mod routes;
+mod scheduler;
+mod socket;
+mod state;
 mod tracing;
 
 #[tokio::main]
@@ -17,8 +24,22 @@ async fn main() {
     let pool = flagrant::db::init_pool()
         .await
         .expect("Cannot initialize DB");
-    let router = routes::init_router()
-        .with_state(pool)
+    let state = AppState::new(pool);
+
+    jwt::init().expect("Invalid JWT configuration");
+    if auth::auth_disabled() {
+        ::tracing::warn!(
+            "Authentication disabled, API is open to anyone who can reach it - never run it like this outside of a local setup"
+        );
+    } else if auth::master_key().is_none() {
+        ::tracing::warn!("No master key configured, projects and API keys can't be created");
+    }
+    scheduler::spawn(state.clone());
+    socket::spawn(state.clone())
+        .await
+        .expect("Cannot start socket protocol listener");
+    let router = routes::init_router(state.clone())
+        .with_state(state)
         .layer(CompressionLayer::new())
         .layer(TraceLayer::new_for_http());
 
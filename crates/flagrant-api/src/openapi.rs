@@ -5,22 +5,42 @@ use utoipa::OpenApi;
     paths(
         crate::handlers::projects::list,
         crate::handlers::projects::fetch,
+        crate::handlers::projects::patch,
         crate::handlers::projects::create,
+        crate::handlers::api_keys::list,
+        crate::handlers::api_keys::create,
+        crate::handlers::api_keys::delete,
         crate::handlers::environments::list,
         crate::handlers::environments::fetch_by_id_or_name,
         crate::handlers::environments::create,
+        crate::handlers::environments::freeze,
+        crate::handlers::environments::thaw,
+        crate::handlers::environments::history,
+        crate::handlers::snapshots::export,
+        crate::handlers::snapshots::import,
+        crate::handlers::schedules::list,
+        crate::handlers::schedules::create,
+        crate::handlers::schedules::cancel,
         crate::handlers::features::list,
         crate::handlers::features::fetch_by_id_or_name,
         crate::handlers::features::create,
         crate::handlers::features::update,
         crate::handlers::features::delete,
         crate::handlers::features::patch,
+        crate::handlers::features::history,
+        crate::handlers::features::revert,
         crate::handlers::features::clear_distribution,
         crate::handlers::variants::list,
         crate::handlers::variants::fetch,
         crate::handlers::variants::create,
         crate::handlers::variants::update,
         crate::handlers::variants::delete,
+        crate::handlers::rollouts::list,
+        crate::handlers::rollouts::fetch,
+        crate::handlers::rollouts::create,
+        crate::handlers::rollouts::pause,
+        crate::handlers::rollouts::resume,
+        crate::handlers::rollouts::abort,
         crate::handlers::tags::list,
         crate::handlers::identities::list,
         crate::handlers::identities::fetch,
@@ -29,6 +49,13 @@ use utoipa::OpenApi;
         crate::handlers::identities::delete,
         crate::handlers::identities::clear,
         crate::handlers::identities::get_variants,
+        crate::handlers::identities::explain,
+        crate::handlers::layers::list,
+        crate::handlers::layers::create,
+        crate::handlers::layers::fetch_by_id_or_name,
+        crate::handlers::layers::delete,
+        crate::handlers::layers::set_share,
+        crate::handlers::layers::unset_share,
         crate::handlers::traits::list,
         crate::handlers::traits::create,
         crate::handlers::traits::delete,
@@ -42,14 +69,22 @@ use utoipa::OpenApi;
         crate::handlers::segments::add_rule,
         crate::handlers::segments::delete_rule,
         crate::api::get_features,
+        crate::api::evaluate_features,
+        crate::api::stream_features,
     ),
     components(
         schemas(
             flagrant_types::Project,
+            flagrant_types::TraitPersistence,
             flagrant_types::Environment,
+            flagrant_types::ApiKey,
+            flagrant_types::ApiKeyKind,
             flagrant_types::Feature,
             flagrant_types::Variant,
+            flagrant_types::Prerequisite,
             flagrant_types::FeatureValue,
+            flagrant_types::FeatureVisibility,
+            flagrant_types::Distribution,
             flagrant_types::Tag,
             flagrant_types::TagList,
             flagrant_types::FeatureResponse,
@@ -57,15 +92,47 @@ use utoipa::OpenApi;
             flagrant_types::IdentityTrait,
             flagrant_types::IdentityVariant,
             flagrant_types::IdentityWithTraits,
+            flagrant_types::Revision,
+            flagrant_types::RevisionEntity,
+            flagrant_types::ScheduledPatch,
+            flagrant_types::ScheduleStatus,
+            flagrant_types::ScheduledChange,
+            flagrant_types::RolloutStatus,
+            flagrant_types::RolloutPlan,
+            flagrant_types::Layer,
+            flagrant_types::LayerFeature,
+            flagrant_types::explain::FeatureExplanation,
+            flagrant_types::explain::Resolution,
+            flagrant_types::explain::SegmentEvaluation,
+            flagrant_types::explain::GroupEvaluation,
+            flagrant_types::explain::RuleEvaluation,
+            flagrant_types::snapshot::Snapshot,
+            flagrant_types::snapshot::FeatureSnapshot,
+            flagrant_types::snapshot::VariantSnapshot,
+            flagrant_types::snapshot::PrerequisiteSnapshot,
+            flagrant_types::snapshot::SegmentSnapshot,
+            flagrant_types::snapshot::GroupSnapshot,
+            flagrant_types::snapshot::RuleSnapshot,
+            flagrant_types::snapshot::SegmentOverrideSnapshot,
+            flagrant_types::snapshot::IdentitySnapshot,
+            flagrant_types::snapshot::TraitSnapshot,
+            flagrant_types::snapshot::PinSnapshot,
+            flagrant_types::snapshot::SnapshotImportSummary,
             flagrant_types::payload::NewProjectPayload,
             flagrant_types::payload::ProjectCreatedResponse,
+            flagrant_types::payload::ProjectPatch,
+            flagrant_types::payload::NewApiKeyPayload,
+            flagrant_types::payload::ApiKeyCreatedResponse,
             flagrant_types::payload::NewEnvironmentPayload,
+            flagrant_types::payload::FreezePayload,
             flagrant_types::payload::NewFeaturePayload,
             flagrant_types::payload::NewVariantPayload,
             flagrant_types::payload::FeaturePatch,
             flagrant_types::payload::VariantPatchOp,
+            flagrant_types::payload::PrerequisitePatchOp,
             flagrant_types::payload::NewTraitPayload,
             flagrant_types::payload::IdentityTraitPayload,
+            flagrant_types::payload::EvaluationPayload,
             flagrant_types::payload::NewIdentityPayload,
             flagrant_types::payload::TraitPatchOp,
             flagrant_types::payload::IdentityPatch,
@@ -79,17 +146,26 @@ use utoipa::OpenApi;
             flagrant_types::payload::NewSegmentPayload,
             flagrant_types::payload::NewGroupPayload,
             flagrant_types::payload::NewRulePayload,
+            flagrant_types::payload::NewScheduledChangePayload,
+            flagrant_types::payload::NewRolloutPlanPayload,
+            flagrant_types::payload::NewLayerPayload,
+            flagrant_types::payload::LayerSharePayload,
         )
     ),
     tags(
         (name = "projects", description = "Project management"),
+        (name = "api-keys", description = "API keys management"),
         (name = "environments", description = "Environment management"),
         (name = "features", description = "Feature flag management"),
         (name = "variants", description = "Feature variant management"),
+        (name = "rollouts", description = "Progressive rollouts of feature variants"),
         (name = "tags", description = "Tag management"),
         (name = "identities", description = "Identity management"),
         (name = "traits", description = "Trait management"),
         (name = "segments", description = "Segment management"),
+        (name = "layers", description = "Mutually exclusive experiment layers"),
+        (name = "snapshots", description = "Environment snapshots export and import"),
+        (name = "schedules", description = "Scheduled feature and segment changes"),
         (name = "api", description = "Public client API"),
     ),
     info(
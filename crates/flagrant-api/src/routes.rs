@@ -1,21 +1,48 @@
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
+    middleware::from_fn_with_state,
     routing::{delete, get, patch, post, put},
 };
-use sqlx::{Pool, Sqlite};
 use utoipa::OpenApi;
 use utoipa_scalar::{Scalar, Servable};
 
-use crate::handlers::{environments, features, identities, projects, segments, traits, variants};
+use crate::handlers::{
+    api_keys, environments, features, identities, layers, projects, rollouts, schedules, segments,
+    snapshots, traits, variants,
+};
 use crate::openapi::ApiDoc;
-use crate::{api, handlers::tags};
+use crate::{api, auth, changes, handlers::tags, state::AppState};
 
-pub fn init_router() -> Router<Pool<Sqlite>> {
+/// Builds the router. Routes are guarded by API key authentication, see [`auth`].
+/// Successful changes made through the management API are published, see [`changes`].
+pub fn init_router(state: AppState) -> Router<AppState> {
+    let pool = state.pool.clone();
     let project_routes = Router::new()
+        // API keys
+        .route("/api-keys", get(api_keys::list))
+        .route("/api-keys", post(api_keys::create))
+        .route("/api-keys/:api_key_id", delete(api_keys::delete))
         // Environments
         .route("/envs", get(environments::list))
         .route("/envs", post(environments::create))
         .route("/envs/:env_id", get(environments::fetch_by_id_or_name))
+        .route("/envs/:environment/freeze", put(environments::freeze))
+        .route("/envs/:environment/freeze", delete(environments::thaw))
+        .route("/envs/:environment/history", get(environments::history))
+        // Snapshots
+        .route("/envs/:environment/snapshot", get(snapshots::export))
+        .route(
+            "/envs/:environment/snapshot",
+            post(snapshots::import).layer(DefaultBodyLimit::max(snapshots::SNAPSHOT_BODY_LIMIT)),
+        )
+        // Scheduled changes
+        .route("/envs/:environment/schedules", get(schedules::list))
+        .route("/envs/:environment/schedules", post(schedules::create))
+        .route(
+            "/envs/:environment/schedules/:scheduled_change_id",
+            delete(schedules::cancel),
+        )
         // Tags
         .route("/envs/:environment/tags", get(tags::list))
         // Features
@@ -37,6 +64,14 @@ pub fn init_router() -> Router<Pool<Sqlite>> {
             "/envs/:environment/features/:feature_id",
             patch(features::patch),
         )
+        .route(
+            "/envs/:environment/features/:feature_id/history",
+            get(features::history),
+        )
+        .route(
+            "/envs/:environment/features/:feature_id/history/:revision_id/revert",
+            post(features::revert),
+        )
         // Variants
         .route(
             "/envs/:environment/features/:feature_id/overrides",
@@ -66,6 +101,31 @@ pub fn init_router() -> Router<Pool<Sqlite>> {
             "/envs/:environment/variants/:variant_id",
             delete(variants::delete),
         )
+        // Rollouts
+        .route(
+            "/envs/:environment/features/:feature_id/rollouts",
+            get(rollouts::list),
+        )
+        .route(
+            "/envs/:environment/variants/:variant_id/rollout",
+            get(rollouts::fetch),
+        )
+        .route(
+            "/envs/:environment/variants/:variant_id/rollout",
+            post(rollouts::create),
+        )
+        .route(
+            "/envs/:environment/variants/:variant_id/rollout/pause",
+            post(rollouts::pause),
+        )
+        .route(
+            "/envs/:environment/variants/:variant_id/rollout/resume",
+            post(rollouts::resume),
+        )
+        .route(
+            "/envs/:environment/variants/:variant_id/rollout/abort",
+            post(rollouts::abort),
+        )
         // Identities
         .route("/envs/:environment/identities", get(identities::list))
         .route("/envs/:environment/identities", post(identities::create))
@@ -86,6 +146,26 @@ pub fn init_router() -> Router<Pool<Sqlite>> {
             "/envs/:environment/identities/:identity/variants",
             get(identities::get_variants),
         )
+        .route(
+            "/envs/:environment/identities/:identity/explain",
+            get(identities::explain),
+        )
+        // Exclusion layers
+        .route("/envs/:environment/layers", get(layers::list))
+        .route("/envs/:environment/layers", post(layers::create))
+        .route(
+            "/envs/:environment/layers/:layer",
+            get(layers::fetch_by_id_or_name),
+        )
+        .route("/envs/:environment/layers/:layer", delete(layers::delete))
+        .route(
+            "/envs/:environment/layers/:layer/features/:feature_id",
+            put(layers::set_share),
+        )
+        .route(
+            "/envs/:environment/layers/:layer/features/:feature_id",
+            delete(layers::unset_share),
+        )
         // Traits
         .route("/traits", get(traits::list))
         .route("/traits", post(traits::create))
@@ -117,18 +197,39 @@ pub fn init_router() -> Router<Pool<Sqlite>> {
         .route(
             "/segments/:segment_id/overrides/:environment_id",
             get(segments::get_overridden_features),
-        );
+        )
+        .route_layer(from_fn_with_state(pool.clone(), auth::management))
+        .route_layer(from_fn_with_state(state.clone(), changes::notify));
 
     Router::new()
         .merge(Scalar::with_url("/scalar", ApiDoc::openapi()))
         // Projects
-        .route("/projects/", get(projects::list))
-        .route("/projects/", post(projects::create))
-        .route("/projects/:project", get(projects::fetch))
+        .merge(
+            Router::new()
+                .route("/projects/", get(projects::list))
+                .route("/projects/", post(projects::create))
+                .route_layer(from_fn_with_state(pool.clone(), auth::master)),
+        )
+        .route(
+            "/projects/:project",
+            get(projects::fetch)
+                .patch(projects::patch)
+                .route_layer(from_fn_with_state(pool.clone(), auth::management))
+                .route_layer(from_fn_with_state(state, changes::notify)),
+        )
         .nest("/projects/:project", project_routes)
         // Public API
         .nest(
             "/api/v1/projects/:project",
-            Router::new().route("/envs/:environment/features", get(api::get_features)),
+            Router::new()
+                .route(
+                    "/envs/:environment/features",
+                    get(api::get_features).post(api::evaluate_features),
+                )
+                .route(
+                    "/envs/:environment/features/stream",
+                    get(api::stream_features),
+                )
+                .route_layer(from_fn_with_state(pool, auth::client)),
         )
 }
@@ -0,0 +1,52 @@
+//! Background task applying scheduled changes and rollout steps once they're due.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use flagrant::models::{rollout, schedule};
+
+use crate::{changes::Change, state::AppState};
+
+/// How often pending changes and rollout steps are checked for being due. They get
+/// applied with a delay of at most this long.
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Spawns a task which periodically applies all the scheduled changes and rollout steps
+/// being due.
+pub fn spawn(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = apply_due(&state).await {
+                tracing::error!(error = ?error, "Could not apply scheduled changes");
+            }
+            if let Err(error) = advance_rollouts(&state).await {
+                tracing::error!(error = ?error, "Could not advance rollouts");
+            }
+        }
+    });
+}
+
+async fn apply_due(state: &AppState) -> anyhow::Result<()> {
+    let mut conn = state.pool.acquire().await?;
+    let applied = schedule::apply_due(&mut conn, Utc::now().naive_utc()).await?;
+
+    if applied > 0 {
+        tracing::info!(applied, "Applied scheduled changes");
+        state.segments.invalidate();
+        state.changes.publish(Change::Any);
+    }
+    Ok(())
+}
+
+async fn advance_rollouts(state: &AppState) -> anyhow::Result<()> {
+    let mut conn = state.pool.acquire().await?;
+    let advanced = rollout::advance_due(&mut conn, Utc::now().naive_utc()).await?;
+
+    if advanced > 0 {
+        tracing::info!(advanced, "Advanced rollouts");
+        state.changes.publish(Change::Any);
+    }
+    Ok(())
+}
@@ -0,0 +1,426 @@
+//! Socket protocol server, a persistent alternative to the HTTP client API for clients
+//! reading flags often and wanting to hear about their changes as soon as possible.
+//! Frames are described in [`flagrant_types::protocol`].
+//!
+//! Server listens on a TCP address given by `FLAGRANT_SOCKET_ADDR` (e.g.
+//! `127.0.0.1:3031`) and/or on a Unix domain socket at `FLAGRANT_SOCKET_PATH`. With
+//! none of them set, protocol is not served at all.
+//!
+//! Connections get authenticated once, at handshake, with the same API keys the HTTP
+//! client API accepts. Identities are taken as they are or, if JWT identities are
+//! configured, verified as tokens.
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+use flagrant::{
+    errors::FlagrantError,
+    models::{environment, project},
+};
+use flagrant_types::{
+    Environment, FeatureResponse,
+    protocol::{self, ClientFrame, PROTOCOL_VERSION, ServerFrame},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+    sync::{broadcast::error::RecvError, mpsc},
+};
+
+use crate::{
+    api, auth,
+    errors::ServiceError,
+    extractors::{Audience, Identity},
+    jwt,
+    state::AppState,
+};
+
+const ADDR_VAR: &str = "FLAGRANT_SOCKET_ADDR";
+const PATH_VAR: &str = "FLAGRANT_SOCKET_PATH";
+
+/// Max number of identities a single connection may watch.
+const MAX_WATCHED: usize = 1024;
+
+/// Starts listening on configured sockets, serving each connection by a separate task.
+pub async fn spawn(state: AppState) -> anyhow::Result<()> {
+    if let Some(addr) = var(ADDR_VAR) {
+        let listener = TcpListener::bind(&addr).await?;
+        let state = state.clone();
+
+        tracing::info!("socket protocol listening on {}", listener.local_addr()?);
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let _ = stream.set_nodelay(true);
+                        tokio::spawn(serve(state.clone(), stream));
+                    }
+                    Err(error) => {
+                        tracing::warn!(error = ?error, "Could not accept socket connection")
+                    }
+                }
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    if let Some(path) = var(PATH_VAR) {
+        // Socket file left by previous run would make binding fail.
+        let _ = std::fs::remove_file(&path);
+        let listener = tokio::net::UnixListener::bind(&path)?;
+
+        tracing::info!("socket protocol listening on {path}");
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(state.clone(), stream));
+                    }
+                    Err(error) => {
+                        tracing::warn!(error = ?error, "Could not accept socket connection")
+                    }
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+async fn serve<S>(state: AppState, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let (frames_tx, mut frames) = mpsc::channel(16);
+
+    // Reading a frame can't be safely cancelled halfway, hence frames are read by
+    // a separate task instead of being raced against change notifications.
+    let reader_task = tokio::spawn(async move {
+        loop {
+            let frame = read_frame(&mut reader).await;
+            let failed = frame.is_err();
+            if frames_tx.send(frame).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+    if let Err(error) = run_session(&state, &mut frames, &mut writer).await {
+        tracing::debug!(error = ?error, "Socket session closed");
+    }
+    reader_task.abort();
+}
+
+async fn run_session<W: AsyncWrite + Unpin>(
+    state: &AppState,
+    frames: &mut mpsc::Receiver<anyhow::Result<ClientFrame>>,
+    writer: &mut W,
+) -> anyhow::Result<()> {
+    let mut session = match frames.recv().await {
+        Some(Ok(ClientFrame::Hello {
+            version,
+            project,
+            environment,
+            key,
+        })) => match Session::open(state, version, project, environment, key).await {
+            Ok(session) => session,
+            Err(error) => {
+                write_frame(writer, &error_frame(error)).await?;
+                return Ok(());
+            }
+        },
+        Some(Ok(_)) => {
+            let error = FlagrantError::BadRequest("Session not opened");
+            write_frame(writer, &error_frame(error.into())).await?;
+            return Ok(());
+        }
+        Some(Err(error)) => return Err(error),
+        None => return Ok(()),
+    };
+    let mut changes = state.changes.subscribe();
+
+    write_frame(
+        writer,
+        &ServerFrame::Ready {
+            version: PROTOCOL_VERSION,
+        },
+    )
+    .await?;
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => {
+                let Some(frame) = frame else {
+                    return Ok(());
+                };
+                let reply = match session.handle(frame?).await {
+                    Ok(reply) => reply,
+                    Err(error) => Some(error_frame(error)),
+                };
+                if let Some(reply) = reply {
+                    write_frame(writer, &reply).await?;
+                }
+            }
+            change = changes.recv() => {
+                match change {
+                    Ok(change) if !change.affects(&session.project) => {}
+                    // Lagging behind means some changes have been missed, each one
+                    // might have been relevant.
+                    Ok(_) | Err(RecvError::Lagged(_)) => {
+                        for frame in session.refresh().await? {
+                            write_frame(writer, &frame).await?;
+                        }
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Connection authenticated and bound to a project environment.
+struct Session {
+    state: AppState,
+    project: String,
+    env: Environment,
+    audience: Audience,
+    /// Watched identities (as sent by client) along with features sent last.
+    watched: HashMap<String, (Identity, Vec<FeatureResponse>)>,
+}
+
+impl Session {
+    async fn open(
+        state: &AppState,
+        version: u32,
+        project_name: String,
+        env_name: String,
+        key: Option<String>,
+    ) -> anyhow::Result<Session> {
+        if version != PROTOCOL_VERSION {
+            bail!(FlagrantError::BadRequest("Unsupported protocol version"));
+        }
+        let mut conn = state.pool.acquire().await?;
+        let params = HashMap::from([
+            ("project".to_owned(), project_name.clone()),
+            ("environment".to_owned(), env_name.clone()),
+        ]);
+        let audience = auth::client_audience(&mut conn, key.as_deref(), &params).await?;
+        let project = project::get_by_name(&mut conn, project_name).await?;
+        let env = environment::get_by_name(&mut conn, &project, env_name).await?;
+
+        Ok(Session {
+            state: state.clone(),
+            project: project.name,
+            env,
+            audience,
+            watched: HashMap::new(),
+        })
+    }
+
+    /// Handles a frame, returning the answer to send back, if any.
+    async fn handle(&mut self, frame: ClientFrame) -> anyhow::Result<Option<ServerFrame>> {
+        match frame {
+            ClientFrame::Hello { .. } => {
+                bail!(FlagrantError::BadRequest("Session already opened"))
+            }
+            ClientFrame::Features { identity, watch } => {
+                let Identity {
+                    value,
+                    transient_traits,
+                    persisted_traits,
+                } = resolve_identity(&identity)?;
+
+                let mut conn = self.state.pool.acquire().await?;
+                api::persist_traits(&mut conn, &self.env, &value, persisted_traits).await?;
+                let features = api::evaluate(
+                    &mut conn,
+                    &self.state.segments,
+                    &self.env,
+                    value.clone(),
+                    &transient_traits,
+                    self.audience,
+                )
+                .await?;
+
+                if watch {
+                    if self.watched.len() >= MAX_WATCHED && !self.watched.contains_key(&identity) {
+                        bail!(FlagrantError::BadRequest("Too many watched identities"));
+                    }
+                    let resolved = Identity {
+                        value,
+                        transient_traits,
+                        persisted_traits: Vec::new(),
+                    };
+                    self.watched
+                        .insert(identity.clone(), (resolved, features.clone()));
+                }
+                Ok(Some(ServerFrame::Features { identity, features }))
+            }
+            ClientFrame::Unwatch { identity } => {
+                self.watched.remove(&identity);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Re-evaluates features of all the watched identities, returning frames with
+    /// those which have changed. Identities failing to evaluate get an error frame each
+    /// and stay watched, with features sent last kept to compare the next refresh to.
+    async fn refresh(&mut self) -> anyhow::Result<Vec<ServerFrame>> {
+        let mut conn = self.state.pool.acquire().await?;
+        let mut frames = Vec::new();
+
+        for (identity, (resolved, last)) in self.watched.iter_mut() {
+            let features = match api::evaluate(
+                &mut conn,
+                &self.state.segments,
+                &self.env,
+                resolved.value.clone(),
+                &resolved.transient_traits,
+                self.audience,
+            )
+            .await
+            {
+                Ok(features) => features,
+                Err(error) => {
+                    tracing::debug!(identity, error = ?error, "Could not refresh watched identity");
+                    frames.push(error_frame(error));
+                    continue;
+                }
+            };
+
+            if *last != features {
+                *last = features.clone();
+                frames.push(ServerFrame::Changed {
+                    identity: identity.clone(),
+                    features,
+                });
+            }
+        }
+        Ok(frames)
+    }
+}
+
+/// Resolves identity sent by client, verifying it as a token if JWT identities
+/// are configured.
+fn resolve_identity(identity: &str) -> anyhow::Result<Identity> {
+    match jwt::config() {
+        Some(config) => config.verify(identity),
+        None if identity.is_empty() => bail!(FlagrantError::NoIdentity("Empty identity")),
+        None => Ok(Identity {
+            value: identity.to_owned(),
+            transient_traits: Vec::new(),
+            persisted_traits: Vec::new(),
+        }),
+    }
+}
+
+fn error_frame(error: anyhow::Error) -> ServerFrame {
+    let (_, message) = ServiceError::from(error).status_and_message();
+    ServerFrame::Error { message }
+}
+
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> anyhow::Result<ClientFrame> {
+    let mut prefix = [0; 4];
+    reader.read_exact(&mut prefix).await?;
+
+    let mut buf = vec![0; protocol::frame_len(prefix)?];
+    reader.read_exact(&mut buf).await?;
+    Ok(protocol::decode(&buf)?)
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    frame: &ServerFrame,
+) -> anyhow::Result<()> {
+    writer.write_all(&protocol::encode(frame)?).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use flagrant_types::protocol::MAX_FRAME_LEN;
+    use tokio::io::duplex;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_frames_written_by_client() {
+        let (mut client, mut server) = duplex(1024);
+        let frame = ClientFrame::Features {
+            identity: "user-1".to_owned(),
+            watch: true,
+        };
+        client
+            .write_all(&protocol::encode(&frame).unwrap())
+            .await
+            .unwrap();
+        client
+            .write_all(&protocol::encode(&frame).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(read_frame(&mut server).await.unwrap(), frame);
+        assert_eq!(read_frame(&mut server).await.unwrap(), frame);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_frames() {
+        let (mut client, mut server) = duplex(1024);
+        client
+            .write_all(&(MAX_FRAME_LEN + 1).to_be_bytes())
+            .await
+            .unwrap();
+
+        let error = read_frame(&mut server).await.unwrap_err();
+        assert!(error.to_string().starts_with("Frame too long"));
+    }
+
+    #[tokio::test]
+    async fn written_frames_are_length_prefixed() {
+        let (mut server, mut client) = duplex(1024);
+        let frame = ServerFrame::Ready {
+            version: PROTOCOL_VERSION,
+        };
+        write_frame(&mut server, &frame).await.unwrap();
+
+        let len = client.read_u32().await.unwrap();
+        let mut buf = vec![0; len as usize];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(protocol::decode::<ServerFrame>(&buf).unwrap(), frame);
+    }
+
+    #[tokio::test]
+    async fn identities_failing_to_refresh_get_error_frames() {
+        // Database without any schema - every evaluation fails.
+        let pool = sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap();
+        let watched = ["alice", "bob"].map(|value| {
+            let identity = Identity {
+                value: value.to_owned(),
+                transient_traits: Vec::new(),
+                persisted_traits: Vec::new(),
+            };
+            (value.to_owned(), (identity, Vec::new()))
+        });
+        let mut session = Session {
+            state: AppState::new(pool),
+            project: "test".to_owned(),
+            env: Environment::default(),
+            audience: Audience::Backend,
+            watched: HashMap::from(watched),
+        };
+
+        let frames = session.refresh().await.unwrap();
+        assert_eq!(frames.len(), 2);
+        assert!(
+            frames
+                .iter()
+                .all(|frame| matches!(frame, ServerFrame::Error { .. }))
+        );
+        assert_eq!(session.watched.len(), 2);
+    }
+}
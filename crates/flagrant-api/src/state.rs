@@ -0,0 +1,45 @@
+//! State shared by request handlers, socket sessions and background tasks.
+
+use axum::extract::FromRef;
+use flagrant::evaluator::SegmentCache;
+use sqlx::SqlitePool;
+
+use crate::changes::ChangeBus;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: SqlitePool,
+    /// Segments compiled for evaluation, dropped by every change made through the
+    /// management API or by the scheduler.
+    pub segments: SegmentCache,
+    /// Notifications of changes, published by the management API and the scheduler.
+    pub changes: ChangeBus,
+}
+
+impl AppState {
+    pub fn new(pool: SqlitePool) -> AppState {
+        AppState {
+            pool,
+            segments: SegmentCache::default(),
+            changes: ChangeBus::default(),
+        }
+    }
+}
+
+impl FromRef<AppState> for SqlitePool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for SegmentCache {
+    fn from_ref(state: &AppState) -> Self {
+        state.segments.clone()
+    }
+}
+
+impl FromRef<AppState> for ChangeBus {
+    fn from_ref(state: &AppState) -> Self {
+        state.changes.clone()
+    }
+}
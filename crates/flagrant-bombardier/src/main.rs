@@ -1,15 +1,15 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     sync::{
         Arc, RwLock,
         atomic::{AtomicBool, AtomicUsize, Ordering},
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use argh::FromArgs;
-use flagrant_client::{connection::Connection, http::Auth};
+use flagrant_client::{connection::Connection, http::Auth, socket::SocketClient};
 use flagrant_types::{Feature, FeatureResponse, FeatureValue};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use rand::{Rng, rngs::ThreadRng};
@@ -50,10 +50,64 @@ struct Args {
     /// number of worker threads polling the API concurrently (default: 1)
     #[argh(option, short = 't', default = "1")]
     threads: usize,
+
+    /// API key (default: taken from FLAGRANT_API_KEY environment variable)
+    #[argh(option, short = 'k')]
+    key: Option<String>,
+
+    /// socket protocol address (host:port or unix:<path>) to read features through,
+    /// instead of HTTP
+    #[argh(option, short = 's')]
+    socket: Option<String>,
+
+    /// read features of a brand-new identity with every request, so that each one goes
+    /// through distribution (and segment evaluation) instead of reading variants already
+    /// assigned
+    #[argh(switch)]
+    fresh: bool,
 }
 
+/// Number of most recent requests the latency summary is computed over.
+const LATENCY_WINDOW: usize = 1000;
+
 static IDX: AtomicUsize = AtomicUsize::new(0);
 
+/// Latencies of the most recent feature requests, summarized along with the buckets.
+#[derive(Default)]
+struct Latencies {
+    count: usize,
+    recent: VecDeque<Duration>,
+}
+
+impl Latencies {
+    fn record(&mut self, latency: Duration) {
+        if self.recent.len() >= LATENCY_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(latency);
+        self.count += 1;
+    }
+
+    fn summary(&self) -> Option<String> {
+        let mut sorted: Vec<Duration> = self.recent.iter().copied().collect();
+        sorted.sort();
+        let percentile = |p: usize| sorted[(sorted.len() - 1) * p / 100];
+        let mean = sorted
+            .iter()
+            .sum::<Duration>()
+            .checked_div(sorted.len() as u32)?;
+
+        Some(format!(
+            "{} requests, last {}: mean {:.2?}, p50 {:.2?}, p99 {:.2?}",
+            self.count,
+            sorted.len(),
+            mean,
+            percentile(50),
+            percentile(99)
+        ))
+    }
+}
+
 fn feature_value(response: Vec<FeatureResponse>, feature_name: &str) -> Option<FeatureValue> {
     response
         .into_iter()
@@ -89,10 +143,25 @@ pub fn main() -> anyhow::Result<()> {
     );
     println!();
 
+    // Each worker gets its own socket connection, if features are to be read through one.
+    let mut sockets = Vec::with_capacity(args.threads);
+    for _ in 0..args.threads {
+        sockets.push(match &args.socket {
+            Some(addr) => Some(SocketClient::connect(
+                addr,
+                &Auth::from_key_or_env(args.key.clone()),
+                &args.project,
+                &args.environment,
+            )?),
+            None => None,
+        });
+    }
+
     let buckets = Arc::new(RwLock::new(HashMap::new()));
+    let latencies = Arc::new(RwLock::new(Latencies::default()));
     let connection = Arc::new(Connection::init(
         args.host,
-        Auth::None,
+        Auth::from_key_or_env(args.key),
         args.project,
         args.environment,
     )?);
@@ -108,13 +177,15 @@ pub fn main() -> anyhow::Result<()> {
     let enabled = Arc::new(AtomicBool::new(false));
 
     thread::scope(|s| {
-        for _ in 0..args.threads {
+        for mut socket in sockets {
             let idents = Arc::clone(&idents);
             let buckets = Arc::clone(&buckets);
+            let latencies = Arc::clone(&latencies);
             let conn = Arc::clone(&connection);
             let running = Arc::clone(&running);
             let enabled = Arc::clone(&enabled);
             let feature_name = args.feature.as_str();
+            let fresh = args.fresh;
 
             s.spawn(move || {
                 let mut rng = rand::thread_rng();
@@ -124,8 +195,15 @@ pub fn main() -> anyhow::Result<()> {
                         continue;
                     }
                     // TODO: fetch idents_count idents from the pool and generate new ones if needed
-                    if let Some(ident) = get_or_generate_ident(&idents, idents_count, &mut rng)
-                        && let Some(response) = conn.get_features(&ident)
+                    let ident = if fresh {
+                        Some(Ulid::new().to_string())
+                    } else {
+                        get_or_generate_ident(&idents, idents_count, &mut rng)
+                    };
+                    if let Some(ident) = ident
+                        && let Some(response) = timed(&latencies, || {
+                            fetch_features(&conn, socket.as_mut(), &ident)
+                        })
                         && let Some(fv) = feature_value(response, feature_name)
                     {
                         let mut guard = buckets.write().unwrap();
@@ -196,9 +274,21 @@ pub fn main() -> anyhow::Result<()> {
             });
         }
 
+        let summary = m.add(ProgressBar::new_spinner());
+        summary.set_style(ProgressStyle::with_template("{msg}").unwrap());
+
         let mut pbs = HashMap::<String, ProgressBar>::new();
         while running.load(Ordering::Relaxed) {
+            if let Some(msg) = latencies.read().unwrap().summary() {
+                summary.set_message(msg);
+            }
             let guard = buckets.read().unwrap();
+            // Fresh identities never repeat, so there's no fixed pool to compare buckets to.
+            let total = if args.fresh {
+                guard.values().map(HashSet::len).sum()
+            } else {
+                idents_count
+            };
             for (val, set) in guard.iter() {
                 if set.is_empty() {
                     if let Some(pb) = pbs.remove(val) {
@@ -206,15 +296,12 @@ pub fn main() -> anyhow::Result<()> {
                     }
                 } else {
                     let pb = pbs.entry(val.clone()).or_insert_with(|| {
-                        let pb = ProgressBar::new(idents_count as u64);
+                        let pb = ProgressBar::new(total as u64);
                         pb.set_style(sty.clone());
                         m.add(pb)
                     });
-                    pb.set_message(format!(
-                        "{} ({}%)",
-                        val,
-                        ((100 * set.len()) / idents_count) as u64
-                    ));
+                    pb.set_length(total as u64);
+                    pb.set_message(format!("{} ({}%)", val, ((100 * set.len()) / total) as u64));
                     pb.set_position(set.len() as u64);
                 }
             }
@@ -230,6 +317,25 @@ pub fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn fetch_features(
+    conn: &Connection,
+    socket: Option<&mut SocketClient>,
+    ident: &str,
+) -> Option<Vec<FeatureResponse>> {
+    match socket {
+        Some(socket) => socket.get_features(ident).ok(),
+        None => conn.get_features(ident),
+    }
+}
+
+/// Runs `fetch`, recording how long it took.
+fn timed<T>(latencies: &RwLock<Latencies>, fetch: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = fetch();
+    latencies.write().unwrap().record(started.elapsed());
+    result
+}
+
 fn get_or_generate_ident(
     idents: &Arc<RwLock<HashMap<usize, String>>>,
     idents_count: usize,
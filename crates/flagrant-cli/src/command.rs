@@ -29,7 +29,9 @@ pub enum Command {
     Feature,
     Identity,
     Variant,
+    Rollout,
     Segment,
+    Layer,
     Group,
     Rule,
     Set,
@@ -37,6 +39,10 @@ pub enum Command {
     Commit,
     Discard,
     Reset,
+    Schedule,
+    Export,
+    Import,
+    ApiKey,
 }
 
 impl Command {
@@ -84,7 +90,6 @@ impl Command {
     }
 
     /// Builds a no-op (no-operation) version of command.
-    #[allow(dead_code)]
     pub fn no_op(
         &self,
         hint: &str,
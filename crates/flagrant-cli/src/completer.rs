@@ -1,6 +1,7 @@
+use chrono::Utc;
 use flagrant_client::connection::{Connection, Resource};
 use flagrant_repl::{command::Arg, completer::AutoCompleter, session::Session};
-use flagrant_types::{Environment, Feature, IdentityWithTraits, Segment, Tag, Trait};
+use flagrant_types::{Environment, Feature, IdentityWithTraits, Layer, Segment, Tag, Trait};
 
 pub struct ArgCompleter<'a> {
     pub session: &'a Session<Connection>,
@@ -40,7 +41,7 @@ impl AutoCompleter for ArgCompleter<'_> {
                         .into_iter()
                         .map(|t| format!("{}:", t.name))
                         .collect::<Vec<_>>(),
-                    "delete" | "describe" | "use" if arg_n == 2 => ctx
+                    "delete" | "describe" | "explain" | "use" if arg_n == 2 => ctx
                         .client
                         .get::<Vec<IdentityWithTraits>>(
                             env_res.subpath(format!("/identities?prefix={prefix}")),
@@ -48,6 +49,12 @@ impl AutoCompleter for ArgCompleter<'_> {
                         .into_iter()
                         .map(|c| c.value)
                         .collect::<Vec<_>>(),
+                    "explain" if arg_n == 3 => ctx
+                        .client
+                        .get::<Vec<Feature>>(env_res.subpath(format!("/features?prefix={prefix}")))?
+                        .into_iter()
+                        .map(|f| f.name)
+                        .collect::<Vec<_>>(),
                     // Auto-complete trait names for filtering, e.g. `trait:vip` or `trait:-vip`
                     "list" => match prefix.split_once(':') {
                         Some(("trait", val)) => {
@@ -82,6 +89,8 @@ impl AutoCompleter for ArgCompleter<'_> {
 
                 Ok(match op {
                     "status" => filter_by_prefix(&["on", "off", "archived"], prefix),
+                    "visibility" => filter_by_prefix(&["all", "backend"], prefix),
+                    "hashing" => filter_by_prefix(&["off"], prefix),
                     "tags" if arg_n >= 2 => {
                         let ctx = self.session.context.read().unwrap();
                         let res = ctx.env_resource();
@@ -93,6 +102,16 @@ impl AutoCompleter for ArgCompleter<'_> {
                             .map(|t| t.name)
                             .collect::<Vec<_>>()
                     }
+                    "key" if arg_n == 2 => {
+                        let ctx = self.session.context.read().unwrap();
+                        let res = ctx.project.as_base_resource();
+
+                        ctx.client
+                            .get::<Vec<Trait>>(res.subpath(format!("/traits?prefix={prefix}")))?
+                            .into_iter()
+                            .map(|t| t.name)
+                            .collect::<Vec<_>>()
+                    }
                     "trait" if arg_n >= 2 && !prefix.contains('=') => {
                         let ctx = self.session.context.read().unwrap();
                         let res = ctx.project.as_base_resource();
@@ -103,6 +122,14 @@ impl AutoCompleter for ArgCompleter<'_> {
                             .map(|t| format!("{}=", t.name))
                             .collect::<Vec<_>>()
                     }
+                    "trait" if arg_n >= 2 => {
+                        // Suggest current date/time as a value of date/time traits.
+                        let (name, _) = prefix.split_once('=').unwrap_or_default();
+                        let now = format!("{name}=now");
+                        let today = format!("{name}={}", Utc::now().format("%Y-%m-%d"));
+
+                        filter_by_prefix(&[&now, &today], prefix)
+                    }
                     _ => vec![],
                 })
             }
@@ -167,7 +194,7 @@ impl AutoCompleter for ArgCompleter<'_> {
                         }
                     }
                     // Auto-complete feature name
-                    "delete" | "describe" if arg_n == 2 => ctx
+                    "delete" | "describe" | "history" if arg_n == 2 => ctx
                         .client
                         .get::<Vec<Feature>>(res.subpath(format!("/features?prefix={prefix}")))?
                         .into_iter()
@@ -226,8 +253,20 @@ impl AutoCompleter for ArgCompleter<'_> {
                                 .into_iter()
                                 .map(|t| format!("trait:{}", t.name))
                                 .collect()
+                        } else if let Some(segment_prefix) = prefix.strip_prefix("segment:") {
+                            let res = ctx.project_resource();
+                            ctx.client
+                                .get::<Vec<Segment>>(
+                                    res.subpath(format!("/segments?prefix={segment_prefix}")),
+                                )?
+                                .into_iter()
+                                .map(|s| format!("segment:{}", s.name))
+                                .collect()
                         } else {
-                            filter_by_prefix(&["identity", "trait:", "environment"], prefix)
+                            filter_by_prefix(
+                                &["identity", "trait:", "environment", "bucket:", "segment:"],
+                                prefix,
+                            )
                         }
                     }
                     "add" if arg_n == 4 => filter_by_prefix(
@@ -242,6 +281,16 @@ impl AutoCompleter for ArgCompleter<'_> {
                             "lower-equal-than",
                             "in",
                             "not-in",
+                            "matches-regex",
+                            "does-not-match-regex",
+                            "version-greater-than",
+                            "version-greater-equal-than",
+                            "version-lower-than",
+                            "version-lower-equal-than",
+                            "before",
+                            "after",
+                            "older-than",
+                            "newer-than",
                         ],
                         prefix,
                     ),
@@ -266,6 +315,9 @@ impl AutoCompleter for ArgCompleter<'_> {
                                 .map(|e| e.name)
                                 .collect()
                         }
+                        Some(driver) if driver.starts_with("segment:") => {
+                            filter_by_prefix(&["true", "false"], prefix)
+                        }
                         _ => vec![],
                     },
                     _ => vec![],
@@ -274,11 +326,22 @@ impl AutoCompleter for ArgCompleter<'_> {
             "GROUP" if arg_n >= 2 => {
                 let op: &str = &args[1];
                 Ok(match op {
-                    "add" if arg_n == 2 => filter_by_prefix(&["--and", "--and-not"], prefix),
+                    "add" if arg_n == 2 => {
+                        filter_by_prefix(&["--and", "--and-not", "--or", "--or-not"], prefix)
+                    }
                     "describe" | "delete" if arg_n == 2 => filter_by_prefix(&["group-"], prefix),
                     _ => vec![],
                 })
             }
+            "SCHEDULE" if arg_n >= 2 => {
+                let op: &str = &args[1];
+                Ok(match op {
+                    "list" if arg_n == 2 => {
+                        filter_by_prefix(&["pending", "applied", "failed", "cancelled"], prefix)
+                    }
+                    _ => vec![],
+                })
+            }
             "SEGMENT" if arg_n >= 2 => {
                 let ctx = self.session.context.read().unwrap();
                 let res = ctx.project_resource();
@@ -300,6 +363,22 @@ impl AutoCompleter for ArgCompleter<'_> {
                     _ => vec![],
                 })
             }
+            "LAYER" if arg_n >= 2 => {
+                let ctx = self.session.context.read().unwrap();
+                let res = ctx.env_resource();
+                let op: &str = &args[1];
+
+                Ok(match op {
+                    "describe" | "delete" | "assign" | "remove" if arg_n == 2 => ctx
+                        .client
+                        .get::<Vec<Layer>>(res.subpath("/layers"))?
+                        .into_iter()
+                        .map(|l| l.name)
+                        .filter(|name| name.starts_with(prefix))
+                        .collect::<Vec<_>>(),
+                    _ => vec![],
+                })
+            }
             _ => Ok(vec![]),
         }
     }
@@ -0,0 +1,90 @@
+//! REPL command handlers for API keys management.
+//!
+//! | Command          | Handler    | Description                                     |
+//! |------------------|------------|-------------------------------------------------|
+//! | `APIKEY add`     | [`add`]    | Create a new API key for the current project.   |
+//! | `APIKEY list`    | [`list`]   | Print API keys of the current project.          |
+//! | `APIKEY delete`  | [`delete`] | Revoke an API key.                              |
+
+use anyhow::{anyhow, bail};
+use colored::Colorize;
+use flagrant_client::connection::Connection;
+use flagrant_repl::{command::Arg, session::Session};
+use flagrant_types::{
+    ApiKey, ApiKeyKind,
+    payload::{ApiKeyCreatedResponse, NewApiKeyPayload},
+};
+
+use crate::printer::tabular::Tabular;
+
+/// Create a new API key for the current project.
+///
+/// Expects args: `<name> <client|management> [--env]`
+///
+/// With `--env` the key is limited to the current environment. The key itself is printed
+/// only once and can't be retrieved later on.
+pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No API key name provided.")
+    };
+    let kind = match args.get(2).map(|a| a.0) {
+        Some("client") => ApiKeyKind::Client,
+        Some("management") => ApiKeyKind::Management,
+        Some(other) => bail!("Unknown API key kind: {other}"),
+        None => bail!("No API key kind provided (client or management)."),
+    };
+    let env_scoped = match args.get(3).map(|a| a.0) {
+        Some("--env") => true,
+        Some(other) => bail!("Unknown option: {other}"),
+        None => false,
+    };
+
+    let ctx = session.context.read().unwrap();
+    let created = ctx
+        .client
+        .post::<_, ApiKeyCreatedResponse>(
+            ctx.project_resource().subpath("/api-keys"),
+            NewApiKeyPayload {
+                name: name.to_string(),
+                kind,
+                environment_id: env_scoped.then_some(ctx.environment.id),
+            },
+        )
+        .map_err(|err| anyhow!("Could not create an API key: {err}"))?;
+
+    created.api_key.describe(None, &());
+    println!("\n{}\n", created.key.bold());
+    println!("Store the key now - it won't be shown again.");
+    Ok(())
+}
+
+/// List API keys of the current project.
+pub fn list(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let ctx = session.context.read().unwrap();
+    let keys = ctx
+        .client
+        .get::<Vec<ApiKey>>(ctx.project_resource().subpath("/api-keys"))?;
+
+    ApiKey::list(&keys);
+    Ok(())
+}
+
+/// Revoke an API key.
+///
+/// Expects args: `<id>`
+pub fn delete(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(id) = args.get(1) else {
+        bail!("No API key provided.")
+    };
+    let Ok(id) = id.parse::<i32>() else {
+        bail!("API key should be a number, as listed by \"APIKEY list\".")
+    };
+
+    let ctx = session.context.read().unwrap();
+    ctx.client
+        .delete(ctx.project_resource().subpath(format!("/api-keys/{id}")))
+        .map_err(|err| anyhow!("Could not revoke an API key: {err}"))?;
+
+    println!("API key {id} revoked.");
+    Ok(())
+}
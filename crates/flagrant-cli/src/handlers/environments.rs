@@ -7,12 +7,18 @@
 //! | `ENV add`     | [`add`]    | Create a new environment in the project. |
 //! | `ENV list`    | [`list`]   | Print all environments in the project.   |
 //! | `ENV use`     | [`r#use`]  | Switch the active environment.           |
+//! | `ENV freeze`  | [`freeze`] | Engage kill switch of the environment.   |
+//! | `ENV thaw`    | [`thaw`]   | Release kill switch of the environment.  |
+//! | `ENV history` | [`history`]| Print kill switch changes.               |
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 use colored::Colorize;
 use flagrant_client::connection::{Connection, Resource};
 use flagrant_repl::{command::Arg, session::Session};
-use flagrant_types::{Environment, payload::NewEnvironmentPayload};
+use flagrant_types::{
+    Environment, Revision,
+    payload::{FreezePayload, NewEnvironmentPayload},
+};
 
 use crate::printer::tabular::Tabular;
 
@@ -94,3 +100,67 @@ pub fn r#use(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()>
     }
     bail!("No environment name provided.");
 }
+
+/// Engage kill switch of the current environment: all of its features - or the ones tagged
+/// with `tag` only - serve their control value until the environment thaws.
+///
+/// Expects args: `[tag]`
+pub fn freeze(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let mut ctx = session.context.write().unwrap();
+    let path = ctx.env_resource().subpath("/freeze");
+    ctx.client
+        .put(
+            path,
+            FreezePayload {
+                tag: args.get(1).map(|t| t.to_string()),
+            },
+        )
+        .map_err(|err| anyhow!("Could not freeze environment: {err}"))?;
+
+    let res = ctx.project.as_base_resource();
+    let env = ctx
+        .client
+        .get::<Environment>(res.subpath(format!("/envs/{}", ctx.environment.id)))?;
+    ctx.environment = env;
+
+    match &ctx.environment.frozen_tag {
+        Some(tag) => println!(
+            "{} features tagged {} serve their control value now.",
+            "Frozen:".red().bold(),
+            tag.bold()
+        ),
+        None => println!(
+            "{} all features serve their control value now.",
+            "Frozen:".red().bold()
+        ),
+    }
+    Ok(())
+}
+
+/// Release kill switch of the current environment, letting its features serve their
+/// variants again.
+pub fn thaw(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let mut ctx = session.context.write().unwrap();
+    let path = ctx.env_resource().subpath("/freeze");
+    ctx.client
+        .delete(path)
+        .map_err(|err| anyhow!("Could not thaw environment: {err}"))?;
+
+    let res = ctx.project.as_base_resource();
+    let env = ctx
+        .client
+        .get::<Environment>(res.subpath(format!("/envs/{}", ctx.environment.id)))?;
+    ctx.environment = env;
+
+    println!("Environment {} thawed.", ctx.environment.name.bold());
+    Ok(())
+}
+
+/// Print recorded kill switch changes of the current environment, newest first.
+pub fn history(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let ctx = session.context.read().unwrap();
+    let path = ctx.env_resource().subpath("/history");
+
+    Revision::list(&ctx.client.get::<Vec<Revision>>(path)?);
+    Ok(())
+}
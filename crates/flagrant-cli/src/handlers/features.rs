@@ -10,11 +10,19 @@
 //! | `FEATURE use`        | [`r#use`]              | Switch into a feature context.                      |
 //! | `FEATURE describe`   | [`describe`]           | Print details of a feature.                         |
 //! | `FEATURE delete`     | [`delete`]             | Delete a feature.                                   |
+//! | `FEATURE history`    | [`history`]            | List recorded changes of a feature.                 |
+//! | `FEATURE revert`     | [`revert`]             | Restore a feature to its state before a revision.   |
 //! | `SET status`         | [`set_status`]         | Stage a feature status (`on` / `off` / 'archived'). |
 //! | `SET description`    | [`set_description`]    | Stage a feature description.                        |
 //! | `SET tags`           | [`set_tags`]           | Stage adding tags to a feature.                     |
+//! | `SET visibility`     | [`set_visibility`]     | Stage a feature visibility (`all` / `backend`).     |
+//! | `SET requires`       | [`set_requires`]       | Stage a prerequisite on another feature's variant.  |
+//! | `SET hashing`        | [`set_hashing`]        | Stage a switch to (or from) hash distribution.      |
+//! | `SET key`            | [`set_key`]            | Stage a trait to distribute identities by.          |
 //! | `UNSET distribution` | [`unset_distribution`] | Clear variant assignments matching a pattern.       |
 //! | `UNSET tags`         | [`unset_tags`]         | Stage removing tags from a feature.                 |
+//! | `UNSET requires`     | [`unset_requires`]     | Stage removing a prerequisite from a feature.       |
+//! | `UNSET key`          | [`unset_key`]          | Stage distributing by identity value again.         |
 //! | `COMMIT`             | [`commit`]             | Send all staged changes to the API.                 |
 //! | `DISCARD`            | [`discard`]            | Drop all staged changes for the current feature.    |
 
@@ -24,8 +32,8 @@ use anyhow::bail;
 use flagrant_client::connection::Connection;
 use flagrant_repl::{command::Arg, session::Session};
 use flagrant_types::{
-    Feature, FeatureOverride, FeatureValue,
-    payload::{NewFeaturePayload, SegmentPatchOp},
+    Distribution, Feature, FeatureOverride, FeatureValue, FeatureVisibility, Revision, RolloutPlan,
+    payload::{NewFeaturePayload, PrerequisitePatchOp, SegmentPatchOp},
 };
 
 use crate::{
@@ -56,6 +64,15 @@ fn fetch_overrides(feature_id: i32, session: &Session<Connection>) -> Vec<Featur
         .unwrap_or_default()
 }
 
+fn fetch_rollouts(feature_id: i32, session: &Session<Connection>) -> Vec<RolloutPlan> {
+    let ctx = session.context.read().unwrap();
+    let res = ctx.env_resource();
+
+    ctx.client
+        .get::<Vec<RolloutPlan>>(res.subpath(format!("/features/{feature_id}/rollouts")))
+        .unwrap_or_default()
+}
+
 /// Create a new feature in the current environment.
 ///
 /// Expected args: `<feature> [value] [description]`
@@ -81,6 +98,7 @@ pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
                     name: name.to_string(),
                     description: args.get(3).map(|d| d.to_string()),
                     is_enabled: false,
+                    visibility: FeatureVisibility::All,
                     value: parsed,
                 },
             )?
@@ -120,7 +138,11 @@ pub fn r#use(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()>
             .map_err(|_| anyhow::anyhow!("Feature '{}' not found.", feature_name))?;
 
         let overrides = fetch_overrides(feature.id, session);
-        feature.describe(None, &OverridesContext::committed_only(overrides));
+        let rollouts = fetch_rollouts(feature.id, session);
+        feature.describe(
+            None,
+            &OverridesContext::committed_only(overrides).with_rollouts(rollouts),
+        );
         {
             let mut ctx = session.context.write().unwrap();
             ctx.feature = Some(feature);
@@ -157,8 +179,12 @@ pub fn describe(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<(
 
         let feature = fetch_feature(name, session)?;
         let overrides = fetch_overrides(feature.id, session);
+        let rollouts = fetch_rollouts(feature.id, session);
 
-        feature.describe(None, &OverridesContext::committed_only(overrides));
+        feature.describe(
+            None,
+            &OverridesContext::committed_only(overrides).with_rollouts(rollouts),
+        );
         return Ok(());
     }
 
@@ -167,6 +193,7 @@ pub fn describe(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<(
     })?;
     let patch = ctx.feature_patch.as_ref().filter(|p| !p.is_empty());
     let overrides = fetch_overrides(feature.id, session);
+    let rollouts = fetch_rollouts(feature.id, session);
 
     let identity_pending = ctx.identity_patch.as_ref().and_then(|ipatch| {
         let identity_value = ctx.identity.as_ref()?.value.clone();
@@ -211,6 +238,7 @@ pub fn describe(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<(
             committed: overrides,
             identity_pending,
             segment_pending,
+            rollouts,
         },
     );
     drop(ctx);
@@ -264,6 +292,77 @@ pub fn set_description(args: &[Arg], session: &Session<Connection>) -> anyhow::R
     Ok(())
 }
 
+/// Stage a feature visibility change.
+///
+/// Expected args: `all` or `backend`
+pub fn set_visibility(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let visibility = match args.get(1).map(|a| a.to_lowercase()).as_deref() {
+        Some("all") => FeatureVisibility::All,
+        Some("backend") => FeatureVisibility::Backend,
+        Some(other) => bail!("Unknown visibility: {other}"),
+        None => bail!("No visibility provided (all or backend)."),
+    };
+    let mut ctx = session.context.write().unwrap();
+
+    if ctx.feature.is_none() {
+        bail!("Not in a feature context. Use \"FEATURE use ...\" to set a context.");
+    }
+    ctx.get_or_init_pending().visibility = Some(visibility);
+    println!(
+        "Staged: visibility = {}",
+        match visibility {
+            FeatureVisibility::All => "ALL",
+            FeatureVisibility::Backend => "BACKEND",
+        }
+    );
+    Ok(())
+}
+
+/// Stage a switch to hash distribution, or back to sticky distribution.
+///
+/// Expected args: `[salt]` or `off`
+///
+/// Salt defaults to the feature name. Changing the salt reshuffles identities across
+/// variants, keeping it lets them stay where they are whatever else changes.
+pub fn set_hashing(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let mut ctx = session.context.write().unwrap();
+    let Some(feature) = ctx.feature.as_ref() else {
+        bail!("Not in a feature context. Use \"FEATURE use ...\" to set a context.");
+    };
+    let distribution = match args.get(1).map(|a| a.to_string()) {
+        Some(off) if off.eq_ignore_ascii_case("off") => Distribution::Sticky,
+        Some(salt) => Distribution::Hash { salt },
+        None => Distribution::Hash {
+            salt: feature.name.clone(),
+        },
+    };
+    match &distribution {
+        Distribution::Sticky => println!("Staged: distribution = STICKY"),
+        Distribution::Hash { salt } => println!("Staged: distribution = HASH (salt: {salt})"),
+    }
+    ctx.get_or_init_pending().distribution = Some(distribution);
+    Ok(())
+}
+
+/// Stage a distribution key of the current feature, within current environment.
+///
+/// Expected args: `trait`
+///
+/// Identities sharing a value of the trait get the same variant, identities lacking it get
+/// the control value.
+pub fn set_key(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(key) = args.get(1) else {
+        bail!("No trait provided.");
+    };
+    let mut ctx = session.context.write().unwrap();
+    if ctx.feature.is_none() {
+        bail!("Not in a feature context. Use \"FEATURE use ...\" to set a context.");
+    }
+    ctx.get_or_init_pending().distribution_key = Some(key.to_string());
+    println!("Staged: distribution key = {key}");
+    Ok(())
+}
+
 /// Stage adding one or more tags to the current feature.
 ///
 /// Expected args: `tag1[, tag2, ...]`
@@ -321,6 +420,92 @@ pub fn unset_tags(args: &[Arg], session: &Session<Connection>) -> anyhow::Result
     Ok(())
 }
 
+/// Stage a prerequisite of the current feature: the feature applies to an identity only if
+/// feature named `feature` resolves to its variant of given value for that identity.
+///
+/// Expected args: `feature value`
+///
+/// Replaces the variant previously required of the same feature, if any. Prerequisites are
+/// scoped to the current environment.
+pub fn set_requires(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No required feature provided.");
+    };
+    let raw = args[2..]
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if raw.is_empty() {
+        bail!("No required value provided.");
+    }
+    if session.context.read().unwrap().feature.is_none() {
+        bail!("Not in a feature context. Use \"FEATURE use ...\" to set a context.");
+    }
+
+    let required = fetch_feature(name, session)?;
+    let parsed = raw.parse().unwrap_or_else(|_| FeatureValue::build(&raw));
+    let Some(variant) = required.variants.iter().find(|v| v.value == parsed) else {
+        bail!(
+            "Feature {} has no variant of value {parsed}.",
+            required.name
+        );
+    };
+
+    let mut ctx = session.context.write().unwrap();
+    stage::stage_prerequisite(
+        ctx.get_or_init_pending(),
+        PrerequisitePatchOp::Set {
+            feature_id: required.id,
+            variant_id: variant.id,
+        },
+    );
+    println!("Staged: requires {} = {parsed}", required.name);
+    Ok(())
+}
+
+/// Stage removing a prerequisite of the current feature.
+///
+/// Expected args: `feature`
+pub fn unset_requires(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No required feature provided.");
+    };
+    let mut ctx = session.context.write().unwrap();
+    let Some(feature) = ctx.feature.as_ref() else {
+        bail!("Not in a feature context. Use \"FEATURE use ...\" to set a context.");
+    };
+    let Some(required_id) = feature
+        .prerequisites
+        .iter()
+        .find(|p| p.feature_name == name.as_ref())
+        .map(|p| p.feature_id)
+    else {
+        bail!("Feature doesn't require {name}.");
+    };
+
+    stage::stage_prerequisite(
+        ctx.get_or_init_pending(),
+        PrerequisitePatchOp::Unset {
+            feature_id: required_id,
+        },
+    );
+    println!("Staged: unset requires {name}");
+    Ok(())
+}
+
+/// Stage removing distribution key of the current feature, within current environment,
+/// so that identities get distributed by their values again.
+pub fn unset_key(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let mut ctx = session.context.write().unwrap();
+    if ctx.feature.is_none() {
+        bail!("Not in a feature context. Use \"FEATURE use ...\" to set a context.");
+    }
+    ctx.get_or_init_pending().distribution_key = Some(String::new());
+    println!("Staged: unset distribution key");
+    Ok(())
+}
+
 /// Parses a list of tag names out of REPL args, splitting on commas and/or whitespace.
 /// Deduplicates and sorts the result.
 fn parse_tags(args: &[Arg]) -> Vec<String> {
@@ -364,6 +549,7 @@ pub fn commit(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()
             matches!(op,
                 SegmentPatchOp::SetFeatureOverride { feature_id: fid, .. }
                 | SegmentPatchOp::UnsetFeatureOverride { feature_id: fid, .. }
+                | SegmentPatchOp::SetFeaturePriority { feature_id: fid, .. }
                 if *fid == feature_id
             )
         })
@@ -386,8 +572,18 @@ pub fn commit(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()
             .client
             .get::<Vec<FeatureOverride>>(overrides_path)
             .unwrap_or_default();
+        let rollouts_path = ctx
+            .env_resource()
+            .subpath(format!("/features/{}/rollouts", updated.id));
+        let rollouts = ctx
+            .client
+            .get::<Vec<RolloutPlan>>(rollouts_path)
+            .unwrap_or_default();
 
-        updated.describe(None, &OverridesContext::committed_only(overrides));
+        updated.describe(
+            None,
+            &OverridesContext::committed_only(overrides).with_rollouts(rollouts),
+        );
     }
 
     ctx.feature_patch = None;
@@ -406,7 +602,11 @@ pub fn commit(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()
 pub(crate) fn describe_by_id(feature_id: i32, session: &Session<Connection>) -> anyhow::Result<()> {
     let updated = fetch_feature(&feature_id.to_string(), session)?;
     let overrides = fetch_overrides(updated.id, session);
-    updated.describe(None, &OverridesContext::committed_only(overrides));
+    let rollouts = fetch_rollouts(updated.id, session);
+    updated.describe(
+        None,
+        &OverridesContext::committed_only(overrides).with_rollouts(rollouts),
+    );
 
     let mut ctx = session.context.write().unwrap();
     if ctx.feature.as_ref().is_some_and(|f| f.id == updated.id) {
@@ -491,6 +691,82 @@ pub fn delete(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()>
     bail!("No feature name or value provided.")
 }
 
+/// List changes recorded for a feature in the current environment, newest first.
+///
+/// Expected args: `[feature]`
+///
+/// Lists history of the feature in context if no feature name is provided.
+pub fn history(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let feature_id = match args.get(1) {
+        Some(name) => fetch_feature(name, session)?.id,
+        None => {
+            let ctx = session.context.read().unwrap();
+            ctx.feature.as_ref().map(|f| f.id).ok_or_else(|| {
+                anyhow::anyhow!("No feature name provided and not within a feature context.")
+            })?
+        }
+    };
+    let ctx = session.context.read().unwrap();
+    let path = ctx
+        .env_resource()
+        .subpath(format!("/features/{feature_id}/history"));
+
+    Revision::list(&ctx.client.get::<Vec<Revision>>(path)?);
+    Ok(())
+}
+
+/// Restore the current feature to the state it had right before given revision.
+///
+/// Expected args: `<revision>`
+///
+/// Restoration is applied at once on the API side and shows up in `FEATURE history`
+/// as a new revision. Fails if there are uncommitted staged changes.
+pub fn revert(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(revision) = args.get(1) else {
+        bail!("No revision provided.")
+    };
+    let Ok(revision_id) = revision.parse::<i32>() else {
+        bail!("Revision should be a number, as listed by \"FEATURE history\".")
+    };
+    stage::ensure_no_pending(session)?;
+
+    let mut ctx = session.context.write().unwrap();
+    let feature_id = ctx.feature.as_ref().map(|f| f.id).ok_or_else(|| {
+        anyhow::anyhow!("Not within a feature context. Use \"FEATURE use ...\" to set a context.")
+    })?;
+    let path = ctx.env_resource().subpath(format!(
+        "/features/{feature_id}/history/{revision_id}/revert"
+    ));
+    let reverted = ctx
+        .client
+        .post::<_, Feature>(path, ())
+        .map_err(|err| anyhow::anyhow!("Feature revert failed: {err}"))?;
+
+    let overrides_path = ctx
+        .env_resource()
+        .subpath(format!("/features/{}/overrides", reverted.id));
+    let overrides = ctx
+        .client
+        .get::<Vec<FeatureOverride>>(overrides_path)
+        .unwrap_or_default();
+    let rollouts_path = ctx
+        .env_resource()
+        .subpath(format!("/features/{}/rollouts", reverted.id));
+    let rollouts = ctx
+        .client
+        .get::<Vec<RolloutPlan>>(rollouts_path)
+        .unwrap_or_default();
+
+    reverted.describe(
+        None,
+        &OverridesContext::committed_only(overrides).with_rollouts(rollouts),
+    );
+    ctx.feature = Some(reverted);
+    index::rebuild(&mut ctx);
+
+    Ok(())
+}
+
 /// Clears the current feature's variant assignments for every identity matching `pattern`,
 /// freeing them to be redistributed on the next evaluation.
 ///
@@ -2,7 +2,7 @@
 //!
 //! | Command                          | Handler      | Description                                          |
 //! |----------------------------------|--------------|------------------------------------------------------|
-//! | `GROUP add [--and|--and-not]`    | [`add`]      | Stage a new group on the current segment.            |
+//! | `GROUP add [--and|--or|...]`     | [`add`]      | Stage a new group on the current segment.            |
 //! | `GROUP describe <label>`         | [`describe`] | Print details of a group with its rules.             |
 //! | `GROUP delete <label>`           | [`delete`]   | Stage a group deletion by label.                     |
 
@@ -15,7 +15,10 @@ use crate::printer::tabular::Tabular;
 
 /// Stage a group addition for the current segment.
 ///
-/// Expected args: `[--and|--and-not] [description]`
+/// Expected args: `[--and|--and-not|--or|--or-not] [description]`
+///
+/// AND/AND-NOT groups bind tighter than OR/OR-NOT ones, e.g. groups `a`, `--and b` and
+/// `--or c` match identities matching `(a AND b) OR c`.
 pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
     let segment = segment_from_ctx(session)?;
     let (connector, description) = match args.get(1).map(|a| a.as_ref()) {
@@ -27,6 +30,11 @@ pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
             Some(GroupConnector::AndNot),
             args.get(2).map(|d| d.to_string()),
         ),
+        Some("--or") => (Some(GroupConnector::Or), args.get(2).map(|d| d.to_string())),
+        Some("--or-not") => (
+            Some(GroupConnector::OrNot),
+            args.get(2).map(|d| d.to_string()),
+        ),
         other => (None, other.map(str::to_string)),
     };
 
@@ -44,6 +52,8 @@ pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
     let connector_hint = match &connector {
         Some(GroupConnector::And) => " (AND ...)",
         Some(GroupConnector::AndNot) => " (AND NOT ...)",
+        Some(GroupConnector::Or) => " (OR ...)",
+        Some(GroupConnector::OrNot) => " (OR NOT ...)",
         None => "",
     };
 
@@ -7,6 +7,7 @@
 //! | `IDENTITY describe`            | [`describe`]    | Print details of an identity with its traits.       |
 //! | `IDENTITY delete`              | [`delete`]      | Delete identities matching a pattern (`*` wildcard).|
 //! | `IDENTITY use`                 | [`r#use`]       | Switch into an identity context.                    |
+//! | `IDENTITY explain`             | [`explain`]     | Explain why an identity gets its feature values.    |
 //! | `SET trait <name=value ...>`   | [`set_trait`]   | Stage one or more trait value changes.              |
 //! | `SET override [value]`         | [`set_override`]| Pin the identity to a specific feature variant.     |
 //! | `UNSET trait <name>`           | [`unset_trait`] | Stage a trait removal for the current identity.     |
@@ -17,10 +18,12 @@
 use std::ops::Deref;
 
 use anyhow::bail;
+use chrono::Utc;
 use flagrant_client::connection::Connection;
 use flagrant_repl::{command::Arg, session::Session};
 use flagrant_types::{
     Feature, FeatureValue, IdentityVariant, IdentityWithTraits, TraitValue,
+    explain::FeatureExplanation,
     payload::{
         FeaturePatch, IdentityOverridePatch, IdentityTraitPayload, NewIdentityPayload,
         VariantPatchOp,
@@ -56,12 +59,43 @@ pub fn describe(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<(
     Ok(())
 }
 
+/// Explain why an identity gets the feature values it does - pinned, pending migration,
+/// matching a segment (with its groups and rules) or distributed organically. Nothing
+/// gets distributed by explaining.
+///
+/// Expected args: `[identity] [feature]` - the identity and feature in context by default.
+pub fn explain(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let ctx = session.context.read().unwrap();
+    let identity = match args.get(1) {
+        Some(identity) => identity.to_string(),
+        None => match &ctx.identity {
+            Some(identity) => identity.value.clone(),
+            None => bail!("No identity provided."),
+        },
+    };
+    let feature = match args.get(2) {
+        Some(feature) => Some(feature.to_string()),
+        None => ctx.feature.as_ref().map(|f| f.name.clone()),
+    };
+    let path = match feature {
+        Some(feature) => format!("/identities/{identity}/explain?feature={feature}"),
+        None => format!("/identities/{identity}/explain"),
+    };
+    let explanations = ctx
+        .client
+        .get::<Vec<FeatureExplanation>>(ctx.env_resource().subpath(path))?;
+
+    FeatureExplanation::list(&explanations);
+    Ok(())
+}
+
 /// Create or upsert an identity with optional traits, then switch into its context.
 ///
 /// Expected args: `<identity> [trait:value ...]`
 ///
 /// Traits are separated by spaces; each in `name:value` form. Values are
-/// auto-typed (bool → i32 → f32 → str).
+/// auto-typed (bool → i32 → f32 → version → datetime → str), with `now`
+/// standing for the current date/time.
 pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
     if let Some(identity_str) = args.get(1) {
         stage::ensure_no_pending(session)?;
@@ -71,7 +105,7 @@ pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
                 let (name, value) = arg.split_once(':')?;
                 Some(IdentityTraitPayload {
                     name: name.to_owned(),
-                    value: Some(TraitValue::build(value)),
+                    value: Some(parse_trait_value(value)),
                 })
             })
             .collect();
@@ -187,7 +221,8 @@ pub(crate) fn switch_to(identity_str: &str, session: &Session<Connection>) -> an
 ///
 /// Expected args: `trait <name=value> [name=value ...]`
 ///
-/// Each value is auto-typed (bool → i32 → f32 → str).
+/// Each value is auto-typed (bool → i32 → f32 → version → datetime → str), with `now`
+/// standing for the current date/time.
 pub fn set_trait(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
     let pairs: Vec<(&str, &str)> = args[1..]
         .iter()
@@ -205,7 +240,7 @@ pub fn set_trait(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<
 
         for (name, value) in pairs {
             let trait_exists = existing.iter().any(|n| n == name);
-            let trait_value = TraitValue::build(value);
+            let trait_value = parse_trait_value(value);
             stage::stage_trait(patch, trait_exists, name.to_string(), trait_value);
         }
         return Ok(());
@@ -213,6 +248,14 @@ pub fn set_trait(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<
     bail!("Not in an identity context. Use `IDENTITY use <identity>` first.");
 }
 
+/// Builds a typed trait value out of its raw form, resolving `now` to the current date/time.
+fn parse_trait_value(raw: &str) -> TraitValue {
+    match raw {
+        "now" => TraitValue::DateTime(Utc::now()),
+        _ => TraitValue::build(raw),
+    }
+}
+
 /// Stage a trait removal for the current identity.
 ///
 /// Expected args: `trait <name>`
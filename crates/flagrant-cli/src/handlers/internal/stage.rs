@@ -6,7 +6,9 @@ use flagrant_client::connection::{Connection, VariantRef};
 use flagrant_repl::{command::Arg, session::Session};
 use flagrant_types::{
     FeatureValue, TraitValue,
-    payload::{FeaturePatch, IdentityPatch, TagPatchOp, TraitPatchOp, VariantPatchOp},
+    payload::{
+        FeaturePatch, IdentityPatch, PrerequisitePatchOp, TagPatchOp, TraitPatchOp, VariantPatchOp,
+    },
 };
 
 use crate::handlers::{features, identities, segments};
@@ -164,6 +166,26 @@ pub(crate) fn stage_tag(pending: &mut FeaturePatch, tag: String, add: bool) {
     }
 }
 
+/// Stages a prerequisite change on a feature patch.
+///
+/// If a pending op for the same required feature already exists, it is replaced - so
+/// staging `Unset` after a pending `Set` cancels the requirement out, and vice versa.
+pub(crate) fn stage_prerequisite(pending: &mut FeaturePatch, op: PrerequisitePatchOp) {
+    let required_id = |op: &PrerequisitePatchOp| match op {
+        PrerequisitePatchOp::Set { feature_id, .. } | PrerequisitePatchOp::Unset { feature_id } => {
+            *feature_id
+        }
+    };
+    match pending
+        .prerequisites
+        .iter_mut()
+        .find(|o| required_id(o) == required_id(&op))
+    {
+        Some(slot) => *slot = op,
+        None => pending.prerequisites.push(op),
+    }
+}
+
 /// Stages a trait value change on an identity patch.
 ///
 /// Uses `SetValue` if the trait already exists on the identity, `Add` otherwise.
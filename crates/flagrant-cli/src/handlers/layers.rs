@@ -0,0 +1,149 @@
+//! REPL command handlers for exclusion layers management.
+//!
+//! | Command           | Handler      | Description                                                  |
+//! |-------------------|--------------|--------------------------------------------------------------|
+//! | `LAYER add`       | [`add`]      | Create a new exclusion layer in the current environment.     |
+//! | `LAYER list`      | [`list`]     | List exclusion layers of the current environment.            |
+//! | `LAYER describe`  | [`describe`] | Print a layer along with shares of its features.             |
+//! | `LAYER delete`    | [`delete`]   | Remove an exclusion layer.                                   |
+//! | `LAYER assign`    | [`assign`]   | Give the current feature a share of a layer's traffic.       |
+//! | `LAYER remove`    | [`remove`]   | Take the current feature out of a layer, releasing its share.|
+
+use anyhow::{anyhow, bail};
+use flagrant_client::connection::Connection;
+use flagrant_repl::{command::Arg, session::Session};
+use flagrant_types::{
+    Layer,
+    payload::{LayerSharePayload, NewLayerPayload},
+};
+
+use crate::printer::tabular::Tabular;
+
+/// Create a new exclusion layer in the current environment.
+///
+/// Expects args: `<name> [description]`
+pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No layer name provided.")
+    };
+    let description = args.get(2).map(|d| d.to_string());
+
+    let ctx = session.context.read().unwrap();
+    let layer = ctx
+        .client
+        .post::<_, Layer>(
+            ctx.env_resource().subpath("/layers"),
+            NewLayerPayload {
+                name: name.to_string(),
+                description,
+            },
+        )
+        .map_err(|err| anyhow!("Could not create a layer: {err}"))?;
+
+    layer.describe(None, &());
+    Ok(())
+}
+
+/// List exclusion layers of the current environment.
+pub fn list(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let ctx = session.context.read().unwrap();
+    let layers = ctx
+        .client
+        .get::<Vec<Layer>>(ctx.env_resource().subpath("/layers"))?;
+
+    Layer::list(&layers);
+    Ok(())
+}
+
+/// Print a layer along with shares of its features.
+///
+/// Expects args: `<name>`
+pub fn describe(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No layer name provided.")
+    };
+
+    let ctx = session.context.read().unwrap();
+    let layer = ctx
+        .client
+        .get::<Layer>(ctx.env_resource().subpath(format!("/layers/{name}")))?;
+
+    layer.describe(None, &());
+    Ok(())
+}
+
+/// Remove an exclusion layer. Features of the layer stay as they are, they just stop
+/// excluding each other.
+///
+/// Expects args: `<name>`
+pub fn delete(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No layer name provided.")
+    };
+
+    let ctx = session.context.read().unwrap();
+    ctx.client
+        .delete(ctx.env_resource().subpath(format!("/layers/{name}")))
+        .map_err(|err| anyhow!("Could not remove a layer: {err}"))?;
+
+    println!("Layer {name} removed.");
+    Ok(())
+}
+
+/// Give the current feature a share (in percent) of a layer's traffic. Identities falling
+/// outside of the share get the feature's control value.
+///
+/// Expects args: `<name> <share>`
+pub fn assign(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No layer name provided.")
+    };
+    let share = match args.get(2) {
+        Some(share) => share
+            .trim_end_matches('%')
+            .parse::<u8>()
+            .map_err(|_| anyhow!("Share should be a percentage between 1 and 100."))?,
+        None => bail!("No share provided."),
+    };
+
+    let ctx = session.context.read().unwrap();
+    let Some(feature) = ctx.feature.as_ref() else {
+        bail!("Not within a feature context.")
+    };
+    let path = ctx
+        .env_resource()
+        .subpath(format!("/layers/{name}/features/{}", feature.id));
+    ctx.client
+        .put(path, LayerSharePayload { share })
+        .map_err(|err| anyhow!("Could not assign feature to layer: {err}"))?;
+
+    let layer = ctx
+        .client
+        .get::<Layer>(ctx.env_resource().subpath(format!("/layers/{name}")))?;
+
+    layer.describe(None, &());
+    Ok(())
+}
+
+/// Take the current feature out of a layer, releasing its share.
+///
+/// Expects args: `<name>`
+pub fn remove(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(name) = args.get(1) else {
+        bail!("No layer name provided.")
+    };
+
+    let ctx = session.context.read().unwrap();
+    let Some(feature) = ctx.feature.as_ref() else {
+        bail!("Not within a feature context.")
+    };
+    let path = ctx
+        .env_resource()
+        .subpath(format!("/layers/{name}/features/{}", feature.id));
+    ctx.client
+        .delete(path)
+        .map_err(|err| anyhow!("Could not remove feature from layer: {err}"))?;
+
+    println!("Feature {} removed from layer {name}.", feature.name);
+    Ok(())
+}
@@ -1,10 +1,15 @@
+pub mod api_keys;
 pub mod environments;
 pub mod features;
 pub mod groups;
 pub mod identities;
+pub mod layers;
 pub mod projects;
+pub mod rollouts;
 pub mod rules;
+pub mod schedules;
 pub mod segments;
+pub mod snapshots;
 pub mod variants;
 
 pub(crate) mod internal;
@@ -0,0 +1,140 @@
+//! REPL command handlers for progressive rollouts of feature variants.
+//!
+//! | Command           | Handler    | Description                                           |
+//! |-------------------|------------|-------------------------------------------------------|
+//! | `ROLLOUT start`   | [`start`]  | Start rolling a variant out step by step.             |
+//! | `ROLLOUT pause`   | [`pause`]  | Pause an active rollout, keeping the current weight.  |
+//! | `ROLLOUT resume`  | [`resume`] | Resume a paused rollout.                              |
+//! | `ROLLOUT abort`   | [`abort`]  | Abort a rollout, rolling back to its starting weight. |
+//!
+//! Rollouts act on committed variants only, and progress on the API side - current
+//! state and the next step of each rollout are shown by `FEATURE describe`.
+
+use anyhow::{anyhow, bail};
+use flagrant_client::connection::{Connection, VariantRef};
+use flagrant_repl::{command::Arg, session::Session};
+use flagrant_types::{RolloutPlan, payload::NewRolloutPlanPayload};
+
+use super::{
+    features,
+    internal::{index, stage},
+};
+
+/// Start a progressive rollout of a variant.
+///
+/// Expects args: `<index> <start> <step> <every> <target>`
+///
+/// Weights are given in percents and `every` as an interval in minutes (`m`), hours (`h`)
+/// or days (`d`), e.g. `ROLLOUT start 2 5 10 2h 50` sets variant 2 to 5% right away and
+/// adds 10% every 2 hours, until it reaches 50%.
+pub fn start(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let weight = |i: usize, name: &str| -> anyhow::Result<u8> {
+        let Some(arg) = args.get(i) else {
+            bail!("No {name} weight provided.")
+        };
+        let weight = arg.trim_end_matches('%').parse::<u8>()?;
+        if weight > 100 {
+            bail!("Rollout {name} weight should be in range of <0, 100>.")
+        }
+        Ok(weight)
+    };
+    let start_weight = weight(2, "start")?;
+    let step = weight(3, "step")?;
+    let Some(every) = args.get(4) else {
+        bail!("No interval provided.")
+    };
+    let interval_minutes = parse_interval(every)?;
+    let target_weight = weight(5, "target")?;
+
+    let (feature_id, _) = post_for_variant(
+        args,
+        session,
+        "",
+        NewRolloutPlanPayload {
+            start_weight,
+            step,
+            target_weight,
+            interval_minutes,
+        },
+    )?;
+    features::describe_by_id(feature_id, session)
+}
+
+/// Pause an active rollout of a variant. Variant keeps its current weight.
+///
+/// Expects args: `<index>`
+pub fn pause(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let (feature_id, _) = post_for_variant(args, session, "/pause", ())?;
+    features::describe_by_id(feature_id, session)
+}
+
+/// Resume a paused rollout of a variant. Next step is due one interval from now.
+///
+/// Expects args: `<index>`
+pub fn resume(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let (feature_id, _) = post_for_variant(args, session, "/resume", ())?;
+    features::describe_by_id(feature_id, session)
+}
+
+/// Abort a rollout of a variant and roll variant back to the weight rollout started at.
+///
+/// Expects args: `<index>`
+pub fn abort(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let (feature_id, plan) = post_for_variant(args, session, "/abort", ())?;
+    println!(
+        "Rollout aborted, weight rolled back to {}%.",
+        plan.start_weight
+    );
+    features::describe_by_id(feature_id, session)
+}
+
+/// Posts `payload` to the rollout (sub)resource of a committed variant given by its
+/// display index. Returns id of the feature in context along with the rollout plan.
+fn post_for_variant<P: serde::Serialize>(
+    args: &[Arg],
+    session: &Session<Connection>,
+    subpath: &str,
+    payload: P,
+) -> anyhow::Result<(i32, RolloutPlan)> {
+    stage::ensure_no_pending(session)?;
+
+    let ctx = session.context.read().unwrap();
+    let Some(feature) = ctx.feature.as_ref() else {
+        bail!("Not within a feature context.")
+    };
+    let variant_id = match args.get(1) {
+        Some(idx) => match index::resolve(idx.parse::<usize>()?, &ctx)? {
+            VariantRef::Committed(id) => id,
+            VariantRef::Staged(_) => bail!("Only committed variants can be rolled out."),
+        },
+        None => bail!("No variant index provided."),
+    };
+    let path = ctx
+        .env_resource()
+        .subpath(format!("/variants/{variant_id}/rollout{subpath}"));
+    let plan = ctx
+        .client
+        .post::<_, RolloutPlan>(path, payload)
+        .map_err(|err| anyhow!("Rollout failed: {err}"))?;
+
+    Ok((feature.id, plan))
+}
+
+/// Parses an interval given in minutes (`30m`), hours (`2h`) or days (`1d`) into minutes.
+fn parse_interval(every: &str) -> anyhow::Result<u32> {
+    let Some(unit) = every.chars().last() else {
+        bail!("No interval provided.")
+    };
+    let amount = every[..every.len() - unit.len_utf8()]
+        .parse::<u32>()
+        .map_err(|_| anyhow!("Invalid interval: {every}"))?;
+    let minutes = match unit {
+        'm' => Some(amount),
+        'h' => amount.checked_mul(60),
+        'd' => amount.checked_mul(24 * 60),
+        _ => bail!("Interval should be given in minutes (m), hours (h) or days (d)."),
+    };
+    minutes
+        .filter(|m| *m > 0)
+        .ok_or_else(|| anyhow!("Interval out of range: {every}"))
+}
@@ -66,7 +66,10 @@ pub fn add(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
         )
     })?;
     let driver_str = args.get(2).ok_or_else(|| {
-        anyhow::anyhow!("Missing driver. Expected: identity, environment, trait:<name>")
+        anyhow::anyhow!(
+            "Missing driver. Expected: identity, environment, trait:<name>, bucket[:<salt>], \
+             segment:<name>"
+        )
     })?;
     let comparator_str = args
         .get(3)
@@ -153,8 +156,22 @@ fn parse_driver(s: &str) -> anyhow::Result<SegmentDriver> {
             }
             Ok(SegmentDriver::Trait(name.to_string()))
         }
+        "bucket" => Ok(SegmentDriver::Bucket {
+            salt: String::new(),
+        }),
+        _ if s.starts_with("bucket:") => Ok(SegmentDriver::Bucket {
+            salt: s.trim_start_matches("bucket:").to_string(),
+        }),
+        _ if s.starts_with("segment:") => {
+            let name = s.trim_start_matches("segment:");
+            if name.is_empty() {
+                bail!("Segment name cannot be empty. Use: segment:<name>");
+            }
+            Ok(SegmentDriver::Segment(name.to_string()))
+        }
         _ => bail!(
-            "Unknown driver '{}'. Expected: identity, environment, trait:<name>",
+            "Unknown driver '{}'. Expected: identity, environment, trait:<name>, bucket[:<salt>], \
+             segment:<name>",
             s
         ),
     }
@@ -172,10 +189,26 @@ fn parse_comparator(s: &str) -> anyhow::Result<Comparator> {
         "lower-equal-than" | "lower_equal_than" => Ok(Comparator::LowerEqualThan),
         "in" => Ok(Comparator::In),
         "not-in" | "not_in" => Ok(Comparator::NotIn),
+        "matches-regex" | "matches_regex" => Ok(Comparator::MatchesRegex),
+        "does-not-match-regex" | "does_not_match_regex" => Ok(Comparator::DoesNotMatchRegex),
+        "version-greater-than" | "version_greater_than" => Ok(Comparator::VersionGreaterThan),
+        "version-greater-equal-than" | "version_greater_equal_than" => {
+            Ok(Comparator::VersionGreaterEqualThan)
+        }
+        "version-lower-than" | "version_lower_than" => Ok(Comparator::VersionLowerThan),
+        "version-lower-equal-than" | "version_lower_equal_than" => {
+            Ok(Comparator::VersionLowerEqualThan)
+        }
+        "before" => Ok(Comparator::Before),
+        "after" => Ok(Comparator::After),
+        "older-than" | "older_than" => Ok(Comparator::OlderThan),
+        "newer-than" | "newer_than" => Ok(Comparator::NewerThan),
         _ => bail!(
             "Unknown comparator '{}'. Expected: exactly-matches, does-not-match, contains, \
              does-not-contain, greater-than, greater-equal-than, lower-than, lower-equal-than, \
-             in, not-in",
+             in, not-in, matches-regex, does-not-match-regex, version-greater-than, \
+             version-greater-equal-than, version-lower-than, version-lower-equal-than, before, \
+             after, older-than, newer-than",
             s
         ),
     }
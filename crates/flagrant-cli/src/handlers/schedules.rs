@@ -0,0 +1,144 @@
+//! REPL command handlers for scheduled changes.
+//!
+//! | Command           | Handler     | Description                                          |
+//! |-------------------|-------------|------------------------------------------------------|
+//! | `SCHEDULE at`     | [`at`]      | Schedule staged changes instead of committing them.  |
+//! | `SCHEDULE list`   | [`list`]    | Print changes scheduled in the current environment.  |
+//! | `SCHEDULE cancel` | [`cancel`]  | Cancel a pending scheduled change.                   |
+
+use anyhow::{anyhow, bail};
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use colored::Colorize;
+use flagrant_client::connection::Connection;
+use flagrant_repl::{command::Arg, session::Session};
+use flagrant_types::{ScheduledChange, ScheduledPatch, payload::NewScheduledChangePayload};
+
+use super::internal::index;
+use crate::printer::tabular::Tabular;
+
+/// Schedule staged changes to be applied at given time, instead of committing them.
+///
+/// Expects args: `<when>`
+///
+/// `when` is either an offset from now (`+30m`, `+2h`, `+1d`) or an absolute UTC time
+/// (`2026-10-20T08:00`). Staged feature changes and staged segment overrides get
+/// scheduled as separate changes, and are dropped from stage once scheduled.
+pub fn at(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(when) = args.get(1) else {
+        bail!("No time provided.")
+    };
+    let apply_at = parse_time(when)?;
+
+    let mut ctx = session.context.write().unwrap();
+    let mut changes = Vec::new();
+
+    if let Some(feature) = &ctx.feature
+        && let Some(patch) = ctx.feature_patch.as_ref().filter(|p| !p.is_empty())
+    {
+        changes.push(ScheduledPatch::Feature {
+            feature_id: feature.id,
+            patch: patch.clone(),
+        });
+    }
+    if let Some(segment) = &ctx.segment
+        && let Some(patch) = ctx.segment_patch.as_ref().filter(|p| !p.is_empty())
+    {
+        changes.push(ScheduledPatch::Segment {
+            segment_id: segment.id,
+            patch: patch.clone(),
+        });
+    }
+    if changes.is_empty() {
+        bail!("No staged feature or segment changes to schedule.")
+    }
+
+    let path = ctx.env_resource().subpath("/schedules");
+    for change in changes {
+        let is_feature = matches!(change, ScheduledPatch::Feature { .. });
+        let scheduled = ctx
+            .client
+            .post::<_, ScheduledChange>(
+                path.clone(),
+                NewScheduledChangePayload { apply_at, change },
+            )
+            .map_err(|err| anyhow!("Scheduling failed: {err}"))?;
+
+        if is_feature {
+            ctx.discard_pending();
+            index::rebuild(&mut ctx);
+        } else {
+            ctx.discard_segment_patch();
+        }
+        println!(
+            "Scheduled change {} at {}",
+            scheduled.id.to_string().bold(),
+            scheduled.apply_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
+    Ok(())
+}
+
+/// List changes scheduled in the current environment.
+///
+/// Expects args: `[pending|applied|failed|cancelled]`
+pub fn list(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let status = match args.get(1).map(|a| a.0) {
+        None => "pending",
+        Some(s @ ("pending" | "applied" | "failed" | "cancelled")) => s,
+        Some(other) => bail!("Unknown status: {other}"),
+    };
+
+    let ctx = session.context.read().unwrap();
+    let path = ctx
+        .env_resource()
+        .subpath(format!("/schedules?status={status}"));
+
+    ScheduledChange::list(&ctx.client.get::<Vec<ScheduledChange>>(path)?);
+    Ok(())
+}
+
+/// Cancel a pending scheduled change.
+///
+/// Expects args: `<id>`
+pub fn cancel(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(id) = args.get(1) else {
+        bail!("No scheduled change provided.")
+    };
+    let Ok(id) = id.parse::<i32>() else {
+        bail!("Scheduled change should be a number, as listed by \"SCHEDULE list\".")
+    };
+
+    let ctx = session.context.read().unwrap();
+    let cancelled = ctx
+        .client
+        .delete(ctx.env_resource().subpath(format!("/schedules/{id}")))
+        .map_err(|err| anyhow!("Cancelling failed: {err}"))?
+        .json::<ScheduledChange>()?;
+
+    cancelled.describe(None, &());
+    Ok(())
+}
+
+/// Parses either an offset from now (`+30m`, `+2h`, `+1d`) or an absolute UTC time.
+fn parse_time(when: &str) -> anyhow::Result<NaiveDateTime> {
+    if let Some(offset) = when.strip_prefix('+') {
+        let Some(unit) = offset.chars().last() else {
+            bail!("Invalid offset: {when}")
+        };
+        let amount = offset[..offset.len() - unit.len_utf8()]
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Invalid offset: {when}"))?;
+        let delta = match unit {
+            'm' => TimeDelta::try_minutes(amount),
+            'h' => TimeDelta::try_hours(amount),
+            'd' => TimeDelta::try_days(amount),
+            _ => bail!("Offset should be given in minutes (m), hours (h) or days (d)."),
+        };
+        return delta
+            .map(|delta| Utc::now().naive_utc() + delta)
+            .ok_or_else(|| anyhow!("Offset out of range: {when}"));
+    }
+    NaiveDateTime::parse_from_str(when, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(when, "%Y-%m-%dT%H:%M"))
+        .map_err(|_| anyhow!("Expected +<offset> or YYYY-MM-DDTHH:MM time, got: {when}"))
+}
@@ -11,6 +11,7 @@
 //! | `SET description`         | [`set_description`]| Stage a segment description change.                                         |
 //! | `SET override`            | [`set_override`]   | Stage variant weight overrides for the current feature within this segment. |
 //! | `UNSET override`          | [`unset_override`] | Remove staged weight overrides for the current feature within this segment. |
+//! | `SET priority`            | [`set_priority`]   | Stage priority of this segment's override of the current feature.           |
 //! | `COMMIT`                  | [`commit`]         | Send staged segment changes to the API.                                     |
 //! | `DISCARD`                 | [`discard`]        | Drop all staged segment changes.                                            |
 
@@ -192,6 +193,7 @@ pub fn describe(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<(
                     overrides.push(SegmentFeatureOverride {
                         feature_id,
                         feature_name: feature.name.clone(),
+                        priority: 0,
                         weights: vec![],
                     });
                 }
@@ -423,11 +425,23 @@ pub fn set_override(args: &[Arg], session: &Session<Connection>) -> anyhow::Resu
             if *fid == feature_id
         )
     });
+    // A staged priority has to follow the override it refers to, which might not be
+    // committed yet.
+    let staged_priority = patch
+        .ops
+        .iter()
+        .position(|op| {
+            matches!(op,
+                SegmentPatchOp::SetFeaturePriority { feature_id: fid, .. } if *fid == feature_id
+            )
+        })
+        .map(|idx| patch.ops.remove(idx));
     patch.ops.push(SegmentPatchOp::SetFeatureOverride {
         feature_id,
         environment_id,
         variant_weights: variant_weights.clone(),
     });
+    patch.ops.extend(staged_priority);
     println!(
         "Staged: segment override for '{}' ({} variant weight(s))",
         feature_name,
@@ -457,7 +471,8 @@ pub fn unset_override(_args: &[Arg], session: &Session<Connection>) -> anyhow::R
     patch.ops.retain(|op| {
         !matches!(op,
             SegmentPatchOp::SetFeatureOverride { feature_id: fid, .. } |
-            SegmentPatchOp::UnsetFeatureOverride { feature_id: fid, .. }
+            SegmentPatchOp::UnsetFeatureOverride { feature_id: fid, .. } |
+            SegmentPatchOp::SetFeaturePriority { feature_id: fid, .. }
             if *fid == feature_id
         )
     });
@@ -469,6 +484,55 @@ pub fn unset_override(_args: &[Arg], session: &Session<Connection>) -> anyhow::R
     Ok(())
 }
 
+/// Stage priority of this segment's override of the current feature. When several
+/// segments overriding a feature match an identity, the one of highest priority wins
+/// (0 by default, oldest segment on ties).
+///
+/// Expected args: `<priority>`
+pub fn set_priority(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let priority = args
+        .get(1)
+        .ok_or_else(|| anyhow::anyhow!("Usage: SET priority <number>"))?
+        .parse::<i32>()?;
+
+    let mut ctx = session.context.write().unwrap();
+    let feature = ctx.feature.as_ref().ok_or_else(|| {
+        anyhow::anyhow!("Not in a feature context. Use \"FEATURE use ...\" to set a context.")
+    })?;
+    let feature_id = feature.id;
+    let feature_name = feature.name.clone();
+    let environment_id = ctx.environment.id;
+
+    if ctx.segment.is_none() {
+        bail!("Not in a segment context. Use \"SEGMENT use ...\" to set a context.");
+    }
+
+    let patch = ctx.get_or_init_segment_patch();
+    if patch.ops.iter().any(|op| {
+        matches!(op,
+            SegmentPatchOp::UnsetFeatureOverride { feature_id: fid, .. } if *fid == feature_id
+        )
+    }) {
+        bail!("Segment override for '{feature_name}' is staged for removal.");
+    }
+    let op = SegmentPatchOp::SetFeaturePriority {
+        feature_id,
+        environment_id,
+        priority,
+    };
+    if let Some(existing) = patch.ops.iter_mut().find(|o| {
+        matches!(o,
+            SegmentPatchOp::SetFeaturePriority { feature_id: fid, .. } if *fid == feature_id
+        )
+    }) {
+        *existing = op;
+    } else {
+        patch.ops.push(op);
+    }
+    println!("Staged: priority of segment override for '{feature_name}' = {priority}");
+    Ok(())
+}
+
 fn build_segment_override_editor_content(
     feature: &Feature,
     patch: Option<&FeaturePatch>,
@@ -600,7 +664,8 @@ pub fn commit(_args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()
         .iter()
         .filter_map(|op| match op {
             SegmentPatchOp::SetFeatureOverride { feature_id, .. }
-            | SegmentPatchOp::UnsetFeatureOverride { feature_id, .. } => Some(*feature_id),
+            | SegmentPatchOp::UnsetFeatureOverride { feature_id, .. }
+            | SegmentPatchOp::SetFeaturePriority { feature_id, .. } => Some(*feature_id),
             _ => None,
         })
         .collect();
@@ -0,0 +1,89 @@
+//! REPL command handlers for environment snapshots.
+//!
+//! | Command  | Handler    | Description                                            |
+//! |----------|------------|--------------------------------------------------------|
+//! | `EXPORT` | [`export`] | Write a snapshot of the current environment to a file. |
+//! | `IMPORT` | [`import`] | Restore a snapshot file into the current environment.  |
+
+use anyhow::bail;
+use colored::Colorize;
+use flagrant_client::connection::Connection;
+use flagrant_repl::{command::Arg, session::Session};
+use flagrant_types::snapshot::{Snapshot, SnapshotImportSummary};
+
+use super::internal::stage;
+
+/// Export the current environment as a snapshot file.
+///
+/// Expects args: `<file> [--identities]`
+///
+/// Identities (along with their traits and pinned variants) are exported only when
+/// `--identities` switch is provided.
+pub fn export(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(file) = args.first() else {
+        bail!("No file name provided.")
+    };
+    let with_identities = match args.get(1).map(|a| a.0) {
+        Some("--identities") => true,
+        Some(other) => bail!("Unexpected argument: {other}"),
+        None => false,
+    };
+
+    let ctx = session.context.read().unwrap();
+    let snapshot = ctx.client.get::<Snapshot>(
+        ctx.env_resource()
+            .subpath(format!("/snapshot?identities={with_identities}")),
+    )?;
+
+    std::fs::write(file.0, serde_json::to_string_pretty(&snapshot)?)?;
+    println!(
+        "Exported {} features, {} segments{} → {}",
+        snapshot.features.len(),
+        snapshot.segments.len(),
+        snapshot
+            .identities
+            .map(|i| format!(", {} identities", i.len()))
+            .unwrap_or_default(),
+        file.0.bold()
+    );
+    Ok(())
+}
+
+/// Import a snapshot file into the current environment.
+///
+/// Expects args: `<file>`
+///
+/// Features and segments present in the snapshot get created or overwritten, everything
+/// else is left intact. Fails if there are uncommitted staged changes. Context is reset
+/// afterwards, as features and segments in context might have changed.
+pub fn import(args: &[Arg], session: &Session<Connection>) -> anyhow::Result<()> {
+    let Some(file) = args.first() else {
+        bail!("No file name provided.")
+    };
+    stage::ensure_no_pending(session)?;
+
+    let content = std::fs::read_to_string(file.0)
+        .map_err(|err| anyhow::anyhow!("Could not read {file}: {err}"))?;
+    let snapshot = serde_json::from_str::<Snapshot>(&content)
+        .map_err(|err| anyhow::anyhow!("Not a valid snapshot: {err}"))?;
+
+    let summary = {
+        let ctx = session.context.read().unwrap();
+        ctx.client
+            .post::<_, SnapshotImportSummary>(ctx.env_resource().subpath("/snapshot"), snapshot)
+            .map_err(|err| anyhow::anyhow!("Snapshot import failed: {err}"))?
+    };
+
+    println!(
+        "Features: {} created, {} updated",
+        summary.features_created, summary.features_updated
+    );
+    println!(
+        "Segments: {} created, {} updated",
+        summary.segments_created, summary.segments_updated
+    );
+    if summary.identities > 0 {
+        println!("Identities: {} restored", summary.identities);
+    }
+    stage::reset(&[], session)
+}
@@ -45,6 +45,10 @@ struct Args {
     /// list all projects
     #[argh(switch)]
     list_projects: bool,
+
+    /// API key (default: taken from FLAGRANT_API_KEY environment variable)
+    #[argh(option, short = 'k')]
+    key: Option<String>,
 }
 
 fn prompter(session: &Session<Connection>) -> String {
@@ -71,10 +75,17 @@ fn prompter(session: &Session<Connection>) -> String {
         Some(s) => format!(" [{}]", s.name),
         None => String::default(),
     };
+    // Kill switch is meant to be impossible to miss, whatever context one works in.
+    let frozen = match (&ctx.environment.frozen_at, &ctx.environment.frozen_tag) {
+        (Some(_), Some(tag)) => format!(" FROZEN:{tag} ").on_red().white().bold().to_string(),
+        (Some(_), None) => " FROZEN ".on_red().white().bold().to_string(),
+        _ => String::default(),
+    };
     format!(
-        "{}/{}{}{}{}\x1b[0m › ",
+        "{}/{}{}{}{}{}\x1b[0m › ",
         ctx.project.name,
         ctx.environment.name.purple(),
+        frozen,
         feat.green(),
         id.cyan(),
         seg.yellow()
@@ -108,9 +119,10 @@ fn pending_ctx(session: &Session<Connection>) -> bool {
 
 fn main() -> anyhow::Result<()> {
     let args: Args = argh::from_env();
+    let auth = || Auth::from_key_or_env(args.key.clone());
 
     if args.list_projects {
-        let client = HttpClient::new(args.host.clone(), Auth::None);
+        let client = HttpClient::new(args.host.clone(), auth());
         let projects = handlers::projects::list_projects(&client)?;
         println!("Known projects:\n---------------");
         for project in projects {
@@ -121,12 +133,12 @@ fn main() -> anyhow::Result<()> {
 
     let connection = match (args.project, args.create_project) {
         (Some(project_name), None) => {
-            Connection::init(args.host, Auth::None, project_name, args.environment)?
+            Connection::init(args.host, auth(), project_name, args.environment)?
         }
         (None, Some(name)) => {
-            let client = HttpClient::new(args.host.clone(), Auth::None);
+            let client = HttpClient::new(args.host.clone(), auth());
             let (project, env) = handlers::projects::create_with_env(&name, &client)?;
-            Connection::init(args.host, Auth::None, project.name, env.id)?
+            Connection::init(args.host, auth(), project.name, env.id)?
         }
         (Some(_), Some(_)) => {
             anyhow::bail!("--project and --create-project are mutually exclusive")
@@ -140,14 +152,24 @@ fn main() -> anyhow::Result<()> {
         Command::Environment.op("add", "environment base", handlers::environments::add),
         Command::Environment.op("use", "environment", handlers::environments::r#use),
         Command::Environment.op("list", "", handlers::environments::list),
-        Command::Environment.args("add · list · use"),
+        Command::Environment.op("freeze", "[tag]", handlers::environments::freeze),
+        Command::Environment.op("thaw", "", handlers::environments::thaw),
+        Command::Environment.op("history", "", handlers::environments::history),
+        Command::Environment.args("add · freeze · history · list · thaw · use"),
         // Features
         Command::Feature.op("list", "status|tag|[pattern]", handlers::features::list),
         Command::Feature.op("add", "feature value", handlers::features::add),
         Command::Feature.op("describe", "feature", handlers::features::describe),
         Command::Feature.op("delete", "feature", handlers::features::delete),
         Command::Feature.op("use", "feature", handlers::features::r#use),
-        Command::Feature.args("add · delete · describe · list · use"),
+        Command::Feature.op("history", "[feature]", handlers::features::history),
+        Command::Feature.op_in_context(
+            "revert",
+            "revision",
+            handlers::features::revert,
+            in_context!(feature_ctx),
+        ),
+        Command::Feature.args("add · delete · describe · history · list · revert · use"),
         // Identities
         Command::Identity.op(
             "add",
@@ -158,7 +180,8 @@ fn main() -> anyhow::Result<()> {
         Command::Identity.op("describe", "[identity]", handlers::identities::describe),
         Command::Identity.op("delete", "pattern", handlers::identities::delete),
         Command::Identity.op("use", "identity", handlers::identities::r#use),
-        Command::Identity.args("add · delete · describe · list · use"),
+        Command::Identity.op("explain", "[identity] [feature]", handlers::identities::explain),
+        Command::Identity.args("add · delete · describe · explain · list · use"),
         // Variants
         Command::Variant.op_in_context(
             "add",
@@ -185,6 +208,33 @@ fn main() -> anyhow::Result<()> {
             in_context!(feature_ctx),
         ),
         Command::Variant.args_in_context("add · delete · weight · value", in_context!(feature_ctx)),
+        // Rollouts
+        Command::Rollout.op_in_context(
+            "start",
+            "index start% step% 30m|2h|1d target%",
+            handlers::rollouts::start,
+            in_context!(feature_ctx),
+        ),
+        Command::Rollout.op_in_context(
+            "pause",
+            "index",
+            handlers::rollouts::pause,
+            in_context!(feature_ctx),
+        ),
+        Command::Rollout.op_in_context(
+            "resume",
+            "index",
+            handlers::rollouts::resume,
+            in_context!(feature_ctx),
+        ),
+        Command::Rollout.op_in_context(
+            "abort",
+            "index",
+            handlers::rollouts::abort,
+            in_context!(feature_ctx),
+        ),
+        Command::Rollout
+            .args_in_context("abort · pause · resume · start", in_context!(feature_ctx)),
         // Feature setters (only in feature context)
         Command::Set.op_in_context(
             "status",
@@ -204,6 +254,30 @@ fn main() -> anyhow::Result<()> {
             handlers::features::set_tags,
             in_context!(feature_ctx),
         ),
+        Command::Set.op_in_context(
+            "visibility",
+            "all|backend",
+            handlers::features::set_visibility,
+            in_context!(feature_ctx),
+        ),
+        Command::Set.op_in_context(
+            "requires",
+            "feature value",
+            handlers::features::set_requires,
+            in_context!(feature_ctx),
+        ),
+        Command::Set.op_in_context(
+            "hashing",
+            "[salt]|off",
+            handlers::features::set_hashing,
+            in_context!(feature_ctx),
+        Command::Set.op_in_context(
+            "key",
+            "trait",
+            handlers::features::set_key,
+            in_context!(feature_ctx),
+        ),
+        ),
         // Identity setters (only in identity context)
         Command::Set.op_in_context(
             "trait",
@@ -236,15 +310,24 @@ fn main() -> anyhow::Result<()> {
             handlers::segments::set_override,
             in_context!(feature_ctx, segment_ctx),
         ),
+        Command::Set.op_in_context(
+            "priority",
+            "number",
+            handlers::segments::set_priority,
+            in_context!(feature_ctx, segment_ctx),
+        ),
         Command::Set.args_in_context(
-            "status · description · tags · name · override",
+            "status · description · tags · visibility · requires · hashing · key · name · override · priority",
             in_context!(feature_ctx, segment_ctx),
         ),
         Command::Set.args_in_context(
-            "status · description · tags · trait · override",
+            "status · description · tags · visibility · requires · hashing · key · trait · override",
             in_context!(feature_ctx, identity_ctx),
         ),
-        Command::Set.args_in_context("status · description · tags", in_context!(feature_ctx)),
+        Command::Set.args_in_context(
+            "status · description · tags · visibility · requires · hashing · key",
+            in_context!(feature_ctx),
+        ),
         Command::Set.args_in_context("trait", in_context!(identity_ctx)),
         Command::Set.args_in_context("name · description", in_context!(segment_ctx)),
         // UNSET (only in feature context)
@@ -260,6 +343,18 @@ fn main() -> anyhow::Result<()> {
             handlers::features::unset_tags,
             in_context!(feature_ctx),
         ),
+        Command::Unset.op_in_context(
+            "requires",
+            "feature",
+            handlers::features::unset_requires,
+            in_context!(feature_ctx),
+        ),
+        Command::Unset.op_in_context(
+            "key",
+            "",
+            handlers::features::unset_key,
+            in_context!(feature_ctx),
+        ),
         // UNSET (only in identity context)
         Command::Unset.op_in_context(
             "trait",
@@ -281,15 +376,15 @@ fn main() -> anyhow::Result<()> {
             in_context!(segment_ctx),
         ),
         Command::Unset.args_in_context(
-            "distribution · tags · override",
+            "distribution · tags · requires · key · override",
             in_context!(feature_ctx, segment_ctx),
         ),
         Command::Unset.args_in_context(
-            "distribution · tags · trait · override",
+            "distribution · tags · requires · key · trait · override",
             in_context!(feature_ctx, identity_ctx),
         ),
         Command::Unset.args_in_context("trait", in_context!(identity_ctx)),
-        Command::Unset.args_in_context("distribution · tags", in_context!(feature_ctx)),
+        Command::Unset.args_in_context("distribution · tags · requires · key", in_context!(feature_ctx)),
         // Segments
         Command::Segment.op("add", "name [description]", handlers::segments::add),
         Command::Segment.op("list", "[pattern]", handlers::segments::list),
@@ -300,7 +395,7 @@ fn main() -> anyhow::Result<()> {
         // Groups (only in segment context)
         Command::Group.op_in_context(
             "add",
-            "[--and|--and-not] [description]",
+            "[--and|--and-not|--or|--or-not] [description]",
             handlers::groups::add,
             in_context!(segment_ctx),
         ),
@@ -353,6 +448,50 @@ fn main() -> anyhow::Result<()> {
             handlers::reset,
             in_context!(any_ctx),
         ),
+        // Exclusion layers
+        Command::Layer.op("add", "name [description]", handlers::layers::add),
+        Command::Layer.op("list", "", handlers::layers::list),
+        Command::Layer.op("describe", "name", handlers::layers::describe),
+        Command::Layer.op("delete", "name", handlers::layers::delete),
+        Command::Layer.op_in_context(
+            "assign",
+            "name share",
+            handlers::layers::assign,
+            in_context!(feature_ctx),
+        ),
+        Command::Layer.op_in_context(
+            "remove",
+            "name",
+            handlers::layers::remove,
+            in_context!(feature_ctx),
+        ),
+        Command::Layer.args("add · assign · delete · describe · list · remove"),
+        // Scheduled changes
+        Command::Schedule.op_in_context(
+            "at",
+            "+30m|+2h|+1d|YYYY-MM-DDTHH:MM",
+            handlers::schedules::at,
+            in_context!(pending_ctx),
+        ),
+        Command::Schedule.op(
+            "list",
+            "[pending|applied|failed|cancelled]",
+            handlers::schedules::list,
+        ),
+        Command::Schedule.op("cancel", "id", handlers::schedules::cancel),
+        Command::Schedule.args("at · cancel · list"),
+        // Snapshots
+        Command::Export.no_op("file [--identities]", handlers::snapshots::export),
+        Command::Import.no_op("file", handlers::snapshots::import),
+        // API keys
+        Command::ApiKey.op(
+            "add",
+            "name client|management [--env]",
+            handlers::api_keys::add,
+        ),
+        Command::ApiKey.op("list", "", handlers::api_keys::list),
+        Command::ApiKey.op("delete", "id", handlers::api_keys::delete),
+        Command::ApiKey.args("add · delete · list"),
     ];
     let overlays = vec![
         (']', "\x1b[36mdir> \x1b[0m"),
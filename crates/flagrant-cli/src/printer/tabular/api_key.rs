@@ -0,0 +1,53 @@
+use colored::Colorize;
+use fancy_table::{Align, FancyTable, FancyTableOpts, Layout, Width};
+use flagrant_types::{ApiKey, ApiKeyKind};
+
+use super::Tabular;
+
+impl Tabular for ApiKey {
+    type Patch = ();
+    type Context = ();
+
+    fn list(selfs: &[Self]) {
+        if selfs.is_empty() {
+            println!("No API keys found.");
+            return;
+        }
+        let rows: Vec<_> = selfs
+            .iter()
+            .map(|key| {
+                let kind = match key.kind {
+                    ApiKeyKind::Client => "client".to_string(),
+                    ApiKeyKind::Management => "management".yellow().to_string(),
+                };
+                let scope = match key.environment_id {
+                    Some(id) => format!("environment (ID={id})"),
+                    None => "project".to_string(),
+                };
+                [
+                    key.id.to_string(),
+                    key.name.clone(),
+                    kind,
+                    format!("{}…", key.prefix).dimmed().to_string(),
+                    scope,
+                    key.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                ]
+            })
+            .collect();
+
+        FancyTable::create(FancyTableOpts::default())
+            .add_column_named_with_align("ID".into(), Layout::Fixed(6), Align::Right)
+            .add_column_named_with_align("NAME".into(), Layout::Expandable(30), Align::Left)
+            .add_column_named_with_align("KIND".into(), Layout::Fixed(12), Align::Left)
+            .add_column_named_with_align("KEY".into(), Layout::Fixed(16), Align::Left)
+            .add_column_named_with_align("SCOPE".into(), Layout::Fixed(22), Align::Left)
+            .add_column_named_with_align("CREATED (UTC)".into(), Layout::Fixed(20), Align::Left)
+            .width(Width::Percentage(100))
+            .build()
+            .render(rows);
+    }
+
+    fn describe(&self, _patch: Option<&()>, _ctx: &()) {
+        Self::list(std::slice::from_ref(self));
+    }
+}
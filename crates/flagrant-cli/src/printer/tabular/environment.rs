@@ -1,3 +1,4 @@
+use colored::Colorize;
 use fancy_table::{Align, FancyTable, FancyTableOpts, Layout, Overflow, TitleAlign, Width};
 use flagrant_types::Environment;
 
@@ -15,10 +16,11 @@ impl Tabular for Environment {
         let rows: Vec<_> = selfs
             .iter()
             .map(|env| {
-                [
-                    env.name.clone(),
-                    env.description.clone().unwrap_or_default(),
-                ]
+                let name = match env.frozen_at {
+                    Some(_) => format!("{} {}", env.name, "FROZEN".red().bold()),
+                    None => env.name.clone(),
+                };
+                [name, env.description.clone().unwrap_or_default()]
             })
             .collect();
 
@@ -47,6 +49,18 @@ impl Tabular for Environment {
             .add_title_with_align(title.as_str(), TitleAlign::RightOffset(1))
             .build();
 
-        table.render(vec![&["NAME", &self.name], &["DESCRIPTION", desc_str]]);
+        let frozen = match (&self.frozen_at, &self.frozen_tag) {
+            (Some(at), Some(tag)) => format!("features tagged {tag}, since {at}"),
+            (Some(at), None) => format!("all features, since {at}"),
+            _ => String::default(),
+        };
+        let mut rows: Vec<Vec<String>> = vec![
+            vec!["NAME".to_string(), self.name.clone()],
+            vec!["DESCRIPTION".to_string(), desc_str.to_string()],
+        ];
+        if !frozen.is_empty() {
+            rows.push(vec!["FROZEN".to_string(), frozen.red().bold().to_string()]);
+        }
+        table.render(rows);
     }
 }
@@ -0,0 +1,140 @@
+use colored::Colorize;
+use fancy_table::{Align, FancyTable, FancyTableOpts, Layout, Width};
+use flagrant_types::{
+    FeatureValue,
+    explain::{FeatureExplanation, Resolution, SegmentEvaluation},
+};
+
+use super::{
+    Tabular,
+    segment::{format_comparator, format_connector, format_driver},
+};
+
+impl Tabular for FeatureExplanation {
+    type Patch = ();
+    type Context = ();
+
+    fn list(selfs: &[Self]) {
+        if selfs.is_empty() {
+            println!("No features to explain.");
+            return;
+        }
+        let rows: Vec<[String; 4]> = selfs
+            .iter()
+            .map(|fe| {
+                [
+                    fe.feature_name.bright_blue().to_string(),
+                    format_value(fe.value.as_ref()),
+                    resolution(fe),
+                    fe.segments
+                        .iter()
+                        .map(evaluation_lines)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ]
+            })
+            .collect();
+
+        FancyTable::create(FancyTableOpts::default())
+            .add_column_named_with_align("FEATURE".into(), Layout::Fixed(24), Align::Left)
+            .add_column_named_with_align("VALUE".into(), Layout::Fixed(20), Align::Left)
+            .add_column_named_with_align("RESOLUTION".into(), Layout::Fixed(30), Align::Left)
+            .add_column_named_with_align("SEGMENTS".into(), Layout::Expandable(80), Align::Left)
+            .width(Width::Percentage(100))
+            .build()
+            .render(rows);
+
+        if selfs.iter().any(|fe| fe.pending) {
+            println!(
+                "  {} resolved with the next request\n",
+                "(pending)".dimmed()
+            );
+        }
+    }
+
+    fn describe(&self, _patch: Option<&()>, _ctx: &()) {
+        Self::list(std::slice::from_ref(self));
+    }
+}
+
+fn format_value(value: Option<&FeatureValue>) -> String {
+    match value {
+        Some(value) => {
+            let (_, bare) = value.decompose();
+            bare.lines().next().unwrap_or(bare).to_string()
+        }
+        None => "(none)".dimmed().to_string(),
+    }
+}
+
+fn resolution(fe: &FeatureExplanation) -> String {
+    let path = match &fe.resolution {
+        Resolution::Prerequisite { feature_name, .. } => {
+            format!("{} {}", "✗ requires".red(), feature_name.bright_blue())
+        }
+        Resolution::Excluded { layer_name, .. } => {
+            format!("{} {}", "✗ excluded by".red(), layer_name.bright_blue())
+        }
+        Resolution::Frozen { tag: Some(tag) } => {
+            format!("{} {}", "✗ frozen, tagged".red().bold(), tag.bright_blue())
+        }
+        Resolution::Frozen { tag: None } => "✗ frozen environment".red().bold().to_string(),
+        Resolution::MissingKey { key } => {
+            format!("{} {}", "✗ lacks".red(), key.bright_blue())
+        }
+        Resolution::Pinned { pinned_at } => format!(
+            "{} since {}",
+            "★ pinned".yellow(),
+            pinned_at.format("%Y-%m-%d %H:%M")
+        ),
+        Resolution::PendingMigration { value, .. } => {
+            format!("migrating → {}", format_value(value.as_ref()))
+        }
+        Resolution::Segment { segment_name, .. } => {
+            format!("segment {}", segment_name.green())
+        }
+        Resolution::Organic => "organic".to_string(),
+    };
+    if fe.pending {
+        format!("{path} {}", "(pending)".dimmed())
+    } else {
+        path
+    }
+}
+
+/// Segment outcome, followed by outcomes of its groups and rules.
+fn evaluation_lines(se: &SegmentEvaluation) -> String {
+    let mut lines = vec![format!("{} {}", mark(se.matched), se.segment_name.bold())];
+    for group in &se.groups {
+        let connector = group
+            .connector
+            .as_ref()
+            .map(|c| format!("{} ", format_connector(c).bright_cyan()))
+            .unwrap_or_default();
+        lines.push(format!(
+            "  {} {connector}{}",
+            mark(group.matched),
+            group.label.yellow()
+        ));
+        for rule in &group.rules {
+            let actual = rule.actual.as_deref().unwrap_or("(none)");
+            lines.push(format!(
+                "    {} {} {} {} {}",
+                mark(rule.matched),
+                format_driver(&rule.driver).bright_blue(),
+                format_comparator(&rule.comparator).dimmed(),
+                rule.value,
+                format!("(got {actual})").dimmed()
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+fn mark(matched: bool) -> String {
+    if matched {
+        "✓".green().to_string()
+    } else {
+        "✗".red().to_string()
+    }
+}
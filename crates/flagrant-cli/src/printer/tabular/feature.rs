@@ -1,8 +1,8 @@
 use colored::Colorize;
 use fancy_table::{Align, FancyTable, FancyTableOpts, Layout, Overflow, TitleAlign, Width};
 use flagrant_types::{
-    Feature, FeatureOverride, Variant,
-    payload::{FeaturePatch, SegmentVariantWeight, TagPatchOp},
+    Distribution, Feature, FeatureOverride, FeatureVisibility, RolloutPlan, RolloutStatus, Variant,
+    payload::{FeaturePatch, PrerequisitePatchOp, SegmentVariantWeight, TagPatchOp},
 };
 
 use crate::handlers::internal::effectives as effective;
@@ -35,6 +35,8 @@ pub struct OverridesContext {
     /// If the segment in context has a staged change for this feature:
     /// `(segment_name, Some(weights))` = override set; `(segment_name, None)` = unset.
     pub segment_pending: Option<(String, Option<Vec<SegmentVariantWeight>>)>,
+    /// The most recent rollout plan of each feature variant.
+    pub rollouts: Vec<RolloutPlan>,
 }
 
 impl OverridesContext {
@@ -43,8 +45,14 @@ impl OverridesContext {
             committed,
             identity_pending: None,
             segment_pending: None,
+            rollouts: Vec::new(),
         }
     }
+
+    pub fn with_rollouts(mut self, rollouts: Vec<RolloutPlan>) -> Self {
+        self.rollouts = rollouts;
+        self
+    }
 }
 
 impl Tabular for Feature {
@@ -152,6 +160,41 @@ impl Tabular for Feature {
             String::new()
         };
 
+        let pending_visibility = patch.and_then(|p| p.visibility);
+        let visibility_str = resolve(
+            pending_visibility.map(|v| v == FeatureVisibility::Backend),
+            self.visibility == FeatureVisibility::Backend,
+            "backend only",
+            "all",
+        );
+        let visibility_stage = if pending_visibility.is_some() {
+            "▪ updating".yellow().to_string()
+        } else {
+            String::new()
+        };
+
+        let pending_distribution = patch.and_then(|p| p.distribution.as_ref());
+        let pending_key = patch.and_then(|p| p.distribution_key.as_deref());
+        let distribution_pending = pending_distribution.is_some() || pending_key.is_some();
+        let distribution_str = distribution_label(
+            pending_distribution.unwrap_or(&self.distribution),
+            match pending_key {
+                Some("") => None,
+                Some(key) => Some(key),
+                None => self.distribution_key.as_deref(),
+            },
+        );
+        let distribution_str = if distribution_pending {
+            distribution_str.yellow().to_string()
+        } else {
+            distribution_str
+        };
+        let distribution_stage = if distribution_pending {
+            "▪ updating".yellow().to_string()
+        } else {
+            String::new()
+        };
+
         let desc_str = match patch.and_then(|p| p.description.as_deref()) {
             Some("") => "(cleared)".yellow().to_string(),
             Some(d) => d.yellow().to_string(),
@@ -364,14 +407,30 @@ impl Tabular for Feature {
             overrides_stages.push("▪ adding".green().to_string());
         }
 
+        // Rollouts still in progress (or stuck), one line per variant being rolled out.
+        let rollouts_str = ctx
+            .rollouts
+            .iter()
+            .filter(|plan| plan.is_pending() || plan.status == RolloutStatus::Failed)
+            .map(|plan| rollout_line(plan, &self.variants))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let (requires_lines, requires_stages) = prerequisite_lines(self, patch);
+        let requires_str = requires_lines.join("\n");
+        let requires_stage_str = requires_stages.join("\n");
+
         let overrides_str = overrides_lines.join("\n");
         let overrides_stage_str = overrides_stages.join("\n");
         let overrides_has_staged = overrides_stages.iter().any(|s| !s.is_empty());
 
         let has_staged = !status_stage.is_empty()
+            || !visibility_stage.is_empty()
+            || !distribution_stage.is_empty()
             || !desc_stage.is_empty()
             || !tags_stage.is_empty()
             || variant_stage.iter().any(|s| !s.is_empty())
+            || requires_stages.iter().any(|s| !s.is_empty())
             || overrides_has_staged;
 
         let table = if has_staged {
@@ -412,8 +471,24 @@ impl Tabular for Feature {
         let rows: Vec<Vec<String>> = if has_staged {
             let mut rows = vec![
                 vec!["STATUS".to_string(), status, status_stage],
+                vec!["VISIBILITY".to_string(), visibility_str, visibility_stage],
+                vec![
+                    "DISTRIBUTION".to_string(),
+                    distribution_str,
+                    distribution_stage,
+                ],
                 vec!["VARIANTS".to_string(), variants, variants_stage_str],
             ];
+            if !rollouts_str.is_empty() {
+                rows.push(vec!["ROLLOUT".to_string(), rollouts_str, String::new()]);
+            }
+            if !requires_str.is_empty() {
+                rows.push(vec![
+                    "REQUIRES".to_string(),
+                    requires_str,
+                    requires_stage_str,
+                ]);
+            }
             if !overrides_str.is_empty() {
                 rows.push(vec![
                     "OVERRIDES".to_string(),
@@ -427,8 +502,16 @@ impl Tabular for Feature {
         } else {
             let mut rows = vec![
                 vec!["STATUS".to_string(), status],
+                vec!["VISIBILITY".to_string(), visibility_str],
+                vec!["DISTRIBUTION".to_string(), distribution_str],
                 vec!["VARIANTS".to_string(), variants],
             ];
+            if !rollouts_str.is_empty() {
+                rows.push(vec!["ROLLOUT".to_string(), rollouts_str]);
+            }
+            if !requires_str.is_empty() {
+                rows.push(vec!["REQUIRES".to_string(), requires_str]);
+            }
             if !overrides_str.is_empty() {
                 rows.push(vec!["OVERRIDDEN-BY".to_string(), overrides_str]);
             }
@@ -463,6 +546,120 @@ fn with_control_remainder(
     result
 }
 
+/// Describes a rollout plan, e.g. `beta 15% → 50% (+10% every 2h) · active, next step at ...`.
+fn rollout_line(plan: &RolloutPlan, variants: &[Variant]) -> String {
+    let value = variants
+        .iter()
+        .find(|v| v.id == plan.variant_id)
+        .map(|v| {
+            let (_, bare) = v.value.decompose();
+            bare.lines().next().unwrap_or(bare).to_string()
+        })
+        .unwrap_or_else(|| format!("#{}", plan.variant_id));
+    let progress = format!(
+        "{} {} → {} (+{}% every {})",
+        value,
+        format!("{}%", plan.current_weight).bold(),
+        format!("{}%", plan.target_weight).bold(),
+        plan.step,
+        interval(plan.interval_minutes)
+    );
+    let state = match (plan.status, plan.next_step_at) {
+        (RolloutStatus::Active, Some(at)) => format!(
+            "{}, next step at {}",
+            "active".green(),
+            at.format("%Y-%m-%d %H:%M UTC")
+        ),
+        (RolloutStatus::Paused, _) => "paused".yellow().to_string(),
+        (RolloutStatus::Failed, _) => format!(
+            "{}: {}",
+            "failed".red(),
+            plan.error.as_deref().unwrap_or_default()
+        ),
+        (status, _) => format!("{status:?}").to_lowercase(),
+    };
+    format!("{progress} · {state}")
+}
+
+/// Describes prerequisites of `feature`, e.g. `payments_v2 › on`, along with stage
+/// annotations of pending changes. Staged prerequisites carry ids only, hence the ones not
+/// committed yet are shown as `#feature_id › #variant_id`.
+fn prerequisite_lines(
+    feature: &Feature,
+    patch: Option<&FeaturePatch>,
+) -> (Vec<String>, Vec<String>) {
+    let ops = patch
+        .map(|p| p.prerequisites.as_slice())
+        .unwrap_or_default();
+    let staged = |feature_id: i32| {
+        ops.iter().find(|op| match op {
+            PrerequisitePatchOp::Set { feature_id: id, .. }
+            | PrerequisitePatchOp::Unset { feature_id: id } => *id == feature_id,
+        })
+    };
+    let mut lines = Vec::new();
+    let mut stages = Vec::new();
+
+    for p in &feature.prerequisites {
+        let (_, bare) = p.value.decompose();
+        let value = bare.lines().next().unwrap_or(bare);
+
+        match staged(p.feature_id) {
+            Some(PrerequisitePatchOp::Set { variant_id, .. }) if *variant_id != p.variant_id => {
+                lines.push(
+                    format!("{} › #{variant_id}", p.feature_name)
+                        .yellow()
+                        .to_string(),
+                );
+                stages.push("▪ updating".yellow().to_string());
+            }
+            Some(PrerequisitePatchOp::Unset { .. }) => {
+                lines.push(format!("{} › {value}", p.feature_name).dimmed().to_string());
+                stages.push("▪ removing".red().to_string());
+            }
+            _ => {
+                lines.push(format!("{} › {value}", p.feature_name.bright_blue()));
+                stages.push(String::new());
+            }
+        }
+    }
+    for op in ops {
+        if let PrerequisitePatchOp::Set {
+            feature_id,
+            variant_id,
+        } = op
+            && !feature
+                .prerequisites
+                .iter()
+                .any(|p| p.feature_id == *feature_id)
+        {
+            lines.push(format!("#{feature_id} › #{variant_id}").green().to_string());
+            stages.push("▪ adding".green().to_string());
+        }
+    }
+    (lines, stages)
+}
+
+fn distribution_label(distribution: &Distribution, key: Option<&str>) -> String {
+    let label = match distribution {
+        Distribution::Sticky => "sticky".to_string(),
+        Distribution::Hash { salt } => format!("hash (salt: {salt})"),
+    };
+    match key {
+        Some(key) => format!("{label} by {key}"),
+        None => label,
+    }
+}
+
+/// Formats rollout interval with the largest unit it divides into, e.g. `90m`, `2h`, `1d`.
+fn interval(minutes: u32) -> String {
+    match minutes {
+        m if m % (24 * 60) == 0 => format!("{}d", m / (24 * 60)),
+        m if m % 60 == 0 => format!("{}h", m / 60),
+        m => format!("{m}m"),
+    }
+}
+
 fn segment_weight_parts(weights: &[SegmentVariantWeight], variants: &[Variant]) -> Vec<String> {
     weights
         .iter()
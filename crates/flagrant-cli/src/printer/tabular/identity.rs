@@ -205,6 +205,8 @@ fn format_trait_value(trait_name: &str, value: &Option<TraitValue>, with_type: b
         Some(TraitValue::Int(v)) => ("int", v.to_string()),
         Some(TraitValue::Float(v)) => ("float", v.to_string()),
         Some(TraitValue::Bool(v)) => ("bool", v.to_string()),
+        Some(TraitValue::Version(v)) => ("version", v.to_string()),
+        Some(TraitValue::DateTime(v)) => ("datetime", v.to_rfc3339()),
         None => ("unset", String::new()),
     };
     if with_type {
@@ -0,0 +1,78 @@
+use colored::Colorize;
+use fancy_table::{Align, FancyTable, FancyTableOpts, Layout, TitleAlign, Width};
+use flagrant_types::Layer;
+
+use super::Tabular;
+
+impl Tabular for Layer {
+    type Patch = ();
+    type Context = ();
+
+    fn list(selfs: &[Self]) {
+        if selfs.is_empty() {
+            println!("No layers found.");
+            return;
+        }
+        let rows: Vec<_> = selfs
+            .iter()
+            .map(|layer| {
+                let taken: u32 = layer.features.iter().map(|f| f.share as u32).sum();
+                [
+                    layer.id.to_string(),
+                    layer.name.clone(),
+                    layer.features.len().to_string(),
+                    format!("{taken}%"),
+                    layer.description.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+
+        FancyTable::create(FancyTableOpts::default())
+            .add_column_named_with_align("ID".into(), Layout::Fixed(6), Align::Right)
+            .add_column_named_with_align("NAME".into(), Layout::Fixed(30), Align::Left)
+            .add_column_named_with_align("FEATURES".into(), Layout::Fixed(10), Align::Right)
+            .add_column_named_with_align("TAKEN".into(), Layout::Fixed(8), Align::Right)
+            .add_column_named_with_align("DESCRIPTION".into(), Layout::Expandable(100), Align::Left)
+            .width(Width::Percentage(100))
+            .build()
+            .render(rows);
+    }
+
+    fn describe(&self, _patch: Option<&()>, _ctx: &()) {
+        let title = format!("Layer: {} (ID={})", self.name, self.id);
+        let mut rows: Vec<_> = self
+            .features
+            .iter()
+            .map(|f| {
+                let last = f.first_bucket as u32 + f.share as u32 - 1;
+                [
+                    f.feature_name.clone(),
+                    format!("{}%", f.share),
+                    format!("{}..{}", f.first_bucket, last).dimmed().to_string(),
+                ]
+            })
+            .collect();
+
+        let free = 100 - self.features.iter().map(|f| f.share as u32).sum::<u32>();
+        if free > 0 {
+            rows.push([
+                "(control for all)".dimmed().to_string(),
+                format!("{free}%").dimmed().to_string(),
+                String::new(),
+            ]);
+        }
+
+        FancyTable::create(FancyTableOpts::default())
+            .add_column_named_with_align("FEATURE".into(), Layout::Expandable(60), Align::Left)
+            .add_column_named_with_align("SHARE".into(), Layout::Fixed(8), Align::Right)
+            .add_column_named_with_align("BUCKETS".into(), Layout::Fixed(10), Align::Left)
+            .add_title_with_align(title.as_str(), TitleAlign::RightOffset(1))
+            .width(Width::Percentage(100))
+            .build()
+            .render(rows);
+
+        if let Some(description) = &self.description {
+            println!("{description}");
+        }
+    }
+}
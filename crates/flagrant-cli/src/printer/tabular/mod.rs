@@ -1,6 +1,11 @@
+mod api_key;
 mod environment;
+mod explain;
 pub mod feature;
 mod identity;
+mod layer;
+mod revision;
+mod schedule;
 pub mod segment;
 
 pub trait Tabular {
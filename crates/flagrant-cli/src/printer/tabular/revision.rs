@@ -0,0 +1,171 @@
+use colored::Colorize;
+use fancy_table::{Align, FancyTable, FancyTableOpts, Layout, Width};
+use flagrant_types::{Distribution, Environment, Feature, Revision, RevisionEntity};
+
+use super::Tabular;
+
+impl Tabular for Revision {
+    type Patch = ();
+    type Context = ();
+
+    fn list(selfs: &[Self]) {
+        if selfs.is_empty() {
+            println!("No history recorded.");
+            return;
+        }
+        let mut rows: Vec<[String; 4]> = Vec::with_capacity(selfs.len());
+        for rev in selfs {
+            // First change goes along with revision details, the rest gets listed below.
+            for (i, change) in changes(rev).into_iter().enumerate() {
+                if i == 0 {
+                    rows.push([
+                        rev.id.to_string(),
+                        rev.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        rev.author
+                            .clone()
+                            .unwrap_or_else(|| "-".dimmed().to_string()),
+                        change,
+                    ]);
+                } else {
+                    rows.push([String::new(), String::new(), String::new(), change]);
+                }
+            }
+        }
+
+        FancyTable::create(FancyTableOpts::default())
+            .add_column_named_with_align("REVISION".into(), Layout::Fixed(10), Align::Right)
+            .add_column_named_with_align("WHEN".into(), Layout::Fixed(20), Align::Left)
+            .add_column_named_with_align("WHO".into(), Layout::Fixed(16), Align::Left)
+            .add_column_named_with_align("CHANGES".into(), Layout::Expandable(80), Align::Left)
+            .width(Width::Percentage(100))
+            .build()
+            .render(rows);
+    }
+
+    fn describe(&self, _patch: Option<&()>, _ctx: &()) {
+        Self::list(std::slice::from_ref(self));
+    }
+}
+
+/// Returns human readable list of changes recorded by a revision.
+fn changes(rev: &Revision) -> Vec<String> {
+    if rev.entity == RevisionEntity::Environment {
+        return serde_json::from_value::<Environment>(rev.after.clone())
+            .map(|after| vec![freeze(&after)])
+            .unwrap_or_else(|_| vec!["environment changed".to_string()]);
+    }
+    let snapshots = match rev.entity {
+        RevisionEntity::Feature => serde_json::from_value::<Feature>(rev.before.clone())
+            .ok()
+            .zip(serde_json::from_value::<Feature>(rev.after.clone()).ok()),
+        _ => None,
+    };
+    let Some((before, after)) = snapshots else {
+        return vec![format!("{:?} changed", rev.entity).to_lowercase()];
+    };
+
+    let mut changes = Vec::new();
+    if status(&before) != status(&after) {
+        changes.push(format!("status: {} → {}", status(&before), status(&after)));
+    }
+    if before.description != after.description {
+        changes.push(format!("description: {}", after.description));
+    }
+    if before.visibility != after.visibility {
+        changes.push(format!("visibility: {:?}", after.visibility).to_lowercase());
+    }
+    if before.distribution != after.distribution {
+        changes.push(match &after.distribution {
+            Distribution::Sticky => "distribution: sticky".to_string(),
+            Distribution::Hash { salt } => format!("distribution: hash (salt: {salt})"),
+        });
+    }
+    if before.distribution_key != after.distribution_key {
+        changes.push(match &after.distribution_key {
+            Some(key) => format!("distribution key: {key}"),
+            None => "distribution key: none".to_string(),
+        });
+    }
+    for tag in &after.tags.0 {
+        if !before.tags.0.iter().any(|t| t.name == tag.name) {
+            changes.push(format!("{} tag {}", "+".green(), tag.name));
+        }
+    }
+    for tag in &before.tags.0 {
+        if !after.tags.0.iter().any(|t| t.name == tag.name) {
+            changes.push(format!("{} tag {}", "-".red(), tag.name));
+        }
+    }
+    for var in &before.variants {
+        match after.variants.iter().find(|v| v.id == var.id) {
+            Some(v) if var.is_control() && v.value != var.value => {
+                changes.push(format!("default value: {} → {}", var.value, v.value));
+            }
+            Some(v) if !var.is_control() => {
+                if v.value != var.value {
+                    changes.push(format!("variant: {} → {}", var.value, v.value));
+                }
+                if v.weight != var.weight {
+                    changes.push(format!(
+                        "variant {}: {}% → {}%",
+                        v.value, var.weight, v.weight
+                    ));
+                }
+            }
+            None => changes.push(format!("{} variant {}", "-".red(), var.value)),
+            _ => {}
+        }
+    }
+    for var in &after.variants {
+        if !before.variants.iter().any(|v| v.id == var.id) {
+            changes.push(format!(
+                "{} variant {} ({}%)",
+                "+".green(),
+                var.value,
+                var.weight
+            ));
+        }
+    }
+    for req in &after.prerequisites {
+        if !before.prerequisites.contains(req) {
+            changes.push(format!(
+                "{} requires {} = {}",
+                "+".green(),
+                req.feature_name,
+                req.value
+            ));
+        }
+    }
+    for req in &before.prerequisites {
+        if !after
+            .prerequisites
+            .iter()
+            .any(|p| p.feature_id == req.feature_id)
+        {
+            changes.push(format!("{} requires {}", "-".red(), req.feature_name));
+        }
+    }
+    if changes.is_empty() {
+        changes.push("no visible changes".dimmed().to_string());
+    }
+    changes
+}
+
+/// Describes state of the kill switch of an environment.
+fn freeze(env: &Environment) -> String {
+    match (&env.frozen_at, &env.frozen_tag) {
+        (Some(_), Some(tag)) => format!("{} features tagged {tag}", "frozen".red().bold()),
+        (Some(_), None) => format!("{} all features", "frozen".red().bold()),
+        _ => "thawed".to_string(),
+    }
+}
+
+fn status(feature: &Feature) -> &'static str {
+    if feature.is_archived {
+        "archived"
+    } else if feature.is_enabled {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
@@ -0,0 +1,192 @@
+use colored::Colorize;
+use fancy_table::{Align, FancyTable, FancyTableOpts, Layout, Width};
+use flagrant_types::{
+    Distribution, ScheduleStatus, ScheduledChange, ScheduledPatch,
+    payload::{PrerequisitePatchOp, SegmentPatchOp, TagPatchOp, VariantPatchOp},
+};
+
+use super::Tabular;
+
+impl Tabular for ScheduledChange {
+    type Patch = ();
+    type Context = ();
+
+    fn list(selfs: &[Self]) {
+        if selfs.is_empty() {
+            println!("No changes scheduled.");
+            return;
+        }
+        let mut rows: Vec<[String; 5]> = Vec::with_capacity(selfs.len());
+        for sc in selfs {
+            // First change goes along with schedule details, the rest gets listed below.
+            for (i, change) in changes(sc).into_iter().enumerate() {
+                if i == 0 {
+                    rows.push([
+                        sc.id.to_string(),
+                        sc.apply_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        sc.author
+                            .clone()
+                            .unwrap_or_else(|| "-".dimmed().to_string()),
+                        status(sc),
+                        change,
+                    ]);
+                } else {
+                    rows.push([
+                        String::new(),
+                        String::new(),
+                        String::new(),
+                        String::new(),
+                        change,
+                    ]);
+                }
+            }
+        }
+
+        FancyTable::create(FancyTableOpts::default())
+            .add_column_named_with_align("ID".into(), Layout::Fixed(6), Align::Right)
+            .add_column_named_with_align("WHEN (UTC)".into(), Layout::Fixed(20), Align::Left)
+            .add_column_named_with_align("WHO".into(), Layout::Fixed(16), Align::Left)
+            .add_column_named_with_align("STATUS".into(), Layout::Fixed(10), Align::Left)
+            .add_column_named_with_align("CHANGES".into(), Layout::Expandable(80), Align::Left)
+            .width(Width::Percentage(100))
+            .build()
+            .render(rows);
+
+        for sc in selfs {
+            if let Some(error) = &sc.error {
+                println!("{} {}: {}", "✗".red(), sc.id, error);
+            }
+        }
+    }
+
+    fn describe(&self, _patch: Option<&()>, _ctx: &()) {
+        Self::list(std::slice::from_ref(self));
+    }
+}
+
+fn status(sc: &ScheduledChange) -> String {
+    match sc.status {
+        ScheduleStatus::Pending => "pending".yellow().to_string(),
+        ScheduleStatus::Applied => "applied".green().to_string(),
+        ScheduleStatus::Cancelled => "cancelled".dimmed().to_string(),
+        ScheduleStatus::Failed => "failed".red().to_string(),
+    }
+}
+
+/// Returns human readable list of changes to be applied.
+fn changes(sc: &ScheduledChange) -> Vec<String> {
+    let mut changes = Vec::new();
+    match &sc.change {
+        ScheduledPatch::Feature { feature_id, patch } => {
+            let feature = format!("feature #{feature_id}");
+            match (patch.is_archived, patch.is_enabled) {
+                (Some(true), _) => changes.push(format!("{feature} status: archived")),
+                (_, Some(true)) => changes.push(format!("{feature} status: ON")),
+                (_, Some(false)) => changes.push(format!("{feature} status: OFF")),
+                (Some(false), None) => changes.push(format!("{feature} status: unarchived")),
+                (None, None) => {}
+            }
+            if let Some(description) = &patch.description {
+                changes.push(format!("{feature} description: {description}"));
+            }
+            if let Some(visibility) = &patch.visibility {
+                changes.push(format!("{feature} visibility: {visibility:?}").to_lowercase());
+            }
+            match &patch.distribution {
+                Some(Distribution::Sticky) => {
+                    changes.push(format!("{feature} distribution: sticky"))
+                }
+                Some(Distribution::Hash { salt }) => {
+                    changes.push(format!("{feature} distribution: hash (salt: {salt})"))
+                }
+                None => {}
+            }
+            match patch.distribution_key.as_deref() {
+                Some("") => changes.push(format!("{feature} distribution key: none")),
+                Some(key) => changes.push(format!("{feature} distribution key: {key}")),
+                None => {}
+            }
+            for op in &patch.tags {
+                match op {
+                    TagPatchOp::Add(tag) => {
+                        changes.push(format!("{feature} {} tag {tag}", "+".green()))
+                    }
+                    TagPatchOp::Remove(tag) => {
+                        changes.push(format!("{feature} {} tag {tag}", "-".red()))
+                    }
+                }
+            }
+            for op in &patch.variants {
+                changes.push(match op {
+                    VariantPatchOp::Add { value, weight } => {
+                        format!("{feature} {} variant {value} ({weight}%)", "+".green())
+                    }
+                    VariantPatchOp::SetValue { id, value } => {
+                        format!("{feature} variant #{id} value: {value}")
+                    }
+                    VariantPatchOp::SetWeight { id, weight } => {
+                        format!("{feature} variant #{id} weight: {weight}%")
+                    }
+                    VariantPatchOp::Delete { id } => {
+                        format!("{feature} {} variant #{id}", "-".red())
+                    }
+                });
+            }
+            for op in &patch.prerequisites {
+                changes.push(match op {
+                    PrerequisitePatchOp::Set {
+                        feature_id,
+                        variant_id,
+                    } => format!(
+                        "{feature} {} requires feature #{feature_id} variant #{variant_id}",
+                        "+".green()
+                    ),
+                    PrerequisitePatchOp::Unset { feature_id } => {
+                        format!("{feature} {} requires feature #{feature_id}", "-".red())
+                    }
+                });
+            }
+        }
+        ScheduledPatch::Segment { segment_id, patch } => {
+            let segment = format!("segment #{segment_id}");
+            for op in &patch.ops {
+                match op {
+                    SegmentPatchOp::SetFeatureOverride {
+                        feature_id,
+                        variant_weights,
+                        ..
+                    } => {
+                        let weights = variant_weights
+                            .iter()
+                            .map(|w| format!("#{}: {}%", w.variant_id, w.weight))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        changes.push(format!(
+                            "{segment} override feature #{feature_id} ({weights})"
+                        ));
+                    }
+                    SegmentPatchOp::UnsetFeatureOverride { feature_id, .. } => {
+                        changes.push(format!(
+                            "{segment} {} override feature #{feature_id}",
+                            "-".red()
+                        ));
+                    }
+                    SegmentPatchOp::SetFeaturePriority {
+                        feature_id,
+                        priority,
+                        ..
+                    } => {
+                        changes.push(format!(
+                            "{segment} override feature #{feature_id} priority: {priority}"
+                        ));
+                    }
+                    _ => changes.push(format!("{segment} changed")),
+                }
+            }
+        }
+    }
+    if changes.is_empty() {
+        changes.push("no visible changes".dimmed().to_string());
+    }
+    changes
+}
@@ -212,13 +212,29 @@ impl Tabular for Segment {
                 )
             });
 
+            let staged_priority = patch
+                .into_iter()
+                .flat_map(|p| &p.ops)
+                .find_map(|op| match op {
+                    SegmentPatchOp::SetFeaturePriority {
+                        feature_id,
+                        priority,
+                        ..
+                    } if *feature_id == o.feature_id => Some(*priority),
+                    _ => None,
+                });
+
             let parts = overridden_variant_parts(&o.weights);
-            let plain_line = format!(
+            let mut plain_line = format!(
                 "{} › {} {}",
                 "feature".bright_blue(),
                 o.feature_name.dimmed(),
                 parts.join(", ")
             );
+            let priority = staged_priority.unwrap_or(o.priority);
+            if priority != 0 {
+                plain_line.push_str(&format!(" {}", format!("(priority {priority})").dimmed()));
+            }
 
             match pending_op {
                 Some(SegmentPatchOp::UnsetFeatureOverride { .. }) => {
@@ -237,6 +253,10 @@ impl Tabular for Segment {
                     overrides_lines.push(plain_line.yellow().to_string());
                     overrides_stages.push("▪ updating".yellow().to_string());
                 }
+                None if staged_priority.is_some() => {
+                    overrides_lines.push(plain_line.yellow().to_string());
+                    overrides_stages.push("▪ updating".yellow().to_string());
+                }
                 _ => {
                     overrides_lines.push(plain_line);
                     overrides_stages.push(String::new());
@@ -622,15 +642,18 @@ fn overridden_variant_parts(weights: &[OverriddenVariant]) -> Vec<String> {
         .collect()
 }
 
-fn format_driver(driver: &SegmentDriver) -> String {
+pub(super) fn format_driver(driver: &SegmentDriver) -> String {
     match driver {
         SegmentDriver::Identity => "identity".to_string(),
         SegmentDriver::Trait(name) => format!("trait:{name}"),
         SegmentDriver::Environment => "environment".to_string(),
+        SegmentDriver::Bucket { salt } if salt.is_empty() => "bucket".to_string(),
+        SegmentDriver::Bucket { salt } => format!("bucket:{salt}"),
+        SegmentDriver::Segment(name) => format!("segment:{name}"),
     }
 }
 
-fn format_comparator(comparator: &Comparator) -> &'static str {
+pub(super) fn format_comparator(comparator: &Comparator) -> &'static str {
     match comparator {
         Comparator::ExactlyMatches => "exactly-matches",
         Comparator::DoesNotMatch => "does-not-match",
@@ -642,12 +665,24 @@ fn format_comparator(comparator: &Comparator) -> &'static str {
         Comparator::LowerEqualThan => "lower-equal-than",
         Comparator::In => "in",
         Comparator::NotIn => "not-in",
+        Comparator::MatchesRegex => "matches-regex",
+        Comparator::DoesNotMatchRegex => "does-not-match-regex",
+        Comparator::VersionGreaterThan => "version-greater-than",
+        Comparator::VersionGreaterEqualThan => "version-greater-equal-than",
+        Comparator::VersionLowerThan => "version-lower-than",
+        Comparator::VersionLowerEqualThan => "version-lower-equal-than",
+        Comparator::Before => "before",
+        Comparator::After => "after",
+        Comparator::OlderThan => "older-than",
+        Comparator::NewerThan => "newer-than",
     }
 }
 
-fn format_connector(connector: &GroupConnector) -> &'static str {
+pub(super) fn format_connector(connector: &GroupConnector) -> &'static str {
     match connector {
         GroupConnector::And => "⊕ AND",
         GroupConnector::AndNot => "⊖ AND NOT",
+        GroupConnector::Or => "⊕ OR",
+        GroupConnector::OrNot => "⊖ OR NOT",
     }
 }
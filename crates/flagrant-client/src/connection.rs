@@ -97,10 +97,11 @@ impl Connection {
     #[cfg(not(feature = "blocking"))]
     pub async fn init(
         api_host: String,
+        auth: Auth,
         project_name: String,
         environment: impl Into<EnvironmentRef>,
     ) -> anyhow::Result<Connection> {
-        let client = HttpClient::new(api_host, Auth::None);
+        let client = HttpClient::new(api_host, auth);
         let path = format!("/projects/{project_name}");
         let environment = environment.into();
 
@@ -1,3 +1,5 @@
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+
 type Host = String;
 
 #[derive(Debug)]
@@ -6,8 +8,42 @@ pub enum Auth {
     None,
 }
 
+impl Auth {
+    /// Name of environment variable API key is read from, if not given explicitly.
+    pub const API_KEY_VAR: &str = "FLAGRANT_API_KEY";
+
+    /// Authenticates with given API key or, if none given, with the one set in
+    /// `FLAGRANT_API_KEY` environment variable.
+    pub fn from_key_or_env(key: Option<String>) -> Auth {
+        key.or_else(|| std::env::var(Self::API_KEY_VAR).ok())
+            .filter(|k| !k.is_empty())
+            .map_or(Auth::None, Auth::Token)
+    }
+}
+
 #[derive(Debug)]
 pub enum HttpClient {
     Async(reqwest::Client, Host, Auth),
     Blocking(reqwest::blocking::Client, Host, Auth),
 }
+
+/// Headers sent along with every request. Local user name is sent as an author
+/// of changes, so that API can attribute the revisions it records. API key, if any,
+/// is sent as a bearer token.
+pub(crate) fn default_headers(auth: &Auth) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let author = std::env::var("USER").or_else(|_| std::env::var("USERNAME"));
+
+    if let Ok(author) = author
+        && let Ok(value) = HeaderValue::from_str(&author)
+    {
+        headers.insert("X-Flagrant-Author", value);
+    }
+    if let Auth::Token(token) = auth
+        && let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {token}"))
+    {
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+    headers
+}
@@ -1,11 +1,14 @@
 use reqwest::Response;
 use serde::{Serialize, de::DeserializeOwned};
 
-use crate::http::{Auth, HttpClient};
+use crate::http::{Auth, HttpClient, default_headers};
 
 impl HttpClient {
     pub fn new(host: String, auth: Auth) -> HttpClient {
-        let client = reqwest::Client::new();
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers(&auth))
+            .build()
+            .expect("Could not initialize HTTP client");
         HttpClient::Async(client, host, auth)
     }
 
@@ -1,6 +1,7 @@
 pub mod connection;
 pub mod http;
 pub mod resource;
+pub mod socket;
 
 #[cfg(not(feature = "blocking"))]
 pub mod impl_async;
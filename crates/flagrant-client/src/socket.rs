@@ -0,0 +1,165 @@
+//! Transport speaking the socket protocol (see [`flagrant_types::protocol`]) - an
+//! alternative to [`HttpClient`](crate::http::HttpClient) for reading flags with low
+//! latency over a persistent TCP or Unix domain socket connection.
+//!
+//! Socket I/O is blocking, hence a client is best owned by a dedicated thread.
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use anyhow::bail;
+use flagrant_types::{
+    FeatureResponse,
+    protocol::{self, ClientFrame, PROTOCOL_VERSION, ServerFrame},
+};
+
+use crate::http::Auth;
+
+/// Prefix of addresses pointing at Unix domain sockets.
+const UNIX_PREFIX: &str = "unix:";
+
+#[derive(Debug)]
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+/// Features of a watched identity, pushed by server once they have changed.
+#[derive(Debug)]
+pub struct FeaturesChange {
+    pub identity: String,
+    pub features: Vec<FeatureResponse>,
+}
+
+#[derive(Debug)]
+pub struct SocketClient {
+    stream: Stream,
+    /// Changes received while waiting for answers, not taken yet.
+    changes: VecDeque<FeaturesChange>,
+}
+
+impl SocketClient {
+    /// Connects to `addr` - either a `host:port` TCP address or a `unix:<path>` socket
+    /// path - and opens a session for given project environment.
+    pub fn connect(
+        addr: &str,
+        auth: &Auth,
+        project: &str,
+        environment: &str,
+    ) -> anyhow::Result<SocketClient> {
+        let stream = match addr.strip_prefix(UNIX_PREFIX) {
+            #[cfg(unix)]
+            Some(path) => Stream::Unix(std::os::unix::net::UnixStream::connect(path)?),
+            #[cfg(not(unix))]
+            Some(_) => bail!("Unix domain sockets not supported on this platform"),
+            None => {
+                let stream = TcpStream::connect(addr)?;
+                stream.set_nodelay(true)?;
+                Stream::Tcp(stream)
+            }
+        };
+        let mut client = SocketClient {
+            stream,
+            changes: VecDeque::new(),
+        };
+        client.send(&ClientFrame::Hello {
+            version: PROTOCOL_VERSION,
+            project: project.to_owned(),
+            environment: environment.to_owned(),
+            key: match auth {
+                Auth::Token(key) => Some(key.clone()),
+                Auth::None => None,
+            },
+        })?;
+        match client.receive()? {
+            ServerFrame::Ready { .. } => Ok(client),
+            ServerFrame::Error { message } => bail!(message),
+            frame => bail!("Unexpected frame: {frame:?}"),
+        }
+    }
+
+    /// Returns features evaluated for given identity (or identity token).
+    pub fn get_features(&mut self, identity: &str) -> anyhow::Result<Vec<FeatureResponse>> {
+        self.request_features(identity, false)
+    }
+
+    /// Returns features evaluated for given identity (or identity token), and keeps
+    /// receiving their changes, to be taken with [`SocketClient::next_change`].
+    pub fn watch(&mut self, identity: &str) -> anyhow::Result<Vec<FeatureResponse>> {
+        self.request_features(identity, true)
+    }
+
+    /// Stops receiving changes of features of given identity.
+    pub fn unwatch(&mut self, identity: &str) -> anyhow::Result<()> {
+        self.send(&ClientFrame::Unwatch {
+            identity: identity.to_owned(),
+        })
+    }
+
+    /// Waits for features of any of the watched identities to change.
+    pub fn next_change(&mut self) -> anyhow::Result<FeaturesChange> {
+        if let Some(change) = self.changes.pop_front() {
+            return Ok(change);
+        }
+        match self.receive()? {
+            ServerFrame::Changed { identity, features } => {
+                Ok(FeaturesChange { identity, features })
+            }
+            ServerFrame::Error { message } => bail!(message),
+            frame => bail!("Unexpected frame: {frame:?}"),
+        }
+    }
+
+    fn request_features(
+        &mut self,
+        identity: &str,
+        watch: bool,
+    ) -> anyhow::Result<Vec<FeatureResponse>> {
+        self.send(&ClientFrame::Features {
+            identity: identity.to_owned(),
+            watch,
+        })?;
+        loop {
+            match self.receive()? {
+                ServerFrame::Features { features, .. } => return Ok(features),
+                ServerFrame::Changed { identity, features } => {
+                    self.changes
+                        .push_back(FeaturesChange { identity, features });
+                }
+                ServerFrame::Error { message } => bail!(message),
+                frame => bail!("Unexpected frame: {frame:?}"),
+            }
+        }
+    }
+
+    fn send(&mut self, frame: &ClientFrame) -> anyhow::Result<()> {
+        let buf = protocol::encode(frame)?;
+        match &mut self.stream {
+            Stream::Tcp(stream) => stream.write_all(&buf)?,
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write_all(&buf)?,
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> anyhow::Result<ServerFrame> {
+        let mut prefix = [0; 4];
+        self.read_exact(&mut prefix)?;
+
+        let mut buf = vec![0; protocol::frame_len(prefix)?];
+        self.read_exact(&mut buf)?;
+        Ok(protocol::decode(&buf)?)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        match &mut self.stream {
+            Stream::Tcp(stream) => stream.read_exact(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read_exact(buf),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Explanation of why an identity sees the feature values it does - the path feature
+//! resolution takes for each feature, along with segment rules evaluated on the way.
+//!
+//! Explaining never changes anything: identities not distributed yet, or flagged for
+//! re-evaluation, are reported as they would be resolved by their next request.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{Comparator, FeatureValue, GroupConnector, SegmentDriver};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeatureExplanation {
+    pub feature_id: i32,
+    pub feature_name: String,
+    /// Value of the variant identity is attached to, if distributed already.
+    pub value: Option<FeatureValue>,
+    pub resolution: Resolution,
+    /// Whether resolution is yet to happen with the next request - identity hasn't been
+    /// distributed yet, or got flagged for re-evaluation by a segment change.
+    pub pending: bool,
+    /// Segments overriding the feature, evaluated in priority order up to the first one
+    /// matching. Empty for pinned identities, pending migrations and failed prerequisites,
+    /// which skip segments.
+    pub segments: Vec<SegmentEvaluation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "path", rename_all = "snake_case")]
+pub enum Resolution {
+    /// Identity fails prerequisite requiring feature of `feature_id`, hence gets the control
+    /// value.
+    Prerequisite {
+        feature_id: i32,
+        feature_name: String,
+    },
+    /// Identity lacks trait `key` feature is distributed by, hence gets the control value.
+    MissingKey { key: String },
+    /// Identity falls into the share of another feature of exclusion layer of `layer_id`,
+    /// hence gets the control value.
+    Excluded { layer_id: i32, layer_name: String },
+    /// Kill switch of the environment is engaged - for features tagged with `tag` only,
+    /// if given - hence identity gets the control value.
+    Frozen { tag: Option<String> },
+    /// Identity is pinned to its variant by an identity override.
+    Pinned { pinned_at: NaiveDateTime },
+    /// Identity is being moved to another variant, following a weight change.
+    PendingMigration {
+        variant_id: i32,
+        value: Option<FeatureValue>,
+    },
+    /// Identity is distributed across weights of a segment override.
+    Segment { segment_id: i32, segment_name: String },
+    /// Identity is distributed across the feature's own weights.
+    Organic,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SegmentEvaluation {
+    pub segment_id: i32,
+    pub segment_name: String,
+    pub matched: bool,
+    /// Groups in order. Groups following an already matching OR term are not evaluated,
+    /// hence not listed.
+    pub groups: Vec<GroupEvaluation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GroupEvaluation {
+    pub label: String,
+    pub connector: Option<GroupConnector>,
+    pub matched: bool,
+    pub rules: Vec<RuleEvaluation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleEvaluation {
+    pub rule_id: i32,
+    pub driver: SegmentDriver,
+    pub comparator: Comparator,
+    pub value: String,
+    /// Value the driver resolved to. `None` if there was none, e.g. identity lacks the
+    /// trait, which makes the rule fail.
+    pub actual: Option<String>,
+    pub matched: bool,
+}
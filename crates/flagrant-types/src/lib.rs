@@ -1,14 +1,20 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use serde_valid::Validate;
 use sqlx::{Decode, Encode, Sqlite, Type, encode::IsNull, sqlite::SqliteValueRef};
 use std::{fmt, str::FromStr};
 use thiserror::Error;
-use utoipa::ToSchema;
+use utoipa::{
+    PartialSchema, ToSchema,
+    openapi::{self, KnownFormat, ObjectBuilder, OneOfBuilder, RefOr, Schema, SchemaFormat},
+};
 
 extern crate regex;
 
+pub mod explain;
 pub mod payload;
+pub mod protocol;
+pub mod snapshot;
 
 // max variant size is 1kb (1024 bytes)
 const MAX_VARIANT_SIZE: usize = 1024;
@@ -32,6 +38,8 @@ pub struct Project {
     #[validate(pattern = r"^[A-Za-z][A-Za-z0-9_]+$")]
     #[validate(max_length = 255)]
     pub name: String,
+    #[serde(default)]
+    pub trait_persistence: TraitPersistence,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, sqlx::FromRow, Validate, ToSchema)]
@@ -43,6 +51,13 @@ pub struct Environment {
     #[validate(max_length = 255)]
     pub name: String,
     pub description: Option<String>,
+    /// When the kill switch got engaged, `None` unless environment is frozen. Features of
+    /// a frozen environment serve their control value.
+    #[serde(default)]
+    pub frozen_at: Option<NaiveDateTime>,
+    /// Tag of features the kill switch applies to, all the features if `None`.
+    #[serde(default)]
+    pub frozen_tag: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Validate, ToSchema)]
@@ -59,6 +74,28 @@ pub struct Feature {
     pub tags: TagList,
     pub is_enabled: bool,
     pub is_archived: bool,
+    #[serde(default)]
+    pub visibility: FeatureVisibility,
+    #[serde(default)]
+    pub distribution: Distribution,
+    /// Trait identities get distributed by within the environment feature was fetched for,
+    /// `None` if distributed by identity value.
+    #[serde(default)]
+    pub distribution_key: Option<String>,
+    /// Prerequisites within the environment feature was fetched for.
+    #[serde(default)]
+    pub prerequisites: Vec<Prerequisite>,
+}
+
+/// Prerequisite of a feature: the feature applies to an identity only if feature of
+/// `feature_id` resolves to its variant `variant_id` for that identity. Identities failing
+/// a prerequisite get the control value, without being distributed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Prerequisite {
+    pub feature_id: i32,
+    pub feature_name: String,
+    pub variant_id: i32,
+    pub value: FeatureValue,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -91,7 +128,8 @@ pub struct Trait {
     pub name: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+/// Schema of the enum is hand-written - see `PartialSchema` implementation below.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
 pub enum TraitValue {
     // Same charset restriction as `Trait::name`, and for the same reason: string trait
     // values are embedded unescaped into the same hand-built JSON filter blobs.
@@ -103,8 +141,37 @@ pub enum TraitValue {
     Int(i32),
     Float(f32),
     Bool(bool),
+    /// Semantic version, e.g. `5.10.0` or `6.0.0-beta.2`.
+    Version(semver::Version),
+    /// Point in time, e.g. `2026-12-01T10:00:00Z`.
+    DateTime(DateTime<Utc>),
 }
 
+// utoipa can't override schema of a tuple variant's field, and `semver::Version` has no
+// schema of its own. Versions are (de)serialized as strings, hence described as such.
+impl PartialSchema for TraitValue {
+    fn schema() -> RefOr<Schema> {
+        let variant = |name: &str, schema: RefOr<Schema>| {
+            ObjectBuilder::new().property(name, schema).required(name)
+        };
+        OneOfBuilder::new()
+            .item(variant("Str", String::schema()))
+            .item(variant("Int", i32::schema()))
+            .item(variant("Float", f32::schema()))
+            .item(variant("Bool", bool::schema()))
+            .item(variant("Version", String::schema()))
+            .item(variant(
+                "DateTime",
+                ObjectBuilder::new()
+                    .schema_type(openapi::Type::String)
+                    .format(Some(SchemaFormat::KnownFormat(KnownFormat::DateTime)))
+                    .into(),
+            ))
+            .into()
+    }
+}
+impl ToSchema for TraitValue {}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct IdentityTrait {
     pub trait_id: i32,
@@ -129,7 +196,16 @@ pub struct IdentityVariant {
     pub segment_dirty: bool,
     pub feature_name: String,
     pub feature_value: Option<FeatureValue>,
+    /// Whether the feature is switched on in the environment variant got resolved in.
+    pub is_enabled: bool,
+    pub feature_visibility: FeatureVisibility,
     pub pinned_at: Option<NaiveDateTime>,
+    /// Salt of features distributed by [`Distribution::Hash`], `None` for sticky ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_salt: Option<String>,
+    /// Trait features get distributed by instead of identity value, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distribution_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -141,6 +217,14 @@ pub enum SegmentDriver {
     Trait(String),
     /// Match against the environment name.
     Environment,
+    /// Match against the identity's bucket - a number within `0..10000` the identity
+    /// value hashes into, along with the `salt`. Buckets are stable, so numeric
+    /// comparators carve out a fixed percentage of identities, e.g. `lower-than 1000`
+    /// for 10% of them. Different salts slice identities independently.
+    Bucket { salt: String },
+    /// Match against whether the identity matches another segment of the same project,
+    /// as a `true`/`false` value. The `String` is the name of referenced segment.
+    Segment(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -158,6 +242,31 @@ pub enum Comparator {
     In,
     /// Value must be a JSON array string.
     NotIn,
+    /// Value must be a regular expression, matched anywhere within the actual value
+    /// unless anchored with `^`/`$`.
+    MatchesRegex,
+    /// Value must be a regular expression.
+    DoesNotMatchRegex,
+    /// Value must be a version. Actual value is compared as a version too, with
+    /// pre-releases ordered before their release, e.g. `5.2.0-rc.1 < 5.2.0 < 5.10.0`.
+    VersionGreaterThan,
+    /// Value must be a version.
+    VersionGreaterEqualThan,
+    /// Value must be a version.
+    VersionLowerThan,
+    /// Value must be a version.
+    VersionLowerEqualThan,
+    /// Value must be a date (`2026-12-01`) or a date and time in RFC 3339 format
+    /// (`2026-12-01T10:00:00Z`). Actual value is compared as a point in time too.
+    Before,
+    /// Value must be a date, or a date and time.
+    After,
+    /// Value must be a duration, e.g. `30d`. Matches points in time further in the past
+    /// than given duration, as of evaluation time.
+    OlderThan,
+    /// Value must be a duration, e.g. `12h`. Matches points in time within given duration
+    /// in the past (or in the future), as of evaluation time.
+    NewerThan,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -166,7 +275,8 @@ pub struct SegmentRule {
     pub id: i32,
     pub driver: SegmentDriver,
     pub comparator: Comparator,
-    /// For `In`/`NotIn` comparators this is a JSON array string; otherwise a plain value.
+    /// For `In`/`NotIn` comparators this is a JSON array string, for `MatchesRegex`/
+    /// `DoesNotMatchRegex` a regular expression; otherwise a plain value.
     pub value: String,
 }
 
@@ -175,6 +285,10 @@ pub struct SegmentRule {
 pub enum GroupConnector {
     And,
     AndNot,
+    /// Binds looser than `And`/`AndNot`, e.g. `a AND b OR c` reads `(a AND b) OR c`.
+    Or,
+    /// Binds looser than `And`/`AndNot`, negating its own group only.
+    OrNot,
 }
 
 impl sqlx::Type<Sqlite> for SegmentDriver {
@@ -191,6 +305,8 @@ impl Encode<'_, Sqlite> for SegmentDriver {
             Self::Identity => "identity".to_string(),
             Self::Trait(name) => format!("trait:{name}"),
             Self::Environment => "environment".to_string(),
+            Self::Bucket { salt } => format!("bucket:{salt}"),
+            Self::Segment(name) => format!("segment:{name}"),
         };
         Encode::<Sqlite>::encode(s, buf)
     }
@@ -202,6 +318,10 @@ impl<'r> Decode<'r, Sqlite> for SegmentDriver {
             "identity" => Ok(Self::Identity),
             "environment" => Ok(Self::Environment),
             _ if s.starts_with("trait:") => Ok(Self::Trait(s[6..].to_string())),
+            _ if s.starts_with("bucket:") => Ok(Self::Bucket {
+                salt: s[7..].to_string(),
+            }),
+            _ if s.starts_with("segment:") => Ok(Self::Segment(s[8..].to_string())),
             _ => Err(format!("Unknown segment driver: {s}").into()),
         }
     }
@@ -228,6 +348,16 @@ impl Encode<'_, Sqlite> for Comparator {
             Self::LowerEqualThan => "lower_equal_than",
             Self::In => "in",
             Self::NotIn => "not_in",
+            Self::MatchesRegex => "matches_regex",
+            Self::DoesNotMatchRegex => "does_not_match_regex",
+            Self::VersionGreaterThan => "version_greater_than",
+            Self::VersionGreaterEqualThan => "version_greater_equal_than",
+            Self::VersionLowerThan => "version_lower_than",
+            Self::VersionLowerEqualThan => "version_lower_equal_than",
+            Self::Before => "before",
+            Self::After => "after",
+            Self::OlderThan => "older_than",
+            Self::NewerThan => "newer_than",
         };
         Encode::<Sqlite>::encode(s, buf)
     }
@@ -246,6 +376,16 @@ impl<'r> Decode<'r, Sqlite> for Comparator {
             "lower_equal_than" => Ok(Self::LowerEqualThan),
             "in" => Ok(Self::In),
             "not_in" => Ok(Self::NotIn),
+            "matches_regex" => Ok(Self::MatchesRegex),
+            "does_not_match_regex" => Ok(Self::DoesNotMatchRegex),
+            "version_greater_than" => Ok(Self::VersionGreaterThan),
+            "version_greater_equal_than" => Ok(Self::VersionGreaterEqualThan),
+            "version_lower_than" => Ok(Self::VersionLowerThan),
+            "version_lower_equal_than" => Ok(Self::VersionLowerEqualThan),
+            "before" => Ok(Self::Before),
+            "after" => Ok(Self::After),
+            "older_than" => Ok(Self::OlderThan),
+            "newer_than" => Ok(Self::NewerThan),
             _ => Err(format!("Unknown comparator: {s}").into()),
         }
     }
@@ -264,6 +404,8 @@ impl Encode<'_, Sqlite> for GroupConnector {
         let s = match self {
             Self::And => "and",
             Self::AndNot => "and_not",
+            Self::Or => "or",
+            Self::OrNot => "or_not",
         };
         Encode::<Sqlite>::encode(s, buf)
     }
@@ -274,11 +416,197 @@ impl<'r> Decode<'r, Sqlite> for GroupConnector {
         match s {
             "and" => Ok(Self::And),
             "and_not" => Ok(Self::AndNot),
+            "or" => Ok(Self::Or),
+            "or_not" => Ok(Self::OrNot),
             _ => Err(format!("Unknown group connector: {s}").into()),
         }
     }
 }
 
+impl sqlx::Type<Sqlite> for RevisionEntity {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+}
+impl Encode<'_, Sqlite> for RevisionEntity {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> Result<IsNull, sqlx::error::BoxDynError> {
+        let s = match self {
+            Self::Feature => "feature",
+            Self::Segment => "segment",
+            Self::Identity => "identity",
+            Self::Environment => "environment",
+        };
+        Encode::<Sqlite>::encode(s, buf)
+    }
+}
+impl<'r> Decode<'r, Sqlite> for RevisionEntity {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as sqlx::Decode<Sqlite>>::decode(value)?;
+        match s {
+            "feature" => Ok(Self::Feature),
+            "segment" => Ok(Self::Segment),
+            "identity" => Ok(Self::Identity),
+            "environment" => Ok(Self::Environment),
+            _ => Err(format!("Unknown revision entity: {s}").into()),
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for ScheduleStatus {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+}
+impl Encode<'_, Sqlite> for ScheduleStatus {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> Result<IsNull, sqlx::error::BoxDynError> {
+        let s = match self {
+            Self::Pending => "pending",
+            Self::Applied => "applied",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        };
+        Encode::<Sqlite>::encode(s, buf)
+    }
+}
+impl<'r> Decode<'r, Sqlite> for ScheduleStatus {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as sqlx::Decode<Sqlite>>::decode(value)?;
+        match s {
+            "pending" => Ok(Self::Pending),
+            "applied" => Ok(Self::Applied),
+            "cancelled" => Ok(Self::Cancelled),
+            "failed" => Ok(Self::Failed),
+            _ => Err(format!("Unknown schedule status: {s}").into()),
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for RolloutStatus {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+}
+impl Encode<'_, Sqlite> for RolloutStatus {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> Result<IsNull, sqlx::error::BoxDynError> {
+        let s = match self {
+            Self::Active => "active",
+            Self::Paused => "paused",
+            Self::Completed => "completed",
+            Self::Aborted => "aborted",
+            Self::Failed => "failed",
+        };
+        Encode::<Sqlite>::encode(s, buf)
+    }
+}
+impl<'r> Decode<'r, Sqlite> for RolloutStatus {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as sqlx::Decode<Sqlite>>::decode(value)?;
+        match s {
+            "active" => Ok(Self::Active),
+            "paused" => Ok(Self::Paused),
+            "completed" => Ok(Self::Completed),
+            "aborted" => Ok(Self::Aborted),
+            "failed" => Ok(Self::Failed),
+            _ => Err(format!("Unknown rollout status: {s}").into()),
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for ApiKeyKind {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+}
+impl Encode<'_, Sqlite> for ApiKeyKind {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> Result<IsNull, sqlx::error::BoxDynError> {
+        let s = match self {
+            Self::Client => "client",
+            Self::Management => "management",
+        };
+        Encode::<Sqlite>::encode(s, buf)
+    }
+}
+impl<'r> Decode<'r, Sqlite> for ApiKeyKind {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as sqlx::Decode<Sqlite>>::decode(value)?;
+        match s {
+            "client" => Ok(Self::Client),
+            "management" => Ok(Self::Management),
+            _ => Err(format!("Unknown API key kind: {s}").into()),
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for FeatureVisibility {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+}
+impl Encode<'_, Sqlite> for FeatureVisibility {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> Result<IsNull, sqlx::error::BoxDynError> {
+        let s = match self {
+            Self::All => "all",
+            Self::Backend => "backend",
+        };
+        Encode::<Sqlite>::encode(s, buf)
+    }
+}
+impl<'r> Decode<'r, Sqlite> for FeatureVisibility {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as sqlx::Decode<Sqlite>>::decode(value)?;
+        match s {
+            "all" => Ok(Self::All),
+            "backend" => Ok(Self::Backend),
+            _ => Err(format!("Unknown feature visibility: {s}").into()),
+        }
+    }
+}
+
+impl sqlx::Type<Sqlite> for TraitPersistence {
+    fn type_info() -> <Sqlite as sqlx::Database>::TypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+}
+impl Encode<'_, Sqlite> for TraitPersistence {
+    fn encode_by_ref(
+        &self,
+        buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'_>,
+    ) -> Result<IsNull, sqlx::error::BoxDynError> {
+        let s = match self {
+            Self::Never => "never",
+            Self::Known => "known",
+            Self::Always => "always",
+        };
+        Encode::<Sqlite>::encode(s, buf)
+    }
+}
+impl<'r> Decode<'r, Sqlite> for TraitPersistence {
+    fn decode(value: SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let s = <&str as sqlx::Decode<Sqlite>>::decode(value)?;
+        match s {
+            "never" => Ok(Self::Never),
+            "known" => Ok(Self::Known),
+            "always" => Ok(Self::Always),
+            _ => Err(format!("Unknown trait persistence: {s}").into()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct SegmentGroup {
     #[sqlx(rename = "group_id")]
@@ -331,6 +659,9 @@ pub struct OverriddenVariant {
 pub struct SegmentFeatureOverride {
     pub feature_id: i32,
     pub feature_name: String,
+    /// Overrides of higher priority take precedence over other matching segments.
+    #[serde(default)]
+    pub priority: i32,
     pub weights: Vec<OverriddenVariant>,
 }
 
@@ -342,6 +673,51 @@ pub enum FeatureValue {
     Toml(String),
 }
 
+/// Who a feature is served to through the client API: `All` callers, or `Backend` ones
+/// only - requests authenticated with a public client key don't get to see it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureVisibility {
+    #[default]
+    All,
+    Backend,
+}
+
+/// How identities get distributed across variants of a feature. `Sticky` distribution
+/// weighs each first-time identity against variant accumulators and stores the variant
+/// picked. `Hash` maps a hash of the identity value and `salt` onto buckets laid out along
+/// variant weights - nothing gets stored per identity, and weight changes move only as many
+/// buckets as the weights changed by. Identity overrides are honored in either mode.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    #[default]
+    Sticky,
+    Hash { salt: String },
+}
+
+impl Distribution {
+    /// Salt identities get hashed with, `None` for sticky distribution.
+    pub fn salt(&self) -> Option<&str> {
+        match self {
+            Self::Sticky => None,
+            Self::Hash { salt } => Some(salt),
+        }
+    }
+}
+
+/// Whether traits sent along with client API requests get stored with the identity:
+/// `Never` - they're used for the request only, `Known` - stored if the project defines
+/// a trait of the same name already, used for the request only otherwise, `Always`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TraitPersistence {
+    #[default]
+    Never,
+    Known,
+    Always,
+}
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 #[schema(value_type = Vec<Tag>)]
 pub struct TagList(pub Vec<Tag>);
@@ -351,13 +727,175 @@ pub struct Tag {
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct FeatureResponse {
     pub feature_id: i32,
     pub name: String,
     pub value: FeatureValue,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionEntity {
+    Feature,
+    Segment,
+    Identity,
+    Environment,
+}
+
+/// A single recorded change. `before` and `after` are JSON snapshots of the entity
+/// taken right before and right after the change got applied.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Revision {
+    pub id: i32,
+    pub entity: RevisionEntity,
+    pub entity_id: i32,
+    /// `None` for project-wide entities (segments).
+    pub environment_id: Option<i32>,
+    pub author: Option<String>,
+    #[schema(value_type = Object)]
+    pub before: serde_json::Value,
+    #[schema(value_type = Object)]
+    pub after: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+/// Patch put aside to be applied at a scheduled point in time.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub enum ScheduledPatch {
+    Feature {
+        feature_id: i32,
+        patch: payload::FeaturePatch,
+    },
+    /// Segment patch limited to feature overrides within the environment it's scheduled in.
+    Segment {
+        segment_id: i32,
+        patch: payload::SegmentPatch,
+    },
+}
+
+impl ScheduledPatch {
+    pub fn entity(&self) -> (RevisionEntity, i32) {
+        match self {
+            Self::Feature { feature_id, .. } => (RevisionEntity::Feature, *feature_id),
+            Self::Segment { segment_id, .. } => (RevisionEntity::Segment, *segment_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    Pending,
+    Applied,
+    Cancelled,
+    Failed,
+}
+
+/// A change scheduled within an environment, along with its outcome once it's been applied.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledChange {
+    pub id: i32,
+    pub environment_id: i32,
+    pub change: ScheduledPatch,
+    pub apply_at: NaiveDateTime,
+    pub author: Option<String>,
+    pub status: ScheduleStatus,
+    /// Reason of failure, if change could not be applied.
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub applied_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RolloutStatus {
+    Active,
+    Paused,
+    Completed,
+    Aborted,
+    Failed,
+}
+
+/// Progressive rollout of a variant within an environment: starting at `start_weight`,
+/// variant weight grows by `step` every `interval_minutes` until it reaches `target_weight`.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct RolloutPlan {
+    #[sqlx(rename = "rollout_plan_id")]
+    pub id: i32,
+    pub environment_id: i32,
+    pub feature_id: i32,
+    pub variant_id: i32,
+    pub start_weight: u8,
+    pub step: u8,
+    pub target_weight: u8,
+    pub interval_minutes: u32,
+    pub current_weight: u8,
+    pub status: RolloutStatus,
+    /// When next step is due. Set only while plan is active.
+    pub next_step_at: Option<NaiveDateTime>,
+    /// Reason of failure, if a step could not be applied.
+    pub error: Option<String>,
+    pub author: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Kind of an API key: `Client` keys give read-only access to the public client API,
+/// `Management` keys give access to the management API and, used with the client API,
+/// see backend-only features too.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyKind {
+    Client,
+    Management,
+}
+
+/// API key scoped to a project and, optionally, to one of its environments.
+/// The key itself is never stored - only its hash and a short prefix to tell keys apart.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct ApiKey {
+    #[sqlx(rename = "api_key_id")]
+    pub id: i32,
+    pub project_id: i32,
+    pub environment_id: Option<i32>,
+    pub name: String,
+    pub kind: ApiKeyKind,
+    pub prefix: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// Exclusion layer: features of an environment, each given a share of the traffic.
+/// An identity falls into the share of at most one of them, and gets the control value
+/// of all the other features of the layer.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Layer {
+    pub id: i32,
+    pub environment_id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    /// Features in order of their shares.
+    pub features: Vec<LayerFeature>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Share of a feature within an exclusion layer: percent of the traffic, starting at
+/// `first_bucket` out of 100.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct LayerFeature {
+    pub feature_id: i32,
+    pub feature_name: String,
+    pub share: u8,
+    pub first_bucket: u8,
+}
+
+impl RolloutPlan {
+    /// Returns `true` for plans still to be completed, either active or paused.
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status, RolloutStatus::Active | RolloutStatus::Paused)
+    }
+}
+
 impl Feature {
     pub fn get_default_variant(&self) -> &Variant {
         self.variants
@@ -372,6 +910,10 @@ impl Feature {
         self.variants = variants;
         self
     }
+    pub fn with_prerequisites(mut self, prerequisites: Vec<Prerequisite>) -> Self {
+        self.prerequisites = prerequisites;
+        self
+    }
 }
 
 impl Variant {
@@ -546,13 +1088,21 @@ impl fmt::Display for TraitValue {
             Self::Int(v) => write!(f, "int::{v}"),
             Self::Float(v) => write!(f, "float::{v}"),
             Self::Bool(v) => write!(f, "bool::{v}"),
+            Self::Version(v) => write!(f, "version::{v}"),
+            Self::DateTime(v) => write!(
+                f,
+                "datetime::{}",
+                v.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            ),
         }
     }
 }
 
 impl TraitValue {
     /// Infers the type from the raw string and returns the appropriate variant.
-    /// Detection order: bool → i32 → f32 → Str.
+    /// Detection order: bool → i32 → f32 → version → datetime → Str. Only complete
+    /// versions (`major.minor.patch`) are detected, `5.2` stays a float. Date times are
+    /// detected as understood by [`parse_datetime`].
     pub fn build(value: &str) -> Self {
         if let Ok(b) = value.parse::<bool>() {
             return Self::Bool(b);
@@ -563,10 +1113,63 @@ impl TraitValue {
         if let Ok(f) = value.parse::<f32>() {
             return Self::Float(f);
         }
+        if let Ok(v) = semver::Version::parse(value) {
+            return Self::Version(v);
+        }
+        if let Some(dt) = parse_datetime(value) {
+            return Self::DateTime(dt);
+        }
         Self::Str(value.to_owned())
     }
 }
 
+/// Parses a version leniently: a leading `v` is skipped and missing minor and patch
+/// numbers default to zero, so `v5`, `5.0` and `5.0.0` are all the same version.
+/// Pre-release and build suffixes follow the semver rules.
+pub fn parse_version(raw: &str) -> Option<semver::Version> {
+    let raw = raw.strip_prefix('v').unwrap_or(raw);
+    if let Ok(version) = semver::Version::parse(raw) {
+        return Some(version);
+    }
+    let (core, suffix) = raw.split_at(raw.find(['-', '+']).unwrap_or(raw.len()));
+    let missing = match core.split('.').count() {
+        n @ 1..=2 => 3 - n,
+        _ => return None,
+    };
+    semver::Version::parse(&format!("{core}{}{suffix}", ".0".repeat(missing))).ok()
+}
+
+/// Parses a point in time given either in RFC 3339 format (`2026-12-01T10:00:00+02:00`),
+/// as a date and time with no offset (`2026-12-01T10:00:00`, taken as UTC), or as
+/// a sole date (`2026-12-01`, taken as UTC midnight).
+pub fn parse_datetime(raw: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    if let Ok(dt) = NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(dt.and_utc());
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+/// Parses a duration given as a number followed by a unit: `s` (seconds), `m` (minutes),
+/// `h` (hours), `d` (days) or `w` (weeks), e.g. `90s` or `30d`.
+pub fn parse_duration(raw: &str) -> Option<TimeDelta> {
+    let (amount, unit) = raw.split_at(raw.char_indices().last()?.0);
+    let amount = amount.parse::<i64>().ok().filter(|a| *a >= 0)?;
+    match unit {
+        "s" => TimeDelta::try_seconds(amount),
+        "m" => TimeDelta::try_minutes(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+}
+
 impl FromStr for TraitValue {
     type Err = ParseTypeError;
 
@@ -586,6 +1189,12 @@ impl FromStr for TraitValue {
                 .parse::<bool>()
                 .map(Self::Bool)
                 .map_err(|_| ParseTypeError::Encoding),
+            "version" => semver::Version::parse(val)
+                .map(Self::Version)
+                .map_err(|_| ParseTypeError::Encoding),
+            "datetime" => DateTime::parse_from_rfc3339(val)
+                .map(|dt| Self::DateTime(dt.with_timezone(&Utc)))
+                .map_err(|_| ParseTypeError::Encoding),
             _ => Err(ParseTypeError::Type(typ.to_owned())),
         }
     }
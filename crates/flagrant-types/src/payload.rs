@@ -1,8 +1,12 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::{
-    Comparator, Environment, Feature, FeatureValue, GroupConnector, Project, SegmentDriver,
+    ApiKey, ApiKeyKind, Comparator, Distribution, Environment, Feature, FeatureValue,
+    FeatureVisibility, GroupConnector, Project, ScheduledPatch, SegmentDriver, TraitPersistence,
     TraitValue,
 };
 
@@ -35,6 +39,19 @@ pub enum TagPatchOp {
     Remove(String),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub enum PrerequisitePatchOp {
+    /// Requires feature of `feature_id` to resolve to its variant `variant_id`. Replaces
+    /// the variant previously required of the same feature, if any.
+    Set {
+        feature_id: i32,
+        variant_id: i32,
+    },
+    Unset {
+        feature_id: i32,
+    },
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct NewProjectPayload {
     pub name: String,
@@ -46,6 +63,11 @@ pub struct ProjectCreatedResponse {
     pub environment: Environment,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct ProjectPatch {
+    pub trait_persistence: Option<TraitPersistence>,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct NewEnvironmentPayload {
     pub name: String,
@@ -53,12 +75,20 @@ pub struct NewEnvironmentPayload {
     pub base_env: Option<String>,
 }
 
+/// Engages the kill switch of an environment, for features tagged with `tag` only if given.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct FreezePayload {
+    pub tag: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct NewFeaturePayload {
     pub name: String,
     pub value: FeatureValue,
     pub description: Option<String>,
     pub is_enabled: bool,
+    #[serde(default)]
+    pub visibility: FeatureVisibility,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -84,13 +114,31 @@ pub struct IdentityTraitPayload {
     pub value: Option<TraitValue>,
 }
 
+/// Traits sent along with a client API request for feature values, keyed by trait name.
+/// String values get their type inferred, `null` unsets a trait for the request.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct EvaluationPayload {
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub traits: BTreeMap<String, serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct FeaturePatch {
     pub is_enabled: Option<bool>,
     pub is_archived: Option<bool>,
     pub description: Option<String>,
+    pub visibility: Option<FeatureVisibility>,
+    pub distribution: Option<Distribution>,
+    /// Trait to distribute by within the environment feature gets patched in. An empty
+    /// name goes back to distributing by identity value.
+    #[serde(default)]
+    pub distribution_key: Option<String>,
     pub tags: Vec<TagPatchOp>,
     pub variants: Vec<VariantPatchOp>,
+    /// Prerequisites get changed within the environment feature gets patched in.
+    #[serde(default)]
+    pub prerequisites: Vec<PrerequisitePatchOp>,
 }
 
 impl From<Feature> for NewFeaturePayload {
@@ -105,6 +153,7 @@ impl From<Feature> for NewFeaturePayload {
                 .value,
             description: None,
             is_enabled: feature.is_enabled,
+            visibility: feature.visibility,
         }
     }
 }
@@ -114,8 +163,12 @@ impl FeaturePatch {
         self.is_enabled.is_none()
             && self.is_archived.is_none()
             && self.description.is_none()
+            && self.visibility.is_none()
+            && self.distribution.is_none()
+            && self.distribution_key.is_none()
             && self.tags.is_empty()
             && self.variants.is_empty()
+            && self.prerequisites.is_empty()
     }
 }
 
@@ -198,6 +251,13 @@ pub enum SegmentPatchOp {
         feature_id: i32,
         environment_id: i32,
     },
+    /// Sets priority of this segment's override of a feature within an environment. When
+    /// several overriding segments match, the override of highest priority wins.
+    SetFeaturePriority {
+        feature_id: i32,
+        environment_id: i32,
+        priority: i32,
+    },
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
@@ -210,3 +270,49 @@ impl SegmentPatch {
         self.ops.is_empty()
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewScheduledChangePayload {
+    /// Point in time (UTC) the change should be applied at.
+    pub apply_at: NaiveDateTime,
+    pub change: ScheduledPatch,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewRolloutPlanPayload {
+    /// Weight (%) variant gets right away.
+    pub start_weight: u8,
+    /// Weight (%) added with every step.
+    pub step: u8,
+    /// Weight (%) at which the rollout stops.
+    pub target_weight: u8,
+    /// Minutes between consecutive steps.
+    pub interval_minutes: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewApiKeyPayload {
+    pub name: String,
+    pub kind: ApiKeyKind,
+    /// Environment the key is limited to. Key is valid project-wide if not provided.
+    pub environment_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewLayerPayload {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Share of the traffic within an exclusion layer, in percent.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LayerSharePayload {
+    pub share: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyCreatedResponse {
+    pub api_key: ApiKey,
+    /// The key itself. Returned only once, it can't be retrieved later on.
+    pub key: String,
+}
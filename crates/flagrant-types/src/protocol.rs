@@ -0,0 +1,199 @@
+//! Frames of the socket protocol - a persistent, lightweight alternative to the HTTP
+//! client API, served over TCP or Unix domain sockets.
+//!
+//! Each frame is a JSON document preceded by its length, encoded as a 4-byte big-endian
+//! unsigned integer. A session starts with [`ClientFrame::Hello`], which authenticates
+//! the connection and binds it to a project environment. Each [`ClientFrame::Features`]
+//! is answered with [`ServerFrame::Features`], in order. Identities requested with
+//! `watch` set are also pushed [`ServerFrame::Changed`] frames whenever their feature
+//! values change, interleaved with the answers.
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::FeatureResponse;
+
+/// Version of the protocol spoken by this build, exchanged in the handshake.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Max length of a single frame (without its length prefix). Longer frames break
+/// the connection.
+pub const MAX_FRAME_LEN: u32 = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientFrame {
+    /// Opens a session. Has to come first, once per connection.
+    Hello {
+        version: u32,
+        project: String,
+        environment: String,
+        /// API key, required as long as the API is guarded by keys.
+        key: Option<String>,
+    },
+    /// Asks for features evaluated for an identity - or for an identity token, if
+    /// the server takes identities from JWTs.
+    Features {
+        identity: String,
+        /// Keeps pushing changed features of this identity.
+        #[serde(default)]
+        watch: bool,
+    },
+    /// Stops pushing changed features of an identity. Not answered.
+    Unwatch { identity: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerFrame {
+    /// Session has been opened.
+    Ready { version: u32 },
+    /// Features evaluated for an identity, in answer to [`ClientFrame::Features`].
+    Features {
+        identity: String,
+        features: Vec<FeatureResponse>,
+    },
+    /// Features of a watched identity have changed.
+    Changed {
+        identity: String,
+        features: Vec<FeatureResponse>,
+    },
+    /// Request could not be handled. Errors within handshake close the connection.
+    Error { message: String },
+}
+
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error("Frame too long ({0} bytes)")]
+    TooLong(usize),
+
+    #[error("Malformed frame: {0}")]
+    Malformed(#[from] serde_json::Error),
+}
+
+/// Encodes a frame, along with its length prefix. Frames longer than [`MAX_FRAME_LEN`]
+/// are rejected, as the other side would reject them anyway.
+pub fn encode<T: Serialize>(frame: &T) -> Result<Vec<u8>, FrameError> {
+    let json = serde_json::to_vec(frame)?;
+    if json.len() > MAX_FRAME_LEN as usize {
+        return Err(FrameError::TooLong(json.len()));
+    }
+    let mut buf = Vec::with_capacity(json.len() + 4);
+    buf.extend_from_slice(&(json.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&json);
+    Ok(buf)
+}
+
+/// Returns length of a frame given its length `prefix`, rejecting frames longer than
+/// [`MAX_FRAME_LEN`] before anything gets allocated for them.
+pub fn frame_len(prefix: [u8; 4]) -> Result<usize, FrameError> {
+    let len = u32::from_be_bytes(prefix);
+    if len > MAX_FRAME_LEN {
+        return Err(FrameError::TooLong(len as usize));
+    }
+    Ok(len as usize)
+}
+
+/// Decodes a frame out of its JSON document, read after the length prefix.
+pub fn decode<T: DeserializeOwned>(buf: &[u8]) -> Result<T, FrameError> {
+    Ok(serde_json::from_slice(buf)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FeatureValue;
+
+    fn roundtrip<T: Serialize + DeserializeOwned>(frame: &T) -> T {
+        let buf = encode(frame).unwrap();
+        let (prefix, json) = buf.split_at(4);
+        let len = frame_len(prefix.try_into().unwrap()).unwrap();
+
+        assert_eq!(len, json.len());
+        decode(json).unwrap()
+    }
+
+    #[test]
+    fn client_frames_roundtrip() {
+        let frames = [
+            ClientFrame::Hello {
+                version: PROTOCOL_VERSION,
+                project: "shop".to_owned(),
+                environment: "production".to_owned(),
+                key: Some("fl_secret".to_owned()),
+            },
+            ClientFrame::Features {
+                identity: "user-1".to_owned(),
+                watch: true,
+            },
+            ClientFrame::Unwatch {
+                identity: "user-1".to_owned(),
+            },
+        ];
+        for frame in frames {
+            assert_eq!(roundtrip(&frame), frame);
+        }
+    }
+
+    #[test]
+    fn server_frames_roundtrip() {
+        let features = vec![FeatureResponse {
+            feature_id: 1,
+            name: "checkout".to_owned(),
+            value: FeatureValue::build("on"),
+        }];
+        let frames = [
+            ServerFrame::Ready {
+                version: PROTOCOL_VERSION,
+            },
+            ServerFrame::Features {
+                identity: "user-1".to_owned(),
+                features: features.clone(),
+            },
+            ServerFrame::Changed {
+                identity: "user-1".to_owned(),
+                features,
+            },
+            ServerFrame::Error {
+                message: "Session not opened".to_owned(),
+            },
+        ];
+        for frame in frames {
+            assert_eq!(roundtrip(&frame), frame);
+        }
+    }
+
+    #[test]
+    fn watch_defaults_to_false() {
+        let frame: ClientFrame = decode(br#"{"type":"features","identity":"user-1"}"#).unwrap();
+        assert_eq!(
+            frame,
+            ClientFrame::Features {
+                identity: "user-1".to_owned(),
+                watch: false
+            }
+        );
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let prefix = (MAX_FRAME_LEN + 1).to_be_bytes();
+        assert!(matches!(frame_len(prefix), Err(FrameError::TooLong(_))));
+        assert_eq!(
+            frame_len(MAX_FRAME_LEN.to_be_bytes()).unwrap(),
+            MAX_FRAME_LEN as usize
+        );
+
+        let frame = ClientFrame::Features {
+            identity: "x".repeat(MAX_FRAME_LEN as usize),
+            watch: false,
+        };
+        assert!(matches!(encode(&frame), Err(FrameError::TooLong(_))));
+    }
+
+    #[test]
+    fn malformed_frames_are_rejected() {
+        let frame = decode::<ClientFrame>(br#"{"type":"goodbye"}"#);
+        assert!(matches!(frame, Err(FrameError::Malformed(_))));
+    }
+}
@@ -0,0 +1,134 @@
+//! Versioned, self-contained document capturing a full environment.
+//!
+//! Everything within a snapshot refers to other entities by their names (features,
+//! segments, traits) or values (variants) rather than by database ids, so the document
+//! can be restored into the very same environment as well as into any other project.
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    Comparator, Distribution, FeatureValue, FeatureVisibility, GroupConnector, SegmentDriver,
+    TraitValue,
+};
+
+/// Version of the snapshot document produced by this build. Bumped on every
+/// incompatible change of the document layout.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Snapshot {
+    pub version: u32,
+    pub created_at: NaiveDateTime,
+    /// Name of the project snapshot was taken from. Informational only.
+    pub project: String,
+    /// Name of the environment snapshot was taken from. Informational only.
+    pub environment: String,
+    pub traits: Vec<String>,
+    pub features: Vec<FeatureSnapshot>,
+    pub segments: Vec<SegmentSnapshot>,
+    /// Identities along with their traits and pinned variants.
+    /// `None` if snapshot was taken without identities.
+    pub identities: Option<Vec<IdentitySnapshot>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeatureSnapshot {
+    pub name: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub is_enabled: bool,
+    pub is_archived: bool,
+    /// Missing in documents taken before features could be limited to backends.
+    #[serde(default)]
+    pub visibility: FeatureVisibility,
+    /// Missing in documents taken before features could be distributed by hash.
+    #[serde(default)]
+    pub distribution: Distribution,
+    /// Missing in documents taken before features could be distributed by a trait.
+    #[serde(default)]
+    pub distribution_key: Option<String>,
+    /// Value of the control variant.
+    pub value: FeatureValue,
+    /// Non-control variants. Control variant takes the remainder up to 100.
+    pub variants: Vec<VariantSnapshot>,
+    /// Missing in documents taken before features could require other features.
+    #[serde(default)]
+    pub requires: Vec<PrerequisiteSnapshot>,
+}
+
+/// Feature named `feature` has to resolve to a variant of given `value`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PrerequisiteSnapshot {
+    pub feature: String,
+    pub value: FeatureValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VariantSnapshot {
+    pub value: FeatureValue,
+    pub weight: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SegmentSnapshot {
+    pub name: String,
+    pub description: Option<String>,
+    /// Groups ordered by position.
+    pub groups: Vec<GroupSnapshot>,
+    /// Feature overrides within the snapshot environment.
+    pub overrides: Vec<SegmentOverrideSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GroupSnapshot {
+    pub description: Option<String>,
+    pub connector: Option<GroupConnector>,
+    pub rules: Vec<RuleSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleSnapshot {
+    pub driver: SegmentDriver,
+    pub comparator: Comparator,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SegmentOverrideSnapshot {
+    pub feature: String,
+    #[serde(default)]
+    pub priority: i32,
+    /// Non-control variants weights. Control variant takes the remainder up to 100.
+    pub weights: Vec<VariantSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IdentitySnapshot {
+    pub identity: String,
+    pub traits: Vec<TraitSnapshot>,
+    pub pins: Vec<PinSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TraitSnapshot {
+    pub name: String,
+    pub value: Option<TraitValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PinSnapshot {
+    pub feature: String,
+    pub value: FeatureValue,
+}
+
+/// Summary of what got restored by a snapshot import.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct SnapshotImportSummary {
+    pub features_created: usize,
+    pub features_updated: usize,
+    pub segments_created: usize,
+    pub segments_updated: usize,
+    pub identities: usize,
+}
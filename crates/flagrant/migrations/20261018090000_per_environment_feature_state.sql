@@ -0,0 +1,20 @@
+-- Enabled/archived state is owned by each environment independently (the same way control
+-- variants and variant_weights are), so switching a feature off in one environment no
+-- longer switches it off everywhere else in the project.
+CREATE TABLE IF NOT EXISTS feature_states (
+  feature_id INTEGER NOT NULL REFERENCES features,
+  environment_id INTEGER NOT NULL REFERENCES environments,
+  is_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+  archived_at DATETIME,
+
+  PRIMARY KEY (feature_id, environment_id)
+);
+
+-- Existing features keep their current (project-wide) state in every environment.
+INSERT INTO feature_states(feature_id, environment_id, is_enabled, archived_at)
+SELECT f.feature_id, e.environment_id, f.is_enabled, f.archived_at
+FROM features f
+JOIN environments e ON e.project_id = f.project_id;
+
+ALTER TABLE features DROP COLUMN is_enabled;
+ALTER TABLE features DROP COLUMN archived_at;
@@ -0,0 +1,20 @@
+-- Every patch applied to a feature, segment or identity is recorded as a revision holding
+-- JSON snapshots of the entity taken right before and right after the change. Revisions are
+-- never updated, reverting a change records a brand new revision instead.
+--
+-- entity_id is deliberately not a foreign key: history outlives the entity it describes.
+CREATE TABLE IF NOT EXISTS revisions (
+  revision_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL REFERENCES projects,
+  -- NULL for project-wide entities (segments)
+  environment_id INTEGER REFERENCES environments,
+  entity TEXT NOT NULL,
+  entity_id INTEGER NOT NULL,
+  author TEXT CHECK(LENGTH(author) <= 255),
+  before TEXT NOT NULL,
+  after TEXT NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_revisions_entity
+  ON revisions(entity, entity_id, environment_id);
@@ -0,0 +1,23 @@
+-- Feature and segment patches scheduled to be applied at given point in time.
+-- A background task picks up pending changes once they're due and applies them
+-- through regular patches, so that applied changes get recorded as revisions.
+--
+-- patch holds the JSON-serialized change, entity and entity_id are kept apart
+-- for filtering only and, similar to revisions, are not foreign keys.
+CREATE TABLE IF NOT EXISTS scheduled_changes (
+  scheduled_change_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL REFERENCES projects,
+  environment_id INTEGER NOT NULL REFERENCES environments,
+  entity TEXT NOT NULL,
+  entity_id INTEGER NOT NULL,
+  patch TEXT NOT NULL,
+  apply_at DATETIME NOT NULL,
+  author TEXT CHECK(LENGTH(author) <= 255),
+  status TEXT NOT NULL DEFAULT 'pending',
+  error TEXT,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  applied_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_scheduled_changes_due
+  ON scheduled_changes(status, apply_at);
@@ -0,0 +1,31 @@
+-- Progressive rollouts of non-control variants. Variant weight starts at start_weight
+-- and a background task bumps it by step every interval_minutes, until it reaches
+-- target_weight. Each step goes through a regular feature patch, so only the delta
+-- of identities gets migrated and the change is recorded as a revision.
+--
+-- next_step_at is set only for active plans. At most one plan per variant and
+-- environment is expected to be active or paused at a time.
+CREATE TABLE IF NOT EXISTS rollout_plans (
+  rollout_plan_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL REFERENCES projects,
+  environment_id INTEGER NOT NULL REFERENCES environments,
+  feature_id INTEGER NOT NULL REFERENCES features,
+  variant_id INTEGER NOT NULL REFERENCES variants ON DELETE CASCADE,
+  start_weight INTEGER NOT NULL CHECK(start_weight BETWEEN 0 AND 100),
+  step INTEGER NOT NULL CHECK(step BETWEEN 1 AND 100),
+  target_weight INTEGER NOT NULL CHECK(target_weight BETWEEN 0 AND 100),
+  interval_minutes INTEGER NOT NULL CHECK(interval_minutes > 0),
+  current_weight INTEGER NOT NULL,
+  status TEXT NOT NULL DEFAULT 'active',
+  next_step_at DATETIME,
+  error TEXT,
+  author TEXT CHECK(LENGTH(author) <= 255),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_rollout_plans_due
+  ON rollout_plans(status, next_step_at);
+
+CREATE INDEX IF NOT EXISTS idx_rollout_plans_variant
+  ON rollout_plans(environment_id, variant_id);
@@ -0,0 +1,16 @@
+-- API keys scoped to a project and optionally to one of its environments.
+-- Client keys grant read-only access to the public client API, management keys
+-- grant access to the management API.
+--
+-- Keys are never stored in plain text - key_hash holds a SHA-256 digest of the key
+-- and key_prefix its first few characters, so that keys can be told apart when listed.
+CREATE TABLE IF NOT EXISTS api_keys (
+  api_key_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  project_id INTEGER NOT NULL REFERENCES projects,
+  environment_id INTEGER REFERENCES environments,
+  name TEXT NOT NULL CHECK(LENGTH(name) <= 255),
+  kind TEXT NOT NULL,
+  key_prefix TEXT NOT NULL,
+  key_hash TEXT NOT NULL UNIQUE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
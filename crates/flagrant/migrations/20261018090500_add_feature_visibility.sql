@@ -0,0 +1,4 @@
+-- Features limited to backends ('backend') are left out of client API responses to
+-- requests authenticated with public client keys. Visibility is project-wide, the
+-- same in every environment.
+ALTER TABLE features ADD COLUMN visibility TEXT NOT NULL DEFAULT 'all';
@@ -0,0 +1,11 @@
+-- Explicit priority of segment feature overrides, per environment. When several
+-- segments overriding a feature match an identity, the override of the highest
+-- priority wins. Overrides with no row here have priority 0; ties go to the oldest
+-- segment.
+CREATE TABLE IF NOT EXISTS segment_priorities (
+  segment_id INTEGER NOT NULL REFERENCES segments,
+  feature_id INTEGER NOT NULL REFERENCES features,
+  environment_id INTEGER NOT NULL REFERENCES environments,
+  priority INTEGER NOT NULL,
+  PRIMARY KEY(segment_id, feature_id, environment_id)
+);
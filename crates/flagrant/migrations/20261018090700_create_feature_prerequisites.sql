@@ -0,0 +1,12 @@
+-- Prerequisites of features, per environment: a feature applies to an identity only if
+-- feature `required_feature_id` resolves to variant `variant_id` for that identity.
+-- Identities failing any of the prerequisites get the control value of the feature,
+-- without being distributed. Deleting the required feature or variant drops the
+-- prerequisite along.
+CREATE TABLE IF NOT EXISTS feature_prerequisites (
+  feature_id INTEGER NOT NULL REFERENCES features ON DELETE CASCADE,
+  environment_id INTEGER NOT NULL REFERENCES environments ON DELETE CASCADE,
+  required_feature_id INTEGER NOT NULL REFERENCES features ON DELETE CASCADE,
+  variant_id INTEGER NOT NULL REFERENCES variants ON DELETE CASCADE,
+  PRIMARY KEY(feature_id, environment_id, required_feature_id)
+);
@@ -0,0 +1,4 @@
+-- Tells whether traits sent along with client API requests get stored with identities:
+-- 'never' (used for the request only), 'known' (stored if the project defines a trait
+-- of the same name) or 'always'.
+ALTER TABLE projects ADD COLUMN trait_persistence TEXT NOT NULL DEFAULT 'never';
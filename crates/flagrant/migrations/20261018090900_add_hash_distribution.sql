@@ -0,0 +1,7 @@
+-- Features with a salt get distributed by hashing identity values along with the salt,
+-- instead of accumulators. Salt is project-wide, the same in every environment.
+ALTER TABLE features ADD COLUMN hash_salt TEXT;
+
+-- Buckets (out of 100) owned by a variant within hash-distributed features, as a hex
+-- bit mask. Buckets owned by no variant belong to the control variant.
+ALTER TABLE variant_weights ADD COLUMN buckets TEXT;
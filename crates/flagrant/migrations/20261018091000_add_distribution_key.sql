@@ -0,0 +1,25 @@
+-- Features with a distribution key get distributed by value of identity trait of that name
+-- instead of identity value, so that all the identities sharing the value get the same
+-- variant. Set per environment, like on/off status.
+ALTER TABLE feature_states ADD COLUMN distribution_key TEXT;
+
+-- Variants values of distribution keys are attached to, the same way identity_variants
+-- attaches identities. Deleting the variant or the segment assignment is attributed to
+-- drops the assignment, so that the key value gets distributed again.
+CREATE TABLE IF NOT EXISTS key_variants (
+  feature_id INTEGER NOT NULL REFERENCES features ON DELETE CASCADE,
+  environment_id INTEGER NOT NULL REFERENCES environments ON DELETE CASCADE,
+  key_value TEXT NOT NULL CHECK(LENGTH(key_value) <= 1024),
+  variant_id INTEGER NOT NULL REFERENCES variants ON DELETE CASCADE,
+  segment_id INTEGER REFERENCES segments ON DELETE CASCADE,
+
+  -- Settled on next read, the same way as in identity_variants: variant the value is being
+  -- migrated to after weights change, and whether a segment change requires the value's
+  -- segment attribution to be re-evaluated.
+  migrated_id INTEGER REFERENCES variants ON DELETE CASCADE,
+  segment_dirty BOOLEAN NOT NULL DEFAULT FALSE,
+
+  attached_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  PRIMARY KEY(feature_id, environment_id, key_value)
+);
@@ -0,0 +1,26 @@
+-- Exclusion layers group features of an environment, each of them given a share of the
+-- traffic. Identities get hashed into one of 100 buckets per layer, and only the feature
+-- whose share covers the bucket applies - the other features of the layer give them their
+-- control value.
+CREATE TABLE IF NOT EXISTS layers (
+  layer_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  environment_id INTEGER NOT NULL REFERENCES environments ON DELETE CASCADE,
+  name TEXT NOT NULL CHECK(LENGTH(name) <= 255),
+  description TEXT CHECK(LENGTH(description) <= 2048),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  UNIQUE(environment_id, name)
+);
+
+-- Shares of features within layers: buckets from first_bucket up to first_bucket + share
+-- (exclusive). A feature belongs to at most one layer per environment.
+CREATE TABLE IF NOT EXISTS layer_features (
+  layer_id INTEGER NOT NULL REFERENCES layers ON DELETE CASCADE,
+  feature_id INTEGER NOT NULL REFERENCES features ON DELETE CASCADE,
+  environment_id INTEGER NOT NULL REFERENCES environments ON DELETE CASCADE,
+  share INTEGER NOT NULL CHECK(share BETWEEN 1 AND 100),
+  first_bucket INTEGER NOT NULL CHECK(first_bucket BETWEEN 0 AND 99),
+
+  PRIMARY KEY(layer_id, feature_id),
+  UNIQUE(feature_id, environment_id)
+);
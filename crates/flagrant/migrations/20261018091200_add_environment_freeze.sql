@@ -0,0 +1,5 @@
+-- Kill switch of an environment. While frozen_at is set, features of the environment - or
+-- only the ones tagged with frozen_tag, if there is one - serve their control value. Weights,
+-- pins and assignments are left intact, to be served again once the environment thaws.
+ALTER TABLE environments ADD COLUMN frozen_at DATETIME;
+ALTER TABLE environments ADD COLUMN frozen_tag TEXT CHECK(LENGTH(frozen_tag) <= 32);
@@ -0,0 +1,22 @@
+-- :name create_api_key :<> :1
+-- :doc Stores a hashed API key of given kind, scoped to a project and optionally to an environment
+INSERT INTO api_keys(project_id, environment_id, name, kind, key_prefix, key_hash)
+VALUES($1, $2, $3, $4, $5, $6)
+RETURNING api_key_id, project_id, environment_id, name, kind, key_prefix AS prefix, created_at
+
+-- :name fetch_api_keys :<> :*
+-- :doc Returns all API keys of a project
+SELECT api_key_id, project_id, environment_id, name, kind, key_prefix AS prefix, created_at
+FROM api_keys
+WHERE project_id = $1
+ORDER BY api_key_id
+
+-- :name fetch_api_key_by_hash :<> :?
+-- :doc Returns API key of given hash
+SELECT api_key_id, project_id, environment_id, name, kind, key_prefix AS prefix, created_at
+FROM api_keys
+WHERE key_hash = $1
+
+-- :name delete_api_key :<> :!
+-- :doc Removes API key of given id within a project
+DELETE FROM api_keys WHERE api_key_id = $1 AND project_id = $2
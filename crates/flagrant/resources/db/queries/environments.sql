@@ -1,28 +1,56 @@
 -- :name create_environment :<> :1
 -- :doc Creates a new environment with name and description
 INSERT INTO environments(project_id, name, description) VALUES($1, $2, $3)
-RETURNING environment_id, project_id, name, description
+RETURNING environment_id, project_id, name, description, frozen_at, frozen_tag
 
 -- :name fetch_environment :<> :1
 -- :doc Returns a environment of given id
-SELECT environment_id, project_id, name, description
+SELECT environment_id, project_id, name, description, frozen_at, frozen_tag
 FROM environments
 WHERE environment_id = $1
 
 -- :name fetch_environments_for_project :<> :*
 -- :doc Returns all environments of given project
-SELECT environment_id, project_id, name, description
+SELECT environment_id, project_id, name, description, frozen_at, frozen_tag
 FROM environments
 WHERE project_id = $1
 
 -- :name fetch_environment_by_name :<> :1
 -- :doc Returns environment of given name
-SELECT environment_id, project_id, name, description
+SELECT environment_id, project_id, name, description, frozen_at, frozen_tag
 FROM environments
 WHERE project_id = $1 AND name = $2
 
 -- :name fetch_environments_by_pattern :<> :*
 -- :doc Returns list of environments in a project with names matching given pattern
-SELECT environment_id, project_id, name, description
+SELECT environment_id, project_id, name, description, frozen_at, frozen_tag
 FROM environments
 WHERE project_id = $1 AND name LIKE $2
+
+-- :name clone_feature_states :<> :!
+-- :doc Copies on/off and archivisation state of all features from one environment into another
+INSERT INTO feature_states(feature_id, environment_id, is_enabled, archived_at)
+SELECT feature_id, $2, is_enabled, archived_at
+FROM feature_states
+WHERE environment_id = $1
+
+-- :name freeze_environment :<> :!
+-- :doc Engages kill switch of an environment, for features of given tag only unless it's NULL
+UPDATE environments
+SET frozen_at = CURRENT_TIMESTAMP, frozen_tag = $2
+WHERE environment_id = $1
+
+-- :name thaw_environment :<> :!
+-- :doc Releases kill switch of an environment
+UPDATE environments
+SET frozen_at = NULL, frozen_tag = NULL
+WHERE environment_id = $1
+
+-- :name fetch_frozen_features :<> :*
+-- :doc Returns ids of features kill switch of an environment applies to, along with the tag switch is limited to, none if it's not engaged
+SELECT f.feature_id, e.frozen_tag
+FROM features f
+JOIN environments e ON e.project_id = f.project_id
+WHERE e.environment_id = $1 AND e.frozen_at IS NOT NULL
+  AND (e.frozen_tag IS NULL
+       OR EXISTS (SELECT 1 FROM feature_tags ft WHERE ft.feature_id = f.feature_id AND ft.tag = e.frozen_tag))
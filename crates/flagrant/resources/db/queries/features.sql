@@ -1,20 +1,24 @@
 -- :name create_feature :|| :1
--- :doc Creates a new feature with name, on/off status and value type
-INSERT INTO features(project_id, name, description, is_enabled) VALUES($1, $2, $3, $4)
-RETURNING feature_id, project_id, name, description, is_enabled, archived_at
+-- :doc Creates a new feature with name and description. On/off status is stored per environment.
+INSERT INTO features(project_id, name, description, visibility) VALUES($1, $2, $3, $4)
+RETURNING feature_id, project_id, name, description, visibility, hash_salt, FALSE AS is_enabled
 
 -- :name fetch_feature_by_id :|| :1
--- :doc Returns a feature of given id (without corresponding variants)
-SELECT f.feature_id, project_id, name, description, is_enabled, archived_at, GROUP_CONCAT(ft.tag, ',') AS tags
+-- :doc Returns a feature of given id (without corresponding variants) along with its status in given environment
+SELECT f.feature_id, project_id, name, description, visibility, hash_salt,
+       COALESCE(fs.is_enabled, FALSE) AS is_enabled, fs.archived_at, fs.distribution_key, GROUP_CONCAT(ft.tag, ',') AS tags
 FROM features f
+LEFT JOIN feature_states fs ON fs.feature_id = f.feature_id AND fs.environment_id = $2
 LEFT JOIN feature_tags ft ON ft.feature_id = f.feature_id
 WHERE f.feature_id = $1
 GROUP BY f.feature_id
 
 -- :name fetch_feature_by_name :|| :1
--- :doc Returns a feature with provided name
-SELECT f.feature_id, project_id, name, description, is_enabled, archived_at, GROUP_CONCAT(ft.tag, ',') AS tags
+-- :doc Returns a feature with provided name along with its status in given environment
+SELECT f.feature_id, project_id, name, description, visibility, hash_salt,
+       COALESCE(fs.is_enabled, FALSE) AS is_enabled, fs.archived_at, fs.distribution_key, GROUP_CONCAT(ft.tag, ',') AS tags
 FROM features f
+LEFT JOIN feature_states fs ON fs.feature_id = f.feature_id AND fs.environment_id = $3
 LEFT JOIN feature_tags ft ON ft.feature_id = f.feature_id
 WHERE project_id = $1 AND name = $2
 GROUP BY f.feature_id
@@ -26,20 +30,22 @@ WITH feature_tag_groups AS (
   FROM feature_tags
   GROUP BY feature_id
 )
-SELECT f.feature_id, f.project_id, f.name, f.description, f.is_enabled, f.archived_at,
+SELECT f.feature_id, f.project_id, f.name, f.description, f.visibility, f.hash_salt,
+       COALESCE(fs.is_enabled, FALSE) AS is_enabled, fs.archived_at, fs.distribution_key,
        v.variant_id, v.environment_id, v.value,
        COALESCE(vw.weight, 0) AS weight, vw.accumulator,
        ftg.tags
 FROM features f
+LEFT JOIN feature_states fs ON fs.feature_id = f.feature_id AND fs.environment_id = $2
 LEFT JOIN variants v ON v.feature_id = f.feature_id AND COALESCE(v.environment_id, $2) = $2
-LEFT JOIN variant_weights vw ON vw.variant_id = v.variant_id AND vw.environment_id = $2
+LEFT JOIN variant_weights vw ON vw.variant_id = v.variant_id AND vw.environment_id = $2 AND vw.segment_id IS NULL
 LEFT JOIN feature_tag_groups ftg ON ftg.feature_id = f.feature_id
 WHERE f.project_id = $1
 --~{ is_archived
-AND ($3 = (f.archived_at IS NOT NULL))
+AND ($3 = (fs.archived_at IS NOT NULL))
 --~}
 --~{ is_enabled
-AND f.is_enabled = $4
+AND COALESCE(fs.is_enabled, FALSE) = $4
 --~}
 --~{ pattern
 AND f.name LIKE($5)
@@ -56,21 +62,40 @@ AND NOT EXISTS (
   WHERE ft.feature_id = f.feature_id AND ft.tag = je.value
 )
 --~}
-ORDER BY f.is_enabled DESC, f.archived_at ASC, f.name, weight DESC
+ORDER BY is_enabled DESC, fs.archived_at ASC, f.name, weight DESC
 
 -- :name update_feature :<> :!
--- :doc Updates feature with new values of name and is_enabled flag
+-- :doc Updates feature with new name
 UPDATE features
-SET name = $2, is_enabled = $3
+SET name = $2
 WHERE feature_id = $1
 
+-- :name upsert_feature_status :<> :!
+-- :doc Sets feature on/off status in given environment
+INSERT INTO feature_states(feature_id, environment_id, is_enabled) VALUES($1, $2, $3)
+ON CONFLICT(feature_id, environment_id) DO UPDATE SET is_enabled = excluded.is_enabled
+
 -- :name update_feature_description :<> :!
 -- :doc Updates feature description
 UPDATE features SET description = $2 WHERE feature_id = $1
 
+-- :name update_feature_visibility :<> :!
+-- :doc Updates feature visibility (all or backend only)
+UPDATE features SET visibility = $2 WHERE feature_id = $1
+
+-- :name update_feature_hash_salt :<> :!
+-- :doc Updates salt of hash distribution (NULL for sticky distribution)
+UPDATE features SET hash_salt = $2 WHERE feature_id = $1
+
+-- :name upsert_feature_distribution_key :<> :!
+-- :doc Sets trait feature gets distributed by in given environment (NULL for identity value)
+INSERT INTO feature_states(feature_id, environment_id, distribution_key) VALUES($1, $2, $3)
+ON CONFLICT(feature_id, environment_id) DO UPDATE SET distribution_key = excluded.distribution_key
+
 -- :name archive_feature :<> :!
--- :doc Updates feature archivisation timestamp. If NULL then feature is not archived.
-UPDATE features SET archived_at = $2 WHERE feature_id = $1
+-- :doc Updates feature archivisation timestamp in given environment. If NULL then feature is not archived.
+INSERT INTO feature_states(feature_id, environment_id, archived_at) VALUES($1, $2, $3)
+ON CONFLICT(feature_id, environment_id) DO UPDATE SET archived_at = excluded.archived_at
 
 -- :name update_feature_variants_accumulators :<> :!
 -- :doc Bumps accumulators for feature variants, scoped to a segment (NULL = organic)
@@ -95,6 +120,14 @@ DELETE FROM variant_weights WHERE variant_id IN (SELECT variant_id FROM variants
 -- :doc Removes all identity_variants rows for a feature (across all environments).
 DELETE FROM identity_variants WHERE feature_id = $1
 
+-- :name delete_states_for_feature :<> :!
+-- :doc Removes feature on/off and archivisation state (across all environments).
+DELETE FROM feature_states WHERE feature_id = $1
+
+-- :name delete_segment_priorities_for_feature :<> :!
+-- :doc Removes priorities of segment overrides of a feature (across all environments).
+DELETE FROM segment_priorities WHERE feature_id = $1
+
 -- :name delete_tags_for_feature :<> :!
 -- :doc Removes a feature tags.
 DELETE FROM feature_tags WHERE feature_id = $1
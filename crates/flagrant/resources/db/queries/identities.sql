@@ -6,6 +6,10 @@ SELECT identity_id, identity, environment_id FROM identities WHERE environment_i
 -- :doc Fetches a single identity by environment and value
 SELECT identity_id, identity, environment_id FROM identities WHERE environment_id = $1 AND identity = lower($2)
 
+-- :name fetch_all_identities :<> :*
+-- :doc Fetches all identities of given environment ordered by identity value
+SELECT identity_id, identity, environment_id FROM identities WHERE environment_id = $1 ORDER BY identity
+
 -- :name fetch_identities_with_traits :<> :*
 -- :doc Lists up to 10 identities with their traits matching LIKE pattern (use '%' to match all)
 SELECT i.identity_id, i.identity, t.trait_id, t.name AS trait_name, it.value AS trait_value
@@ -94,6 +98,11 @@ WHERE feature_id = $1 AND environment_id = $2 AND identity_id IN (
     SELECT identity_id FROM identities WHERE environment_id = $2 AND identity LIKE $3
 )
 
+-- :name delete_key_variants_for_feature_pattern :<> :!
+-- :doc Removes variant assignments of distribution key values matching a LIKE pattern, for a single feature in an environment
+DELETE FROM key_variants
+WHERE feature_id = $1 AND environment_id = $2 AND key_value LIKE $3
+
 -- :name delete_identities_for_environment_pattern :<> :!
 -- :doc Removes identity records in an environment matching a LIKE pattern
 DELETE FROM identities WHERE environment_id = $1 AND identity LIKE $2
@@ -180,3 +189,49 @@ DELETE FROM identity_variants WHERE identity_id = $1 AND feature_id = $2 AND env
 -- :name delete_attachments :<> :!
 -- :doc Removes attachments of all identitites to given variant. This is executed only on variant deletion.
 DELETE FROM identity_variants WHERE variant_id = $1 OR migrated_id = $1
+
+-- :name fetch_key_variant :<> :?
+-- :doc Returns variant (and segment it's attributed to) given distribution key value is attached to for given feature+environment, along with its pending migration and dirty flag
+SELECT kv.variant_id, kv.migrated_id, kv.segment_id, kv.segment_dirty FROM key_variants kv
+WHERE kv.feature_id = $1 AND kv.environment_id = $2 AND kv.key_value = $3
+
+-- :name insert_key_variant :<> :!
+-- :doc Attaches distribution key value to variant of given id, attributed to segment_id (NULL = organic)
+INSERT INTO key_variants(feature_id, environment_id, key_value, variant_id, segment_id)
+VALUES($1, $2, $3, $4, $5)
+ON CONFLICT(feature_id, environment_id, key_value) DO UPDATE SET
+  variant_id = excluded.variant_id, segment_id = excluded.segment_id,
+  migrated_id = NULL, segment_dirty = FALSE, attached_at = CURRENT_TIMESTAMP
+
+-- :name clear_key_dirty :<> :!
+-- :doc Clears segment_dirty for a single distribution key value of a feature+environment, the key_variants counterpart of clear_identity_dirty
+UPDATE key_variants SET segment_dirty = FALSE
+WHERE feature_id = $1 AND environment_id = $2 AND key_value = $3
+
+-- :name mark_feature_keys_dirty :<> :!
+-- :doc Flags every distribution key value attached for a feature+environment as needing re-evaluation against current segment state, the key_variants counterpart of mark_feature_dirty
+UPDATE key_variants SET segment_dirty = TRUE
+WHERE feature_id = $1 AND environment_id = $2
+
+-- :name migrate_key_values :<> :!
+-- :doc Migrates given percent of organic distribution key values attached to one variant into
+-- the other variant, the key_variants counterpart of migrate_identities. The percent is taken
+-- of all the key values attached for the feature.
+WITH attached AS (
+  SELECT key_value, migrated_id, attached_at
+  FROM key_variants
+  WHERE environment_id = $1 AND ((variant_id = $2 AND migrated_id IS NULL) OR migrated_id = $2)
+    AND segment_id IS NULL
+)
+UPDATE key_variants SET migrated_id = $3
+WHERE environment_id = $1
+  AND feature_id = (SELECT feature_id FROM variants WHERE variant_id = $2)
+  AND key_value IN (
+    SELECT key_value FROM attached
+    ORDER BY migrated_id DESC, attached_at
+    LIMIT (
+      -- round division up
+      SELECT MAX(0, (SELECT CAST((COUNT(*) * $4 + 99) / 100.0 AS INTEGER) FROM key_variants
+        WHERE environment_id = $1 AND feature_id = (SELECT feature_id FROM variants WHERE variant_id = $2)))
+    )
+  )
@@ -0,0 +1,50 @@
+-- :name create_layer :<> :1
+-- :doc Creates a new exclusion layer within given environment
+INSERT INTO layers(environment_id, name, description) VALUES($1, $2, $3)
+RETURNING layer_id, environment_id, name, description, created_at
+
+-- :name fetch_layers :<> :*
+-- :doc Returns all exclusion layers of given environment
+SELECT layer_id, environment_id, name, description, created_at
+FROM layers
+WHERE environment_id = $1
+ORDER BY name
+
+-- :name fetch_layer :<> :1
+-- :doc Returns exclusion layer of given id within given environment
+SELECT layer_id, environment_id, name, description, created_at
+FROM layers
+WHERE environment_id = $1 AND layer_id = $2
+
+-- :name fetch_layer_by_name :<> :1
+-- :doc Returns exclusion layer of given name within given environment
+SELECT layer_id, environment_id, name, description, created_at
+FROM layers
+WHERE environment_id = $1 AND name = $2
+
+-- :name delete_layer :<> :!
+-- :doc Removes exclusion layer of given id within given environment, along with shares of its features
+DELETE FROM layers WHERE environment_id = $1 AND layer_id = $2
+
+-- :name fetch_layer_features :<> :*
+-- :doc Returns shares of features within exclusion layers of given environment, in order of their buckets
+SELECT lf.layer_id, lf.feature_id, f.name AS feature_name, lf.share, lf.first_bucket
+FROM layer_features lf
+JOIN features f ON f.feature_id = lf.feature_id
+WHERE lf.environment_id = $1
+ORDER BY lf.layer_id, lf.first_bucket
+
+-- :name fetch_feature_layer :<> :?
+-- :doc Returns id of exclusion layer given feature belongs to within given environment
+SELECT layer_id FROM layer_features WHERE feature_id = $1 AND environment_id = $2
+
+-- :name upsert_layer_feature :<> :!
+-- :doc Sets share of a feature within exclusion layer, starting at given bucket
+INSERT INTO layer_features(layer_id, feature_id, environment_id, share, first_bucket)
+VALUES($1, $2, $3, $4, $5)
+ON CONFLICT(layer_id, feature_id) DO UPDATE SET
+  share = excluded.share, first_bucket = excluded.first_bucket
+
+-- :name delete_layer_feature :<> :!
+-- :doc Removes a feature from exclusion layer
+DELETE FROM layer_features WHERE layer_id = $1 AND feature_id = $2
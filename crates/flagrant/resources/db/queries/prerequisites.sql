@@ -0,0 +1,26 @@
+-- :name fetch_prerequisites_for_feature :<> :*
+-- :doc Returns prerequisites of given feature in given environment, along with names of required features and values of required variants
+SELECT p.feature_id AS dependent_id, p.required_feature_id AS feature_id, f.name AS feature_name, p.variant_id, v.value
+FROM feature_prerequisites p
+JOIN features f ON f.feature_id = p.required_feature_id
+JOIN variants v ON v.variant_id = p.variant_id
+WHERE p.feature_id = $1 AND p.environment_id = $2
+ORDER BY f.name
+
+-- :name fetch_prerequisites_for_environment :<> :*
+-- :doc Returns prerequisites of all the features in given environment
+SELECT p.feature_id AS dependent_id, p.required_feature_id AS feature_id, f.name AS feature_name, p.variant_id, v.value
+FROM feature_prerequisites p
+JOIN features f ON f.feature_id = p.required_feature_id
+JOIN variants v ON v.variant_id = p.variant_id
+WHERE p.environment_id = $1
+ORDER BY p.feature_id, f.name
+
+-- :name upsert_prerequisite :<> :!
+-- :doc Requires feature to resolve to given variant, replacing a variant required of the same feature before
+INSERT INTO feature_prerequisites(feature_id, environment_id, required_feature_id, variant_id) VALUES($1, $2, $3, $4)
+ON CONFLICT(feature_id, environment_id, required_feature_id) DO UPDATE SET variant_id = excluded.variant_id
+
+-- :name delete_prerequisite :<> :!
+-- :doc Removes a prerequisite of feature in given environment
+DELETE FROM feature_prerequisites WHERE feature_id = $1 AND environment_id = $2 AND required_feature_id = $3
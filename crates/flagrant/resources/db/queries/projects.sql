@@ -1,22 +1,29 @@
 -- :name create_project :<> :1
 -- :doc Creates a new project with a name
 INSERT INTO projects(name) VALUES($1)
-RETURNING project_id, name
+RETURNING project_id, name, trait_persistence
 
 -- :name fetch_project :<> :1
 -- :doc Fetches a project of given id
-SELECT project_id, name
+SELECT project_id, name, trait_persistence
 FROM projects
 WHERE project_id = $1
 
 -- :name fetch_project_by_name :<> :1
 -- :doc Fetches a project by name
-SELECT project_id, name
+SELECT project_id, name, trait_persistence
 FROM projects
 WHERE name = $1
 
 -- :name list_projects :<> :*
 -- :doc Lists all projects
-SELECT project_id, name
+SELECT project_id, name, trait_persistence
 FROM projects
 ORDER BY project_id
+
+-- :name update_project_trait_persistence :<> :1
+-- :doc Sets persistence policy of traits sent along with client API requests
+UPDATE projects
+SET trait_persistence = $2
+WHERE project_id = $1
+RETURNING project_id, name, trait_persistence
@@ -0,0 +1,18 @@
+-- :name create_revision :<> :!
+-- :doc Records a new revision of an entity with JSON snapshots taken before and after the change
+INSERT INTO revisions(project_id, environment_id, entity, entity_id, author, before, after)
+VALUES($1, $2, $3, $4, $5, $6, $7)
+
+-- :name fetch_revision_by_id :|| :1
+-- :doc Returns a single revision of given id within a project
+SELECT revision_id, environment_id, entity, entity_id, author, before, after, created_at
+FROM revisions
+WHERE revision_id = $1 AND project_id = $2
+
+-- :name fetch_revisions_for_entity :|| :*
+-- :doc Returns revisions of an entity, newest first. Project-wide entities are matched by NULL environment
+SELECT revision_id, environment_id, entity, entity_id, author, before, after, created_at
+FROM revisions
+WHERE entity = $1 AND entity_id = $2 AND environment_id IS $3
+ORDER BY revision_id DESC
+LIMIT $4
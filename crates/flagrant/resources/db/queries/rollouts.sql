@@ -0,0 +1,48 @@
+-- :name create_rollout_plan :<> :1
+-- :doc Creates a rollout plan of a variant, starting at its start weight
+INSERT INTO rollout_plans(project_id, environment_id, feature_id, variant_id, start_weight, step, target_weight, interval_minutes, current_weight, status, next_step_at, author)
+VALUES($1, $2, $3, $4, $5, $6, $7, $8, $5, $9, $10, $11)
+RETURNING rollout_plan_id, environment_id, feature_id, variant_id, start_weight, step, target_weight, interval_minutes, current_weight, status, next_step_at, error, author, created_at, updated_at
+
+-- :name fetch_variant_feature_id :<> :?
+-- :doc Returns id of a feature given variant belongs to
+SELECT feature_id FROM variants WHERE variant_id = $1
+
+-- :name fetch_rollout_plan_by_id :<> :1
+-- :doc Returns a single rollout plan of given id
+SELECT rollout_plan_id, environment_id, feature_id, variant_id, start_weight, step, target_weight, interval_minutes, current_weight, status, next_step_at, error, author, created_at, updated_at
+FROM rollout_plans
+WHERE rollout_plan_id = $1
+
+-- :name fetch_latest_rollout_plan :<> :?
+-- :doc Returns the most recent rollout plan of a variant within an environment
+SELECT rollout_plan_id, environment_id, feature_id, variant_id, start_weight, step, target_weight, interval_minutes, current_weight, status, next_step_at, error, author, created_at, updated_at
+FROM rollout_plans
+WHERE environment_id = $1 AND variant_id = $2
+ORDER BY rollout_plan_id DESC
+LIMIT 1
+
+-- :name fetch_feature_rollout_plans :<> :*
+-- :doc Returns the most recent rollout plan of each feature variant within an environment
+SELECT rollout_plan_id, environment_id, feature_id, variant_id, start_weight, step, target_weight, interval_minutes, current_weight, status, next_step_at, error, author, created_at, updated_at
+FROM rollout_plans
+WHERE rollout_plan_id IN (
+  SELECT MAX(rollout_plan_id)
+  FROM rollout_plans
+  WHERE environment_id = $1 AND feature_id = $2
+  GROUP BY variant_id)
+ORDER BY variant_id
+
+-- :name fetch_due_rollout_plans :<> :*
+-- :doc Returns active plans, across all environments, with a step due at given time
+SELECT rollout_plan_id, environment_id, feature_id, variant_id, start_weight, step, target_weight, interval_minutes, current_weight, status, next_step_at, error, author, created_at, updated_at
+FROM rollout_plans
+WHERE status = 'active' AND next_step_at <= $1
+ORDER BY next_step_at, rollout_plan_id
+LIMIT $2
+
+-- :name update_rollout_plan :<> :!
+-- :doc Moves a plan to a new state, provided it's still in the expected status. Otherwise plan is left untouched
+UPDATE rollout_plans
+SET status = $2, current_weight = $3, next_step_at = $4, error = $5, updated_at = CURRENT_TIMESTAMP
+WHERE rollout_plan_id = $1 AND status = $6
@@ -0,0 +1,33 @@
+-- :name create_scheduled_change :<> :1
+-- :doc Schedules a JSON-serialized patch of an entity to be applied at given time
+INSERT INTO scheduled_changes(project_id, environment_id, entity, entity_id, patch, apply_at, author)
+VALUES($1, $2, $3, $4, $5, $6, $7)
+RETURNING scheduled_change_id, environment_id, patch, apply_at, author, status, error, created_at, applied_at
+
+-- :name fetch_scheduled_change_by_id :<> :1
+-- :doc Returns a single scheduled change of given id within an environment
+SELECT scheduled_change_id, environment_id, patch, apply_at, author, status, error, created_at, applied_at
+FROM scheduled_changes
+WHERE scheduled_change_id = $1 AND environment_id = $2
+
+-- :name fetch_scheduled_changes :<> :*
+-- :doc Returns changes of given status scheduled within an environment, optionally narrowed down to a single entity
+SELECT scheduled_change_id, environment_id, patch, apply_at, author, status, error, created_at, applied_at
+FROM scheduled_changes
+WHERE environment_id = $1 AND status = $2 AND ($3 IS NULL OR (entity = $3 AND entity_id = $4))
+ORDER BY apply_at, scheduled_change_id
+LIMIT $5
+
+-- :name fetch_due_scheduled_changes :<> :*
+-- :doc Returns pending changes, across all environments, which are due at given time
+SELECT scheduled_change_id, environment_id, patch, apply_at, author, status, error, created_at, applied_at
+FROM scheduled_changes
+WHERE status = 'pending' AND apply_at <= $1
+ORDER BY apply_at, scheduled_change_id
+LIMIT $2
+
+-- :name update_scheduled_change_status :<> :!
+-- :doc Moves a pending scheduled change to its final status. Changes no longer pending are left untouched
+UPDATE scheduled_changes
+SET status = $2, error = $3, applied_at = $4
+WHERE scheduled_change_id = $1 AND status = 'pending'
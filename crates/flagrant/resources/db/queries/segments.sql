@@ -33,6 +33,47 @@ ORDER BY name
 -- :doc Updates segment name and description
 UPDATE segments SET name = $2, description = $3 WHERE segment_id = $1
 
+-- :name fetch_sibling_segments :<> :*
+-- :doc Returns all segments of the project given segment belongs to, including itself
+SELECT segment_id, project_id, name, description
+FROM segments
+WHERE project_id = (SELECT project_id FROM segments WHERE segment_id = $1)
+
+-- :name fetch_segment_references :<> :*
+-- :doc Returns segment ids along with names of segments they reference, within the project given segment belongs to
+SELECT DISTINCT g.segment_id, substr(r.driver, 9) AS referenced
+FROM segment_rules r
+JOIN segment_groups g ON g.group_id = r.group_id
+JOIN segments s ON s.segment_id = g.segment_id
+WHERE s.project_id = (SELECT project_id FROM segments WHERE segment_id = $1)
+  AND r.driver LIKE 'segment:%'
+
+-- :name rename_segment_references :<> :!
+-- :doc Points rules referencing segment named $2 within project $1 to its new name $3
+UPDATE segment_rules SET driver = 'segment:' || $3
+WHERE driver = 'segment:' || $2
+  AND group_id IN (
+    SELECT g.group_id
+    FROM segment_groups g
+    JOIN segments s ON s.segment_id = g.segment_id
+    WHERE s.project_id = $1
+  )
+
+-- :name upsert_segment_priority :<> :!
+-- :doc Sets priority of segment's override of a feature within an environment
+INSERT INTO segment_priorities(segment_id, feature_id, environment_id, priority)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT(segment_id, feature_id, environment_id) DO UPDATE SET priority = excluded.priority
+
+-- :name delete_segment_priority :<> :!
+-- :doc Resets priority of segment's override of a feature within an environment
+DELETE FROM segment_priorities
+WHERE segment_id = $1 AND feature_id = $2 AND environment_id = $3
+
+-- :name delete_segment_priorities :<> :!
+-- :doc Removes priorities of all segment's overrides (across all environments)
+DELETE FROM segment_priorities WHERE segment_id = $1
+
 -- :name delete_segment :<> :!
 -- :doc Deletes a segment by id
 DELETE FROM segments WHERE segment_id = $1
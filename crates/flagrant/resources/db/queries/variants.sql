@@ -116,18 +116,25 @@ DELETE FROM variant_weights
 WHERE segment_id = $1 AND environment_id = $3
   AND variant_id IN (SELECT variant_id FROM variants WHERE feature_id = $2)
 
+-- :name delete_segment_variant_weight :<> :!
+-- :doc Removes a segment-scoped weight override of a single variant within given environment
+DELETE FROM variant_weights
+WHERE segment_id = $1 AND variant_id = $2 AND environment_id = $3
+
 -- :name fetch_segment_overrides_with_weights :<> :*
 -- :doc Returns (segment_id, segment_name, variant_id, weight) for all segments overriding
 -- feature+environment. Includes the control variant's auto-balanced remainder row (listed
 -- first per segment) so callers can display where the rest of the percentages go, or (for
 -- the rule evaluator) run a full weighted distribution once a segment matches. Ordered by
--- segment_id ascending (creation order) so callers grouping these rows don't need to re-sort.
+-- priority descending, then segment_id ascending (creation order) so callers grouping these
+-- rows don't need to re-sort.
 SELECT vw.segment_id, s.name, vw.variant_id, vw.weight
 FROM variant_weights vw
 JOIN segments s USING(segment_id)
 JOIN variants v ON v.variant_id = vw.variant_id
+LEFT JOIN segment_priorities sp ON sp.segment_id = vw.segment_id AND sp.feature_id = $1 AND sp.environment_id = $2
 WHERE v.feature_id = $1 AND vw.environment_id = $2 AND vw.segment_id IS NOT NULL
-ORDER BY vw.segment_id, (v.environment_id IS NULL), vw.variant_id
+ORDER BY COALESCE(sp.priority, 0) DESC, vw.segment_id, (v.environment_id IS NULL), vw.variant_id
 
 -- :name fetch_segment_variant_weights :<> :*
 -- :doc Returns variant_id + weight overrides for a given segment+feature+environment.
@@ -138,26 +145,29 @@ JOIN variants v ON v.variant_id = vw.variant_id
 WHERE vw.segment_id = $1 AND v.feature_id = $2 AND vw.environment_id = $3 AND v.environment_id IS NULL
 
 -- :name fetch_features_overridden_by_segment :<> :*
--- :doc Returns (feature_id, feature_name, variant_id, is_control, value, weight) for every
--- variant this segment overrides (including each feature's control-variant remainder),
+-- :doc Returns (feature_id, feature_name, priority, variant_id, is_control, value, weight) for
+-- every variant this segment overrides (including each feature's control-variant remainder),
 -- across all features, within a given environment.
-SELECT f.feature_id, f.name AS feature_name, vw.variant_id,
+SELECT f.feature_id, f.name AS feature_name, COALESCE(sp.priority, 0) AS priority, vw.variant_id,
        (v.environment_id IS NOT NULL) AS is_control, v.value, vw.weight
 FROM variant_weights vw
 JOIN variants v ON v.variant_id = vw.variant_id
 JOIN features f ON f.feature_id = v.feature_id
+LEFT JOIN segment_priorities sp ON sp.segment_id = vw.segment_id AND sp.feature_id = f.feature_id AND sp.environment_id = vw.environment_id
 WHERE vw.segment_id = $1 AND vw.environment_id = $2
 ORDER BY f.name, (v.environment_id IS NULL), vw.variant_id
 
 -- :name fetch_variants_for_identity :<> :*
--- :doc Fetches feature variants for given identity. Variants attached to identity by distributor are denoted by non-NULL identity_id field.
-SELECT f.feature_id, iv.variant_id, f.name AS feature_name, iv_v.value AS feature_value, iv.migrated_id,
+-- :doc Fetches variants of features not archived in given environment for given identity. Variants attached to identity by distributor are denoted by non-NULL identity_id field.
+SELECT f.feature_id, iv.variant_id, f.name AS feature_name, iv_v.value AS feature_value,
+       fs.is_enabled, f.visibility AS feature_visibility, f.hash_salt, fs.distribution_key, iv.migrated_id,
        iv.segment_id, COALESCE(iv.segment_dirty, FALSE) AS segment_dirty, iv.pinned_at, iv.identity_id
 FROM features f
+JOIN feature_states fs ON fs.feature_id = f.feature_id AND fs.environment_id = $2
 LEFT JOIN identities i ON i.identity = lower($3) AND i.environment_id = $2
 LEFT JOIN identity_variants iv ON iv.feature_id = f.feature_id AND iv.environment_id = $2 AND iv.identity_id = i.identity_id
 LEFT JOIN variants iv_v ON iv_v.variant_id = iv.variant_id
-WHERE f.archived_at IS NULL AND f.project_id = $1
+WHERE fs.archived_at IS NULL AND f.project_id = $1
 ORDER BY iv.identity_id DESC
 
 -- :name fetch_count_of_feature_variants :<> :1
@@ -181,3 +191,24 @@ SELECT DISTINCT vw.environment_id, v.feature_id
 FROM variant_weights vw
 JOIN variants v ON v.variant_id = vw.variant_id
 WHERE vw.segment_id = $1
+
+-- :name fetch_control_variant :<> :1
+-- :doc Fetches control variant of given feature in given environment, along with its organic weight
+SELECT v.variant_id, v.environment_id, feature_id, value, COALESCE(weight, 0) AS weight, COALESCE(accumulator, 0) AS accumulator
+FROM variants v
+LEFT JOIN variant_weights vw ON v.variant_id = vw.variant_id AND vw.environment_id = $1 AND vw.segment_id IS NULL
+WHERE v.feature_id = $2 AND v.environment_id = $1
+
+-- :name fetch_variant_buckets :<> :*
+-- :doc Fetches weights and bucket masks of non-control variants of given feature, scoped to a segment's weights (NULL = organic default weights)
+SELECT v.variant_id, vw.weight, vw.buckets
+FROM variants v
+JOIN variant_weights vw ON vw.variant_id = v.variant_id AND vw.environment_id = $1 AND vw.segment_id IS $3
+WHERE v.feature_id = $2 AND v.environment_id IS NULL
+ORDER BY v.variant_id
+
+-- :name update_variant_buckets :<> :!
+-- :doc Sets bucket mask of a variant, scoped to a segment's weights (NULL = organic default weights)
+UPDATE variant_weights
+SET buckets = $4
+WHERE variant_id = $1 AND environment_id = $2 AND segment_id IS $3
@@ -1,7 +1,17 @@
 use flagrant_types::{Environment, Variant};
 use sqlx::{Connection, SqliteConnection};
 
-use crate::models::{feature, variant};
+use crate::{
+    evaluator,
+    models::{feature, variant},
+};
+
+/// Number of buckets identities get hashed into by hash distribution - one per percent of
+/// variant weight.
+const HASH_BUCKETS: u32 = 100;
+
+/// Mask of all the [`HASH_BUCKETS`].
+const ALL_BUCKETS: u128 = (1 << HASH_BUCKETS) - 1;
 
 /// Distributes a hit among the defined feature variants according to their associated weights.
 /// `segment_id` scopes distribution to a segment's override weights (`None` = organic
@@ -38,3 +48,110 @@ pub async fn distribute(
     tx.commit().await?;
     Ok(variant)
 }
+
+/// Distributes identity of given `value` among feature variants by hashing it along with
+/// `salt` into one of [`HASH_BUCKETS`], and returns the variant owning that bucket.
+/// `segment_id` scopes distribution to a segment's override weights (`None` = organic
+/// default weights).
+///
+/// Buckets are laid out along variant weights whenever weights change, see
+/// [`lay_out_buckets`]. Hash distribution itself never writes anything - should the stored
+/// layout lag behind weights, it gets adjusted on the fly.
+pub async fn hash(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+    segment_id: Option<i32>,
+    value: &str,
+    salt: &str,
+) -> anyhow::Result<Variant> {
+    let variants = variant::get_buckets(conn, environment, feature_id, segment_id).await?;
+    let mut layout = stored_layout(&variants);
+    lay_out(&mut layout);
+
+    let bucket = evaluator::bucket(value, salt) as u64 * HASH_BUCKETS as u64 / evaluator::BUCKETS;
+    let owner = variants
+        .iter()
+        .zip(&layout)
+        .find(|(_, (_, mask))| mask & (1 << bucket) != 0);
+
+    match owner {
+        Some(((variant_id, ..), _)) => {
+            variant::get_by_id(conn, environment, *variant_id, segment_id).await
+        }
+        None => variant::get_control(conn, environment, feature_id).await,
+    }
+}
+
+/// Adjusts stored bucket layout of non-control variants of `feature_id` to their current
+/// weights. Has to be called by every write changing weights, within the same transaction.
+/// `segment_id` scopes the layout to a segment's override weights (`None` = organic
+/// default weights).
+pub(crate) async fn lay_out_buckets(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+    segment_id: Option<i32>,
+) -> anyhow::Result<()> {
+    let variants = variant::get_buckets(conn, environment, feature_id, segment_id).await?;
+    let mut layout = stored_layout(&variants);
+    let stored: Vec<u128> = layout.iter().map(|(_, mask)| *mask).collect();
+    lay_out(&mut layout);
+
+    for ((variant_id, ..), ((_, mask), stored)) in variants.iter().zip(layout.iter().zip(stored)) {
+        if *mask != stored {
+            variant::set_buckets(
+                conn,
+                environment,
+                *variant_id,
+                segment_id,
+                format!("{mask:x}"),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Turns `(variant_id, weight, buckets)` rows into `(weight, mask)` pairs, with buckets
+/// never laid out taken as an empty mask.
+fn stored_layout(variants: &[(i32, u8, Option<String>)]) -> Vec<(u8, u128)> {
+    variants
+        .iter()
+        .map(|(_, weight, buckets)| {
+            let mask = buckets
+                .as_deref()
+                .and_then(|b| u128::from_str_radix(b, 16).ok())
+                .unwrap_or(0);
+            (*weight, mask)
+        })
+        .collect()
+}
+
+/// Adjusts bucket masks of non-control variants, given as `(weight, mask)`, so that each
+/// variant owns as many buckets as its weight. Variants owning too many buckets give up
+/// their highest ones, variants owning too few take the lowest ones owned by no variant
+/// (which are the control variant's). Buckets change hands only as far as weights have
+/// changed, the rest of the layout stays intact - hence so do identities hashed into them.
+fn lay_out(variants: &mut [(u8, u128)]) {
+    let mut taken = 0u128;
+    for (weight, mask) in variants.iter_mut() {
+        // Should a bucket ever end up owned twice, it stays with the first owner.
+        *mask &= ALL_BUCKETS & !taken;
+        while mask.count_ones() > *weight as u32 {
+            *mask &= !(1 << (127 - mask.leading_zeros()));
+        }
+        taken |= *mask;
+    }
+    for (weight, mask) in variants.iter_mut() {
+        while mask.count_ones() < *weight as u32 {
+            let free = ALL_BUCKETS & !taken;
+            if free == 0 {
+                break;
+            }
+            let bucket = 1 << free.trailing_zeros();
+            *mask |= bucket;
+            taken |= bucket;
+        }
+    }
+}
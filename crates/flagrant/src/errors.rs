@@ -19,4 +19,21 @@ pub enum FlagrantError {
 
     #[error("Not found: {0}")]
     NotFound(&'static str),
+
+    #[error("Unauthorized ({0})")]
+    Unauthorized(&'static str),
+
+    #[error("Forbidden ({0})")]
+    Forbidden(&'static str),
+
+    #[error("Invalid token ({0}). Cause: {1}")]
+    InvalidToken(&'static str, anyhow::Error),
+}
+
+/// Whether `error` tells that the thing looked up doesn't exist.
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::NotFound(_))
+    )
 }
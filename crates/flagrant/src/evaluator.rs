@@ -4,17 +4,36 @@
 //! (environment + identity), and returns that segment's id for the caller to pass into
 //! `distributor::distribute` (which scopes the weighted pick, and its accumulator state,
 //! to that segment).
+//!
+//! Segments get compiled before evaluation - rule values parsed up front into whatever
+//! their comparators need. Compiled segments, along with ids of segments overriding each
+//! feature, are kept in a [`SegmentCache`] handed over to [`evaluate`] by the caller, who
+//! also takes care of invalidating it whenever segments, their rules or overrides change.
 
-use std::{borrow::Cow, cmp::Ordering};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
+use chrono::{DateTime, TimeDelta, Utc};
 use flagrant_types::{
     Comparator, Environment, GroupConnector, IdentityTrait, Project, Segment, SegmentDriver,
     SegmentGroup, SegmentRule, TraitValue,
+    explain::{GroupEvaluation, RuleEvaluation, SegmentEvaluation},
+    parse_datetime, parse_duration, parse_version,
 };
+use regex::Regex;
+use sha2::{Digest, Sha256};
 use sqlx::SqliteConnection;
 
 use crate::models::segment;
 
+/// Number of buckets identities get hashed into by `Bucket` drivers.
+pub(crate) const BUCKETS: u64 = 10_000;
+
 /// A borrowed view of just the identity data the evaluator needs (value + traits).
 ///
 /// Deliberately not `flagrant_types::IdentityWithTraits`: that type owns its `value: String`,
@@ -37,14 +56,16 @@ pub struct IdentityContext<'a> {
 ///
 /// Borrows rather than owns (so `Str(&'a str)`, not `Str(String)`) so resolving a driver
 /// never needs to clone the identity's value, the environment's name, or a trait's string -
-/// everything it points at already lives in `Environment`/`IdentityContext`/`SegmentRule`
+/// everything it points at already lives in `Environment`/`IdentityContext`/`CompiledRule`
 /// for at least as long as one rule evaluation.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum ActualValue<'a> {
     Str(&'a str),
     Int(i32),
     Float(f32),
     Bool(bool),
+    Version(Cow<'a, semver::Version>),
+    DateTime(DateTime<Utc>),
 }
 
 impl<'a> From<&'a TraitValue> for ActualValue<'a> {
@@ -54,49 +75,444 @@ impl<'a> From<&'a TraitValue> for ActualValue<'a> {
             TraitValue::Int(i) => ActualValue::Int(*i),
             TraitValue::Float(f) => ActualValue::Float(*f),
             TraitValue::Bool(b) => ActualValue::Bool(*b),
+            TraitValue::Version(v) => ActualValue::Version(Cow::Borrowed(v)),
+            TraitValue::DateTime(dt) => ActualValue::DateTime(*dt),
+        }
+    }
+}
+
+/// A segment compiled for evaluation: groups and rules of a [`Segment`], with rule values
+/// parsed ahead, so that evaluating a rule never parses anything again.
+#[derive(Debug)]
+struct CompiledSegment {
+    id: i32,
+    name: String,
+    groups: Vec<CompiledGroup>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledGroup {
+    label: String,
+    connector: Option<GroupConnector>,
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    id: i32,
+    driver: SegmentDriver,
+    comparator: Comparator,
+    value: RuleValue,
+}
+
+/// Rule value along with its parsed forms. Comparisons are type-directed - the type of the
+/// actual value decides how a rule value gets interpreted - so value is parsed into every
+/// scalar type it happens to parse into. Sets, patterns and durations are parsed only for
+/// comparators taking them. `None` wherever parsing fails, which makes comparisons against
+/// that form fail closed.
+#[derive(Debug, Clone)]
+struct RuleValue {
+    raw: String,
+    int: Option<i32>,
+    float: Option<f32>,
+    bool: Option<bool>,
+    version: Option<semver::Version>,
+    datetime: Option<DateTime<Utc>>,
+    duration: Option<TimeDelta>,
+    set: Option<Vec<serde_json::Value>>,
+    pattern: Option<Regex>,
+}
+
+impl RuleValue {
+    fn new(comparator: &Comparator, raw: String) -> Self {
+        RuleValue {
+            int: raw.parse().ok(),
+            float: raw.parse().ok(),
+            bool: raw.parse().ok(),
+            version: parse_version(&raw),
+            datetime: parse_datetime(&raw),
+            duration: match comparator {
+                Comparator::OlderThan | Comparator::NewerThan => parse_duration(&raw),
+                _ => None,
+            },
+            set: match comparator {
+                Comparator::In | Comparator::NotIn => serde_json::from_str(&raw).ok(),
+                _ => None,
+            },
+            pattern: match comparator {
+                Comparator::MatchesRegex | Comparator::DoesNotMatchRegex => Regex::new(&raw).ok(),
+                _ => None,
+            },
+            raw,
         }
     }
 }
 
+impl From<Segment> for CompiledSegment {
+    fn from(segment: Segment) -> Self {
+        CompiledSegment {
+            id: segment.id,
+            name: segment.name,
+            groups: segment
+                .groups
+                .into_iter()
+                .map(CompiledGroup::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<SegmentGroup> for CompiledGroup {
+    fn from(group: SegmentGroup) -> Self {
+        CompiledGroup {
+            label: group.label,
+            connector: group.connector,
+            rules: group.rules.into_iter().map(CompiledRule::from).collect(),
+        }
+    }
+}
+
+impl From<SegmentRule> for CompiledRule {
+    fn from(rule: SegmentRule) -> Self {
+        CompiledRule {
+            id: rule.id,
+            value: RuleValue::new(&rule.comparator, rule.value),
+            driver: rule.driver,
+            comparator: rule.comparator,
+        }
+    }
+}
+
+/// Handle to compiled segments and overrides, shared by all the evaluations it gets
+/// passed to - clones of a handle share the same cache. Cache is keyed by ids, so a handle
+/// is meant to be used with a single database only, like the one `flagrant-api` keeps in
+/// its state.
+#[derive(Clone, Default)]
+pub struct SegmentCache(Arc<RwLock<CachedSegments>>);
+
+#[derive(Default)]
+struct CachedSegments {
+    /// Bumped with each invalidation, so that segments loaded before one don't get cached
+    /// after it.
+    generation: u64,
+    /// Ids of segments overriding a feature, in priority order, keyed by
+    /// `(environment_id, feature_id)`.
+    overrides: HashMap<(i32, i32), Arc<[i32]>>,
+    segments: HashMap<i32, Arc<CompiledSegment>>,
+    /// Ids of cached segments, keyed by `(project_id, name)`.
+    names: HashMap<(i32, String), i32>,
+}
+
+impl SegmentCache {
+    /// Drops all the segments and overrides cached. Has to be called after every write
+    /// changing segments, their groups and rules, or segment overrides - once the change
+    /// is committed, so that evaluations running meanwhile don't cache the state it replaces.
+    pub fn invalidate(&self) {
+        let mut cache = self.0.write().unwrap();
+        *cache = CachedSegments {
+            generation: cache.generation + 1,
+            ..Default::default()
+        };
+    }
+}
+
+/// Returns ids of segments overriding `feature_id` in `environment`, in priority order.
+async fn overriding_segments(
+    conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    environment: &Environment,
+    feature_id: i32,
+) -> anyhow::Result<Arc<[i32]>> {
+    let key = (environment.id, feature_id);
+    let generation = {
+        let cache = segments.0.read().unwrap();
+        if let Some(ids) = cache.overrides.get(&key) {
+            return Ok(ids.clone());
+        }
+        cache.generation
+    };
+    let ids: Arc<[i32]> = segment::list_overrides_for_feature(conn, environment.id, feature_id)
+        .await?
+        .into_iter()
+        .map(|(segment_id, _name, _weights)| segment_id)
+        .collect();
+
+    let mut cache = segments.0.write().unwrap();
+    if cache.generation == generation {
+        cache.overrides.insert(key, ids.clone());
+    }
+    Ok(ids)
+}
+
+/// Returns compiled segment of given id, loading (and compiling) it unless cached already.
+async fn compiled_by_id(
+    conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    project: &Project,
+    segment_id: i32,
+) -> anyhow::Result<Arc<CompiledSegment>> {
+    let generation = {
+        let cache = segments.0.read().unwrap();
+        if let Some(segment) = cache.segments.get(&segment_id) {
+            return Ok(segment.clone());
+        }
+        cache.generation
+    };
+    let segment = segment::get_by_id(conn, project, segment_id).await?;
+    Ok(cache_compiled(segments, generation, segment))
+}
+
+/// Returns compiled segment of given name, loading (and compiling) it unless cached already.
+async fn compiled_by_name(
+    conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    project: &Project,
+    name: &str,
+) -> anyhow::Result<Arc<CompiledSegment>> {
+    let generation = {
+        let cache = segments.0.read().unwrap();
+        if let Some(segment) = cache
+            .names
+            .get(&(project.id, name.to_owned()))
+            .and_then(|id| cache.segments.get(id))
+        {
+            return Ok(segment.clone());
+        }
+        cache.generation
+    };
+    let segment = segment::get_by_name(conn, project, name.to_owned()).await?;
+    Ok(cache_compiled(segments, generation, segment))
+}
+
+/// Compiles `segment` and caches it, unless the cache got invalidated since `generation`.
+fn cache_compiled(
+    segments: &SegmentCache,
+    generation: u64,
+    segment: Segment,
+) -> Arc<CompiledSegment> {
+    let project_id = segment.project_id;
+    let compiled = Arc::new(CompiledSegment::from(segment));
+
+    let mut cache = segments.0.write().unwrap();
+    if cache.generation == generation {
+        cache
+            .names
+            .insert((project_id, compiled.name.clone()), compiled.id);
+        cache.segments.insert(compiled.id, compiled.clone());
+    }
+    compiled
+}
+
+/// Segments referenced by `Segment(name)` rules, loaded ahead of evaluation along with the
+/// segments they reference in turn, so that rule evaluation itself never hits the DB. Each
+/// of them is evaluated at most once, no matter how many rules (of how many candidate
+/// segments, overriding how many features) refer to it.
+///
+/// Outcomes get remembered for the identity being evaluated, hence references are meant
+/// to be passed to [`evaluate`] calls for a single identity within a single environment
+/// only - like all the features resolved for one request.
+#[derive(Default)]
+pub struct References {
+    segments: HashMap<String, Arc<CompiledSegment>>,
+    matched: RefCell<HashMap<String, bool>>,
+}
+
+impl References {
+    /// Loads segments referenced by `segment`, transitively, unless already loaded.
+    async fn load(
+        &mut self,
+        conn: &mut SqliteConnection,
+        segments: &SegmentCache,
+        project: &Project,
+        segment: &CompiledSegment,
+    ) -> anyhow::Result<()> {
+        let mut pending: Vec<String> = referenced_names(segment).collect();
+
+        while let Some(name) = pending.pop() {
+            if self.segments.contains_key(&name) {
+                continue;
+            }
+            // Referenced segments can't be deleted, but let's fail closed if one is gone.
+            let Ok(referenced) = compiled_by_name(conn, segments, project, &name).await else {
+                continue;
+            };
+            pending.extend(referenced_names(&referenced));
+            self.segments.insert(name, referenced);
+        }
+        Ok(())
+    }
+
+    /// Whether `identity` matches referenced segment of given `name`. Unknown segments
+    /// never match.
+    fn matches(
+        &self,
+        name: &str,
+        environment: &Environment,
+        identity: &IdentityContext<'_>,
+        now: DateTime<Utc>,
+    ) -> Option<bool> {
+        if let Some(matched) = self.matched.borrow().get(name) {
+            return Some(*matched);
+        }
+        let segment = self.segments.get(name)?;
+
+        // Reference cycles are rejected when rules get added; should one slip through,
+        // a segment being evaluated doesn't match itself instead of recursing forever.
+        self.matched.borrow_mut().insert(name.to_owned(), false);
+        let matched = segment_matches(segment, environment, identity, now, self);
+        self.matched.borrow_mut().insert(name.to_owned(), matched);
+        Some(matched)
+    }
+}
+
+/// Names of segments referenced by rules of `segment`.
+fn referenced_names(segment: &CompiledSegment) -> impl Iterator<Item = String> + '_ {
+    segment
+        .groups
+        .iter()
+        .flat_map(|g| &g.rules)
+        .filter_map(|r| match &r.driver {
+            SegmentDriver::Segment(name) => Some(name.clone()),
+            _ => None,
+        })
+}
+
 /// Evaluates every segment overriding `feature_id` in `environment`, in priority order
-/// (oldest segment first), and returns the id of the first segment whose rules match
-/// `identity`. Returns `None` if no segment overrides this feature, or none of them match.
+/// (highest priority first, oldest segment on ties), and returns the id of the first
+/// segment whose rules match `identity`. Returns `None` if no segment overrides this
+/// feature, or none of them match. Time-relative rules are evaluated as of the time of
+/// this call, segments referenced by rules - as of the first call given `references`.
 pub async fn evaluate(
     conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    references: &mut References,
     environment: &Environment,
     identity: &IdentityContext<'_>,
     feature_id: i32,
 ) -> anyhow::Result<Option<i32>> {
-    let candidates = segment::list_overrides_for_feature(conn, environment.id, feature_id).await?;
+    let candidates = overriding_segments(conn, segments, environment, feature_id).await?;
     let project = Project {
         id: environment.project_id,
         ..Default::default()
     };
+    let now = Utc::now();
 
-    for (segment_id, _name, _weights) in candidates {
-        let seg = segment::get_by_id(conn, &project, segment_id).await?;
-        if segment_matches(&seg, environment, identity) {
+    for &segment_id in candidates.iter() {
+        let seg = compiled_by_id(conn, segments, &project, segment_id).await?;
+        references.load(conn, segments, &project, &seg).await?;
+        if segment_matches(&seg, environment, identity, now, references) {
             return Ok(Some(segment_id));
         }
     }
     Ok(None)
 }
 
-/// Folds groups left-to-right: the first group is the base predicate; each subsequent
-/// group ANDs or AND-NOTs the running result per its `connector`. A segment with no
-/// groups never matches.
+/// Same as [`evaluate`], but reports how the segments have been evaluated, group by group
+/// and rule by rule - up to, and including, the first one matching. Segments always get
+/// loaded as stored, bypassing any cache.
+pub async fn explain(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    identity: &IdentityContext<'_>,
+    feature_id: i32,
+) -> anyhow::Result<Vec<SegmentEvaluation>> {
+    let segments = &SegmentCache::default();
+    let candidates = overriding_segments(conn, segments, environment, feature_id).await?;
+    let project = Project {
+        id: environment.project_id,
+        ..Default::default()
+    };
+    let now = Utc::now();
+
+    let mut references = References::default();
+    let mut evaluations = Vec::new();
+
+    for &segment_id in candidates.iter() {
+        let seg = compiled_by_id(conn, segments, &project, segment_id).await?;
+        references.load(conn, segments, &project, &seg).await?;
+
+        let evaluation = explain_segment(&seg, environment, identity, now, &references);
+        let matched = evaluation.matched;
+        evaluations.push(evaluation);
+        if matched {
+            break;
+        }
+    }
+    Ok(evaluations)
+}
+
 fn segment_matches(
-    segment: &Segment,
+    segment: &CompiledSegment,
     environment: &Environment,
     identity: &IdentityContext<'_>,
+    now: DateTime<Utc>,
+    references: &References,
+) -> bool {
+    fold_groups(&segment.groups, |group| {
+        group_matches(group, environment, identity, now, references)
+    })
+}
+
+/// Same as [`segment_matches`], but records the outcome of each group and rule evaluated.
+fn explain_segment(
+    segment: &CompiledSegment,
+    environment: &Environment,
+    identity: &IdentityContext<'_>,
+    now: DateTime<Utc>,
+    references: &References,
+) -> SegmentEvaluation {
+    let mut groups = Vec::new();
+    let matched = fold_groups(&segment.groups, |group| {
+        let rules: Vec<RuleEvaluation> = group
+            .rules
+            .iter()
+            .map(|rule| explain_rule(rule, environment, identity, now, references))
+            .collect();
+        let matched = rules.iter().any(|r| r.matched);
+
+        groups.push(GroupEvaluation {
+            label: group.label.clone(),
+            connector: group.connector.clone(),
+            matched,
+            rules,
+        });
+        matched
+    });
+    SegmentEvaluation {
+        segment_id: segment.id,
+        segment_name: segment.name.clone(),
+        matched,
+        groups,
+    }
+}
+
+/// Folds groups left-to-right, with AND/AND-NOT binding tighter than OR/OR-NOT: the first
+/// group is the base predicate; each subsequent group ANDs or AND-NOTs the running term
+/// per its `connector`, while an OR/OR-NOT group starts a new term. `a AND b OR NOT c AND d`
+/// reads `(a AND b) OR (NOT c AND d)`. A segment matches if any of its terms does; a segment
+/// with no groups never matches.
+fn fold_groups(
+    groups: &[CompiledGroup],
+    mut group_matches: impl FnMut(&CompiledGroup) -> bool,
 ) -> bool {
     let mut acc: Option<bool> = None;
-    for group in &segment.groups {
-        let group_match = group_matches(group, environment, identity);
+    for group in groups {
+        if acc == Some(true)
+            && matches!(
+                group.connector,
+                Some(GroupConnector::Or | GroupConnector::OrNot)
+            )
+        {
+            // Previous term already matches, no need to evaluate further ones.
+            return true;
+        }
+        let group_match = group_matches(group);
         acc = Some(match &acc {
             None => group_match,
             Some(prev) => match group.connector {
                 Some(GroupConnector::AndNot) => *prev && !group_match,
+                Some(GroupConnector::Or) => group_match,
+                Some(GroupConnector::OrNot) => !group_match,
                 _ => *prev && group_match,
             },
         });
@@ -107,33 +523,78 @@ fn segment_matches(
 /// A group matches if ANY of its rules match (rules within a group are OR-ed). A group
 /// with no rules never matches (`any()` over an empty iterator is `false`).
 fn group_matches(
-    group: &SegmentGroup,
+    group: &CompiledGroup,
     environment: &Environment,
     identity: &IdentityContext<'_>,
+    now: DateTime<Utc>,
+    references: &References,
 ) -> bool {
     group
         .rules
         .iter()
-        .any(|rule| rule_matches(rule, environment, identity))
+        .any(|rule| rule_matches(rule, environment, identity, now, references))
 }
 
 /// Resolves `rule.driver` to an actual value, then dispatches to `comparator_matches`.
 /// Fail-closed: a `Trait(name)` driver whose trait is absent from `identity` (or present
-/// with `value: None`) never matches, regardless of comparator polarity.
+/// with `value: None`) never matches, regardless of comparator polarity. Time-relative
+/// comparators are evaluated as of `now` - the request time.
 fn rule_matches(
-    rule: &SegmentRule,
+    rule: &CompiledRule,
     environment: &Environment,
     identity: &IdentityContext<'_>,
+    now: DateTime<Utc>,
+    references: &References,
 ) -> bool {
-    let Some(actual) = resolve_actual(&rule.driver, environment, identity) else {
+    let Some(actual) = resolve_rule(rule, environment, identity, now, references) else {
         return false;
     };
-    comparator_matches(&rule.comparator, &actual, &rule.value)
+    comparator_matches(&rule.comparator, &actual, &rule.value, now)
+}
+
+/// Same as [`rule_matches`], but records the value driver resolved to along with outcome.
+fn explain_rule(
+    rule: &CompiledRule,
+    environment: &Environment,
+    identity: &IdentityContext<'_>,
+    now: DateTime<Utc>,
+    references: &References,
+) -> RuleEvaluation {
+    let actual = resolve_rule(rule, environment, identity, now, references);
+    let matched = actual
+        .as_ref()
+        .is_some_and(|actual| comparator_matches(&rule.comparator, actual, &rule.value, now));
+
+    RuleEvaluation {
+        rule_id: rule.id,
+        driver: rule.driver.clone(),
+        comparator: rule.comparator.clone(),
+        value: rule.value.raw.clone(),
+        actual: actual.map(|actual| as_plain_string(&actual).into_owned()),
+        matched,
+    }
+}
+
+/// Resolves driver of `rule`, evaluating referenced segment for `Segment(name)` drivers.
+fn resolve_rule<'a>(
+    rule: &CompiledRule,
+    environment: &'a Environment,
+    identity: &IdentityContext<'a>,
+    now: DateTime<Utc>,
+    references: &References,
+) -> Option<ActualValue<'a>> {
+    match &rule.driver {
+        SegmentDriver::Segment(name) => references
+            .matches(name, environment, identity, now)
+            .map(ActualValue::Bool),
+        driver => resolve_actual(driver, environment, identity),
+    }
 }
 
 /// Resolves the driver to the concrete value from the request context. `Identity` and
 /// `Environment` are plain contextual strings, not trait data - only `Trait(name)` involves
-/// an actual `TraitValue`, converted here into the evaluator's own `ActualValue`.
+/// an actual `TraitValue`, converted here into the evaluator's own `ActualValue`. `Bucket`
+/// is computed out of the identity value on the fly.
 fn resolve_actual<'a>(
     driver: &SegmentDriver,
     environment: &'a Environment,
@@ -148,10 +609,31 @@ fn resolve_actual<'a>(
             .find(|t| &t.name == name)
             .and_then(|t| t.value.as_ref())
             .map(ActualValue::from),
+        SegmentDriver::Bucket { salt } => Some(ActualValue::Int(bucket(identity.value, salt))),
+        // Resolved by `rule_matches`, as it takes evaluating another segment.
+        SegmentDriver::Segment(_) => None,
     }
 }
 
-fn comparator_matches(comparator: &Comparator, actual: &ActualValue, rule_value: &str) -> bool {
+/// Hashes identity `value` along with `salt` into one of [`BUCKETS`]. Unlike the
+/// accumulator-based distribution, buckets need no state at all - an identity always
+/// lands in the same bucket, so long as the salt stays the same.
+pub(crate) fn bucket(value: &str, salt: &str) -> i32 {
+    let digest = Sha256::new()
+        .chain_update(salt.as_bytes())
+        .chain_update([0])
+        .chain_update(value.as_bytes())
+        .finalize();
+    let prefix = u64::from_be_bytes(digest[..8].try_into().unwrap());
+    (prefix % BUCKETS) as i32
+}
+
+fn comparator_matches(
+    comparator: &Comparator,
+    actual: &ActualValue,
+    rule_value: &RuleValue,
+    now: DateTime<Utc>,
+) -> bool {
     match comparator {
         Comparator::ExactlyMatches => {
             parse_as(actual, rule_value).is_some_and(|parsed| *actual == parsed)
@@ -159,8 +641,8 @@ fn comparator_matches(comparator: &Comparator, actual: &ActualValue, rule_value:
         Comparator::DoesNotMatch => {
             !parse_as(actual, rule_value).is_some_and(|parsed| *actual == parsed)
         }
-        Comparator::Contains => as_plain_string(actual).contains(rule_value),
-        Comparator::DoesNotContain => !as_plain_string(actual).contains(rule_value),
+        Comparator::Contains => as_plain_string(actual).contains(rule_value.raw.as_str()),
+        Comparator::DoesNotContain => !as_plain_string(actual).contains(rule_value.raw.as_str()),
         Comparator::GreaterThan => {
             matches!(numeric_cmp(actual, rule_value), Some(Ordering::Greater))
         }
@@ -175,26 +657,91 @@ fn comparator_matches(comparator: &Comparator, actual: &ActualValue, rule_value:
         ),
         Comparator::In => in_set(actual, rule_value),
         Comparator::NotIn => !in_set(actual, rule_value),
+        Comparator::MatchesRegex => matches_pattern(actual, rule_value) == Some(true),
+        Comparator::DoesNotMatchRegex => matches_pattern(actual, rule_value) == Some(false),
+        Comparator::VersionGreaterThan => {
+            matches!(version_cmp(actual, rule_value), Some(Ordering::Greater))
+        }
+        Comparator::VersionGreaterEqualThan => matches!(
+            version_cmp(actual, rule_value),
+            Some(Ordering::Greater | Ordering::Equal)
+        ),
+        Comparator::VersionLowerThan => {
+            matches!(version_cmp(actual, rule_value), Some(Ordering::Less))
+        }
+        Comparator::VersionLowerEqualThan => matches!(
+            version_cmp(actual, rule_value),
+            Some(Ordering::Less | Ordering::Equal)
+        ),
+        Comparator::Before => as_datetime(actual)
+            .zip(rule_value.datetime)
+            .is_some_and(|(actual, instant)| actual < instant),
+        Comparator::After => as_datetime(actual)
+            .zip(rule_value.datetime)
+            .is_some_and(|(actual, instant)| actual > instant),
+        Comparator::OlderThan => as_datetime(actual)
+            .zip(rule_value.duration)
+            .is_some_and(|(actual, duration)| actual < now - duration),
+        Comparator::NewerThan => as_datetime(actual)
+            .zip(rule_value.duration)
+            .is_some_and(|(actual, duration)| actual > now - duration),
     }
 }
 
-fn numeric_cmp(actual: &ActualValue, rule_value: &str) -> Option<Ordering> {
+/// Point in time `actual` stands for, if any. Date times may come as plain strings too,
+/// e.g. with traits taken from a token.
+fn as_datetime(actual: &ActualValue) -> Option<DateTime<Utc>> {
+    match actual {
+        ActualValue::DateTime(dt) => Some(*dt),
+        ActualValue::Str(s) => parse_datetime(s),
+        _ => None,
+    }
+}
+
+/// Matches plain string form of `actual` against the rule's pattern. `None` for an invalid
+/// pattern (rejected at write time), which makes both regex comparators fail closed.
+fn matches_pattern(actual: &ActualValue, rule_value: &RuleValue) -> Option<bool> {
+    let regex = rule_value.pattern.as_ref()?;
+    Some(regex.is_match(&as_plain_string(actual)))
+}
+
+fn numeric_cmp(actual: &ActualValue, rule_value: &RuleValue) -> Option<Ordering> {
     let parsed = parse_as(actual, rule_value)?;
     match (actual, &parsed) {
         (ActualValue::Int(x), ActualValue::Int(y)) => Some(x.cmp(y)),
         (ActualValue::Float(x), ActualValue::Float(y)) => x.partial_cmp(y),
+        (ActualValue::Version(x), ActualValue::Version(y)) => Some(x.cmp_precedence(y)),
+        (ActualValue::DateTime(x), ActualValue::DateTime(y)) => Some(x.cmp(y)),
         _ => None,
     }
 }
 
-/// Parses `raw` into the same `ActualValue` variant as `actual` (type-directed parse).
-/// `None` on parse failure. Borrows `raw` for the `Str` case rather than allocating.
-fn parse_as<'a>(actual: &ActualValue, raw: &'a str) -> Option<ActualValue<'a>> {
+/// Compares `actual` and `rule_value` as versions, whatever the type of `actual` is -
+/// versions often come as plain strings, or even as numbers (`5`, `5.2`). Build metadata
+/// doesn't take part in comparison, pre-releases come before their release.
+fn version_cmp(actual: &ActualValue, rule_value: &RuleValue) -> Option<Ordering> {
+    let expected = rule_value.version.as_ref()?;
+    match actual {
+        ActualValue::Version(v) => Some(v.cmp_precedence(expected)),
+        ActualValue::Bool(_) => None,
+        _ => parse_version(&as_plain_string(actual)).map(|v| v.cmp_precedence(expected)),
+    }
+}
+
+/// Picks the form of `rule_value` of the same `ActualValue` variant as `actual`
+/// (type-directed parse, done ahead by compilation). `None` if the value didn't parse
+/// into that type. Borrows from `rule_value`, so no allocation is needed.
+fn parse_as<'a>(actual: &ActualValue, rule_value: &'a RuleValue) -> Option<ActualValue<'a>> {
     match actual {
-        ActualValue::Str(_) => Some(ActualValue::Str(raw)),
-        ActualValue::Int(_) => raw.parse::<i32>().ok().map(ActualValue::Int),
-        ActualValue::Float(_) => raw.parse::<f32>().ok().map(ActualValue::Float),
-        ActualValue::Bool(_) => raw.parse::<bool>().ok().map(ActualValue::Bool),
+        ActualValue::Str(_) => Some(ActualValue::Str(&rule_value.raw)),
+        ActualValue::Int(_) => rule_value.int.map(ActualValue::Int),
+        ActualValue::Float(_) => rule_value.float.map(ActualValue::Float),
+        ActualValue::Bool(_) => rule_value.bool.map(ActualValue::Bool),
+        ActualValue::Version(_) => rule_value
+            .version
+            .as_ref()
+            .map(|v| ActualValue::Version(Cow::Borrowed(v))),
+        ActualValue::DateTime(_) => rule_value.datetime.map(ActualValue::DateTime),
     }
 }
 
@@ -207,17 +754,24 @@ fn as_plain_string<'a>(actual: &ActualValue<'a>) -> Cow<'a, str> {
         ActualValue::Int(i) => Cow::Owned(i.to_string()),
         ActualValue::Float(f) => Cow::Owned(f.to_string()),
         ActualValue::Bool(b) => Cow::Owned(b.to_string()),
+        ActualValue::Version(v) => Cow::Owned(v.to_string()),
+        ActualValue::DateTime(dt) => Cow::Owned(dt.to_rfc3339()),
     }
 }
 
-/// `In`/`NotIn` membership: parses `rule_value` as a JSON array, checks whether any element
+/// Plain string form of a trait value, same as the one `Contains` rules look into.
+pub(crate) fn plain_trait_value(value: &TraitValue) -> Cow<'_, str> {
+    as_plain_string(&ActualValue::from(value))
+}
+
+/// `In`/`NotIn` membership: checks whether any element of the rule's JSON array
 /// type-appropriately equals `actual`. Malformed JSON is assumed not to occur (validated at
 /// write time); if it does, this simply returns `false` rather than panicking.
-fn in_set(actual: &ActualValue, rule_value: &str) -> bool {
-    let Ok(items) = serde_json::from_str::<Vec<serde_json::Value>>(rule_value) else {
-        return false;
-    };
-    items.iter().any(|item| json_equals(actual, item))
+fn in_set(actual: &ActualValue, rule_value: &RuleValue) -> bool {
+    rule_value
+        .set
+        .as_ref()
+        .is_some_and(|items| items.iter().any(|item| json_equals(actual, item)))
 }
 
 fn json_equals(actual: &ActualValue, item: &serde_json::Value) -> bool {
@@ -226,6 +780,11 @@ fn json_equals(actual: &ActualValue, item: &serde_json::Value) -> bool {
         ActualValue::Int(i) => item.as_i64() == Some(*i as i64),
         ActualValue::Float(f) => item.as_f64() == Some(*f as f64),
         ActualValue::Bool(b) => item.as_bool() == Some(*b),
+        ActualValue::Version(v) => item
+            .as_str()
+            .and_then(parse_version)
+            .is_some_and(|item| item.cmp_precedence(v) == Ordering::Equal),
+        ActualValue::DateTime(dt) => item.as_str().and_then(parse_datetime) == Some(*dt),
     }
 }
 
@@ -241,6 +800,8 @@ mod tests {
             project_id: 1,
             name: name.to_string(),
             description: None,
+            frozen_at: None,
+            frozen_tag: None,
         }
     }
 
@@ -275,35 +836,43 @@ mod tests {
         }
     }
 
-    fn rule(driver: SegmentDriver, comparator: Comparator, value: &str) -> SegmentRule {
+    fn rule(driver: SegmentDriver, comparator: Comparator, value: &str) -> CompiledRule {
         SegmentRule {
             id: 1,
             driver,
             comparator,
             value: value.to_string(),
         }
+        .into()
     }
 
-    fn group(connector: Option<GroupConnector>, rules: Vec<SegmentRule>) -> SegmentGroup {
-        SegmentGroup {
-            id: 1,
+    fn group(connector: Option<GroupConnector>, rules: Vec<CompiledRule>) -> CompiledGroup {
+        CompiledGroup {
             label: "group-1".to_string(),
-            description: None,
             connector,
             rules,
         }
     }
 
-    fn segment(groups: Vec<SegmentGroup>) -> Segment {
-        Segment {
+    fn segment(groups: Vec<CompiledGroup>) -> CompiledSegment {
+        CompiledSegment {
             id: 1,
-            project_id: 1,
             name: "seg".to_string(),
-            description: None,
             groups,
         }
     }
 
+    /// Compiles `rule_value` on the fly, for comparators to be tested on plain values.
+    fn comparator_matches(
+        comparator: &Comparator,
+        actual: &ActualValue,
+        rule_value: &str,
+        now: DateTime<Utc>,
+    ) -> bool {
+        let rule_value = RuleValue::new(comparator, rule_value.to_string());
+        super::comparator_matches(comparator, actual, &rule_value, now)
+    }
+
     // -- comparator_matches --------------------------------------------------------------
 
     #[test]
@@ -311,18 +880,21 @@ mod tests {
         assert!(comparator_matches(
             &Comparator::ExactlyMatches,
             &ActualValue::Int(42),
-            "42"
+            "42",
+            Utc::now()
         ));
         assert!(!comparator_matches(
             &Comparator::ExactlyMatches,
             &ActualValue::Int(42),
-            "43"
+            "43",
+            Utc::now()
         ));
         // Unparseable as the actual's type => never matches.
         assert!(!comparator_matches(
             &Comparator::ExactlyMatches,
             &ActualValue::Int(42),
-            "not-a-number"
+            "not-a-number",
+            Utc::now()
         ));
     }
 
@@ -331,12 +903,14 @@ mod tests {
         assert!(comparator_matches(
             &Comparator::DoesNotMatch,
             &ActualValue::Str("a"),
-            "b"
+            "b",
+            Utc::now()
         ));
         assert!(!comparator_matches(
             &Comparator::DoesNotMatch,
             &ActualValue::Str("a"),
-            "a"
+            "a",
+            Utc::now()
         ));
     }
 
@@ -345,12 +919,14 @@ mod tests {
         assert!(comparator_matches(
             &Comparator::Contains,
             &ActualValue::Int(1234),
-            "23"
+            "23",
+            Utc::now()
         ));
         assert!(!comparator_matches(
             &Comparator::Contains,
             &ActualValue::Int(1234),
-            "int::"
+            "int::",
+            Utc::now()
         ));
     }
 
@@ -359,12 +935,14 @@ mod tests {
         assert!(!comparator_matches(
             &Comparator::GreaterThan,
             &ActualValue::Str("z"),
-            "a"
+            "a",
+            Utc::now()
         ));
         assert!(!comparator_matches(
             &Comparator::GreaterThan,
             &ActualValue::Bool(true),
-            "false"
+            "false",
+            Utc::now()
         ));
     }
 
@@ -373,22 +951,26 @@ mod tests {
         assert!(comparator_matches(
             &Comparator::GreaterThan,
             &ActualValue::Int(10),
-            "5"
+            "5",
+            Utc::now()
         ));
         assert!(comparator_matches(
             &Comparator::GreaterEqualThan,
             &ActualValue::Int(10),
-            "10"
+            "10",
+            Utc::now()
         ));
         assert!(comparator_matches(
             &Comparator::LowerThan,
             &ActualValue::Float(1.5),
-            "2.0"
+            "2.0",
+            Utc::now()
         ));
         assert!(comparator_matches(
             &Comparator::LowerEqualThan,
             &ActualValue::Float(2.0),
-            "2.0"
+            "2.0",
+            Utc::now()
         ));
     }
 
@@ -397,22 +979,215 @@ mod tests {
         assert!(comparator_matches(
             &Comparator::In,
             &ActualValue::Str("b"),
-            r#"["a","b","c"]"#
+            r#"["a","b","c"]"#,
+            Utc::now()
         ));
         assert!(!comparator_matches(
             &Comparator::In,
             &ActualValue::Str("z"),
-            r#"["a","b","c"]"#
+            r#"["a","b","c"]"#,
+            Utc::now()
         ));
         assert!(comparator_matches(
             &Comparator::NotIn,
             &ActualValue::Int(5),
-            "[1,2,3]"
+            "[1,2,3]",
+            Utc::now()
         ));
         assert!(!comparator_matches(
             &Comparator::NotIn,
             &ActualValue::Int(2),
-            "[1,2,3]"
+            "[1,2,3]",
+            Utc::now()
+        ));
+    }
+
+    #[test]
+    fn regex_comparators_match_plain_string_form() {
+        assert!(comparator_matches(
+            &Comparator::MatchesRegex,
+            &ActualValue::Str("jane@ourcompany.com"),
+            r"@ourcompany\.com$",
+            Utc::now()
+        ));
+        assert!(!comparator_matches(
+            &Comparator::MatchesRegex,
+            &ActualValue::Str("jane@example.com"),
+            r"@ourcompany\.com$",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::MatchesRegex,
+            &ActualValue::Int(42),
+            r"^4\d$",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::DoesNotMatchRegex,
+            &ActualValue::Str("3.9.1"),
+            r"^4\.",
+            Utc::now()
+        ));
+        assert!(!comparator_matches(
+            &Comparator::DoesNotMatchRegex,
+            &ActualValue::Str("4.0.2"),
+            r"^4\.",
+            Utc::now()
+        ));
+    }
+
+    #[test]
+    fn version_comparators_order_by_semver_precedence() {
+        let version = |raw: &str| ActualValue::Version(Cow::Owned(parse_version(raw).unwrap()));
+
+        assert!(comparator_matches(
+            &Comparator::VersionGreaterThan,
+            &version("5.10.0"),
+            "5.2.0",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::VersionLowerThan,
+            &version("5.2.0-rc.1"),
+            "5.2.0",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::VersionLowerThan,
+            &version("5.2.0-alpha"),
+            "5.2.0-beta",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::VersionGreaterEqualThan,
+            &version("5.2.0+build.7"),
+            "5.2",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::VersionLowerEqualThan,
+            &version("4.9.9"),
+            "v5",
+            Utc::now()
+        ));
+        // Plain numeric ordering still works on versions.
+        assert!(comparator_matches(
+            &Comparator::GreaterThan,
+            &version("5.10.0"),
+            "5.9.0",
+            Utc::now()
+        ));
+    }
+
+    #[test]
+    fn version_comparators_parse_string_and_numeric_actual_values() {
+        assert!(comparator_matches(
+            &Comparator::VersionGreaterEqualThan,
+            &ActualValue::Str("5.10.0"),
+            "5.2.0",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::VersionGreaterThan,
+            &ActualValue::Int(6),
+            "5.2.0",
+            Utc::now()
+        ));
+        assert!(!comparator_matches(
+            &Comparator::VersionGreaterThan,
+            &ActualValue::Str("not-a-version"),
+            "5.2.0",
+            Utc::now()
+        ));
+        assert!(!comparator_matches(
+            &Comparator::VersionLowerThan,
+            &ActualValue::Bool(true),
+            "5.2.0",
+            Utc::now()
+        ));
+    }
+
+    #[test]
+    fn absolute_time_comparators_compare_points_in_time() {
+        let signed_up = ActualValue::DateTime(parse_datetime("2026-03-01T12:00:00Z").unwrap());
+
+        assert!(comparator_matches(
+            &Comparator::Before,
+            &signed_up,
+            "2026-12-01",
+            Utc::now()
+        ));
+        assert!(!comparator_matches(
+            &Comparator::After,
+            &signed_up,
+            "2026-12-01",
+            Utc::now()
+        ));
+        assert!(comparator_matches(
+            &Comparator::After,
+            &signed_up,
+            "2026-03-01T13:00:00+02:00",
+            Utc::now()
+        ));
+        // Date times given as plain strings are understood too.
+        assert!(comparator_matches(
+            &Comparator::Before,
+            &ActualValue::Str("2026-03-01"),
+            "2026-03-02",
+            Utc::now()
+        ));
+        assert!(!comparator_matches(
+            &Comparator::Before,
+            &ActualValue::Int(20260301),
+            "2026-03-02",
+            Utc::now()
+        ));
+    }
+
+    #[test]
+    fn relative_time_comparators_compare_against_evaluation_time() {
+        let now = parse_datetime("2026-06-30T00:00:00Z").unwrap();
+        let signed_up = ActualValue::DateTime(parse_datetime("2026-06-10T00:00:00Z").unwrap());
+
+        assert!(comparator_matches(
+            &Comparator::NewerThan,
+            &signed_up,
+            "30d",
+            now
+        ));
+        assert!(!comparator_matches(
+            &Comparator::OlderThan,
+            &signed_up,
+            "30d",
+            now
+        ));
+        assert!(comparator_matches(
+            &Comparator::OlderThan,
+            &signed_up,
+            "2w",
+            now
+        ));
+        assert!(!comparator_matches(
+            &Comparator::NewerThan,
+            &signed_up,
+            "not-a-duration",
+            now
+        ));
+    }
+
+    #[test]
+    fn regex_comparators_fail_closed_on_invalid_pattern() {
+        assert!(!comparator_matches(
+            &Comparator::MatchesRegex,
+            &ActualValue::Str("anything"),
+            "(unclosed",
+            Utc::now()
+        ));
+        assert!(!comparator_matches(
+            &Comparator::DoesNotMatchRegex,
+            &ActualValue::Str("anything"),
+            "(unclosed",
+            Utc::now()
         ));
     }
 
@@ -426,7 +1201,13 @@ mod tests {
             Comparator::ExactlyMatches,
             "user-42",
         );
-        assert!(rule_matches(&r, &env("prod"), &id.ctx()));
+        assert!(rule_matches(
+            &r,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
     }
 
     #[test]
@@ -437,8 +1218,20 @@ mod tests {
             Comparator::ExactlyMatches,
             "prod",
         );
-        assert!(rule_matches(&r, &env("prod"), &id.ctx()));
-        assert!(!rule_matches(&r, &env("staging"), &id.ctx()));
+        assert!(rule_matches(
+            &r,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
+        assert!(!rule_matches(
+            &r,
+            &env("staging"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
     }
 
     #[test]
@@ -452,7 +1245,113 @@ mod tests {
             Comparator::ExactlyMatches,
             "premium",
         );
-        assert!(rule_matches(&r, &env("prod"), &id.ctx()));
+        assert!(rule_matches(
+            &r,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
+    }
+
+    #[test]
+    fn bucket_driver_slices_identities_by_stable_hash() {
+        let salt = "beta-10";
+        let r = rule(
+            SegmentDriver::Bucket { salt: salt.into() },
+            Comparator::LowerThan,
+            "1000",
+        );
+        let matching = (0..10_000)
+            .map(|n| identity(&format!("user-{n}"), vec![]))
+            .filter(|id| {
+                rule_matches(
+                    &r,
+                    &env("prod"),
+                    &id.ctx(),
+                    Utc::now(),
+                    &References::default(),
+                )
+            })
+            .count();
+
+        // Roughly 10% of identities, whichever they are.
+        assert!((800..1200).contains(&matching), "matching: {matching}");
+        assert_eq!(bucket("user-42", salt), bucket("user-42", salt));
+        assert!((0..10_000).contains(&bucket("user-42", salt)));
+    }
+
+    #[test]
+    fn bucket_driver_slices_independently_per_salt() {
+        let differing = (0..100)
+            .filter(|n| {
+                let value = format!("user-{n}");
+                bucket(&value, "a") != bucket(&value, "b")
+            })
+            .count();
+        assert!(differing > 90, "differing: {differing}");
+    }
+
+    #[test]
+    fn segment_driver_matches_referenced_segment() {
+        let staff = segment(vec![group(
+            None,
+            vec![rule(
+                SegmentDriver::Identity,
+                Comparator::MatchesRegex,
+                "@corp\\.com$",
+            )],
+        )]);
+        let references = References {
+            segments: HashMap::from([("staff".to_string(), Arc::new(staff))]),
+            ..Default::default()
+        };
+        let is_staff = rule(
+            SegmentDriver::Segment("staff".into()),
+            Comparator::ExactlyMatches,
+            "true",
+        );
+        let is_not_staff = rule(
+            SegmentDriver::Segment("staff".into()),
+            Comparator::ExactlyMatches,
+            "false",
+        );
+
+        let employee = identity("jane@corp.com", vec![]);
+        assert!(rule_matches(
+            &is_staff,
+            &env("prod"),
+            &employee.ctx(),
+            Utc::now(),
+            &references
+        ));
+        assert!(!rule_matches(
+            &is_not_staff,
+            &env("prod"),
+            &employee.ctx(),
+            Utc::now(),
+            &references
+        ));
+        assert_eq!(references.matched.borrow().get("staff"), Some(&true));
+    }
+
+    #[test]
+    fn segment_driver_never_matches_unknown_segment_regardless_of_polarity() {
+        let id = identity("user-42", vec![]);
+        for value in ["true", "false"] {
+            let r = rule(
+                SegmentDriver::Segment("gone".into()),
+                Comparator::ExactlyMatches,
+                value,
+            );
+            assert!(!rule_matches(
+                &r,
+                &env("prod"),
+                &id.ctx(),
+                Utc::now(),
+                &References::default()
+            ));
+        }
     }
 
     #[test]
@@ -468,8 +1367,20 @@ mod tests {
             Comparator::DoesNotMatch,
             "premium",
         );
-        assert!(!rule_matches(&matches_rule, &env("prod"), &id.ctx()));
-        assert!(!rule_matches(&does_not_match_rule, &env("prod"), &id.ctx()));
+        assert!(!rule_matches(
+            &matches_rule,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
+        assert!(!rule_matches(
+            &does_not_match_rule,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
     }
 
     #[test]
@@ -480,7 +1391,13 @@ mod tests {
             Comparator::DoesNotMatch,
             "premium",
         );
-        assert!(!rule_matches(&does_not_match_rule, &env("prod"), &id.ctx()));
+        assert!(!rule_matches(
+            &does_not_match_rule,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
     }
 
     // -- group_matches (OR over rules) -----------------------------------------------------
@@ -499,22 +1416,40 @@ mod tests {
                 ),
             ],
         );
-        assert!(group_matches(&g, &env("prod"), &id.ctx()));
+        assert!(group_matches(
+            &g,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
     }
 
     #[test]
     fn empty_group_never_matches() {
         let id = identity("user-42", vec![]);
         let g = group(None, vec![]);
-        assert!(!group_matches(&g, &env("prod"), &id.ctx()));
+        assert!(!group_matches(
+            &g,
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
     }
 
-    // -- segment_matches (AND / AND-NOT fold over groups) ----------------------------------
+    // -- segment_matches (AND / AND-NOT / OR / OR-NOT fold over groups) -------------------
 
     #[test]
     fn segment_with_no_groups_never_matches() {
         let id = identity("user-42", vec![]);
-        assert!(!segment_matches(&segment(vec![]), &env("prod"), &id.ctx()));
+        assert!(!segment_matches(
+            &segment(vec![]),
+            &env("prod"),
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
+        ));
     }
 
     #[test]
@@ -548,12 +1483,16 @@ mod tests {
         assert!(segment_matches(
             &segment(vec![head.clone(), matching_tail]),
             &env("prod"),
-            &id.ctx()
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
         ));
         assert!(!segment_matches(
             &segment(vec![head, non_matching_tail]),
             &env("prod"),
-            &id.ctx()
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
         ));
     }
 
@@ -588,12 +1527,16 @@ mod tests {
         assert!(segment_matches(
             &segment(vec![head.clone(), non_matching_tail]),
             &env("prod"),
-            &id.ctx()
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
         ));
         assert!(!segment_matches(
             &segment(vec![head, matching_tail]),
             &env("prod"),
-            &id.ctx()
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
         ));
     }
 
@@ -612,7 +1555,138 @@ mod tests {
         assert!(!segment_matches(
             &segment(vec![head, empty_tail]),
             &env("prod"),
-            &id.ctx()
+            &id.ctx(),
+            Utc::now(),
+            &References::default()
         ));
     }
+
+    /// Group matching (or not) the identity on environment name only.
+    fn env_group(connector: Option<GroupConnector>, name: &str) -> CompiledGroup {
+        group(
+            connector,
+            vec![rule(
+                SegmentDriver::Environment,
+                Comparator::ExactlyMatches,
+                name,
+            )],
+        )
+    }
+
+    #[test]
+    fn or_connector_requires_either_term_to_match() {
+        let id = identity("user-42", vec![]);
+        let matches = |groups: Vec<CompiledGroup>| {
+            segment_matches(
+                &segment(groups),
+                &env("prod"),
+                &id.ctx(),
+                Utc::now(),
+                &References::default(),
+            )
+        };
+
+        assert!(matches(vec![
+            env_group(None, "staging"),
+            env_group(Some(GroupConnector::Or), "prod"),
+        ]));
+        assert!(matches(vec![
+            env_group(None, "prod"),
+            env_group(Some(GroupConnector::Or), "staging"),
+        ]));
+        assert!(!matches(vec![
+            env_group(None, "staging"),
+            env_group(Some(GroupConnector::Or), "dev"),
+        ]));
+        assert!(matches(vec![
+            env_group(None, "staging"),
+            env_group(Some(GroupConnector::OrNot), "dev"),
+        ]));
+    }
+
+    #[test]
+    fn and_connectors_bind_tighter_than_or_connectors() {
+        let id = identity("user-42", vec![]);
+        let matches = |groups: Vec<CompiledGroup>| {
+            segment_matches(
+                &segment(groups),
+                &env("prod"),
+                &id.ctx(),
+                Utc::now(),
+                &References::default(),
+            )
+        };
+
+        // prod OR (staging AND dev) - true, while (prod OR staging) AND dev is not.
+        assert!(matches(vec![
+            env_group(None, "prod"),
+            env_group(Some(GroupConnector::Or), "staging"),
+            env_group(Some(GroupConnector::And), "dev"),
+        ]));
+        // (staging AND prod) OR (NOT dev AND prod)
+        assert!(matches(vec![
+            env_group(None, "staging"),
+            env_group(Some(GroupConnector::And), "prod"),
+            env_group(Some(GroupConnector::OrNot), "dev"),
+            env_group(Some(GroupConnector::And), "prod"),
+        ]));
+        // (prod AND NOT prod) OR (staging)
+        assert!(!matches(vec![
+            env_group(None, "prod"),
+            env_group(Some(GroupConnector::AndNot), "prod"),
+            env_group(Some(GroupConnector::Or), "staging"),
+        ]));
+    }
+
+    // -- compilation / cache -------------------------------------------------------------
+
+    #[test]
+    fn rule_values_get_parsed_for_their_comparators() {
+        let value = RuleValue::new(&Comparator::ExactlyMatches, "42".to_string());
+        assert_eq!(value.int, Some(42));
+        assert_eq!(value.float, Some(42.0));
+        assert_eq!(value.bool, None);
+        assert!(value.set.is_none() && value.pattern.is_none());
+
+        let value = RuleValue::new(&Comparator::In, r#"["a", 1]"#.to_string());
+        assert_eq!(value.set.map(|items| items.len()), Some(2));
+
+        let value = RuleValue::new(&Comparator::MatchesRegex, "^user-".to_string());
+        assert!(value.pattern.is_some_and(|regex| regex.is_match("user-42")));
+
+        let value = RuleValue::new(&Comparator::NewerThan, "7d".to_string());
+        assert_eq!(value.duration, Some(TimeDelta::days(7)));
+    }
+
+    #[test]
+    fn segments_loaded_before_invalidation_are_not_cached() {
+        let stored = |name: &str| Segment {
+            id: 42,
+            project_id: 1,
+            name: name.to_string(),
+            description: None,
+            groups: vec![],
+        };
+        let segments = SegmentCache::default();
+        let cached = || {
+            let cache = segments.0.read().unwrap();
+            cache
+                .names
+                .get(&(1, "staff".to_string()))
+                .and_then(|id| cache.segments.get(id))
+                .map(|segment| segment.id)
+        };
+
+        let generation = segments.0.read().unwrap().generation;
+        segments.invalidate();
+        cache_compiled(&segments, generation, stored("staff"));
+        assert_eq!(cached(), None);
+
+        let generation = segments.0.read().unwrap().generation;
+        cache_compiled(&segments, generation, stored("staff"));
+        assert_eq!(cached(), Some(42));
+
+        segments.invalidate();
+        assert_eq!(cached(), None);
+    }
 }
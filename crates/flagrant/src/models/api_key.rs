@@ -0,0 +1,114 @@
+use flagrant_types::{ApiKey, ApiKeyKind, Environment, Project};
+use hugsqlx::{HugSqlx, params};
+use rand::{Rng, distributions::Alphanumeric};
+use sha2::{Digest, Sha256};
+use sqlx::SqliteConnection;
+
+use crate::errors::FlagrantError;
+
+#[derive(HugSqlx)]
+#[queries = "resources/db/queries/api_keys.sql"]
+struct SQLApiKeys {}
+
+/// Length of the random part of generated keys.
+const KEY_LENGTH: usize = 40;
+
+/// Number of leading key characters stored in plain text, to tell keys apart.
+const PREFIX_LENGTH: usize = 12;
+
+/// Creates a new API key of given `kind`, scoped to a `project` and optionally to one
+/// of its environments.
+///
+/// Returns the key details along with the key itself, which is stored hashed only and
+/// can't be retrieved later on.
+pub async fn create(
+    conn: &mut SqliteConnection,
+    project: &Project,
+    environment: Option<&Environment>,
+    name: String,
+    kind: ApiKeyKind,
+) -> anyhow::Result<(ApiKey, String)> {
+    if name.trim().is_empty() {
+        return Err(FlagrantError::BadRequest("API key name can't be empty").into());
+    }
+    if environment.is_some_and(|env| env.project_id != project.id) {
+        return Err(FlagrantError::BadRequest("Environment belongs to another project").into());
+    }
+    let key = generate(kind);
+    let api_key = SQLApiKeys::create_api_key(
+        conn,
+        params![
+            project.id,
+            environment.map(|env| env.id),
+            name,
+            kind,
+            &key[..PREFIX_LENGTH],
+            hash(&key)
+        ],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not create an API key", e))?;
+
+    Ok((api_key, key))
+}
+
+/// Returns all API keys of given `project`.
+pub async fn list(conn: &mut SqliteConnection, project: &Project) -> anyhow::Result<Vec<ApiKey>> {
+    let keys = SQLApiKeys::fetch_api_keys(conn, params![project.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch API keys", e))?;
+
+    Ok(keys)
+}
+
+/// Revokes API key of given id within a `project`.
+pub async fn delete(
+    conn: &mut SqliteConnection,
+    project: &Project,
+    api_key_id: i32,
+) -> anyhow::Result<()> {
+    let result = SQLApiKeys::delete_api_key(conn, params![api_key_id, project.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not remove an API key", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(FlagrantError::NotFound("API key not found").into());
+    }
+    Ok(())
+}
+
+/// Returns API key matching given plain-text `key`, if there is any.
+pub async fn authenticate(
+    conn: &mut SqliteConnection,
+    key: &str,
+) -> anyhow::Result<Option<ApiKey>> {
+    let api_key = SQLApiKeys::fetch_api_key_by_hash(conn, params![hash(key)])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch an API key", e))?;
+
+    Ok(api_key)
+}
+
+/// Generates a new random key, prefixed with its kind to make it recognizable.
+fn generate(kind: ApiKeyKind) -> String {
+    let prefix = match kind {
+        ApiKeyKind::Client => "flgc_",
+        ApiKeyKind::Management => "flgm_",
+    };
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(KEY_LENGTH)
+        .map(char::from)
+        .collect();
+
+    format!("{prefix}{random}")
+}
+
+/// Hashes a key with SHA-256. Keys are long and random, so a plain (unsalted) digest
+/// is enough to make them unrecoverable while keeping lookups by hash possible.
+fn hash(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
@@ -1,12 +1,15 @@
+use std::collections::HashSet;
+
 use anyhow::bail;
 use hugsqlx::{HugSqlx, params};
 use serde_valid::Validate;
 use sqlx::{Acquire, SqliteConnection};
 
-use crate::errors::FlagrantError;
-use flagrant_types::{Environment, Project};
+use crate::{distributor, errors::FlagrantError};
+use flagrant_types::{Environment, Project, RevisionEntity};
 
-use super::{feature, variant};
+use super::revision::{self, Revised};
+use super::{feature, tag, variant};
 
 #[derive(HugSqlx)]
 #[queries = "resources/db/queries/environments.sql"]
@@ -22,8 +25,9 @@ struct SQLEnvironments {}
 /// - On the very first environment in a project there is nothing to inherit, so no
 ///   cloning takes place.
 ///
-/// When a base is resolved, all features will have their control variant value and
-/// non-control variant weights copied from the base into the new environment.
+/// When a base is resolved, all features will have their control variant value, non-control
+/// variant weights and on/off (archivisation) status copied from the base into the new
+/// environment.
 pub async fn create(
     conn: &mut SqliteConnection,
     project: &Project,
@@ -70,15 +74,21 @@ pub async fn create(
 /// between the snapshot read and the writes does not produce a partially-cloned environment.
 ///
 /// For each feature in the project the function:
-/// 1. Creates a control variant in `new_env` with the same value as in `base_env`.
-/// 2. Inserts weight entries for every non-control variant using the weights from `base_env`.
-/// 3. Recalculates the control variant weight so that all weights still sum to 100.
+/// 1. Copies the feature status (enabled, archived) as it is in `base_env`.
+/// 2. Creates a control variant in `new_env` with the same value as in `base_env`.
+/// 3. Inserts weight entries for every non-control variant using the weights from `base_env`.
+/// 4. Recalculates the control variant weight so that all weights still sum to 100.
 async fn clone_variants_from_env(
     conn: &mut SqliteConnection,
     base_env: &Environment,
     new_env: &Environment,
 ) -> anyhow::Result<()> {
     let mut tx = conn.begin().await?;
+
+    SQLEnvironments::clone_feature_states(&mut *tx, params![base_env.id, new_env.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not clone features status", e))?;
+
     let features = feature::get_all(&mut tx, base_env, None, None, None, None, None).await?;
 
     for feat in &features {
@@ -96,6 +106,7 @@ async fn clone_variants_from_env(
         }
         if !non_control.is_empty() {
             variant::recalculate_control_weight(&mut tx, new_env, feat.id).await?;
+            distributor::lay_out_buckets(&mut tx, new_env, feat.id, None).await?;
         }
     }
     tx.commit().await?;
@@ -151,3 +162,98 @@ pub async fn get_by_project(
 ) -> anyhow::Result<Vec<Environment>> {
     list(conn, project, None).await
 }
+
+/// Engages kill switch of `environment`, making all of its features - or the ones tagged
+/// with `tag` only - serve their control value, pinned identities included.
+///
+/// Nothing but the switch itself changes, weights, pins and assignments stay as they are,
+/// so that identities get their variants back once environment thaws. Engaging the switch
+/// again replaces the tag it applies to. Recorded as a revision of the environment.
+pub async fn freeze(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    tag: Option<String>,
+    author: Option<&str>,
+) -> anyhow::Result<Environment> {
+    let mut tx = conn.begin().await?;
+    let before = get_by_id(&mut tx, environment.id).await?;
+    if let Some(tag) = &tag {
+        let tags = tag::get_by_prefix(&mut tx, environment, tag.clone()).await?;
+        if !tags.iter().any(|t| &t.name == tag) {
+            return Err(FlagrantError::BadRequest("No feature tagged with given tag").into());
+        }
+    }
+    SQLEnvironments::freeze_environment(&mut *tx, params![environment.id, tag])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not freeze environment", e))?;
+
+    let frozen = record_freeze(&mut tx, &before, author).await?;
+    tx.commit().await?;
+    Ok(frozen)
+}
+
+/// Releases kill switch of `environment`, letting its features serve their variants again.
+/// Thawing an environment which is not frozen changes nothing.
+pub async fn thaw(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    author: Option<&str>,
+) -> anyhow::Result<Environment> {
+    let mut tx = conn.begin().await?;
+    let before = get_by_id(&mut tx, environment.id).await?;
+    SQLEnvironments::thaw_environment(&mut *tx, params![environment.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not thaw environment", e))?;
+
+    let thawed = record_freeze(&mut tx, &before, author).await?;
+    tx.commit().await?;
+    Ok(thawed)
+}
+
+/// Features kill switch of an environment applies to.
+#[derive(Debug, Default)]
+pub(crate) struct FrozenFeatures {
+    pub ids: HashSet<i32>,
+    /// Tag the switch is limited to, if any.
+    pub tag: Option<String>,
+}
+
+/// Returns features kill switch of `environment` currently applies to, none if the switch
+/// is not engaged.
+///
+/// Read from database rather than taken from `environment`, which might have been fetched
+/// long before (e.g. by a feature stream) and not reflect the switch anymore.
+pub(crate) async fn get_frozen_features(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+) -> anyhow::Result<FrozenFeatures> {
+    let rows: Vec<(i32, Option<String>)> =
+        SQLEnvironments::fetch_frozen_features(conn, params![environment.id])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not fetch frozen features", e))?;
+
+    let tag = rows.first().and_then(|(_, tag)| tag.clone());
+    Ok(FrozenFeatures {
+        ids: rows.into_iter().map(|(id, _)| id).collect(),
+        tag,
+    })
+}
+
+/// Records a revision of kill switch of an environment having been toggled, given the
+/// environment as it was `before`. Returns the environment as it is now.
+async fn record_freeze(
+    conn: &mut SqliteConnection,
+    before: &Environment,
+    author: Option<&str>,
+) -> anyhow::Result<Environment> {
+    let toggled = get_by_id(conn, before.id).await?;
+    let revised = Revised {
+        entity: RevisionEntity::Environment,
+        entity_id: before.id,
+        project_id: before.project_id,
+        environment_id: Some(before.id),
+    };
+    revision::record(conn, revised, author, before, &toggled).await?;
+
+    Ok(toggled)
+}
@@ -3,15 +3,20 @@ use std::collections::HashMap;
 
 use chrono::Utc;
 use flagrant_types::{
-    Environment, Feature, FeatureValue, Project, TagList, Variant,
-    payload::{FeaturePatch, TagPatchOp, VariantPatchOp},
+    Distribution, Environment, Feature, FeatureValue, FeatureVisibility, Project, RevisionEntity,
+    TagList, Trait, Variant,
+    payload::{FeaturePatch, PrerequisitePatchOp, TagPatchOp, VariantPatchOp},
 };
 use hugsqlx::{HugSqlx, params};
 use serde_valid::Validate;
 use smallvec::SmallVec;
 use sqlx::{Connection, Row, SqliteConnection, sqlite::SqliteRow};
 
-use super::{into_json_string, variant};
+use super::{
+    into_json_string, prerequisite,
+    revision::{self, Revised},
+    variant,
+};
 
 #[derive(HugSqlx)]
 #[queries = "resources/db/queries/features.sql"]
@@ -58,13 +63,24 @@ impl<'a> FeatureUpdate<'a> {
         let value = self
             .new_value
             .unwrap_or_else(|| self.feature.get_default_value().clone());
-        let is_enabled = self.is_enabled.unwrap_or(self.feature.is_enabled);
         let mut tx = self.conn.begin().await?;
 
         // In transaction, update feature properties first
-        SQLFeatures::update_feature(&mut *tx, params![self.feature.id, name, is_enabled])
+        SQLFeatures::update_feature(&mut *tx, params![self.feature.id, name])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not update a feature", e))?;
+
+        // On/off status is scoped to the environment, just like the feature value below.
+        // Left untouched unless requested, as it might have been changed already within
+        // the same transaction (e.g. by a patch).
+        if let Some(is_enabled) = self.is_enabled {
+            SQLFeatures::upsert_feature_status(
+                &mut *tx,
+                params![self.feature.id, self.environment.id, is_enabled],
+            )
             .await
             .map_err(|e| FlagrantError::QueryFailed("Could not update a feature", e))?;
+        }
 
         // Then update the feature value, which is stored as the default variant
         variant::create_control(&mut tx, self.environment, self.feature, value)
@@ -85,6 +101,9 @@ impl<'a> FeatureUpdate<'a> {
 /// exists in the project, so the feature is immediately usable everywhere. Each
 /// environment owns its control variant independently - subsequent value changes
 /// affect only the environment they are applied to.
+///
+/// The same goes for the on/off status: `is_enabled` applies to the given `environment`
+/// only, the feature starts disabled in all the other environments.
 pub async fn create(
     conn: &mut SqliteConnection,
     environment: &Environment,
@@ -92,11 +111,34 @@ pub async fn create(
     description: Option<String>,
     value: FeatureValue,
     is_enabled: bool,
+) -> anyhow::Result<Feature> {
+    create_with_visibility(
+        conn,
+        environment,
+        name,
+        description,
+        value,
+        is_enabled,
+        FeatureVisibility::All,
+    )
+    .await
+}
+
+/// Creates a new feature just like [`create`] does, served through the client API
+/// according to given `visibility`.
+pub async fn create_with_visibility(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    name: String,
+    description: Option<String>,
+    value: FeatureValue,
+    is_enabled: bool,
+    visibility: FeatureVisibility,
 ) -> anyhow::Result<Feature> {
     let mut tx = conn.begin().await?;
     let mut feature = SQLFeatures::create_feature(
         &mut *tx,
-        params![environment.project_id, name, description, is_enabled],
+        params![environment.project_id, name, description, visibility],
         |row| row_to_feature(row, environment),
     )
     .await
@@ -110,10 +152,17 @@ pub async fn create(
     // Default value gets turned into a control variant for all existing environments.
     for env in &super::environment::get_by_project(&mut tx, &project).await? {
         let variant = variant::create_control(&mut tx, env, &feature, value.clone()).await?;
+        let env_enabled = env.id == environment.id && is_enabled;
+
+        SQLFeatures::upsert_feature_status(&mut *tx, params![feature.id, env.id, env_enabled])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not create a feature", e))?;
+
         if env.id == environment.id {
             feature.variants.push(variant);
         }
     }
+    feature.is_enabled = is_enabled;
 
     feature.validate()?;
     tx.commit().await?;
@@ -128,18 +177,22 @@ pub async fn get_by_id(
     feature_id: i32,
 ) -> anyhow::Result<Feature> {
     let mut tx = conn.begin().await?;
-    let feature = SQLFeatures::fetch_feature_by_id(&mut *tx, params![feature_id], |row| {
-        row_to_feature(row, environment)
-    })
-    .await
-    .map_err(|e| FlagrantError::QueryFailed("Could not fetch a feature", e))?;
+    let feature =
+        SQLFeatures::fetch_feature_by_id(&mut *tx, params![feature_id, environment.id], |row| {
+            row_to_feature(row, environment)
+        })
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch a feature", e))?;
 
     let variants = variant::get_for_feature(&mut tx, environment, feature.id, None)
         .await
         .unwrap_or_default();
+    let prerequisites = prerequisite::get_for_feature(&mut tx, environment, feature.id).await?;
 
     tx.commit().await?;
-    Ok(feature.with_variants(variants))
+    Ok(feature
+        .with_variants(variants)
+        .with_prerequisites(prerequisites))
 }
 
 /// Returns feature with exact `name` or Error if no feature was found.
@@ -152,20 +205,28 @@ pub async fn get_by_name(
 ) -> anyhow::Result<Feature> {
     let feature = SQLFeatures::fetch_feature_by_name(
         &mut *conn,
-        params![environment.project_id, name],
+        params![environment.project_id, name, environment.id],
         |row| row_to_feature(row, environment),
     )
     .await
-    .map_err(|e| FlagrantError::QueryFailed("Could not fetch a feature", e))?;
+    .map_err(|e| match e {
+        sqlx::Error::RowNotFound => FlagrantError::NotFound("Feature not found"),
+        e => FlagrantError::QueryFailed("Could not fetch a feature", e),
+    })?;
 
     let variants = variant::get_for_feature(conn, environment, feature.id, None)
         .await
         .unwrap_or_default();
+    let prerequisites = prerequisite::get_for_feature(conn, environment, feature.id).await?;
 
-    Ok(feature.with_variants(variants))
+    Ok(feature
+        .with_variants(variants)
+        .with_prerequisites(prerequisites))
 }
 
 /// Returns all features for given `environment`, each with all its variants.
+///
+/// `is_archived` and `is_enabled` filter on the feature status in given `environment`.
 pub async fn get_all(
     conn: &mut SqliteConnection,
     environment: &Environment,
@@ -181,7 +242,7 @@ pub async fn get_all(
 
     // One row per (feature, variant) - aggregate into features below.
     let rows = SQLFeatures::fetch_features_for_environment(
-        conn,
+        &mut *conn,
         |cond_id| match cond_id {
             FetchFeaturesForEnvironment::Pattern => has_pattern,
             FetchFeaturesForEnvironment::IsArchived => is_archived.is_some(),
@@ -230,6 +291,11 @@ pub async fn get_all(
             result.push(feature.with_variants(variant.into_iter().collect()));
         }
     }
+
+    let mut prerequisites = prerequisite::get_for_environment(conn, environment).await?;
+    for feature in result.iter_mut() {
+        feature.prerequisites = prerequisites.remove(&feature.id).unwrap_or_default();
+    }
     Ok(result)
 }
 
@@ -261,21 +327,26 @@ pub async fn bump_up_accumulators(
 ///
 /// Operations are applied in the following order to ensure weight constraints remain
 /// satisfiable throughout the transaction:
-/// 1. Feature-level property changes (is_enabled, archived_at)
+/// 1. Feature-level property changes (is_enabled, archived_at, distribution_key - scoped to
+///    `environment`, description, visibility and distribution - project-wide)
 /// 2. Variant deletes (free up weight)
 /// 3. Variant updates (SetValue / SetWeight, grouped by variant id)
 /// 4. Variant adds (consume weight)
+/// 5. Prerequisites (scoped to `environment`, like weights)
+///
+/// The change gets recorded as a feature revision attributed to given `author`.
 pub async fn patch(
     conn: &mut SqliteConnection,
     environment: &Environment,
     feature: &Feature,
     patch: FeaturePatch,
+    author: Option<&str>,
 ) -> anyhow::Result<Feature> {
     let mut tx = conn.begin().await?;
 
     // Feature-level properties
     if let Some(enabled) = patch.is_enabled {
-        SQLFeatures::update_feature(&mut *tx, params![feature.id, &feature.name, enabled])
+        SQLFeatures::upsert_feature_status(&mut *tx, params![feature.id, environment.id, enabled])
             .await
             .map_err(|e| FlagrantError::QueryFailed("Could not update feature", e))?;
     }
@@ -284,9 +355,42 @@ pub async fn patch(
             .await
             .map_err(|e| FlagrantError::QueryFailed("Could not update feature description", e))?;
     }
+    if let Some(visibility) = patch.visibility {
+        SQLFeatures::update_feature_visibility(&mut *tx, params![feature.id, visibility])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not update feature visibility", e))?;
+    }
+    if let Some(distribution) = patch.distribution {
+        if distribution.salt().is_some_and(str::is_empty) {
+            return Err(FlagrantError::BadRequest("Hash distribution requires a salt").into());
+        }
+        SQLFeatures::update_feature_hash_salt(&mut *tx, params![feature.id, distribution.salt()])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not update feature distribution", e))?;
+    }
+    if let Some(key) = patch.distribution_key {
+        let key = (!key.is_empty()).then_some(key);
+        if let Some(name) = &key {
+            let valid = Trait {
+                id: 0,
+                name: name.clone(),
+            }
+            .validate()
+            .is_ok();
+            if !valid {
+                return Err(FlagrantError::BadRequest("Invalid distribution key").into());
+            }
+        }
+        SQLFeatures::upsert_feature_distribution_key(
+            &mut *tx,
+            params![feature.id, environment.id, key],
+        )
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not update feature distribution key", e))?;
+    }
     if let Some(archived) = patch.is_archived {
         let ts = if archived { Some(Utc::now()) } else { None };
-        SQLFeatures::archive_feature(&mut *tx, params![feature.id, ts])
+        SQLFeatures::archive_feature(&mut *tx, params![feature.id, environment.id, ts])
             .await
             .map_err(|e| FlagrantError::QueryFailed("Could not update feature active state", e))?;
     }
@@ -369,8 +473,151 @@ pub async fn patch(
         }
     }
 
+    for op in patch.prerequisites {
+        match op {
+            PrerequisitePatchOp::Set {
+                feature_id,
+                variant_id,
+            } => {
+                prerequisite::set(&mut tx, environment, feature.id, feature_id, variant_id).await?;
+            }
+            PrerequisitePatchOp::Unset { feature_id } => {
+                prerequisite::unset(&mut tx, environment, feature.id, feature_id).await?;
+            }
+        }
+    }
+
+    let patched = get_by_id(&mut tx, environment, feature.id).await?;
+    let revised = Revised {
+        entity: RevisionEntity::Feature,
+        entity_id: feature.id,
+        project_id: environment.project_id,
+        environment_id: Some(environment.id),
+    };
+    revision::record(&mut tx, revised, author, feature, &patched).await?;
+
     tx.commit().await?;
-    get_by_id(conn, environment, feature.id).await
+    Ok(patched)
+}
+
+/// Restores the feature to the state it had right before given revision was applied.
+///
+/// The difference between current and restored state is turned into a regular
+/// `FeaturePatch`, so the whole restoration happens within a single transaction
+/// and is recorded as yet another revision.
+pub async fn revert(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature: &Feature,
+    revision_id: i32,
+    author: Option<&str>,
+) -> anyhow::Result<Feature> {
+    let rev = revision::get_by_id(conn, environment.project_id, revision_id).await?;
+    if rev.entity != RevisionEntity::Feature
+        || rev.entity_id != feature.id
+        || rev.environment_id != Some(environment.id)
+    {
+        return Err(FlagrantError::NotFound("Revision not found for this feature").into());
+    }
+    let restored: Feature = serde_json::from_value(rev.before).map_err(|e| {
+        FlagrantError::UnexpectedFailure("Could not decode feature revision", e.into())
+    })?;
+
+    patch(conn, environment, feature, diff(feature, &restored), author).await
+}
+
+/// Builds a patch which turns `current` feature into the `target` one.
+///
+/// Variants are matched by their ids; variants which are gone since the `target`
+/// snapshot was taken are re-created with their original value and weight.
+fn diff(current: &Feature, target: &Feature) -> FeaturePatch {
+    let mut patch = FeaturePatch::default();
+
+    if current.is_enabled != target.is_enabled {
+        patch.is_enabled = Some(target.is_enabled);
+    }
+    if current.is_archived != target.is_archived {
+        patch.is_archived = Some(target.is_archived);
+    }
+    if current.description != target.description {
+        patch.description = Some(target.description.clone());
+    }
+    if current.visibility != target.visibility {
+        patch.visibility = Some(target.visibility);
+    }
+    if current.distribution != target.distribution {
+        patch.distribution = Some(target.distribution.clone());
+    }
+    if current.distribution_key != target.distribution_key {
+        patch.distribution_key = Some(target.distribution_key.clone().unwrap_or_default());
+    }
+    for tag in &current.tags.0 {
+        if !target.tags.0.iter().any(|t| t.name == tag.name) {
+            patch.tags.push(TagPatchOp::Remove(tag.name.clone()));
+        }
+    }
+    for tag in &target.tags.0 {
+        if !current.tags.0.iter().any(|t| t.name == tag.name) {
+            patch.tags.push(TagPatchOp::Add(tag.name.clone()));
+        }
+    }
+    for var in &current.variants {
+        if !var.is_control() && !target.variants.iter().any(|v| v.id == var.id) {
+            patch.variants.push(VariantPatchOp::Delete { id: var.id });
+        }
+    }
+    for var in &target.variants {
+        match current.variants.iter().find(|v| v.id == var.id) {
+            Some(cur) => {
+                if cur.value != var.value {
+                    patch.variants.push(VariantPatchOp::SetValue {
+                        id: cur.id,
+                        value: var.value.clone(),
+                    });
+                }
+                if !cur.is_control() && cur.weight != var.weight {
+                    patch.variants.push(VariantPatchOp::SetWeight {
+                        id: cur.id,
+                        weight: var.weight,
+                    });
+                }
+            }
+            None if var.is_control() => {
+                // Control variant can be neither added nor deleted by a patch,
+                // whatever its id is - the value is the only thing to restore.
+                if current.get_default_value() != &var.value {
+                    patch.variants.push(VariantPatchOp::SetValue {
+                        id: current.get_default_variant().id,
+                        value: var.value.clone(),
+                    });
+                }
+            }
+            None => patch.variants.push(VariantPatchOp::Add {
+                value: var.value.clone(),
+                weight: var.weight,
+            }),
+        }
+    }
+    for req in &current.prerequisites {
+        if !target
+            .prerequisites
+            .iter()
+            .any(|p| p.feature_id == req.feature_id)
+        {
+            patch.prerequisites.push(PrerequisitePatchOp::Unset {
+                feature_id: req.feature_id,
+            });
+        }
+    }
+    for req in &target.prerequisites {
+        if !current.prerequisites.contains(req) {
+            patch.prerequisites.push(PrerequisitePatchOp::Set {
+                feature_id: req.feature_id,
+                variant_id: req.variant_id,
+            });
+        }
+    }
+    patch
 }
 
 /// Permanently deletes a feature and all of its variants within a single transaction.
@@ -391,6 +638,8 @@ pub async fn delete(
     // and leave their variant_weights rows behind, causing FK failures.
     SQLFeatures::delete_identity_variants_for_feature(&mut *tx, params![feature.id]).await?;
     SQLFeatures::delete_variant_weights_for_feature(&mut *tx, params![feature.id]).await?;
+    SQLFeatures::delete_segment_priorities_for_feature(&mut *tx, params![feature.id]).await?;
+    SQLFeatures::delete_states_for_feature(&mut *tx, params![feature.id]).await?;
     SQLFeatures::delete_tags_for_feature(&mut *tx, params![feature.id]).await?;
     SQLFeatures::delete_variants_for_feature(&mut *tx, params![feature.id]).await?;
     SQLFeatures::delete_feature(&mut *tx, params![feature.id]).await?;
@@ -425,7 +674,14 @@ pub(crate) fn row_to_feature(row: SqliteRow, environment: &Environment) -> Featu
         is_archived: row
             .try_get::<Option<String>, _>("archived_at")
             .is_ok_and(|v| v.is_some()),
+        visibility: row.try_get("visibility").unwrap_or_default(),
+        distribution: match row.try_get("hash_salt") {
+            Ok(Some(salt)) => Distribution::Hash { salt },
+            _ => Distribution::Sticky,
+        },
+        distribution_key: row.try_get("distribution_key").unwrap_or_default(),
         tags: row.try_get("tags").unwrap_or(TagList(vec![])),
         variants,
+        prerequisites: vec![],
     }
 }
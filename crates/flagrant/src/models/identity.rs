@@ -1,9 +1,11 @@
-use chrono::{NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flagrant_types::explain::{FeatureExplanation, Resolution};
 use flagrant_types::payload::{IdentityPatch, IdentityTraitPayload, TraitPatchOp};
 use flagrant_types::{
     Environment, FeatureOverride, FeatureValue, Identity, IdentityTrait, IdentityVariant,
-    IdentityWithTraits, TraitValue,
+    IdentityWithTraits, Project, RevisionEntity, TraitValue, Variant,
 };
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use super::feature;
 use hugsqlx::{HugSqlx, params};
@@ -11,8 +13,17 @@ use serde_valid::Validate;
 use smallvec::SmallVec;
 use sqlx::{Connection, SqliteConnection};
 
-use crate::{distributor, errors::FlagrantError, evaluator};
+use crate::{
+    distributor,
+    errors::{FlagrantError, is_not_found},
+    evaluator::{self, References, SegmentCache},
+};
 
+use super::environment;
+use super::layer;
+use super::prerequisite;
+use super::revision::{self, Revised};
+use super::segment;
 use super::surround_string;
 use super::traits::upsert;
 use super::variant;
@@ -30,9 +41,18 @@ struct IdentityWithTraitRow {
     trait_value: Option<String>,
 }
 
+/// Variant a distribution key value is attached to, see [`get_key_variant`].
+#[derive(sqlx::FromRow)]
+struct KeyVariantRow {
+    variant_id: i32,
+    migrated_id: Option<i32>,
+    segment_id: Option<i32>,
+    segment_dirty: bool,
+}
+
 /// A single trait filter condition used by [`list`]: matches identities carrying a trait
 /// named `name`. If constructed via [`TraitCondition::value`], only a value that coerces
-/// to the given raw string matches - trying every plausible type (bool/int/float) plus a
+/// to the given raw string matches - trying every plausible type (bool/int/float/version/datetime) plus a
 /// plain-string fallback, so the caller doesn't need to know how the value was originally
 /// typed (e.g. `experimental=true` matches both `bool::true` and `str::true`). If
 /// constructed via [`TraitCondition::any_value`], any value matches - and for exclusions,
@@ -50,7 +70,7 @@ impl<'a> TraitCondition<'a> {
     }
 
     pub fn value(name: &'a str, raw: &str) -> Self {
-        let mut candidates = Vec::with_capacity(4);
+        let mut candidates = Vec::with_capacity(6);
         if let Ok(b) = raw.parse::<bool>() {
             candidates.push(TraitValue::Bool(b).to_string());
         }
@@ -60,6 +80,12 @@ impl<'a> TraitCondition<'a> {
         if let Ok(f) = raw.parse::<f32>() {
             candidates.push(TraitValue::Float(f).to_string());
         }
+        if let Ok(v) = semver::Version::parse(raw) {
+            candidates.push(TraitValue::Version(v).to_string());
+        }
+        if let Ok(v) = raw.parse::<DateTime<Utc>>() {
+            candidates.push(TraitValue::DateTime(v).to_string());
+        }
         candidates.push(TraitValue::Str(raw.to_owned()).to_string());
 
         Self {
@@ -187,6 +213,26 @@ pub async fn get_or_create_by_value(
     })
 }
 
+/// Fetches all identities (with no traits) of given environment.
+pub async fn get_all(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+) -> anyhow::Result<Vec<Identity>> {
+    let rows =
+        SQLIdentities::fetch_all_identities::<_, (i32, String, i32)>(conn, params![environment.id])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not fetch identities", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, value, environment_id)| Identity {
+            id,
+            value,
+            environment_id,
+        })
+        .collect())
+}
+
 /// Fetches a single identity with no traits by environment and identity value
 pub async fn get_by_value(
     conn: &mut SqliteConnection,
@@ -283,15 +329,58 @@ pub async fn update_traits(
     get_by_value_with_traits(conn, environment, identity.value).await
 }
 
+/// Sets given traits of an identity, leaving its other traits intact. Traits already
+/// holding the same value are skipped, so nothing gets written if nothing has changed.
+///
+/// Unlike [`patch`], no revision is recorded - meant for traits provided by the caller
+/// itself (e.g. with identity token claims) rather than changed by hand.
+pub async fn upsert_traits(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    identity: &Identity,
+    trait_payloads: Vec<IdentityTraitPayload>,
+) -> anyhow::Result<()> {
+    let current = load_traits(conn, identity.id).await?;
+    let changed = trait_payloads
+        .into_iter()
+        .filter(|t| {
+            !current
+                .iter()
+                .any(|c| c.name == t.name && c.value == t.value)
+        })
+        .collect::<Vec<_>>();
+
+    if changed.is_empty() {
+        return Ok(());
+    }
+    let mut tx = conn.begin().await?;
+    for t in changed {
+        attach_trait(
+            &mut tx,
+            environment.project_id,
+            identity.id,
+            t.name,
+            t.value,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
 /// Applies a patch to an identity - applies granular trait operations
 /// and pins the identity to specific variants (overrides) per feature.
+///
+/// The change gets recorded as an identity revision attributed to given `author`.
 pub async fn patch(
     conn: &mut SqliteConnection,
     environment: &Environment,
     identity: Identity,
     patch: IdentityPatch,
+    author: Option<&str>,
 ) -> anyhow::Result<IdentityWithTraits> {
     let mut tx = conn.begin().await?;
+    let before = snapshot(&mut tx, environment, &identity).await?;
 
     for op in patch.traits {
         match op {
@@ -349,10 +438,41 @@ pub async fn patch(
         .map_err(|e| FlagrantError::QueryFailed("Could not unpin identity variant", e))?;
     }
 
+    let after = snapshot(&mut tx, environment, &identity).await?;
+    let revised = Revised {
+        entity: RevisionEntity::Identity,
+        entity_id: identity.id,
+        project_id: environment.project_id,
+        environment_id: Some(environment.id),
+    };
+    revision::record(&mut tx, revised, author, &before, &after).await?;
+
     tx.commit().await?;
     get_by_value_with_traits(conn, environment, identity.value).await
 }
 
+/// Returns an identity snapshot as recorded in identity revisions: its traits along
+/// with variants the identity got pinned to (by feature name).
+async fn snapshot(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    identity: &Identity,
+) -> anyhow::Result<serde_json::Value> {
+    let traits = load_traits(&mut *conn, identity.id).await?;
+    let pins: BTreeMap<String, Option<FeatureValue>> =
+        list_variant_assignments(conn, environment, identity)
+            .await?
+            .into_iter()
+            .filter(|iv| iv.pinned_at.is_some())
+            .map(|iv| (iv.feature_name, iv.feature_value))
+            .collect();
+
+    Ok(serde_json::json!({
+        "traits": traits,
+        "pins": pins,
+    }))
+}
+
 /// Deletes an identity and all associated data.
 pub async fn delete(conn: &mut SqliteConnection, identity: Identity) -> anyhow::Result<()> {
     let mut tx = conn.begin().await?;
@@ -398,24 +518,34 @@ pub async fn clear_matching(
 /// value matches `pattern` (SQL LIKE pattern - `*` becomes `%`), freeing them to be
 /// redistributed on the next evaluation. Unlike [`clear_matching`], the identities themselves
 /// (and their traits) are left untouched - only their assignment to this feature is removed.
+/// Assignments of distribution key values matching `pattern` get cleared the same way.
 pub async fn clear_distribution_for_feature(
     conn: &mut SqliteConnection,
     environment: &Environment,
     feature_id: i32,
     pattern: &str,
 ) -> anyhow::Result<()> {
+    let mut tx = conn.begin().await?;
+
     SQLIdentities::delete_identity_variants_for_feature_pattern(
-        conn,
+        &mut *tx,
         params![feature_id, environment.id, pattern],
     )
     .await
     .map_err(|e| FlagrantError::QueryFailed("Could not clear variant assignments", e))?;
+    SQLIdentities::delete_key_variants_for_feature_pattern(
+        &mut *tx,
+        params![feature_id, environment.id, pattern],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not clear key variant assignments", e))?;
 
+    tx.commit().await?;
     Ok(())
 }
 
-/// Flags every already-distributed, unpinned identity for `feature_id` in `environment` as
-/// needing re-evaluation against current segment state - called whenever a segment's
+/// Flags every already-distributed, unpinned identity (and distribution key value) for
+/// `feature_id` in `environment` as needing re-evaluation against current segment state - called whenever a segment's
 /// rules/groups/overrides change in a way that could affect this feature. Resolution itself
 /// is deferred: [`get_identity_variants`] re-evaluates and clears the flag the next time
 /// each identity is actually read, rather than reconciling every affected identity eagerly.
@@ -424,7 +554,10 @@ pub(crate) async fn mark_feature_dirty(
     environment_id: i32,
     feature_id: i32,
 ) -> anyhow::Result<()> {
-    SQLIdentities::mark_feature_dirty(conn, params![feature_id, environment_id])
+    SQLIdentities::mark_feature_dirty(&mut *conn, params![feature_id, environment_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not mark feature dirty", e))?;
+    SQLIdentities::mark_feature_keys_dirty(&mut *conn, params![feature_id, environment_id])
         .await
         .map_err(|e| FlagrantError::QueryFailed("Could not mark feature dirty", e))?;
 
@@ -458,35 +591,262 @@ pub async fn list_variant_assignments(
     variant::get_by_identity(conn, environment, &identity.value).await
 }
 
-/// Evaluates which segment (if any) governs `feature_id` for `identity`, loading and
-/// caching `identity`'s traits into `identity_traits` on first use (shared across every
-/// feature resolved in one [`get_identity_variants`] call).
+/// What gets loaded for an identity on first use and reused across every feature resolved
+/// in one [`get_identity_variants`] call: its traits, and segments referenced by segment
+/// rules along with their outcome for the identity.
+#[derive(Default)]
+struct IdentityEvaluation {
+    traits: Option<Vec<IdentityTrait>>,
+    references: References,
+}
+
+/// Evaluates which segment (if any) governs `feature_id` for `identity`, loading its
+/// traits into `evaluation` on first use. `transient_traits` are merged over the loaded
+/// ones.
 async fn evaluate_segment_for(
     conn: &mut SqliteConnection,
+    segments: &SegmentCache,
     environment: &Environment,
     identity: &Identity,
-    identity_traits: &mut Option<Vec<IdentityTrait>>,
+    evaluation: &mut IdentityEvaluation,
+    transient_traits: &[IdentityTraitPayload],
     feature_id: i32,
 ) -> anyhow::Result<Option<i32>> {
-    if identity_traits.is_none() {
-        *identity_traits = Some(load_traits(conn, identity.id).await?);
-    }
+    let traits = traits_for(conn, identity, &mut evaluation.traits, transient_traits).await?;
     let ctx = evaluator::IdentityContext {
         value: &identity.value,
-        traits: identity_traits.as_ref().unwrap(),
+        traits,
+    };
+    let references = &mut evaluation.references;
+    evaluator::evaluate(conn, segments, references, environment, &ctx, feature_id).await
+}
+
+/// Returns traits of `identity` merged with `transient_traits`, loading them into
+/// `identity_traits` on first use.
+async fn traits_for<'a>(
+    conn: &mut SqliteConnection,
+    identity: &Identity,
+    identity_traits: &'a mut Option<Vec<IdentityTrait>>,
+    transient_traits: &[IdentityTraitPayload],
+) -> anyhow::Result<&'a [IdentityTrait]> {
+    if identity_traits.is_none() {
+        let mut traits = load_traits(conn, identity.id).await?;
+        merge_traits(&mut traits, transient_traits);
+        *identity_traits = Some(traits);
+    }
+    Ok(identity_traits.as_deref().unwrap())
+}
+
+/// Resolves variant of feature `var` distributed by a trait (its distribution key) for
+/// `identity`, along with the segment it's attributed to. Values of the trait get attached to variants the way
+/// identities do otherwise - attributed to the segment the first identity carrying the
+/// value matches, migrated when weights change and re-evaluated when segments change -
+/// unless the feature is distributed by hash, which hashes the value instead. Identities
+/// lacking the trait get the control variant.
+async fn resolve_by_key(
+    conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    environment: &Environment,
+    identity: &Identity,
+    evaluation: &mut IdentityEvaluation,
+    transient_traits: &[IdentityTraitPayload],
+    var: &IdentityVariant,
+) -> anyhow::Result<(Variant, Option<i32>)> {
+    let traits = traits_for(conn, identity, &mut evaluation.traits, transient_traits).await?;
+    let Some(key_value) = var
+        .distribution_key
+        .as_deref()
+        .and_then(|key| key_value(traits, key))
+    else {
+        let control = variant::get_control(conn, environment, var.feature_id).await?;
+        return Ok((control, None));
+    };
+
+    let stored = match var.hash_salt {
+        Some(_) => None,
+        None => get_key_variant(conn, environment, var, &key_value).await?,
+    };
+    // Segment the value is attributed to now, if a dirty value had to be re-evaluated.
+    let mut evaluated = None;
+
+    // Variant the value got attached (or is being migrated) to might have been deleted
+    // since, in which case the value gets attached anew.
+    if let Some(stored) = stored {
+        if let Some(migrated_id) = stored.migrated_id {
+            if let Some(variant) = find_variant(conn, environment, migrated_id).await? {
+                attach_key_value(conn, environment, var, &key_value, variant.id, None).await?;
+                return Ok((variant, None));
+            }
+        } else if !stored.segment_dirty {
+            if let Some(variant) = find_variant(conn, environment, stored.variant_id).await? {
+                return Ok((variant, stored.segment_id));
+            }
+        } else {
+            let segment_id = evaluate_segment_for(
+                conn,
+                segments,
+                environment,
+                identity,
+                evaluation,
+                transient_traits,
+                var.feature_id,
+            )
+            .await?;
+            if segment_id == stored.segment_id
+                && let Some(variant) = find_variant(conn, environment, stored.variant_id).await?
+            {
+                SQLIdentities::clear_key_dirty(
+                    &mut *conn,
+                    params![var.feature_id, environment.id, key_value],
+                )
+                .await
+                .map_err(|e| FlagrantError::QueryFailed("Could not clear dirty flag", e))?;
+                return Ok((variant, segment_id));
+            }
+            evaluated = Some(segment_id);
+        }
+    }
+
+    let segment_id = match evaluated {
+        Some(segment_id) => segment_id,
+        None => {
+            evaluate_segment_for(
+                conn,
+                segments,
+                environment,
+                identity,
+                evaluation,
+                transient_traits,
+                var.feature_id,
+            )
+            .await?
+        }
     };
-    evaluator::evaluate(conn, environment, &ctx, feature_id).await
+    if let Some(salt) = &var.hash_salt {
+        let variant = distributor::hash(
+            conn,
+            environment,
+            var.feature_id,
+            segment_id,
+            &key_value,
+            salt,
+        )
+        .await?;
+        return Ok((variant, segment_id));
+    }
+    let variant = distributor::distribute(conn, environment, var.feature_id, segment_id).await?;
+    attach_key_value(conn, environment, var, &key_value, variant.id, segment_id).await?;
+
+    Ok((variant, segment_id))
+}
+
+/// Returns variant of given id, or `None` if it's been deleted since.
+async fn find_variant(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+) -> anyhow::Result<Option<Variant>> {
+    match variant::get_by_id(conn, environment, variant_id, None).await {
+        Ok(variant) => Ok(Some(variant)),
+        Err(e) if is_not_found(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Attaches `key_value` of distribution key of feature `var` to variant of given id,
+/// attributed to `segment_id`, dropping its pending migration and dirty flag.
+async fn attach_key_value(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    var: &IdentityVariant,
+    key_value: &str,
+    variant_id: i32,
+    segment_id: Option<i32>,
+) -> anyhow::Result<()> {
+    SQLIdentities::insert_key_variant(
+        conn,
+        params![
+            var.feature_id,
+            environment.id,
+            key_value,
+            variant_id,
+            segment_id
+        ],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not attach key value to given variant", e))?;
+
+    Ok(())
+}
+
+/// Returns variant `key_value` of distribution key of feature `var` is attached to, along
+/// with the segment it's attributed to, if attached already.
+async fn get_key_variant(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    var: &IdentityVariant,
+    key_value: &str,
+) -> anyhow::Result<Option<KeyVariantRow>> {
+    SQLIdentities::fetch_key_variant::<_, KeyVariantRow>(
+        conn,
+        params![var.feature_id, environment.id, key_value],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not fetch key variant", e).into())
+}
+
+/// Value of trait `name` among `traits`, stripped of its type - the way values of
+/// distribution keys get stored.
+fn key_value(traits: &[IdentityTrait], name: &str) -> Option<String> {
+    let value = traits.iter().find(|t| t.name == name)?.value.as_ref()?;
+    Some(evaluator::plain_trait_value(value).into_owned())
 }
 
-/// Returns enabled features variants assigned to given identity, distributing the
-/// identity across variants as needed.
+/// Returns features variants assigned to given identity, distributing the identity
+/// across variants as needed. Features archived in given `environment` are left out,
+/// regardless of their status in other environments. Disabled ones are kept, with their
+/// status in `is_enabled`.
 ///
 /// If the identity has a pending migration, it is re-attached to a variant determined
 /// by the distributor and persisted for future requests.
+///
+/// Features get resolved after the features they require. An identity failing any of the
+/// prerequisites of a feature gets its control value, and is neither distributed nor
+/// migrated - whatever variant it's been attached to before is kept for when prerequisites
+/// get met again.
+///
+/// Features distributed by hash are not attached to identities at all, variants get
+/// picked by [`distributor::hash`] anew on every call - unless identity is pinned to one.
+///
+/// Features of an exclusion layer apply only to identities falling into their share of
+/// the layer, the others get the control value unless pinned.
+///
+/// Features under the kill switch of `environment` give their control value to everybody,
+/// pinned identities included. Nothing gets distributed or migrated meanwhile, so that
+/// identities get their variants back once the environment thaws.
+///
+/// Features distributed by a trait attach values of that trait to variants instead of
+/// identities, so that identities sharing the value share the variant. Identities lacking
+/// the trait get the control value.
 pub async fn get_identity_variants(
     conn: &mut SqliteConnection,
     environment: &Environment,
     identity: &Identity,
+) -> anyhow::Result<Vec<IdentityVariant>> {
+    let segments = SegmentCache::default();
+    get_identity_variants_with_traits(conn, &segments, environment, identity, &[]).await
+}
+
+/// Same as [`get_identity_variants`], with `transient_traits` merged over the traits
+/// stored for the identity when segment rules get evaluated. Transient traits take
+/// precedence over stored ones of the same name and are never persisted. Segments get
+/// evaluated as compiled in `segments` cache.
+pub async fn get_identity_variants_with_traits(
+    conn: &mut SqliteConnection,
+    segments: &SegmentCache,
+    environment: &Environment,
+    identity: &Identity,
+    transient_traits: &[IdentityTraitPayload],
 ) -> anyhow::Result<Vec<IdentityVariant>> {
     let mut tx = conn.begin().await?;
     let mut variants = variant::get_by_identity(&mut tx, environment, &identity.value).await?;
@@ -494,10 +854,108 @@ pub async fn get_identity_variants(
     // Only needed to evaluate segment rules; loaded at most once (on first use) and reused
     // across every feature in this call. Kept as just the traits (not a full
     // IdentityWithTraits) so evaluator::evaluate can borrow `identity.value` directly
-    // instead of every caller cloning it.
-    let mut identity_traits: Option<Vec<IdentityTrait>> = None;
+    // instead of every caller cloning it. Segments referenced by rules get evaluated
+    // once for all the features too.
+    let mut evaluation = IdentityEvaluation::default();
+
+    let prerequisites = prerequisite::get_for_environment(&mut tx, environment).await?;
+    let shares = layer::get_shares(&mut tx, environment).await?;
+    let frozen = environment::get_frozen_features(&mut tx, environment).await?;
+    let feature_ids: Vec<i32> = variants.iter().map(|v| v.feature_id).collect();
+    let disabled: HashSet<i32> = variants
+        .iter()
+        .filter(|v| !v.is_enabled)
+        .map(|v| v.feature_id)
+        .collect();
+    let mut resolved: HashMap<i32, Option<i32>> = HashMap::new();
+
+    for idx in prerequisite::resolution_order(&feature_ids, &prerequisites) {
+        let var = &mut variants[idx];
+
+        if frozen.ids.contains(&var.feature_id) {
+            let control = variant::get_control(&mut tx, environment, var.feature_id).await?;
+            var.variant_id = Some(control.id);
+            var.feature_value = Some(control.value);
+            resolved.insert(var.feature_id, var.variant_id);
+            continue;
+        }
+
+        if let Some(required) = prerequisites.get(&var.feature_id)
+            && prerequisite::unmet(required, &resolved, &disabled).is_some()
+        {
+            let control = variant::get_control(&mut tx, environment, var.feature_id).await?;
+            var.variant_id = Some(control.id);
+            var.feature_value = Some(control.value);
+            resolved.insert(var.feature_id, var.variant_id);
+            continue;
+        }
+
+        // Within an exclusion layer, only the feature whose share identity falls into
+        // applies - the others give their control value, unless identity is pinned.
+        if let Some(share) = shares.get(&var.feature_id)
+            && var.pinned_at.is_none()
+            && share.excludes(&identity.value)
+        {
+            let control = variant::get_control(&mut tx, environment, var.feature_id).await?;
+            var.variant_id = Some(control.id);
+            var.feature_value = Some(control.value);
+            resolved.insert(var.feature_id, var.variant_id);
+            continue;
+        }
+
+        // Features distributed by a trait get resolved by value of that trait, shared with
+        // all the identities carrying it - overrides excepted.
+        if var.distribution_key.is_some() && var.pinned_at.is_none() {
+            let (variant, segment_id) = resolve_by_key(
+                &mut tx,
+                segments,
+                environment,
+                identity,
+                &mut evaluation,
+                transient_traits,
+                var,
+            )
+            .await?;
+
+            var.variant_id = Some(variant.id);
+            var.feature_value = Some(variant.value);
+            var.segment_id = segment_id;
+            resolved.insert(var.feature_id, var.variant_id);
+            continue;
+        }
+
+        // Hash-distributed features get resolved from scratch on every call, with nothing
+        // stored for the identity - overrides excepted.
+        if let Some(salt) = &var.hash_salt
+            && var.pinned_at.is_none()
+        {
+            let segment_id = evaluate_segment_for(
+                &mut tx,
+                segments,
+                environment,
+                identity,
+                &mut evaluation,
+                transient_traits,
+                var.feature_id,
+            )
+            .await?;
+            let variant = distributor::hash(
+                &mut tx,
+                environment,
+                var.feature_id,
+                segment_id,
+                &identity.value,
+                salt,
+            )
+            .await?;
+
+            var.variant_id = Some(variant.id);
+            var.feature_value = Some(variant.value);
+            var.segment_id = segment_id;
+            resolved.insert(var.feature_id, var.variant_id);
+            continue;
+        }
 
-    for var in variants.iter_mut() {
         // Resolve the variant (and the segment it's attributed to) to attach to: skip
         // pinned identities, follow a pending weight-migration if one exists, distribute
         // fresh for a never-assigned identity, or - for one flagged `segment_dirty` by a
@@ -514,9 +972,11 @@ pub async fn get_identity_variants(
         } else if var.identity_id.is_none() {
             let segment_id = evaluate_segment_for(
                 &mut tx,
+                segments,
                 environment,
                 identity,
-                &mut identity_traits,
+                &mut evaluation,
+                transient_traits,
                 var.feature_id,
             )
             .await?;
@@ -526,9 +986,11 @@ pub async fn get_identity_variants(
         } else if var.segment_dirty {
             let segment_id = evaluate_segment_for(
                 &mut tx,
+                segments,
                 environment,
                 identity,
-                &mut identity_traits,
+                &mut evaluation,
+                transient_traits,
                 var.feature_id,
             )
             .await?;
@@ -568,13 +1030,355 @@ pub async fn get_identity_variants(
 
             var.variant_id = Some(variant.id);
             var.feature_value = Some(variant.value);
+            var.segment_id = segment_id;
         }
+        resolved.insert(var.feature_id, var.variant_id);
     }
 
     tx.commit().await?;
     Ok(variants)
 }
 
+/// Explains how [`get_identity_variants`] resolves features of `identity` - only the one
+/// named `feature_name`, if given - without changing anything: identities are neither
+/// distributed, nor migrated, nor re-evaluated for real. Segment rules are evaluated as of
+/// now, whatever the stored attribution is.
+pub async fn explain(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    identity: &Identity,
+    feature_name: Option<&str>,
+) -> anyhow::Result<Vec<FeatureExplanation>> {
+    let variants = variant::get_by_identity(conn, environment, &identity.value).await?;
+    let project = Project {
+        id: environment.project_id,
+        ..Default::default()
+    };
+    let mut identity_traits: Option<Vec<IdentityTrait>> = None;
+    let mut explanations: Vec<Option<FeatureExplanation>> = vec![None; variants.len()];
+
+    // Prerequisites are checked against variants required features are attached (or being
+    // migrated) to already - hence every feature gets resolved, whatever `feature_name` is.
+    let prerequisites = prerequisite::get_for_environment(conn, environment).await?;
+    let shares = layer::get_shares(conn, environment).await?;
+    let frozen = environment::get_frozen_features(conn, environment).await?;
+    let feature_ids: Vec<i32> = variants.iter().map(|v| v.feature_id).collect();
+    let disabled: HashSet<i32> = variants
+        .iter()
+        .filter(|v| !v.is_enabled)
+        .map(|v| v.feature_id)
+        .collect();
+    let mut resolved: HashMap<i32, Option<i32>> = HashMap::new();
+
+    for idx in prerequisite::resolution_order(&feature_ids, &prerequisites) {
+        let var = &variants[idx];
+        let explained = feature_name.is_none_or(|name| name == var.feature_name);
+
+        if frozen.ids.contains(&var.feature_id) {
+            let control = variant::get_control(conn, environment, var.feature_id).await?;
+            resolved.insert(var.feature_id, Some(control.id));
+
+            explanations[idx] = explained.then(|| FeatureExplanation {
+                feature_id: var.feature_id,
+                feature_name: var.feature_name.clone(),
+                value: Some(control.value),
+                resolution: Resolution::Frozen {
+                    tag: frozen.tag.clone(),
+                },
+                pending: false,
+                segments: Vec::new(),
+            });
+            continue;
+        }
+
+        if let Some(required) = prerequisites.get(&var.feature_id)
+            && let Some(failed) = prerequisite::unmet(required, &resolved, &disabled)
+        {
+            let control = variant::get_control(conn, environment, var.feature_id).await?;
+            resolved.insert(var.feature_id, Some(control.id));
+
+            // Required feature not distributed yet might still resolve to the variant
+            // required, hence the outcome is pending.
+            explanations[idx] = explained.then(|| FeatureExplanation {
+                feature_id: var.feature_id,
+                feature_name: var.feature_name.clone(),
+                value: Some(control.value),
+                resolution: Resolution::Prerequisite {
+                    feature_id: failed.feature_id,
+                    feature_name: failed.feature_name.clone(),
+                },
+                pending: resolved.get(&failed.feature_id) == Some(&None),
+                segments: Vec::new(),
+            });
+            continue;
+        }
+
+        if let Some(share) = shares.get(&var.feature_id)
+            && var.pinned_at.is_none()
+            && share.excludes(&identity.value)
+        {
+            let control = variant::get_control(conn, environment, var.feature_id).await?;
+            resolved.insert(var.feature_id, Some(control.id));
+
+            explanations[idx] = explained.then(|| FeatureExplanation {
+                feature_id: var.feature_id,
+                feature_name: var.feature_name.clone(),
+                value: Some(control.value),
+                resolution: Resolution::Excluded {
+                    layer_id: share.layer_id,
+                    layer_name: share.layer_name.clone(),
+                },
+                pending: false,
+                segments: Vec::new(),
+            });
+            continue;
+        }
+
+        // Features distributed by a trait resolve by value of the trait, stored or not.
+        if let Some(key) = &var.distribution_key
+            && var.pinned_at.is_none()
+        {
+            if identity_traits.is_none() {
+                identity_traits = Some(load_traits(conn, identity.id).await?);
+            }
+            let traits = identity_traits.as_deref().unwrap();
+            let (variant_id, explanation) =
+                explain_by_key(conn, environment, &project, identity, traits, var, key).await?;
+            resolved.insert(var.feature_id, variant_id);
+            explanations[idx] = explained.then_some(explanation);
+            continue;
+        }
+
+        // Hash-distributed features resolve the same way every time, nothing is ever pending.
+        if let Some(salt) = &var.hash_salt
+            && var.pinned_at.is_none()
+        {
+            if identity_traits.is_none() {
+                identity_traits = Some(load_traits(conn, identity.id).await?);
+            }
+            let ctx = evaluator::IdentityContext {
+                value: &identity.value,
+                traits: identity_traits.as_ref().unwrap(),
+            };
+            let segments = evaluator::explain(conn, environment, &ctx, var.feature_id).await?;
+            let segment = segments.iter().find(|s| s.matched);
+            let variant = distributor::hash(
+                conn,
+                environment,
+                var.feature_id,
+                segment.map(|s| s.segment_id),
+                &identity.value,
+                salt,
+            )
+            .await?;
+            resolved.insert(var.feature_id, Some(variant.id));
+
+            let resolution = match segment {
+                Some(s) => Resolution::Segment {
+                    segment_id: s.segment_id,
+                    segment_name: s.segment_name.clone(),
+                },
+                None => Resolution::Organic,
+            };
+            explanations[idx] = explained.then(|| FeatureExplanation {
+                feature_id: var.feature_id,
+                feature_name: var.feature_name.clone(),
+                value: Some(variant.value),
+                resolution,
+                pending: false,
+                segments,
+            });
+            continue;
+        }
+        resolved.insert(var.feature_id, var.migrated_id.or(var.variant_id));
+        if !explained {
+            continue;
+        }
+
+        // Mirrors the resolution order of `get_identity_variants_with_traits`.
+        let (resolution, pending, segments) = if let Some(pinned_at) = var.pinned_at {
+            (Resolution::Pinned { pinned_at }, false, Vec::new())
+        } else if let Some(variant_id) = var.migrated_id {
+            let value = variant::get_by_id(conn, environment, variant_id, None)
+                .await
+                .ok()
+                .map(|v| v.value);
+            (
+                Resolution::PendingMigration { variant_id, value },
+                true,
+                Vec::new(),
+            )
+        } else {
+            if identity_traits.is_none() {
+                identity_traits = Some(load_traits(conn, identity.id).await?);
+            }
+            let ctx = evaluator::IdentityContext {
+                value: &identity.value,
+                traits: identity_traits.as_ref().unwrap(),
+            };
+            let segments = evaluator::explain(conn, environment, &ctx, var.feature_id).await?;
+
+            // Undistributed and dirty identities get (re-)attributed to the segment matching
+            // now, others stay with the one they've been attributed to.
+            let pending = var.identity_id.is_none() || var.segment_dirty;
+            let segment = if pending {
+                segments
+                    .iter()
+                    .find(|s| s.matched)
+                    .map(|s| (s.segment_id, s.segment_name.clone()))
+            } else if let Some(segment_id) = var.segment_id {
+                let segment = segment::get_by_id(conn, &project, segment_id).await?;
+                Some((segment.id, segment.name))
+            } else {
+                None
+            };
+            let resolution = match segment {
+                Some((segment_id, segment_name)) => Resolution::Segment {
+                    segment_id,
+                    segment_name,
+                },
+                None => Resolution::Organic,
+            };
+            (resolution, pending, segments)
+        };
+
+        explanations[idx] = Some(FeatureExplanation {
+            feature_id: var.feature_id,
+            feature_name: var.feature_name.clone(),
+            value: var.feature_value.clone(),
+            resolution,
+            pending,
+            segments,
+        });
+    }
+
+    let explanations: Vec<FeatureExplanation> = explanations.into_iter().flatten().collect();
+    if feature_name.is_some() && explanations.is_empty() {
+        return Err(FlagrantError::NotFound("Feature not found").into());
+    }
+    Ok(explanations)
+}
+
+/// Explains resolution of feature `var` distributed by trait `key` for `identity`, along
+/// with the variant it resolves to - `None` if the value of the trait is yet to be
+/// distributed. Dirty values are explained as pending, with segments evaluated as of now.
+async fn explain_by_key(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    project: &Project,
+    identity: &Identity,
+    traits: &[IdentityTrait],
+    var: &IdentityVariant,
+    key: &str,
+) -> anyhow::Result<(Option<i32>, FeatureExplanation)> {
+    let mut explanation = FeatureExplanation {
+        feature_id: var.feature_id,
+        feature_name: var.feature_name.clone(),
+        value: None,
+        resolution: Resolution::Organic,
+        pending: false,
+        segments: Vec::new(),
+    };
+    let Some(key_value) = key_value(traits, key) else {
+        let control = variant::get_control(conn, environment, var.feature_id).await?;
+        explanation.value = Some(control.value);
+        explanation.resolution = Resolution::MissingKey {
+            key: key.to_owned(),
+        };
+        return Ok((Some(control.id), explanation));
+    };
+
+    let stored = match var.hash_salt {
+        Some(_) => None,
+        None => get_key_variant(conn, environment, var, &key_value).await?,
+    };
+    // Mirrors the resolution order of `resolve_by_key` - values attached to a variant
+    // deleted since are explained as yet to be distributed.
+    let stored = match stored {
+        Some(stored) => match find_variant(conn, environment, stored.variant_id).await? {
+            Some(variant) => {
+                explanation.value = Some(variant.value);
+                Some(stored)
+            }
+            None => None,
+        },
+        None => None,
+    };
+    if let Some(stored) = &stored {
+        if let Some(variant_id) = stored.migrated_id {
+            let value = variant::get_by_id(conn, environment, variant_id, None)
+                .await
+                .ok()
+                .map(|v| v.value);
+            explanation.resolution = Resolution::PendingMigration { variant_id, value };
+            explanation.pending = true;
+            return Ok((Some(variant_id), explanation));
+        }
+        if !stored.segment_dirty {
+            if let Some(segment_id) = stored.segment_id {
+                let segment = segment::get_by_id(conn, project, segment_id).await?;
+                explanation.resolution = Resolution::Segment {
+                    segment_id: segment.id,
+                    segment_name: segment.name,
+                };
+            }
+            return Ok((Some(stored.variant_id), explanation));
+        }
+    }
+
+    let ctx = evaluator::IdentityContext {
+        value: &identity.value,
+        traits,
+    };
+    explanation.segments = evaluator::explain(conn, environment, &ctx, var.feature_id).await?;
+    let segment_id = match explanation.segments.iter().find(|s| s.matched) {
+        Some(s) => {
+            explanation.resolution = Resolution::Segment {
+                segment_id: s.segment_id,
+                segment_name: s.segment_name.clone(),
+            };
+            Some(s.segment_id)
+        }
+        None => None,
+    };
+    match &var.hash_salt {
+        Some(salt) => {
+            let variant = distributor::hash(
+                conn,
+                environment,
+                var.feature_id,
+                segment_id,
+                &key_value,
+                salt,
+            )
+            .await?;
+            explanation.value = Some(variant.value);
+            Ok((Some(variant.id), explanation))
+        }
+        None => {
+            // Dirty values get (re-)attributed to the segment matching now, keeping their
+            // variant unless it's another segment than before.
+            explanation.pending = true;
+            Ok((stored.map(|s| s.variant_id), explanation))
+        }
+    }
+}
+
+/// Merges `transient` traits over the `stored` ones, by name. Transient traits don't
+/// exist as project traits necessarily, hence no trait id (`0`) is assigned to them.
+fn merge_traits(stored: &mut Vec<IdentityTrait>, transient: &[IdentityTraitPayload]) {
+    for t in transient {
+        match stored.iter_mut().find(|s| s.name == t.name) {
+            Some(s) => s.value = t.value.clone(),
+            None => stored.push(IdentityTrait {
+                trait_id: 0,
+                name: t.name.clone(),
+                value: t.value.clone(),
+            }),
+        }
+    }
+}
+
 /// Gradually redirects a percentage of identities currently evaluating to `from_variant_id`
 /// so that they instead evaluate to `into_variant_id`, without touching identities that are
 /// pinned (`pinned_at IS NOT NULL`).
@@ -600,6 +1404,9 @@ pub async fn get_identity_variants(
 ///   `into_variant_id` this way; older attachments (by `attached_at`) are preferred when
 ///   picking who moves next, so migration is deterministic and repeatable.
 ///
+/// Values of distribution keys attached to `from_variant_id` get migrated the same way,
+/// by percentage of all the values attached for the feature.
+///
 /// Only touches organic (non-segment-governed) identities - segment-governed ones are
 /// reconciled separately, lazily, via the `segment_dirty` flag (see [`mark_feature_dirty`]
 /// and the resolution logic in [`get_identity_variants`]).
@@ -613,7 +1420,12 @@ pub async fn migrate_identities(
     if from_variant_id != into_variant_id {
         tracing::info!(from_variant_id, into_variant_id, "Migrating identities");
         SQLIdentities::migrate_identities(
-            conn,
+            &mut *conn,
+            params![environment.id, from_variant_id, into_variant_id, by_percent],
+        )
+        .await?;
+        SQLIdentities::migrate_key_values(
+            &mut *conn,
             params![environment.id, from_variant_id, into_variant_id, by_percent],
         )
         .await?;
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use flagrant_types::{Environment, Layer, LayerFeature};
+use hugsqlx::{HugSqlx, params};
+use sqlx::{Connection, SqliteConnection};
+
+use super::feature;
+use crate::{errors::FlagrantError, evaluator};
+
+#[derive(HugSqlx)]
+#[queries = "resources/db/queries/layers.sql"]
+struct SQLLayers {}
+
+/// Number of buckets identities get hashed into within a layer - one per percent of share.
+const LAYER_BUCKETS: u8 = 100;
+
+#[derive(sqlx::FromRow)]
+struct LayerRow {
+    layer_id: i32,
+    environment_id: i32,
+    name: String,
+    description: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct LayerFeatureRow {
+    layer_id: i32,
+    feature_id: i32,
+    feature_name: String,
+    share: u8,
+    first_bucket: u8,
+}
+
+impl From<LayerFeatureRow> for LayerFeature {
+    fn from(row: LayerFeatureRow) -> Self {
+        LayerFeature {
+            feature_id: row.feature_id,
+            feature_name: row.feature_name,
+            share: row.share,
+            first_bucket: row.first_bucket,
+        }
+    }
+}
+
+impl LayerRow {
+    fn into_layer(self, features: Vec<LayerFeature>) -> Layer {
+        Layer {
+            id: self.layer_id,
+            environment_id: self.environment_id,
+            name: self.name,
+            description: self.description,
+            features,
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// Share of a feature within the layer it belongs to, as needed to tell identities falling
+/// into it apart.
+#[derive(Debug)]
+pub(crate) struct Share {
+    pub layer_id: i32,
+    pub layer_name: String,
+    first_bucket: u8,
+    share: u8,
+}
+
+impl Share {
+    /// Whether identity of given `value` falls outside of this share, into a share of
+    /// another feature of the layer or into no share at all.
+    pub(crate) fn excludes(&self, value: &str) -> bool {
+        let bucket = evaluator::bucket(value, &format!("layer-{}", self.layer_id)) as u64
+            * LAYER_BUCKETS as u64
+            / evaluator::BUCKETS;
+        let first = self.first_bucket as u64;
+        !(first..first + self.share as u64).contains(&bucket)
+    }
+}
+
+/// Creates a new exclusion layer within `environment`, with no features yet.
+pub async fn create(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    name: String,
+    description: Option<String>,
+) -> anyhow::Result<Layer> {
+    if name.trim().is_empty() {
+        return Err(FlagrantError::BadRequest("Layer name can't be empty").into());
+    }
+    let row: LayerRow = SQLLayers::create_layer(conn, params![environment.id, name, description])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not create a layer", e))?;
+
+    Ok(row.into_layer(Vec::new()))
+}
+
+/// Returns all exclusion layers of `environment`, ordered by their names.
+pub async fn list(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+) -> anyhow::Result<Vec<Layer>> {
+    let rows: Vec<LayerRow> = SQLLayers::fetch_layers(&mut *conn, params![environment.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch layers", e))?;
+
+    let mut features = get_features(conn, environment).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let features = features.remove(&row.layer_id).unwrap_or_default();
+            row.into_layer(features)
+        })
+        .collect())
+}
+
+/// Returns exclusion layer of given id within `environment`.
+pub async fn get_by_id(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    layer_id: i32,
+) -> anyhow::Result<Layer> {
+    let row: LayerRow = SQLLayers::fetch_layer(&mut *conn, params![environment.id, layer_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch layer", e))?;
+
+    with_features(conn, environment, row).await
+}
+
+/// Returns exclusion layer of given name within `environment`.
+pub async fn get_by_name(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    name: String,
+) -> anyhow::Result<Layer> {
+    let row: LayerRow = SQLLayers::fetch_layer_by_name(&mut *conn, params![environment.id, name])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch layer", e))?;
+
+    with_features(conn, environment, row).await
+}
+
+/// Removes exclusion layer of given id within `environment`. Features of the layer stay
+/// as they are, they just stop excluding each other.
+pub async fn delete(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    layer_id: i32,
+) -> anyhow::Result<()> {
+    let result = SQLLayers::delete_layer(conn, params![environment.id, layer_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not remove a layer", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(FlagrantError::NotFound("Layer not found").into());
+    }
+    Ok(())
+}
+
+/// Gives feature of `feature_id` a `share` (in percent) of the traffic of `layer`, adding
+/// the feature to the layer unless it's there already.
+///
+/// Shares don't move once laid out: a feature keeps the buckets it's had so long as its new
+/// share fits there, so that identities within the share stay in. Otherwise, and for
+/// features new to the layer, the share takes the lowest buckets free.
+pub async fn set_share(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    layer: &Layer,
+    feature_id: i32,
+    share: u8,
+) -> anyhow::Result<Layer> {
+    if share == 0 || share > LAYER_BUCKETS {
+        return Err(FlagrantError::BadRequest("Share has to be between 1 and 100").into());
+    }
+    let feature = feature::get_by_id(conn, environment, feature_id).await?;
+    if feature.project_id != environment.project_id {
+        return Err(FlagrantError::BadRequest("Feature not found").into());
+    }
+
+    let mut tx = conn.begin().await?;
+    let current =
+        SQLLayers::fetch_feature_layer::<_, (i32,)>(&mut *tx, params![feature_id, environment.id])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not fetch feature layer", e))?;
+
+    if current.is_some_and(|(layer_id,)| layer_id != layer.id) {
+        return Err(FlagrantError::BadRequest("Feature belongs to another layer already").into());
+    }
+    let others: Vec<&LayerFeature> = layer
+        .features
+        .iter()
+        .filter(|f| f.feature_id != feature_id)
+        .collect();
+    let kept = layer
+        .features
+        .iter()
+        .find(|f| f.feature_id == feature_id)
+        .map(|f| f.first_bucket)
+        .filter(|first| is_free(&others, *first, share));
+
+    let Some(first_bucket) = kept.or_else(|| lowest_free(&others, share)) else {
+        return Err(FlagrantError::BadRequest("Not enough traffic left in layer").into());
+    };
+    SQLLayers::upsert_layer_feature(
+        &mut *tx,
+        params![layer.id, feature_id, environment.id, share, first_bucket],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not set feature share", e))?;
+
+    tx.commit().await?;
+    get_by_id(conn, environment, layer.id).await
+}
+
+/// Removes feature of `feature_id` from `layer`, releasing its share. Shares of the other
+/// features stay where they are.
+pub async fn unset_share(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    layer: &Layer,
+    feature_id: i32,
+) -> anyhow::Result<Layer> {
+    let result = SQLLayers::delete_layer_feature(&mut *conn, params![layer.id, feature_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not remove feature share", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(FlagrantError::NotFound("Feature doesn't belong to layer").into());
+    }
+    get_by_id(conn, environment, layer.id).await
+}
+
+/// Returns shares of all the features belonging to layers of `environment`, keyed by
+/// feature id. Features belonging to no layer are left out.
+pub(crate) async fn get_shares(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+) -> anyhow::Result<HashMap<i32, Share>> {
+    let rows: Vec<LayerRow> = SQLLayers::fetch_layers(&mut *conn, params![environment.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch layers", e))?;
+    if rows.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let names: HashMap<i32, String> = rows.into_iter().map(|r| (r.layer_id, r.name)).collect();
+
+    Ok(get_features(conn, environment)
+        .await?
+        .into_iter()
+        .flat_map(|(layer_id, features)| {
+            let layer_name = names.get(&layer_id).cloned().unwrap_or_default();
+            features.into_iter().map(move |f| {
+                (
+                    f.feature_id,
+                    Share {
+                        layer_id,
+                        layer_name: layer_name.clone(),
+                        first_bucket: f.first_bucket,
+                        share: f.share,
+                    },
+                )
+            })
+        })
+        .collect())
+}
+
+/// Returns features of layers of `environment`, keyed by layer id.
+async fn get_features(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+) -> anyhow::Result<HashMap<i32, Vec<LayerFeature>>> {
+    let rows: Vec<LayerFeatureRow> = SQLLayers::fetch_layer_features(conn, params![environment.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch layer features", e))?;
+
+    let mut features: HashMap<i32, Vec<LayerFeature>> = HashMap::new();
+    for row in rows {
+        features.entry(row.layer_id).or_default().push(row.into());
+    }
+    Ok(features)
+}
+
+async fn with_features(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    row: LayerRow,
+) -> anyhow::Result<Layer> {
+    let features = get_features(conn, environment)
+        .await?
+        .remove(&row.layer_id)
+        .unwrap_or_default();
+
+    Ok(row.into_layer(features))
+}
+
+/// Whether `share` buckets starting at `first` are taken by none of `features`.
+fn is_free(features: &[&LayerFeature], first: u8, share: u8) -> bool {
+    let end = first as u16 + share as u16;
+    let overlaps = |f: &&LayerFeature| {
+        (first as u16) < f.first_bucket as u16 + f.share as u16 && (f.first_bucket as u16) < end
+    };
+    end <= LAYER_BUCKETS as u16 && !features.iter().any(overlaps)
+}
+
+/// Returns the lowest bucket `share` buckets free in a row start at, if there is any.
+fn lowest_free(features: &[&LayerFeature], share: u8) -> Option<u8> {
+    (0..LAYER_BUCKETS).find(|first| is_free(features, *first, share))
+}
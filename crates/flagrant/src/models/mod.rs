@@ -1,9 +1,16 @@
+pub mod api_key;
 pub mod environment;
 pub mod feature;
 pub mod identity;
+pub mod layer;
+pub mod prerequisite;
 pub mod project;
+pub mod revision;
+pub mod rollout;
 pub mod rule;
+pub mod schedule;
 pub mod segment;
+pub mod snapshot;
 pub mod tag;
 pub mod traits;
 pub mod variant;
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+
+use flagrant_types::{Environment, FeatureValue, Prerequisite};
+use hugsqlx::{HugSqlx, params};
+use sqlx::SqliteConnection;
+
+use super::feature;
+use crate::errors::FlagrantError;
+
+#[derive(HugSqlx)]
+#[queries = "resources/db/queries/prerequisites.sql"]
+struct SQLPrerequisites {}
+
+#[derive(sqlx::FromRow)]
+struct PrerequisiteRow {
+    dependent_id: i32,
+    feature_id: i32,
+    feature_name: String,
+    variant_id: i32,
+    value: FeatureValue,
+}
+
+impl From<PrerequisiteRow> for Prerequisite {
+    fn from(row: PrerequisiteRow) -> Self {
+        Prerequisite {
+            feature_id: row.feature_id,
+            feature_name: row.feature_name,
+            variant_id: row.variant_id,
+            value: row.value,
+        }
+    }
+}
+
+/// Returns prerequisites of given feature within `environment`, ordered by names of
+/// features they require.
+pub async fn get_for_feature(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+) -> anyhow::Result<Vec<Prerequisite>> {
+    let rows: Vec<PrerequisiteRow> = SQLPrerequisites::fetch_prerequisites_for_feature(
+        conn,
+        params![feature_id, environment.id],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not fetch feature prerequisites", e))?;
+
+    Ok(rows.into_iter().map(Prerequisite::from).collect())
+}
+
+/// Returns prerequisites of all the features within `environment`, keyed by id of the
+/// feature they belong to. Features without prerequisites are left out.
+pub async fn get_for_environment(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+) -> anyhow::Result<HashMap<i32, Vec<Prerequisite>>> {
+    let rows: Vec<PrerequisiteRow> =
+        SQLPrerequisites::fetch_prerequisites_for_environment(conn, params![environment.id])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not fetch feature prerequisites", e))?;
+
+    let mut prerequisites: HashMap<i32, Vec<Prerequisite>> = HashMap::new();
+    for row in rows {
+        prerequisites
+            .entry(row.dependent_id)
+            .or_default()
+            .push(row.into());
+    }
+    Ok(prerequisites)
+}
+
+/// Requires feature of `required_id` to resolve to its variant `variant_id` for feature
+/// of `feature_id` to apply within `environment`. Replaces the variant previously required
+/// of the same feature, if any.
+///
+/// Required feature has to belong to the same project, and the variant to the required
+/// feature. Prerequisites making a feature depend on itself, directly or through other
+/// features, are rejected.
+pub async fn set(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+    required_id: i32,
+    variant_id: i32,
+) -> anyhow::Result<()> {
+    if feature_id == required_id {
+        return Err(FlagrantError::BadRequest("Feature can't require itself").into());
+    }
+    let required = feature::get_by_id(conn, environment, required_id).await?;
+    if required.project_id != environment.project_id {
+        return Err(FlagrantError::BadRequest("Required feature not found").into());
+    }
+    if !required.variants.iter().any(|v| v.id == variant_id) {
+        return Err(FlagrantError::BadRequest(
+            "Required variant doesn't belong to required feature",
+        )
+        .into());
+    }
+    let prerequisites = get_for_environment(conn, environment).await?;
+    if depends_on(&prerequisites, required_id, feature_id) {
+        return Err(FlagrantError::BadRequest(
+            "Prerequisite would make features require each other",
+        )
+        .into());
+    }
+
+    SQLPrerequisites::upsert_prerequisite(
+        conn,
+        params![feature_id, environment.id, required_id, variant_id],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not set feature prerequisite", e))?;
+
+    Ok(())
+}
+
+/// Removes prerequisite of feature `feature_id` requiring feature `required_id` within
+/// `environment`. Removing a prerequisite which doesn't exist is a no-op.
+pub async fn unset(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+    required_id: i32,
+) -> anyhow::Result<()> {
+    SQLPrerequisites::delete_prerequisite(conn, params![feature_id, environment.id, required_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not unset feature prerequisite", e))?;
+
+    Ok(())
+}
+
+/// Whether feature `feature_id` requires feature `required_id`, directly or transitively.
+fn depends_on(
+    prerequisites: &HashMap<i32, Vec<Prerequisite>>,
+    feature_id: i32,
+    required_id: i32,
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut pending = vec![feature_id];
+
+    while let Some(id) = pending.pop() {
+        if id == required_id {
+            return true;
+        }
+        if visited.insert(id) {
+            pending.extend(
+                prerequisites
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .map(|p| p.feature_id),
+            );
+        }
+    }
+    false
+}
+
+/// Returns indices of `feature_ids` ordered so that every feature comes after the features
+/// it requires, so that those are resolved first. Otherwise the order is kept.
+pub(crate) fn resolution_order(
+    feature_ids: &[i32],
+    prerequisites: &HashMap<i32, Vec<Prerequisite>>,
+) -> Vec<usize> {
+    let index: HashMap<i32, usize> = feature_ids
+        .iter()
+        .enumerate()
+        .map(|(idx, id)| (*id, idx))
+        .collect();
+
+    let mut order = Vec::with_capacity(feature_ids.len());
+    let mut visited = vec![false; feature_ids.len()];
+
+    // Cycles are rejected when prerequisites get set, but visiting a feature before its
+    // requirements guarantees termination should one slip through anyway.
+    fn visit(
+        idx: usize,
+        feature_ids: &[i32],
+        index: &HashMap<i32, usize>,
+        prerequisites: &HashMap<i32, Vec<Prerequisite>>,
+        visited: &mut [bool],
+        order: &mut Vec<usize>,
+    ) {
+        if visited[idx] {
+            return;
+        }
+        visited[idx] = true;
+        for p in prerequisites.get(&feature_ids[idx]).into_iter().flatten() {
+            if let Some(&required) = index.get(&p.feature_id) {
+                visit(required, feature_ids, index, prerequisites, visited, order);
+            }
+        }
+        order.push(idx);
+    }
+    for idx in 0..feature_ids.len() {
+        visit(
+            idx,
+            feature_ids,
+            &index,
+            prerequisites,
+            &mut visited,
+            &mut order,
+        );
+    }
+    order
+}
+
+/// Returns the first of `prerequisites` not met, given variants features resolved to
+/// (keyed by feature id). A required feature among `disabled` ones, or missing from
+/// `resolved` as archived ones are, fails its prerequisite.
+pub(crate) fn unmet<'a>(
+    prerequisites: &'a [Prerequisite],
+    resolved: &HashMap<i32, Option<i32>>,
+    disabled: &HashSet<i32>,
+) -> Option<&'a Prerequisite> {
+    prerequisites.iter().find(|p| {
+        disabled.contains(&p.feature_id)
+            || resolved.get(&p.feature_id).copied().flatten() != Some(p.variant_id)
+    })
+}
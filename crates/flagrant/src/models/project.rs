@@ -3,7 +3,7 @@ use serde_valid::Validate;
 use sqlx::SqliteConnection;
 
 use crate::errors::FlagrantError;
-use flagrant_types::{Environment, Project};
+use flagrant_types::{Environment, Project, payload::ProjectPatch};
 
 use super::environment;
 
@@ -66,3 +66,18 @@ pub async fn get_by_name(conn: &mut SqliteConnection, name: String) -> anyhow::R
 
     Ok(project)
 }
+
+/// Applies a patch to a project. Fields left out of the patch stay unchanged.
+pub async fn patch(
+    conn: &mut SqliteConnection,
+    mut project: Project,
+    patch: ProjectPatch,
+) -> anyhow::Result<Project> {
+    if let Some(persistence) = patch.trait_persistence {
+        project =
+            Projects::update_project_trait_persistence(conn, params!(project.id, persistence))
+                .await
+                .map_err(|e| FlagrantError::QueryFailed("Could not update project", e))?;
+    }
+    Ok(project)
+}
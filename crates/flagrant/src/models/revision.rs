@@ -0,0 +1,105 @@
+use flagrant_types::{Revision, RevisionEntity};
+use hugsqlx::{HugSqlx, params};
+use serde::Serialize;
+use sqlx::{Row, SqliteConnection, sqlite::SqliteRow};
+
+use crate::errors::FlagrantError;
+
+#[derive(HugSqlx)]
+#[queries = "resources/db/queries/revisions.sql"]
+struct SQLRevisions {}
+
+/// Upper limit of revisions returned by [`list_for_entity`].
+const MAX_REVISIONS: i32 = 100;
+
+/// Entity being revised, along with the scope it lives in.
+pub struct Revised {
+    pub entity: RevisionEntity,
+    pub entity_id: i32,
+    pub project_id: i32,
+    pub environment_id: Option<i32>,
+}
+
+/// Records a revision of an entity, snapshotting both of its `before` and `after` states.
+///
+/// Meant to be called within the same transaction which applied the change, so that
+/// the change and its revision either both land in database or none of them does.
+/// Nothing is recorded if the change turned out to be a no-op.
+pub async fn record<T: Serialize>(
+    conn: &mut SqliteConnection,
+    revised: Revised,
+    author: Option<&str>,
+    before: &T,
+    after: &T,
+) -> anyhow::Result<()> {
+    let before = serde_json::to_value(before)?;
+    let after = serde_json::to_value(after)?;
+
+    if before != after {
+        SQLRevisions::create_revision(
+            conn,
+            params![
+                revised.project_id,
+                revised.environment_id,
+                revised.entity,
+                revised.entity_id,
+                author,
+                before.to_string(),
+                after.to_string()
+            ],
+        )
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not record a revision", e))?;
+    }
+    Ok(())
+}
+
+/// Returns revision of given `revision_id` recorded within given project.
+pub async fn get_by_id(
+    conn: &mut SqliteConnection,
+    project_id: i32,
+    revision_id: i32,
+) -> anyhow::Result<Revision> {
+    SQLRevisions::fetch_revision_by_id(conn, params![revision_id, project_id], row_to_revision)
+        .await
+        .map_err(|_| FlagrantError::NotFound("Revision not found").into())
+}
+
+/// Returns most recent revisions of an entity, newest first.
+///
+/// `environment_id` is expected to be `None` for project-wide entities (segments).
+pub async fn list_for_entity(
+    conn: &mut SqliteConnection,
+    entity: RevisionEntity,
+    entity_id: i32,
+    environment_id: Option<i32>,
+) -> anyhow::Result<Vec<Revision>> {
+    let revisions = SQLRevisions::fetch_revisions_for_entity(
+        conn,
+        params![entity, entity_id, environment_id, MAX_REVISIONS],
+        row_to_revision,
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not fetch revisions", e))?;
+
+    Ok(revisions)
+}
+
+fn row_to_revision(row: SqliteRow) -> Revision {
+    let snapshot = |column: &str| {
+        row.try_get::<String, _>(column)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    };
+    Revision {
+        id: row.get("revision_id"),
+        entity: row.get("entity"),
+        entity_id: row.get("entity_id"),
+        environment_id: row.get("environment_id"),
+        author: row.get("author"),
+        before: snapshot("before"),
+        after: snapshot("after"),
+        created_at: row.get("created_at"),
+    }
+}
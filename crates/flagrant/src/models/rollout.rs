@@ -0,0 +1,401 @@
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use flagrant_types::{
+    Environment, Feature, RolloutPlan, RolloutStatus,
+    payload::{FeaturePatch, NewRolloutPlanPayload, VariantPatchOp},
+};
+use hugsqlx::{HugSqlx, params};
+use sqlx::{Connection, SqliteConnection};
+
+use super::{environment, feature};
+use crate::errors::FlagrantError;
+
+#[derive(HugSqlx)]
+#[queries = "resources/db/queries/rollouts.sql"]
+struct SQLRollouts {}
+
+/// Upper limit of rollout plans advanced by a single [`advance_due`] run.
+const MAX_DUE_ROLLOUTS: i32 = 100;
+
+/// Starts a progressive rollout of given non-control variant within `environment`.
+///
+/// Variant weight gets set to `start_weight` right away, and then grows by `step` every
+/// `interval_minutes` until it reaches `target_weight`. Only one rollout of a variant
+/// can be pending (active or paused) at a time.
+pub async fn create(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+    plan: NewRolloutPlanPayload,
+    author: Option<&str>,
+) -> anyhow::Result<RolloutPlan> {
+    if plan.step == 0 || plan.interval_minutes == 0 {
+        return Err(
+            FlagrantError::BadRequest("Rollout step and interval have to be positive").into(),
+        );
+    }
+    if plan.start_weight > plan.target_weight || plan.target_weight > 100 {
+        return Err(FlagrantError::BadRequest(
+            "Rollout has to start below its target weight, which can't exceed 100%",
+        )
+        .into());
+    }
+
+    let mut tx = conn.begin().await?;
+    let feature = get_feature_of_variant(&mut tx, environment, variant_id).await?;
+    if feature
+        .variants
+        .iter()
+        .any(|v| v.id == variant_id && v.is_control())
+    {
+        return Err(FlagrantError::BadRequest("Control variant can't be rolled out").into());
+    }
+    if let Some(latest) = get_latest(&mut tx, environment, variant_id).await?
+        && latest.is_pending()
+    {
+        return Err(FlagrantError::BadRequest("Variant is already being rolled out").into());
+    }
+
+    set_weight(
+        &mut tx,
+        environment,
+        &feature,
+        variant_id,
+        plan.start_weight,
+        author,
+    )
+    .await?;
+
+    let (status, next_step_at) = if plan.start_weight == plan.target_weight {
+        (RolloutStatus::Completed, None)
+    } else {
+        let now = Utc::now().naive_utc();
+        (
+            RolloutStatus::Active,
+            Some(next_step(now, plan.interval_minutes)?),
+        )
+    };
+    let created = SQLRollouts::create_rollout_plan(
+        &mut *tx,
+        params![
+            environment.project_id,
+            environment.id,
+            feature.id,
+            variant_id,
+            plan.start_weight,
+            plan.step,
+            plan.target_weight,
+            plan.interval_minutes,
+            status,
+            next_step_at,
+            author
+        ],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not create a rollout plan", e))?;
+
+    tx.commit().await?;
+    Ok(created)
+}
+
+/// Returns the most recent rollout plan of given variant within `environment`.
+pub async fn get_for_variant(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+) -> anyhow::Result<RolloutPlan> {
+    get_latest(conn, environment, variant_id)
+        .await?
+        .ok_or_else(|| FlagrantError::NotFound("Rollout plan not found").into())
+}
+
+/// Returns the most recent rollout plan of each variant of given feature within `environment`.
+pub async fn list_for_feature(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+) -> anyhow::Result<Vec<RolloutPlan>> {
+    let plans = SQLRollouts::fetch_feature_rollout_plans(conn, params![environment.id, feature_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch rollout plans", e))?;
+
+    Ok(plans)
+}
+
+/// Pauses an active rollout of given variant. Variant keeps its current weight.
+pub async fn pause(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+) -> anyhow::Result<RolloutPlan> {
+    let plan = get_for_variant(conn, environment, variant_id).await?;
+    if plan.status != RolloutStatus::Active
+        || !transition(
+            conn,
+            &plan,
+            RolloutStatus::Paused,
+            plan.current_weight,
+            None,
+            None,
+        )
+        .await?
+    {
+        return Err(FlagrantError::BadRequest("Only active rollouts can be paused").into());
+    }
+    get_by_id(conn, plan.id).await
+}
+
+/// Resumes a paused rollout of given variant. Next step is due one interval from now.
+pub async fn resume(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+) -> anyhow::Result<RolloutPlan> {
+    let plan = get_for_variant(conn, environment, variant_id).await?;
+    let next_step_at = next_step(Utc::now().naive_utc(), plan.interval_minutes)?;
+
+    if plan.status != RolloutStatus::Paused
+        || !transition(
+            conn,
+            &plan,
+            RolloutStatus::Active,
+            plan.current_weight,
+            Some(next_step_at),
+            None,
+        )
+        .await?
+    {
+        return Err(FlagrantError::BadRequest("Only paused rollouts can be resumed").into());
+    }
+    get_by_id(conn, plan.id).await
+}
+
+/// Aborts a pending (or failed) rollout of given variant and rolls the variant back
+/// to the weight rollout started at.
+pub async fn abort(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+    author: Option<&str>,
+) -> anyhow::Result<RolloutPlan> {
+    let mut tx = conn.begin().await?;
+    let plan = get_for_variant(&mut tx, environment, variant_id).await?;
+
+    if !(plan.is_pending() || plan.status == RolloutStatus::Failed)
+        || !transition(
+            &mut tx,
+            &plan,
+            RolloutStatus::Aborted,
+            plan.start_weight,
+            None,
+            None,
+        )
+        .await?
+    {
+        return Err(
+            FlagrantError::BadRequest("Only pending or failed rollouts can be aborted").into(),
+        );
+    }
+    let feature = feature::get_by_id(&mut tx, environment, plan.feature_id).await?;
+    set_weight(
+        &mut tx,
+        environment,
+        &feature,
+        variant_id,
+        plan.start_weight,
+        author,
+    )
+    .await?;
+    let aborted = get_by_id(&mut tx, plan.id).await?;
+
+    tx.commit().await?;
+    Ok(aborted)
+}
+
+/// Advances all active rollouts with a step due at `now`, across all environments.
+///
+/// Each step bumps variant weight by plan's step (up to its target weight) through a
+/// regular feature patch, so only the delta of identities gets migrated and the change
+/// is recorded as a revision attributed to whoever started the rollout. Plans which fail
+/// to advance, e.g. because other variants took the weight over in the meantime, are
+/// marked as failed along with the reason and never retried.
+///
+/// Returns number of advanced plans.
+pub async fn advance_due(conn: &mut SqliteConnection, now: NaiveDateTime) -> anyhow::Result<usize> {
+    let due = SQLRollouts::fetch_due_rollout_plans(&mut *conn, params![now, MAX_DUE_ROLLOUTS])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch due rollout plans", e))?;
+
+    let mut advanced = 0;
+    for plan in due {
+        match advance(conn, &plan, now).await {
+            Ok(true) => advanced += 1,
+            Ok(false) => {}
+            Err(e) => {
+                tracing::warn!(rollout_plan_id = plan.id, error = ?e, "Could not advance rollout");
+                transition(
+                    conn,
+                    &plan,
+                    RolloutStatus::Failed,
+                    plan.current_weight,
+                    None,
+                    Some(e.to_string()),
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(advanced)
+}
+
+/// Advances a single rollout by one step. Returns `false` if plan was no longer active,
+/// e.g. got paused or aborted in the meantime.
+async fn advance(
+    conn: &mut SqliteConnection,
+    plan: &RolloutPlan,
+    now: NaiveDateTime,
+) -> anyhow::Result<bool> {
+    let mut tx = conn.begin().await?;
+
+    let weight = plan
+        .current_weight
+        .saturating_add(plan.step)
+        .min(plan.target_weight);
+    let (status, next_step_at) = if weight >= plan.target_weight {
+        (RolloutStatus::Completed, None)
+    } else {
+        (
+            RolloutStatus::Active,
+            Some(next_step(now, plan.interval_minutes)?),
+        )
+    };
+
+    // Claim the step first, so that it's applied at most once.
+    if !transition(&mut tx, plan, status, weight, next_step_at, None).await? {
+        return Ok(false);
+    }
+
+    let environment = environment::get_by_id(&mut tx, plan.environment_id).await?;
+    let feature = feature::get_by_id(&mut tx, &environment, plan.feature_id).await?;
+    set_weight(
+        &mut tx,
+        &environment,
+        &feature,
+        plan.variant_id,
+        weight,
+        plan.author.as_deref(),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+async fn get_by_id(
+    conn: &mut SqliteConnection,
+    rollout_plan_id: i32,
+) -> anyhow::Result<RolloutPlan> {
+    let plan = SQLRollouts::fetch_rollout_plan_by_id(conn, params![rollout_plan_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch a rollout plan", e))?;
+
+    Ok(plan)
+}
+
+async fn get_latest(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+) -> anyhow::Result<Option<RolloutPlan>> {
+    let plan = SQLRollouts::fetch_latest_rollout_plan(conn, params![environment.id, variant_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch a rollout plan", e))?;
+
+    Ok(plan)
+}
+
+async fn get_feature_of_variant(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+) -> anyhow::Result<Feature> {
+    let Some((feature_id,)) =
+        SQLRollouts::fetch_variant_feature_id::<_, (i32,)>(&mut *conn, params![variant_id])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not fetch a variant", e))?
+    else {
+        return Err(FlagrantError::NotFound("Variant not found").into());
+    };
+    feature::get_by_id(conn, environment, feature_id).await
+}
+
+/// Sets variant weight through a feature patch, unless variant has this weight already.
+/// Fails if the weight doesn't fit next to weights of other non-control variants.
+async fn set_weight(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature: &Feature,
+    variant_id: i32,
+    weight: u8,
+    author: Option<&str>,
+) -> anyhow::Result<()> {
+    let Some(variant) = feature.variants.iter().find(|v| v.id == variant_id) else {
+        return Err(FlagrantError::NotFound("Variant not found").into());
+    };
+    if variant.weight == weight {
+        return Ok(());
+    }
+    let others: u32 = feature
+        .variants
+        .iter()
+        .filter(|v| !v.is_control() && v.id != variant_id)
+        .map(|v| v.weight as u32)
+        .sum();
+    if others + weight as u32 > 100 {
+        return Err(FlagrantError::BadRequest(
+            "Other variants leave not enough weight for the rollout",
+        )
+        .into());
+    }
+    let patch = FeaturePatch {
+        variants: vec![VariantPatchOp::SetWeight {
+            id: variant_id,
+            weight,
+        }],
+        ..Default::default()
+    };
+    feature::patch(conn, environment, feature, patch, author).await?;
+    Ok(())
+}
+
+/// Moves a plan, still being in the status it was read with, to a new state.
+/// Returns `false` if plan's status has changed in the meantime.
+async fn transition(
+    conn: &mut SqliteConnection,
+    plan: &RolloutPlan,
+    status: RolloutStatus,
+    current_weight: u8,
+    next_step_at: Option<NaiveDateTime>,
+    error: Option<String>,
+) -> anyhow::Result<bool> {
+    let result = SQLRollouts::update_rollout_plan(
+        conn,
+        params![
+            plan.id,
+            status,
+            current_weight,
+            next_step_at,
+            error,
+            plan.status
+        ],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not update a rollout plan", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+fn next_step(from: NaiveDateTime, interval_minutes: u32) -> anyhow::Result<NaiveDateTime> {
+    TimeDelta::try_minutes(interval_minutes.into())
+        .and_then(|interval| from.checked_add_signed(interval))
+        .ok_or_else(|| FlagrantError::BadRequest("Rollout interval out of range").into())
+}
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 
-use flagrant_types::{Comparator, SegmentDriver, SegmentGroup, SegmentRule};
+use flagrant_types::{
+    Comparator, SegmentDriver, SegmentGroup, SegmentRule, parse_datetime, parse_duration,
+    parse_version,
+};
 use hugsqlx::{HugSqlx, params};
+use regex::Regex;
 use sqlx::SqliteConnection;
 
 use super::segment;
-use crate::errors::FlagrantError;
+use crate::{errors::FlagrantError, evaluator};
 
 #[derive(HugSqlx)]
 #[queries = "resources/db/queries/segments.sql"]
@@ -32,6 +36,11 @@ pub async fn add(
     comparator: Comparator,
     value: String,
 ) -> anyhow::Result<SegmentRule> {
+    validate(&driver, &comparator, &value)?;
+    if let SegmentDriver::Segment(name) = &driver {
+        segment::check_reference(conn, segment_id, name).await?;
+    }
+
     let rule = SQLSegments::add_rule::<_, SegmentRule>(
         &mut *conn,
         params![group_id, driver, comparator, value],
@@ -43,6 +52,59 @@ pub async fn add(
     Ok(rule)
 }
 
+/// Checks that rule `value` makes sense for its `driver` and `comparator`. Regular
+/// expressions get compiled right away, so that evaluation finds them ready to use.
+fn validate(driver: &SegmentDriver, comparator: &Comparator, value: &str) -> anyhow::Result<()> {
+    if let SegmentDriver::Bucket { .. } = driver {
+        if !matches!(
+            comparator,
+            Comparator::GreaterThan
+                | Comparator::GreaterEqualThan
+                | Comparator::LowerThan
+                | Comparator::LowerEqualThan
+        ) {
+            return Err(FlagrantError::BadRequest("Buckets take numeric comparators only").into());
+        }
+        if !value
+            .parse::<u64>()
+            .is_ok_and(|bucket| bucket <= evaluator::BUCKETS)
+        {
+            return Err(FlagrantError::BadRequest("Bucket out of range").into());
+        }
+    }
+    if let SegmentDriver::Segment(_) = driver {
+        if !matches!(
+            comparator,
+            Comparator::ExactlyMatches | Comparator::DoesNotMatch
+        ) {
+            return Err(FlagrantError::BadRequest("Segments take match comparators only").into());
+        }
+        if value.parse::<bool>().is_err() {
+            return Err(FlagrantError::BadRequest("Segment match must be true or false").into());
+        }
+    }
+    match comparator {
+        Comparator::MatchesRegex | Comparator::DoesNotMatchRegex => {
+            Regex::new(value)
+                .map_err(|_| FlagrantError::BadRequest("Invalid regular expression"))?;
+        }
+        Comparator::VersionGreaterThan
+        | Comparator::VersionGreaterEqualThan
+        | Comparator::VersionLowerThan
+        | Comparator::VersionLowerEqualThan => {
+            parse_version(value).ok_or(FlagrantError::BadRequest("Invalid version"))?;
+        }
+        Comparator::Before | Comparator::After => {
+            parse_datetime(value).ok_or(FlagrantError::BadRequest("Invalid date or time"))?;
+        }
+        Comparator::OlderThan | Comparator::NewerThan => {
+            parse_duration(value).ok_or(FlagrantError::BadRequest("Invalid duration"))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 /// Deletes a single rule by ID, then reconciles already-distributed identities against the
 /// segment's updated rules (see [`add`] for why this lives at the mutation point).
 pub async fn delete(
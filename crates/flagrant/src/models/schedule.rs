@@ -0,0 +1,275 @@
+use chrono::{NaiveDateTime, Utc};
+use flagrant_types::{
+    Environment, RevisionEntity, ScheduleStatus, ScheduledChange, ScheduledPatch,
+    payload::SegmentPatchOp,
+};
+use hugsqlx::{HugSqlx, params};
+use sqlx::{Connection, SqliteConnection};
+
+use super::{environment, feature, project, segment};
+use crate::errors::FlagrantError;
+
+#[derive(HugSqlx)]
+#[queries = "resources/db/queries/schedules.sql"]
+struct SQLSchedules {}
+
+#[derive(sqlx::FromRow)]
+struct ScheduledChangeRow {
+    scheduled_change_id: i32,
+    environment_id: i32,
+    patch: String,
+    apply_at: NaiveDateTime,
+    author: Option<String>,
+    status: ScheduleStatus,
+    error: Option<String>,
+    created_at: NaiveDateTime,
+    applied_at: Option<NaiveDateTime>,
+}
+
+/// Patches are stored serialized, hence a change may no longer decode once the patch
+/// format changes.
+impl TryFrom<ScheduledChangeRow> for ScheduledChange {
+    type Error = serde_json::Error;
+
+    fn try_from(row: ScheduledChangeRow) -> Result<Self, Self::Error> {
+        Ok(ScheduledChange {
+            id: row.scheduled_change_id,
+            environment_id: row.environment_id,
+            change: serde_json::from_str(&row.patch)?,
+            apply_at: row.apply_at,
+            author: row.author,
+            status: row.status,
+            error: row.error,
+            created_at: row.created_at,
+            applied_at: row.applied_at,
+        })
+    }
+}
+
+/// Upper limit of scheduled changes returned by [`list`] and picked up by a single
+/// [`apply_due`] run.
+const MAX_SCHEDULED_CHANGES: i32 = 100;
+
+/// Schedules `change` to be applied within given `environment` at `apply_at` (UTC).
+///
+/// Scheduled entity has to exist at the time of scheduling. As segments are project-wide,
+/// segment patches are limited to feature overrides within given `environment`.
+/// The patch itself gets validated only when applied - a change which can't be applied
+/// by then is marked as failed.
+pub async fn create(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    change: ScheduledPatch,
+    apply_at: NaiveDateTime,
+    author: Option<&str>,
+) -> anyhow::Result<ScheduledChange> {
+    if apply_at <= Utc::now().naive_utc() {
+        return Err(FlagrantError::BadRequest("Scheduled time has to be in the future").into());
+    }
+    match &change {
+        ScheduledPatch::Feature { feature_id, patch } => {
+            if patch.is_empty() {
+                return Err(FlagrantError::BadRequest("Nothing to schedule").into());
+            }
+            feature::get_by_id(conn, environment, *feature_id).await?;
+        }
+        ScheduledPatch::Segment { segment_id, patch } => {
+            if patch.is_empty() {
+                return Err(FlagrantError::BadRequest("Nothing to schedule").into());
+            }
+            let within_environment = patch.ops.iter().all(|op| {
+                matches!(op,
+                    SegmentPatchOp::SetFeatureOverride { environment_id, .. }
+                    | SegmentPatchOp::UnsetFeatureOverride { environment_id, .. }
+                    | SegmentPatchOp::SetFeaturePriority { environment_id, .. }
+                    if *environment_id == environment.id)
+            });
+            if !within_environment {
+                return Err(FlagrantError::BadRequest(
+                    "Only feature overrides within scheduled environment can be scheduled for a segment",
+                )
+                .into());
+            }
+            let project = project::get_by_id(conn, environment.project_id).await?;
+            segment::get_by_id(conn, &project, *segment_id).await?;
+        }
+    }
+
+    let (entity, entity_id) = change.entity();
+    let row: ScheduledChangeRow = SQLSchedules::create_scheduled_change(
+        conn,
+        params![
+            environment.project_id,
+            environment.id,
+            entity,
+            entity_id,
+            serde_json::to_string(&change)?,
+            apply_at,
+            author
+        ],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not schedule a change", e))?;
+
+    Ok(row.try_into()?)
+}
+
+/// Returns change of given `scheduled_change_id` scheduled within given `environment`.
+pub async fn get_by_id(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    scheduled_change_id: i32,
+) -> anyhow::Result<ScheduledChange> {
+    let row: ScheduledChangeRow = SQLSchedules::fetch_scheduled_change_by_id(
+        conn,
+        params![scheduled_change_id, environment.id],
+    )
+    .await
+    .map_err(|_| FlagrantError::NotFound("Scheduled change not found"))?;
+
+    Ok(row.try_into()?)
+}
+
+/// Returns changes of given `status` scheduled within given `environment`, the nearest
+/// first. Changes can be narrowed down to a single `entity` (type and id).
+pub async fn list(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    status: ScheduleStatus,
+    entity: Option<(RevisionEntity, i32)>,
+) -> anyhow::Result<Vec<ScheduledChange>> {
+    let (entity, entity_id) = entity.unzip();
+    let rows: Vec<ScheduledChangeRow> = SQLSchedules::fetch_scheduled_changes(
+        conn,
+        params![
+            environment.id,
+            status,
+            entity,
+            entity_id,
+            MAX_SCHEDULED_CHANGES
+        ],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not fetch scheduled changes", e))?;
+
+    let changes = rows
+        .into_iter()
+        .map(ScheduledChange::try_from)
+        .collect::<Result<_, _>>()?;
+    Ok(changes)
+}
+
+/// Cancels a pending change. Changes already applied (or failed) can't be cancelled.
+pub async fn cancel(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    scheduled_change_id: i32,
+) -> anyhow::Result<ScheduledChange> {
+    let mut tx = conn.begin().await?;
+    let scheduled = get_by_id(&mut tx, environment, scheduled_change_id).await?;
+
+    if !set_status(&mut tx, scheduled.id, ScheduleStatus::Cancelled, None, None).await? {
+        return Err(FlagrantError::BadRequest("Only pending changes can be cancelled").into());
+    }
+    let cancelled = get_by_id(&mut tx, environment, scheduled_change_id).await?;
+
+    tx.commit().await?;
+    Ok(cancelled)
+}
+
+/// Applies all pending changes due at `now`, across all environments.
+///
+/// Each change gets applied within its own transaction through regular feature/segment
+/// patches, so identities are migrated and revisions recorded (attributed to whoever
+/// scheduled the change) just like for any other patch. Changes which fail to apply, or
+/// whose stored patch can't be decoded any more, are marked as failed along with the
+/// reason and never retried.
+///
+/// Returns number of applied changes.
+pub async fn apply_due(conn: &mut SqliteConnection, now: NaiveDateTime) -> anyhow::Result<usize> {
+    let due: Vec<ScheduledChangeRow> =
+        SQLSchedules::fetch_due_scheduled_changes(&mut *conn, params![now, MAX_SCHEDULED_CHANGES])
+            .await
+            .map_err(|e| FlagrantError::QueryFailed("Could not fetch due scheduled changes", e))?;
+
+    let mut applied = 0;
+    for row in due {
+        let scheduled_change_id = row.scheduled_change_id;
+        let result = match ScheduledChange::try_from(row) {
+            Ok(scheduled) => apply(conn, scheduled).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(true) => applied += 1,
+            Ok(false) => {}
+            Err(e) => {
+                tracing::warn!(scheduled_change_id, error = ?e, "Could not apply scheduled change");
+                set_status(
+                    conn,
+                    scheduled_change_id,
+                    ScheduleStatus::Failed,
+                    Some(e.to_string()),
+                    None,
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(applied)
+}
+
+/// Applies a single scheduled change. Returns `false` if change was no longer pending,
+/// e.g. got cancelled or applied in the meantime.
+async fn apply(conn: &mut SqliteConnection, scheduled: ScheduledChange) -> anyhow::Result<bool> {
+    let mut tx = conn.begin().await?;
+
+    // Claim the change first, so that it's applied at most once.
+    let applied_at = Some(Utc::now().naive_utc());
+    let claimed = set_status(
+        &mut tx,
+        scheduled.id,
+        ScheduleStatus::Applied,
+        None,
+        applied_at,
+    )
+    .await?;
+    if !claimed {
+        return Ok(false);
+    }
+
+    let environment = environment::get_by_id(&mut tx, scheduled.environment_id).await?;
+    let author = scheduled.author.as_deref();
+
+    match scheduled.change {
+        ScheduledPatch::Feature { feature_id, patch } => {
+            let feature = feature::get_by_id(&mut tx, &environment, feature_id).await?;
+            feature::patch(&mut tx, &environment, &feature, patch, author).await?;
+        }
+        ScheduledPatch::Segment { segment_id, patch } => {
+            let project = project::get_by_id(&mut tx, environment.project_id).await?;
+            let segment = segment::get_by_id(&mut tx, &project, segment_id).await?;
+            segment::patch(&mut tx, &project, segment, patch, author).await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Moves a pending change to given `status`. Returns `false` if change was not pending.
+async fn set_status(
+    conn: &mut SqliteConnection,
+    scheduled_change_id: i32,
+    status: ScheduleStatus,
+    error: Option<String>,
+    applied_at: Option<NaiveDateTime>,
+) -> anyhow::Result<bool> {
+    let result = SQLSchedules::update_scheduled_change_status(
+        conn,
+        params![scheduled_change_id, status, error, applied_at],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not update scheduled change", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
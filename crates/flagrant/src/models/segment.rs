@@ -1,15 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, btree_map::Entry};
 
 use flagrant_types::{
-    GroupConnector, Project, Segment, SegmentFeatureOverride, SegmentGroup, SegmentRule,
+    GroupConnector, Project, RevisionEntity, Segment, SegmentFeatureOverride, SegmentGroup,
+    SegmentRule,
     payload::{SegmentPatch, SegmentPatchOp, SegmentVariantWeight},
 };
 use hugsqlx::{HugSqlx, params};
 use serde_valid::Validate;
 use sqlx::{Acquire, SqliteConnection};
 
-use super::{environment, identity, rule, variant};
-use crate::errors::FlagrantError;
+use super::{
+    environment, identity,
+    revision::{self, Revised},
+    rule, variant,
+};
+use crate::{distributor, errors::FlagrantError};
 
 #[derive(HugSqlx)]
 #[queries = "resources/db/queries/segments.sql"]
@@ -33,6 +38,12 @@ struct GroupRow {
     description: Option<String>,
 }
 
+#[derive(sqlx::FromRow)]
+struct ReferenceRow {
+    segment_id: i32,
+    referenced: String,
+}
+
 /// Creates a new segment in the given project and returns it with an empty groups list.
 pub async fn create(
     conn: &mut SqliteConnection,
@@ -113,17 +124,28 @@ pub async fn get_all(
     load_all_segments(&mut *conn, rows).await
 }
 
-/// Updates the name and description of an existing segment.
+/// Updates the name and description of an existing segment. Rules of other segments
+/// referencing this one by name follow the new name.
 pub async fn update(
     conn: &mut SqliteConnection,
     segment: &Segment,
     name: &str,
     description: Option<&str>,
 ) -> anyhow::Result<()> {
-    SQLSegments::update_segment::<_>(&mut *conn, params![segment.id, name, description])
+    let mut tx = conn.begin().await?;
+    SQLSegments::update_segment::<_>(&mut *tx, params![segment.id, name, description])
         .await
         .map_err(|e| FlagrantError::QueryFailed("Could not update segment", e))?;
 
+    if segment.name != name {
+        SQLSegments::rename_segment_references::<_>(
+            &mut *tx,
+            params![segment.project_id, &segment.name, name],
+        )
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not rename segment references", e))?;
+    }
+    tx.commit().await?;
     Ok(())
 }
 
@@ -138,13 +160,13 @@ pub async fn get_variant_weights(
 }
 
 /// Returns every segment overriding this feature+environment as `(segment_id, segment_name,
-/// weights)`, in segment_id ascending order (creation order - the query itself is ordered
-/// this way, so no re-sort is needed here). `weights` includes the control variant's
-/// auto-balanced remainder.
+/// weights)`, highest priority first, ties in segment_id ascending order (creation order -
+/// the query itself is ordered this way, so no re-sort is needed here). `weights` includes
+/// the control variant's auto-balanced remainder.
 ///
 /// Backs both "FEATURE describe"/`get_overrides` (display, via `name`) and the rule
 /// evaluator's priority-ordered lookup (via `segment_id`, first match wins) - there's no
-/// need for overrides to be listed alphabetically, so segment-id order serves both.
+/// need for overrides to be listed alphabetically, so priority order serves both.
 pub async fn list_overrides_for_feature(
     conn: &mut SqliteConnection,
     environment_id: i32,
@@ -178,13 +200,14 @@ pub async fn list_overridden_features(
         variant::get_features_overridden_by_segment(conn, segment_id, environment_id).await?;
 
     let mut result: Vec<SegmentFeatureOverride> = Vec::new();
-    for (feature_id, feature_name, ov) in rows {
+    for (feature_id, feature_name, priority, ov) in rows {
         if let Some(entry) = result.iter_mut().find(|f| f.feature_id == feature_id) {
             entry.weights.push(ov);
         } else {
             result.push(SegmentFeatureOverride {
                 feature_id,
                 feature_name,
+                priority,
                 weights: vec![ov],
             });
         }
@@ -192,8 +215,16 @@ pub async fn list_overridden_features(
     Ok(result)
 }
 
-/// Deletes a segment and all its associated groups and rules.
+/// Deletes a segment and all its associated groups and rules. Segments referenced by
+/// rules of other segments can't be deleted, until those rules are deleted first.
 pub async fn delete(conn: &mut SqliteConnection, segment: &Segment) -> anyhow::Result<()> {
+    let graph = ReferenceGraph::load(conn, segment.id).await?;
+    if !graph.referencing(segment.id).is_empty() {
+        return Err(FlagrantError::BadRequest("Segment is referenced by other segments").into());
+    }
+    SQLSegments::delete_segment_priorities::<_>(&mut *conn, params![segment.id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not delete segment priorities", e))?;
     SQLSegments::delete_segment::<_>(&mut *conn, params![segment.id])
         .await
         .map_err(|e| FlagrantError::QueryFailed("Could not delete segment", e))?;
@@ -290,35 +321,44 @@ pub async fn delete_group(
 ///
 /// Each op is executed immediately against the DB; the in-memory `segment` is kept in
 /// sync so that subsequent ops in the same batch (e.g. `AddRule` after `AddGroup`) can
-/// resolve labels and IDs without an extra round-trip.
+/// resolve labels and IDs without an extra round-trip. All the ops are applied within
+/// a single transaction and recorded as a segment revision attributed to given `author`.
 pub async fn patch(
     conn: &mut SqliteConnection,
     project: &Project,
     mut segment: Segment,
     patch: SegmentPatch,
+    author: Option<&str>,
 ) -> anyhow::Result<Segment> {
+    let mut tx = conn.begin().await?;
+    let environment_ids: Vec<i32> = patch
+        .ops
+        .iter()
+        .filter_map(|op| match op {
+            SegmentPatchOp::SetFeatureOverride { environment_id, .. }
+            | SegmentPatchOp::UnsetFeatureOverride { environment_id, .. }
+            | SegmentPatchOp::SetFeaturePriority { environment_id, .. } => Some(*environment_id),
+            _ => None,
+        })
+        .collect();
+    let before = snapshot(&mut tx, &segment, &environment_ids).await?;
+
     for op in patch.ops {
         match op {
             SegmentPatchOp::SetName(name) => {
+                update(&mut tx, &segment, &name, segment.description.as_deref()).await?;
                 segment.name = name;
                 segment.validate()?;
-                update(
-                    conn,
-                    &segment,
-                    &segment.name,
-                    segment.description.as_deref(),
-                )
-                .await?;
             }
             SegmentPatchOp::SetDescription(description) => {
-                update(conn, &segment, &segment.name, description.as_deref()).await?;
+                update(&mut tx, &segment, &segment.name, description.as_deref()).await?;
                 segment.description = description;
             }
             SegmentPatchOp::AddGroup {
                 connector,
                 description,
             } => {
-                let group = add_group(conn, &segment, description, connector).await?;
+                let group = add_group(&mut tx, &segment, description, connector).await?;
                 segment.groups.push(group);
             }
             SegmentPatchOp::DeleteGroup { label } => {
@@ -329,7 +369,7 @@ pub async fn patch(
                     .map(|g| g.id)
                     .ok_or_else(|| FlagrantError::NotFound("Group not found"))?;
 
-                delete_group(conn, &segment, group_id).await?;
+                delete_group(&mut tx, &segment, group_id).await?;
                 segment.groups.retain(|g| g.label != label);
 
                 if let Some(head) = segment.groups.first_mut() {
@@ -348,14 +388,15 @@ pub async fn patch(
                     .find(|g| g.label == group_label)
                     .map(|g| g.id)
                     .ok_or_else(|| FlagrantError::NotFound("Group not found"))?;
-                let sr = rule::add(conn, segment.id, group_id, driver, comparator, value).await?;
+                let sr =
+                    rule::add(&mut tx, segment.id, group_id, driver, comparator, value).await?;
 
                 if let Some(g) = segment.groups.iter_mut().find(|g| g.label == group_label) {
                     g.rules.push(sr);
                 }
             }
             SegmentPatchOp::DeleteRule { rule_id } => {
-                rule::delete(conn, segment.id, rule_id).await?;
+                rule::delete(&mut tx, segment.id, rule_id).await?;
                 rule::remove_from_groups(&mut segment.groups, rule_id);
             }
             SegmentPatchOp::SetFeatureOverride {
@@ -363,18 +404,27 @@ pub async fn patch(
                 environment_id,
                 variant_weights,
             } => {
-                let environment = environment::get_by_id(&mut *conn, environment_id).await?;
-
-                variant::delete_segment_weights_for_feature(
-                    &mut *conn,
-                    segment.id,
-                    feature_id,
-                    environment_id,
-                )
-                .await?;
+                let environment = environment::get_by_id(&mut tx, environment_id).await?;
+
+                // Weights are upserted rather than rewritten, so variants kept in the
+                // override keep their buckets and only the ones dropped from it go away.
+                let current =
+                    variant::get_segment_weights(&mut tx, segment.id, feature_id, environment_id)
+                        .await?;
+                for (variant_id, _) in current {
+                    if !variant_weights.iter().any(|vw| vw.variant_id == variant_id) {
+                        variant::delete_segment_weight(
+                            &mut tx,
+                            segment.id,
+                            variant_id,
+                            environment_id,
+                        )
+                        .await?;
+                    }
+                }
                 for vw in &variant_weights {
                     variant::set_segment_weight(
-                        &mut *conn,
+                        &mut tx,
                         &environment,
                         segment.id,
                         vw.variant_id,
@@ -385,36 +435,99 @@ pub async fn patch(
                 // Balance the control variant's remainder within this segment, mirroring
                 // how organic weights always sum to 100.
                 variant::balance_segment_control_weight(
-                    &mut *conn,
+                    &mut tx,
                     &environment,
                     segment.id,
                     feature_id,
                 )
                 .await?;
+                distributor::lay_out_buckets(&mut tx, &environment, feature_id, Some(segment.id))
+                    .await?;
 
-                identity::mark_feature_dirty(&mut *conn, environment_id, feature_id).await?;
+                identity::mark_feature_dirty(&mut tx, environment_id, feature_id).await?;
             }
             SegmentPatchOp::UnsetFeatureOverride {
                 feature_id,
                 environment_id,
             } => {
                 variant::delete_segment_weights_for_feature(
-                    &mut *conn,
+                    &mut tx,
                     segment.id,
                     feature_id,
                     environment_id,
                 )
                 .await?;
+                SQLSegments::delete_segment_priority::<_>(
+                    &mut *tx,
+                    params![segment.id, feature_id, environment_id],
+                )
+                .await
+                .map_err(|e| FlagrantError::QueryFailed("Could not reset segment priority", e))?;
 
                 // Flag now that this segment no longer overrides the feature, so
                 // identities previously attributed to it re-evaluate (and fall through to
                 // a lower-priority segment or the organic pool) the next time they're read.
-                identity::mark_feature_dirty(&mut *conn, environment_id, feature_id).await?;
+                identity::mark_feature_dirty(&mut tx, environment_id, feature_id).await?;
+            }
+            SegmentPatchOp::SetFeaturePriority {
+                feature_id,
+                environment_id,
+                priority,
+            } => {
+                let scopes = variant::get_segment_override_scopes(&mut tx, segment.id).await?;
+                if !scopes.contains(&(environment_id, feature_id)) {
+                    return Err(FlagrantError::BadRequest(
+                        "Segment does not override this feature",
+                    )
+                    .into());
+                }
+                SQLSegments::upsert_segment_priority::<_>(
+                    &mut *tx,
+                    params![segment.id, feature_id, environment_id, priority],
+                )
+                .await
+                .map_err(|e| FlagrantError::QueryFailed("Could not set segment priority", e))?;
+
+                // Another segment might take precedence now, so let identities of this
+                // feature re-evaluate the next time they're read.
+                identity::mark_feature_dirty(&mut tx, environment_id, feature_id).await?;
             }
         }
     }
 
-    get_by_id(conn, project, segment.id).await
+    let patched = get_by_id(&mut tx, project, segment.id).await?;
+    let after = snapshot(&mut tx, &patched, &environment_ids).await?;
+    let revised = Revised {
+        entity: RevisionEntity::Segment,
+        entity_id: segment.id,
+        project_id: project.id,
+        environment_id: None,
+    };
+    revision::record(&mut tx, revised, author, &before, &after).await?;
+
+    tx.commit().await?;
+    Ok(patched)
+}
+
+/// Returns a segment snapshot as recorded in segment revisions.
+///
+/// Feature overrides are scoped to environments, so only overrides within given
+/// `environment_ids` (those touched by a patch) are captured along with the segment.
+async fn snapshot(
+    conn: &mut SqliteConnection,
+    segment: &Segment,
+    environment_ids: &[i32],
+) -> anyhow::Result<serde_json::Value> {
+    let mut overrides = BTreeMap::new();
+    for &environment_id in environment_ids {
+        if let Entry::Vacant(e) = overrides.entry(environment_id) {
+            e.insert(list_overridden_features(&mut *conn, environment_id, segment.id).await?);
+        }
+    }
+    Ok(serde_json::json!({
+        "segment": segment,
+        "overrides": overrides,
+    }))
 }
 
 //
@@ -425,6 +538,8 @@ pub async fn patch(
 /// re-evaluation - called after a structural rule/group change, where the affected scopes
 /// aren't known at the call site (unlike `SetFeatureOverride`/`UnsetFeatureOverride`, which
 /// already know their exact scope and call [`identity::mark_feature_dirty`] directly).
+/// Segments referencing this one (directly or not) match differently now too, so their
+/// scopes get flagged as well.
 ///
 /// Resolution itself is deferred: flagging is a single cheap `UPDATE` per scope, and each
 /// flagged identity is actually re-evaluated the next time it's read (see
@@ -433,14 +548,113 @@ pub(crate) async fn reconcile_rules_changed(
     conn: &mut SqliteConnection,
     segment_id: i32,
 ) -> anyhow::Result<()> {
-    for (environment_id, feature_id) in
-        variant::get_segment_override_scopes(conn, segment_id).await?
-    {
-        identity::mark_feature_dirty(conn, environment_id, feature_id).await?;
+    let graph = ReferenceGraph::load(conn, segment_id).await?;
+    let mut segment_ids = graph.referencing(segment_id);
+    segment_ids.push(segment_id);
+
+    for segment_id in segment_ids {
+        for (environment_id, feature_id) in
+            variant::get_segment_override_scopes(conn, segment_id).await?
+        {
+            identity::mark_feature_dirty(conn, environment_id, feature_id).await?;
+        }
     }
     Ok(())
 }
 
+/// Makes sure segment `segment_id` may reference a segment named `referenced`: the latter
+/// has to exist within the same project, and must not lead back to the former through
+/// its own references - evaluating either one would never end.
+pub(crate) async fn check_reference(
+    conn: &mut SqliteConnection,
+    segment_id: i32,
+    referenced: &str,
+) -> anyhow::Result<()> {
+    let graph = ReferenceGraph::load(conn, segment_id).await?;
+    let Some(referenced_id) = graph.id_of(referenced) else {
+        return Err(FlagrantError::BadRequest("Referenced segment not found").into());
+    };
+    if graph.reaches(referenced_id, segment_id) {
+        return Err(FlagrantError::BadRequest("Segment reference cycle").into());
+    }
+    Ok(())
+}
+
+/// Segment-in-segment references (see `SegmentDriver::Segment`) within a single project.
+struct ReferenceGraph {
+    /// Names of all the project's segments, by their ids.
+    names: HashMap<i32, String>,
+    /// Referencing segment ids along with names of segments they reference.
+    edges: Vec<ReferenceRow>,
+}
+
+impl ReferenceGraph {
+    /// Loads references within the project given segment belongs to.
+    async fn load(conn: &mut SqliteConnection, segment_id: i32) -> anyhow::Result<Self> {
+        let names =
+            SQLSegments::fetch_sibling_segments::<_, SegmentRow>(&mut *conn, params![segment_id])
+                .await
+                .map_err(|e| FlagrantError::QueryFailed("Could not fetch segments", e))?
+                .into_iter()
+                .map(|row| (row.segment_id, row.name))
+                .collect();
+        let edges = SQLSegments::fetch_segment_references::<_, ReferenceRow>(
+            &mut *conn,
+            params![segment_id],
+        )
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch segment references", e))?;
+
+        Ok(ReferenceGraph { names, edges })
+    }
+
+    fn id_of(&self, name: &str) -> Option<i32> {
+        self.names
+            .iter()
+            .find_map(|(id, n)| (n == name).then_some(*id))
+    }
+
+    /// Whether segment `from` is segment `to`, or references it (directly or not).
+    fn reaches(&self, from: i32, to: i32) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![from];
+
+        while let Some(id) = pending.pop() {
+            if id == to {
+                return true;
+            }
+            if visited.insert(id) {
+                pending.extend(
+                    self.edges
+                        .iter()
+                        .filter(|e| e.segment_id == id)
+                        .filter_map(|e| self.id_of(&e.referenced)),
+                );
+            }
+        }
+        false
+    }
+
+    /// Ids of segments referencing segment `segment_id`, directly or not.
+    fn referencing(&self, segment_id: i32) -> Vec<i32> {
+        let mut found = Vec::new();
+        let mut pending = vec![segment_id];
+
+        while let Some(id) = pending.pop() {
+            let Some(name) = self.names.get(&id) else {
+                continue;
+            };
+            for edge in self.edges.iter().filter(|e| &e.referenced == name) {
+                if edge.segment_id != segment_id && !found.contains(&edge.segment_id) {
+                    found.push(edge.segment_id);
+                    pending.push(edge.segment_id);
+                }
+            }
+        }
+        found
+    }
+}
+
 //
 // Helpers to load and construct segments
 //
@@ -0,0 +1,477 @@
+use chrono::Utc;
+use flagrant_types::{
+    Environment, Feature, Project, Segment,
+    payload::{
+        FeaturePatch, IdentityOverridePatch, IdentityPatch, PrerequisitePatchOp, SegmentPatch,
+        SegmentPatchOp, SegmentVariantWeight, TagPatchOp, TraitPatchOp, VariantPatchOp,
+    },
+    snapshot::{
+        FeatureSnapshot, GroupSnapshot, IdentitySnapshot, PinSnapshot, PrerequisiteSnapshot,
+        RuleSnapshot, SNAPSHOT_VERSION, SegmentOverrideSnapshot, SegmentSnapshot, Snapshot,
+        SnapshotImportSummary, TraitSnapshot, VariantSnapshot,
+    },
+};
+use sqlx::{Connection, SqliteConnection};
+
+use super::{feature, identity, project, segment, traits};
+use crate::errors::{FlagrantError, is_not_found};
+
+/// Captures given `environment` as a snapshot document.
+///
+/// Segments are project-wide, but only their feature overrides set within given
+/// `environment` get captured. Identities are left out unless `with_identities` is set.
+pub async fn export(
+    conn: &mut SqliteConnection,
+    project: &Project,
+    environment: &Environment,
+    with_identities: bool,
+) -> anyhow::Result<Snapshot> {
+    let mut tx = conn.begin().await?;
+
+    let traits = traits::get_all(&mut tx, project.id)
+        .await?
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+
+    let features = feature::get_all(&mut tx, environment, None, None, None, None, None)
+        .await?
+        .into_iter()
+        .map(|f| FeatureSnapshot {
+            tags: f.tags.0.iter().map(|t| t.name.clone()).collect(),
+            value: f.get_default_value().clone(),
+            variants: f
+                .variants
+                .iter()
+                .filter(|v| !v.is_control())
+                .map(|v| VariantSnapshot {
+                    value: v.value.clone(),
+                    weight: v.weight,
+                })
+                .collect(),
+            requires: f
+                .prerequisites
+                .into_iter()
+                .map(|p| PrerequisiteSnapshot {
+                    feature: p.feature_name,
+                    value: p.value,
+                })
+                .collect(),
+            name: f.name,
+            description: f.description,
+            is_enabled: f.is_enabled,
+            is_archived: f.is_archived,
+            visibility: f.visibility,
+            distribution: f.distribution,
+            distribution_key: f.distribution_key,
+        })
+        .collect();
+
+    let mut segments = Vec::new();
+    for seg in segment::get_all(&mut tx, project, None).await? {
+        let overrides = segment::list_overridden_features(&mut tx, environment.id, seg.id)
+            .await?
+            .into_iter()
+            .map(|o| SegmentOverrideSnapshot {
+                feature: o.feature_name,
+                priority: o.priority,
+                weights: o
+                    .weights
+                    .into_iter()
+                    .filter(|w| !w.is_control)
+                    .map(|w| VariantSnapshot {
+                        value: w.value,
+                        weight: w.weight,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        segments.push(SegmentSnapshot {
+            name: seg.name,
+            description: seg.description,
+            groups: seg
+                .groups
+                .into_iter()
+                .map(|g| GroupSnapshot {
+                    description: g.description,
+                    connector: g.connector,
+                    rules: g
+                        .rules
+                        .into_iter()
+                        .map(|r| RuleSnapshot {
+                            driver: r.driver,
+                            comparator: r.comparator,
+                            value: r.value,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            overrides,
+        });
+    }
+
+    let identities = if with_identities {
+        let mut identities = Vec::new();
+        for ident in identity::get_all(&mut tx, environment).await? {
+            let pins = identity::list_variant_assignments(&mut tx, environment, &ident)
+                .await?
+                .into_iter()
+                .filter(|iv| iv.pinned_at.is_some())
+                .filter_map(|iv| {
+                    Some(PinSnapshot {
+                        value: iv.feature_value?,
+                        feature: iv.feature_name,
+                    })
+                })
+                .collect();
+            let traits =
+                identity::get_by_value_with_traits(&mut tx, environment, ident.value.clone())
+                    .await?
+                    .traits
+                    .into_iter()
+                    .map(|t| TraitSnapshot {
+                        name: t.name,
+                        value: t.value,
+                    })
+                    .collect();
+
+            identities.push(IdentitySnapshot {
+                identity: ident.value,
+                traits,
+                pins,
+            });
+        }
+        Some(identities)
+    } else {
+        None
+    };
+
+    tx.commit().await?;
+    Ok(Snapshot {
+        version: SNAPSHOT_VERSION,
+        created_at: Utc::now().naive_utc(),
+        project: project.name.clone(),
+        environment: environment.name.clone(),
+        traits,
+        features,
+        segments,
+        identities,
+    })
+}
+
+/// Restores a snapshot into given `environment`, which may belong to any project.
+///
+/// Features and segments are matched by name - missing ones get created, existing ones
+/// are brought to the captured state. Entities not present in the snapshot are left
+/// intact, so are the traits of restored identities. Prerequisites get restored once all
+/// the features exist, as they may require features captured later. Everything is
+/// restored within a single transaction through regular patches, hence each change is
+/// recorded as a revision attributed to given `author`.
+pub async fn import(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    snapshot: Snapshot,
+    author: Option<&str>,
+) -> anyhow::Result<SnapshotImportSummary> {
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(FlagrantError::BadRequest("Unsupported snapshot version").into());
+    }
+
+    let mut summary = SnapshotImportSummary::default();
+    let mut tx = conn.begin().await?;
+    let project = project::get_by_id(&mut tx, environment.project_id).await?;
+
+    for name in snapshot.traits {
+        traits::upsert(&mut tx, project.id, name).await?;
+    }
+
+    for fs in &snapshot.features {
+        let feature = match feature::get_by_name(&mut tx, environment, fs.name.clone()).await {
+            Ok(f) => {
+                summary.features_updated += 1;
+                f
+            }
+            Err(e) if is_not_found(&e) => {
+                summary.features_created += 1;
+                feature::create_with_visibility(
+                    &mut tx,
+                    environment,
+                    fs.name.clone(),
+                    Some(fs.description.clone()),
+                    fs.value.clone(),
+                    fs.is_enabled,
+                    fs.visibility,
+                )
+                .await?
+            }
+            Err(e) => return Err(e),
+        };
+        restore_feature(&mut tx, environment, feature, fs, author).await?;
+    }
+    for fs in &snapshot.features {
+        restore_prerequisites(&mut tx, environment, fs, author).await?;
+    }
+
+    for ss in snapshot.segments {
+        let seg = match segment::get_by_name(&mut tx, &project, ss.name.clone()).await {
+            Ok(s) => {
+                summary.segments_updated += 1;
+                s
+            }
+            Err(e) if is_not_found(&e) => {
+                summary.segments_created += 1;
+                segment::create(&mut tx, &project, ss.name.clone(), ss.description.clone()).await?
+            }
+            Err(e) => return Err(e),
+        };
+        restore_segment(&mut tx, &project, environment, seg, ss, author).await?;
+    }
+
+    for is in snapshot.identities.unwrap_or_default() {
+        let ident = identity::get_or_create_by_value(&mut tx, environment, is.identity).await?;
+        let patch = IdentityPatch {
+            traits: is
+                .traits
+                .into_iter()
+                .map(|t| TraitPatchOp::SetValue {
+                    name: t.name,
+                    value: t.value,
+                })
+                .collect(),
+            overrides: is
+                .pins
+                .into_iter()
+                .map(|p| IdentityOverridePatch {
+                    feature_name: p.feature,
+                    variant_value: p.value.to_string(),
+                })
+                .collect(),
+            unpins: vec![],
+        };
+        identity::patch(&mut tx, environment, ident, patch, author).await?;
+        summary.identities += 1;
+    }
+
+    tx.commit().await?;
+    Ok(summary)
+}
+
+/// Brings an existing `feature` to the state captured by `fs`.
+///
+/// Non-control variants are shared by all environments of a project, so the ones not
+/// captured by snapshot are not deleted, just weighted down to 0 in given `environment`.
+/// Weights get lowered with a first patch and raised with a second one, so that sum
+/// of weights never exceeds 100 in between.
+async fn restore_feature(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature: Feature,
+    fs: &FeatureSnapshot,
+    author: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut lowering = FeaturePatch::default();
+    let mut raising = FeaturePatch::default();
+
+    if feature.is_enabled != fs.is_enabled {
+        lowering.is_enabled = Some(fs.is_enabled);
+    }
+    if feature.is_archived != fs.is_archived {
+        lowering.is_archived = Some(fs.is_archived);
+    }
+    if feature.description != fs.description {
+        lowering.description = Some(fs.description.clone());
+    }
+    if feature.visibility != fs.visibility {
+        lowering.visibility = Some(fs.visibility);
+    }
+    if feature.distribution != fs.distribution {
+        lowering.distribution = Some(fs.distribution.clone());
+    }
+    if feature.distribution_key != fs.distribution_key {
+        lowering.distribution_key = Some(fs.distribution_key.clone().unwrap_or_default());
+    }
+    for tag in &fs.tags {
+        if !feature.tags.0.iter().any(|t| &t.name == tag) {
+            lowering.tags.push(TagPatchOp::Add(tag.clone()));
+        }
+    }
+    for tag in &feature.tags.0 {
+        if !fs.tags.contains(&tag.name) {
+            lowering.tags.push(TagPatchOp::Remove(tag.name.clone()));
+        }
+    }
+    for var in &feature.variants {
+        if var.is_control() {
+            if var.value != fs.value {
+                lowering.variants.push(VariantPatchOp::SetValue {
+                    id: var.id,
+                    value: fs.value.clone(),
+                });
+            }
+            continue;
+        }
+        let weight = fs
+            .variants
+            .iter()
+            .find(|v| v.value == var.value)
+            .map_or(0, |v| v.weight);
+
+        if weight < var.weight {
+            lowering
+                .variants
+                .push(VariantPatchOp::SetWeight { id: var.id, weight });
+        } else if weight > var.weight {
+            raising
+                .variants
+                .push(VariantPatchOp::SetWeight { id: var.id, weight });
+        }
+    }
+    for vs in &fs.variants {
+        if !feature.variants.iter().any(|v| v.value == vs.value) {
+            raising.variants.push(VariantPatchOp::Add {
+                value: vs.value.clone(),
+                weight: vs.weight,
+            });
+        }
+    }
+
+    let mut feature = feature;
+    if !lowering.is_empty() {
+        feature = feature::patch(conn, environment, &feature, lowering, author).await?;
+    }
+    if !raising.is_empty() {
+        feature::patch(conn, environment, &feature, raising, author).await?;
+    }
+    Ok(())
+}
+
+/// Brings prerequisites of feature captured by `fs` to the captured state. Required
+/// features and their variants are matched by name and value respectively.
+async fn restore_prerequisites(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    fs: &FeatureSnapshot,
+    author: Option<&str>,
+) -> anyhow::Result<()> {
+    let feature = feature::get_by_name(conn, environment, fs.name.clone()).await?;
+    let mut patch = FeaturePatch::default();
+
+    for req in &feature.prerequisites {
+        if !fs.requires.iter().any(|r| r.feature == req.feature_name) {
+            patch.prerequisites.push(PrerequisitePatchOp::Unset {
+                feature_id: req.feature_id,
+            });
+        }
+    }
+    for rs in &fs.requires {
+        if feature
+            .prerequisites
+            .iter()
+            .any(|p| p.feature_name == rs.feature && p.value == rs.value)
+        {
+            continue;
+        }
+        let required = feature::get_by_name(conn, environment, rs.feature.clone()).await?;
+        let variant = required
+            .variants
+            .iter()
+            .find(|v| v.value == rs.value)
+            .ok_or(FlagrantError::BadRequest(
+                "Prerequisite refers to a variant missing in snapshot",
+            ))?;
+
+        patch.prerequisites.push(PrerequisitePatchOp::Set {
+            feature_id: required.id,
+            variant_id: variant.id,
+        });
+    }
+
+    if !patch.is_empty() {
+        feature::patch(conn, environment, &feature, patch, author).await?;
+    }
+    Ok(())
+}
+
+/// Brings an existing segment to the state captured by `ss`.
+///
+/// Groups are rebuilt from scratch: all the existing ones are deleted first, so labels
+/// of re-added groups start over from `group-1`. Feature overrides are replaced within
+/// given `environment` only.
+async fn restore_segment(
+    conn: &mut SqliteConnection,
+    project: &Project,
+    environment: &Environment,
+    seg: Segment,
+    ss: SegmentSnapshot,
+    author: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut ops = Vec::new();
+
+    if seg.description != ss.description {
+        ops.push(SegmentPatchOp::SetDescription(ss.description));
+    }
+    for group in &seg.groups {
+        ops.push(SegmentPatchOp::DeleteGroup {
+            label: group.label.clone(),
+        });
+    }
+    for (i, group) in ss.groups.into_iter().enumerate() {
+        let label = format!("group-{}", i + 1);
+
+        ops.push(SegmentPatchOp::AddGroup {
+            connector: group.connector,
+            description: group.description,
+        });
+        for rule in group.rules {
+            ops.push(SegmentPatchOp::AddRule {
+                group_label: label.clone(),
+                driver: rule.driver,
+                comparator: rule.comparator,
+                value: rule.value,
+            });
+        }
+    }
+    for ovr in segment::list_overridden_features(conn, environment.id, seg.id).await? {
+        ops.push(SegmentPatchOp::UnsetFeatureOverride {
+            feature_id: ovr.feature_id,
+            environment_id: environment.id,
+        });
+    }
+    for ovr in ss.overrides {
+        let feature = feature::get_by_name(conn, environment, ovr.feature).await?;
+        let mut variant_weights = Vec::with_capacity(ovr.weights.len());
+
+        for w in ovr.weights {
+            let variant = feature
+                .variants
+                .iter()
+                .find(|v| !v.is_control() && v.value == w.value)
+                .ok_or(FlagrantError::BadRequest(
+                    "Segment override refers to a variant missing in snapshot",
+                ))?;
+
+            variant_weights.push(SegmentVariantWeight {
+                variant_id: variant.id,
+                weight: w.weight,
+            });
+        }
+        ops.push(SegmentPatchOp::SetFeatureOverride {
+            feature_id: feature.id,
+            environment_id: environment.id,
+            variant_weights,
+        });
+        if ovr.priority != 0 {
+            ops.push(SegmentPatchOp::SetFeaturePriority {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                priority: ovr.priority,
+            });
+        }
+    }
+
+    if !ops.is_empty() {
+        segment::patch(conn, project, seg, SegmentPatch { ops }, author).await?;
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use flagrant_types::Trait;
+use flagrant_types::{Project, Trait, TraitPersistence, payload::IdentityTraitPayload};
 use hugsqlx::{HugSqlx, params};
 use serde_valid::Validate;
 use sqlx::{Acquire, SqliteConnection};
@@ -52,6 +52,40 @@ pub async fn get_by_prefix(
     Ok(traits)
 }
 
+/// Splits traits sent along with a client API request into the ones to store with the
+/// identity and the ones used for the request only, as told by the trait persistence
+/// policy of the `project`. Traits of invalid names or values are rejected.
+pub async fn split_by_persistence(
+    conn: &mut SqliteConnection,
+    project: &Project,
+    traits: Vec<IdentityTraitPayload>,
+) -> anyhow::Result<(Vec<IdentityTraitPayload>, Vec<IdentityTraitPayload>)> {
+    for t in &traits {
+        let valid = Trait {
+            id: 0,
+            name: t.name.clone(),
+        }
+        .validate()
+        .is_ok()
+            && t.value.validate().is_ok();
+
+        if !valid {
+            return Err(FlagrantError::BadRequest("Invalid request trait").into());
+        }
+    }
+    match project.trait_persistence {
+        TraitPersistence::Never => Ok((Vec::new(), traits)),
+        TraitPersistence::Always => Ok((traits, Vec::new())),
+        TraitPersistence::Known if traits.is_empty() => Ok((Vec::new(), Vec::new())),
+        TraitPersistence::Known => {
+            let known = get_all(conn, project.id).await?;
+            Ok(traits
+                .into_iter()
+                .partition(|t| known.iter().any(|k| k.name == t.name)))
+        }
+    }
+}
+
 /// Deletes a trait and removes it from all identities.
 pub async fn delete(conn: &mut SqliteConnection, trait_id: i32) -> anyhow::Result<()> {
     let mut tx = conn.begin().await?;
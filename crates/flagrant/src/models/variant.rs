@@ -2,7 +2,7 @@ use anyhow::bail;
 use hugsqlx::{HugSqlx, params};
 use sqlx::{Connection, Row, SqliteConnection};
 
-use crate::errors::FlagrantError;
+use crate::{distributor, errors::FlagrantError};
 use flagrant_types::{
     Environment, Feature, FeatureValue, IdentityVariant, OverriddenVariant, Variant,
 };
@@ -17,6 +17,7 @@ struct SQLVariants {}
 struct OverriddenFeatureRow {
     feature_id: i32,
     feature_name: String,
+    priority: i32,
     variant_id: i32,
     is_control: bool,
     value: FeatureValue,
@@ -94,6 +95,7 @@ pub async fn create(
         .map_err(|e| FlagrantError::QueryFailed("Could not insert a variant weight", e))?;
 
     balance_control_weight(&mut tx, environment, feature.id, variant_id, weight as i8).await?;
+    distributor::lay_out_buckets(&mut tx, environment, feature.id, None).await?;
     tx.commit().await?;
 
     Ok(Variant::build(variant_id, value, weight))
@@ -158,6 +160,7 @@ pub async fn update_one(
         new_weight as i8 - variant.weight as i8,
     )
     .await?;
+    distributor::lay_out_buckets(&mut tx, environment, feature_id, None).await?;
     tx.commit().await?;
 
     Ok(())
@@ -177,7 +180,10 @@ pub async fn get_by_id(
     let variant =
         SQLVariants::fetch_variant_by_id(conn, params![environment.id, variant_id, segment_id])
             .await
-            .map_err(|e| FlagrantError::QueryFailed("Could fetch a variant", e))?;
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => FlagrantError::NotFound("Variant not found"),
+                e => FlagrantError::QueryFailed("Could fetch a variant", e),
+            })?;
 
     Ok(variant)
 }
@@ -204,6 +210,20 @@ pub async fn get_by_value(
     Ok(variant)
 }
 
+/// Returns the control variant of a feature in the given environment, along with its organic
+/// weight.
+pub async fn get_control(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+) -> anyhow::Result<Variant> {
+    let variant = SQLVariants::fetch_control_variant(conn, params![environment.id, feature_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch control variant", e))?;
+
+    Ok(variant)
+}
+
 /// Returns variants across all features assigned to a given identity in the given environment.
 pub async fn get_by_identity<T: AsRef<str>>(
     conn: &mut SqliteConnection,
@@ -325,6 +345,39 @@ pub(crate) async fn update_accumulator(
     Ok(())
 }
 
+/// Returns `(variant_id, weight, buckets)` of non-control variants of `feature_id` within
+/// `environment`, ordered by their ids. `segment_id` scopes which weights are returned
+/// (`None` = organic default weights). Buckets are the hex bit mask of buckets variant
+/// owns in hash distribution, `None` if never laid out.
+pub(crate) async fn get_buckets(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    feature_id: i32,
+    segment_id: Option<i32>,
+) -> anyhow::Result<Vec<(i32, u8, Option<String>)>> {
+    SQLVariants::fetch_variant_buckets(conn, params![environment.id, feature_id, segment_id])
+        .await
+        .map_err(|e| FlagrantError::QueryFailed("Could not fetch variant buckets", e).into())
+}
+
+/// Sets the hex bit mask of buckets variant owns in hash distribution.
+pub(crate) async fn set_buckets(
+    conn: &mut SqliteConnection,
+    environment: &Environment,
+    variant_id: i32,
+    segment_id: Option<i32>,
+    buckets: String,
+) -> anyhow::Result<()> {
+    SQLVariants::update_variant_buckets(
+        conn,
+        params![variant_id, environment.id, segment_id, buckets],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not update variant buckets", e))?;
+
+    Ok(())
+}
+
 /// Sets a segment-scoped weight for an existing non-control variant, similarly to how
 /// organic weights are set via [`set_weight`].
 pub(crate) async fn set_segment_weight(
@@ -385,7 +438,8 @@ pub async fn get_segment_weights(
 }
 
 /// Returns `(segment_id, segment_name, variant_id, weight)` for all segments overriding a
-/// feature+environment (includes the control variant's auto-balanced remainder row).
+/// feature+environment (includes the control variant's auto-balanced remainder row), highest
+/// priority first.
 pub async fn get_segment_overrides_with_weights(
     conn: &mut SqliteConnection,
     feature_id: i32,
@@ -400,13 +454,13 @@ pub async fn get_segment_overrides_with_weights(
 
 /// Returns every variant a segment overrides (including each feature's control-variant
 /// remainder), across all features, within a given environment - flat rows of
-/// `(feature_id, feature_name, weight-info)`. Group by `feature_id` to build a per-feature
-/// view (see `segment::list_overridden_features`).
+/// `(feature_id, feature_name, priority, weight-info)`. Group by `feature_id` to build
+/// a per-feature view (see `segment::list_overridden_features`).
 pub async fn get_features_overridden_by_segment(
     conn: &mut SqliteConnection,
     segment_id: i32,
     environment_id: i32,
-) -> anyhow::Result<Vec<(i32, String, OverriddenVariant)>> {
+) -> anyhow::Result<Vec<(i32, String, i32, OverriddenVariant)>> {
     let rows = SQLVariants::fetch_features_overridden_by_segment::<_, OverriddenFeatureRow>(
         conn,
         params![segment_id, environment_id],
@@ -420,6 +474,7 @@ pub async fn get_features_overridden_by_segment(
             (
                 r.feature_id,
                 r.feature_name,
+                r.priority,
                 OverriddenVariant {
                     variant_id: r.variant_id,
                     value: r.value,
@@ -431,6 +486,23 @@ pub async fn get_features_overridden_by_segment(
         .collect())
 }
 
+/// Removes a segment-scoped weight override of a single non-control variant, leaving
+/// overrides of other variants (and their bucket layout) intact.
+pub(crate) async fn delete_segment_weight(
+    conn: &mut SqliteConnection,
+    segment_id: i32,
+    variant_id: i32,
+    environment_id: i32,
+) -> anyhow::Result<()> {
+    SQLVariants::delete_segment_variant_weight(
+        conn,
+        params![segment_id, variant_id, environment_id],
+    )
+    .await
+    .map_err(|e| FlagrantError::QueryFailed("Could not delete segment variant weight", e))?;
+    Ok(())
+}
+
 /// Removes all segment-scoped weight overrides (including the control variant's remainder
 /// row) for a segment+feature+environment.
 pub(crate) async fn delete_segment_weights_for_feature(
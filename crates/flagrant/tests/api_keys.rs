@@ -0,0 +1,92 @@
+use common::{create_context, create_environment};
+use flagrant::errors::FlagrantError;
+use flagrant::models::{api_key, project};
+use flagrant_types::ApiKeyKind;
+use sqlx::{Sqlite, pool::PoolConnection};
+
+mod common;
+
+#[sqlx::test]
+async fn created_key_authenticates(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+
+    let (created, key) = api_key::create(
+        &mut conn,
+        &project,
+        Some(&environment),
+        "sdk".to_owned(),
+        ApiKeyKind::Client,
+    )
+    .await
+    .unwrap();
+    assert!(key.starts_with(&created.prefix));
+    assert_eq!(created.environment_id, Some(environment.id));
+
+    let authenticated = api_key::authenticate(&mut conn, &key)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(authenticated.id, created.id);
+    assert_eq!(authenticated.kind, ApiKeyKind::Client);
+
+    // Only the key itself authenticates, not its visible prefix.
+    let result = api_key::authenticate(&mut conn, &created.prefix)
+        .await
+        .unwrap();
+    assert!(result.is_none());
+}
+
+#[sqlx::test]
+async fn deleted_key_no_longer_authenticates(mut conn: PoolConnection<Sqlite>) {
+    let (project, _) = create_context(&mut conn).await;
+    let (created, key) = api_key::create(
+        &mut conn,
+        &project,
+        None,
+        "ci".to_owned(),
+        ApiKeyKind::Management,
+    )
+    .await
+    .unwrap();
+
+    api_key::delete(&mut conn, &project, created.id)
+        .await
+        .unwrap();
+    assert!(
+        api_key::authenticate(&mut conn, &key)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(api_key::list(&mut conn, &project).await.unwrap().is_empty());
+
+    // Already deleted key can't be deleted again.
+    let result = api_key::delete(&mut conn, &project, created.id).await;
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::NotFound(_))
+    ));
+}
+
+#[sqlx::test]
+async fn create_rejects_environment_of_another_project(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let other = project::create(&mut conn, "other_project".to_owned())
+        .await
+        .unwrap();
+    let _ = create_environment(&mut conn, &other).await;
+
+    let result = api_key::create(
+        &mut conn,
+        &other,
+        Some(&environment),
+        "sdk".to_owned(),
+        ApiKeyKind::Client,
+    )
+    .await;
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+}
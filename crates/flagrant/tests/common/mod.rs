@@ -1,7 +1,7 @@
 use flagrant::models::{environment, feature, project, segment};
 use flagrant_types::{
-    Environment, Feature, FeatureValue, GroupConnector, Project, Segment, SegmentDriver,
-    Comparator,
+    Comparator, Environment, Feature, FeatureValue, GroupConnector, Project, Segment,
+    SegmentDriver,
     payload::{SegmentPatch, SegmentPatchOp},
 };
 use rand::Rng;
@@ -78,7 +78,7 @@ pub async fn apply(
     segment: Segment,
     ops: Vec<SegmentPatchOp>,
 ) -> Segment {
-    segment::patch(conn, project, segment, SegmentPatch { ops })
+    segment::patch(conn, project, segment, SegmentPatch { ops }, None)
         .await
         .unwrap()
 }
@@ -1,6 +1,10 @@
 use common::{create_context, create_environment, create_environment_from, create_feature};
-use flagrant::models::{feature, variant};
-use flagrant_types::FeatureValue;
+use flagrant::models::{environment, feature, identity, revision, variant};
+use flagrant_types::{
+    Environment, Feature, FeatureValue, RevisionEntity,
+    explain::Resolution,
+    payload::{FeaturePatch, TagPatchOp},
+};
 use sqlx::{Sqlite, pool::PoolConnection};
 
 mod common;
@@ -66,3 +70,173 @@ async fn create_environment_inherits_from_provided_base_env(mut conn: PoolConnec
         .unwrap();
     assert_eq!(feature_env2.get_default_variant().weight, 60);
 }
+
+/// A newly created environment should start with the feature status (on/off, archived)
+/// its base environment has - not with the status of any other environment.
+#[sqlx::test]
+async fn create_environment_inherits_feature_status_from_base_env(
+    mut conn: PoolConnection<Sqlite>,
+) {
+    let (project, env1) = create_context(&mut conn).await;
+    let env2 = create_environment(&mut conn, &project).await;
+    let feature = create_feature(&mut conn, &env1, "foo").await;
+
+    // Enabled in env1 (at creation time), archived in env2.
+    let feature_env2 = feature::get_by_id(&mut conn, &env2, feature.id)
+        .await
+        .unwrap();
+    feature::patch(
+        &mut conn,
+        &env2,
+        &feature_env2,
+        FeaturePatch {
+            is_archived: Some(true),
+            ..Default::default()
+        },
+        None,
+    )
+    .await
+    .unwrap();
+
+    let env3 = create_environment_from(&mut conn, &project, &env1).await;
+    let feature_env3 = feature::get_by_id(&mut conn, &env3, feature.id)
+        .await
+        .unwrap();
+    assert!(feature_env3.is_enabled);
+    assert!(!feature_env3.is_archived);
+
+    let env4 = create_environment_from(&mut conn, &project, &env2).await;
+    let feature_env4 = feature::get_by_id(&mut conn, &env4, feature.id)
+        .await
+        .unwrap();
+    assert!(!feature_env4.is_enabled);
+    assert!(feature_env4.is_archived);
+}
+
+/// Returns value of `feature` served to identity of given `value`.
+async fn served_value(
+    conn: &mut PoolConnection<Sqlite>,
+    environment: &Environment,
+    value: &str,
+    feature: &Feature,
+) -> Option<FeatureValue> {
+    let ident = identity::get_or_create_by_value(conn, environment, value.to_owned())
+        .await
+        .unwrap();
+    identity::get_identity_variants(conn, environment, &ident)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|iv| iv.feature_id == feature.id)
+        .and_then(|iv| iv.feature_value)
+}
+
+#[sqlx::test]
+async fn frozen_environment_serves_control_values(mut conn: PoolConnection<Sqlite>) {
+    let (_, env) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &env, "off").await;
+    variant::create(&mut conn, &env, &feature, FeatureValue::build("on"), 100)
+        .await
+        .unwrap();
+
+    let on = Some(FeatureValue::build("on"));
+    let off = Some(FeatureValue::build("off"));
+    assert_eq!(served_value(&mut conn, &env, "user-1", &feature).await, on);
+
+    let frozen = environment::freeze(&mut conn, &env, None, Some("oncall"))
+        .await
+        .unwrap();
+    assert!(frozen.frozen_at.is_some());
+    assert_eq!(served_value(&mut conn, &env, "user-1", &feature).await, off);
+    // Identities showing up meanwhile don't get distributed either.
+    assert_eq!(served_value(&mut conn, &env, "user-2", &feature).await, off);
+
+    let ident = identity::get_by_value(&mut conn, &env, "user-1".to_owned())
+        .await
+        .unwrap();
+    let explanations = identity::explain(&mut conn, &frozen, &ident, None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        explanations[0].resolution,
+        Resolution::Frozen { tag: None }
+    ));
+
+    let thawed = environment::thaw(&mut conn, &env, Some("oncall"))
+        .await
+        .unwrap();
+    assert!(thawed.frozen_at.is_none());
+    assert_eq!(served_value(&mut conn, &env, "user-1", &feature).await, on);
+    assert_eq!(served_value(&mut conn, &env, "user-2", &feature).await, on);
+}
+
+#[sqlx::test]
+async fn freezing_by_tag_leaves_other_features_be(mut conn: PoolConnection<Sqlite>) {
+    let (_, env) = create_context(&mut conn).await;
+    let tagged = create_feature(&mut conn, &env, "off").await;
+    let other = create_feature(&mut conn, &env, "off").await;
+    for feature in [&tagged, &other] {
+        variant::create(&mut conn, &env, feature, FeatureValue::build("on"), 100)
+            .await
+            .unwrap();
+    }
+    let patch = FeaturePatch {
+        tags: vec![TagPatchOp::Add("payments".to_owned())],
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &env, &tagged, patch, None)
+        .await
+        .unwrap();
+
+    let unknown = environment::freeze(&mut conn, &env, Some("search".to_owned()), None).await;
+    assert!(unknown.is_err());
+
+    environment::freeze(&mut conn, &env, Some("payments".to_owned()), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        served_value(&mut conn, &env, "user-1", &tagged).await,
+        Some(FeatureValue::build("off"))
+    );
+    assert_eq!(
+        served_value(&mut conn, &env, "user-1", &other).await,
+        Some(FeatureValue::build("on"))
+    );
+
+    // Explained by the switch as stored, not as the (stale) environment tells.
+    let ident = identity::get_by_value(&mut conn, &env, "user-1".to_owned())
+        .await
+        .unwrap();
+    let explanations = identity::explain(&mut conn, &env, &ident, Some(&tagged.name))
+        .await
+        .unwrap();
+    assert!(matches!(
+        &explanations[0].resolution,
+        Resolution::Frozen { tag: Some(tag) } if tag == "payments"
+    ));
+}
+
+#[sqlx::test]
+async fn freezing_is_recorded_as_revisions(mut conn: PoolConnection<Sqlite>) {
+    let (_, env) = create_context(&mut conn).await;
+
+    environment::freeze(&mut conn, &env, None, Some("oncall"))
+        .await
+        .unwrap();
+    environment::thaw(&mut conn, &env, Some("oncall"))
+        .await
+        .unwrap();
+    // Thawing an environment which is not frozen changes nothing, hence records nothing.
+    environment::thaw(&mut conn, &env, Some("oncall"))
+        .await
+        .unwrap();
+
+    let revisions =
+        revision::list_for_entity(&mut conn, RevisionEntity::Environment, env.id, Some(env.id))
+            .await
+            .unwrap();
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions[0].author.as_deref(), Some("oncall"));
+    assert!(revisions[0].after["frozen_at"].is_null());
+    assert!(revisions[1].after["frozen_at"].is_string());
+}
@@ -1,5 +1,5 @@
 use flagrant::{
-    evaluator,
+    evaluator::{self, References, SegmentCache},
     models::{identity, segment, variant},
 };
 use flagrant_types::{
@@ -23,6 +23,8 @@ async fn no_segment_overriding_feature_returns_none(mut conn: PoolConnection<Sql
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -84,6 +86,8 @@ async fn matching_segment_returns_its_id(mut conn: PoolConnection<Sqlite>) {
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -150,6 +154,8 @@ async fn segment_with_non_matching_rule_returns_none(mut conn: PoolConnection<Sq
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -243,6 +249,8 @@ async fn falls_through_a_non_matching_segment_to_a_later_matching_one(
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -337,6 +345,8 @@ async fn first_created_segment_wins_when_multiple_match(mut conn: PoolConnection
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -350,6 +360,85 @@ async fn first_created_segment_wins_when_multiple_match(mut conn: PoolConnection
     assert_eq!(result, Some(older.id));
 }
 
+/// Two segments override the same feature and both match the identity - the newer one
+/// wins once its override gets a higher priority.
+#[sqlx::test]
+async fn higher_priority_segment_wins_when_multiple_match(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    let mut segments = Vec::new();
+    for (name, priority) in [("older", 0), ("newer", 5)] {
+        let seg = segment::create(&mut conn, &project, name.to_owned(), None)
+            .await
+            .unwrap();
+        let seg = apply(
+            &mut conn,
+            &project,
+            seg,
+            vec![
+                add_group(None),
+                add_rule(
+                    "group-1",
+                    SegmentDriver::Environment,
+                    Comparator::ExactlyMatches,
+                    &environment.name,
+                ),
+                SegmentPatchOp::SetFeatureOverride {
+                    feature_id: feature.id,
+                    environment_id: environment.id,
+                    variant_weights: vec![SegmentVariantWeight {
+                        variant_id: alt.id,
+                        weight: 15,
+                    }],
+                },
+                SegmentPatchOp::SetFeaturePriority {
+                    feature_id: feature.id,
+                    environment_id: environment.id,
+                    priority,
+                },
+            ],
+        )
+        .await;
+        segments.push(seg);
+    }
+
+    let overrides = segment::list_overrides_for_feature(&mut conn, environment.id, feature.id)
+        .await
+        .unwrap();
+    let order: Vec<i32> = overrides.iter().map(|(id, _, _)| *id).collect();
+    assert_eq!(order, vec![segments[1].id, segments[0].id]);
+
+    let identity = identity::create(&mut conn, &environment, "any-user".to_owned(), vec![])
+        .await
+        .unwrap();
+
+    let result = evaluator::evaluate(
+        &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
+        &environment,
+        &evaluator::IdentityContext {
+            value: &identity.value,
+            traits: &identity.traits,
+        },
+        feature.id,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(result, Some(segments[1].id));
+}
+
 /// A group's rules are OR-ed: only one of two rules needs to match for the group (and, with
 /// a single-group segment, the whole segment) to match.
 #[sqlx::test]
@@ -406,6 +495,8 @@ async fn rules_within_a_group_are_or_ed(mut conn: PoolConnection<Sqlite>) {
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -484,6 +575,8 @@ async fn groups_combine_via_and_and_and_not(mut conn: PoolConnection<Sqlite>) {
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -557,6 +650,8 @@ async fn empty_non_head_group_blocks_the_segment_from_matching(mut conn: PoolCon
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -611,6 +706,8 @@ async fn segment_with_zero_groups_never_matches(mut conn: PoolConnection<Sqlite>
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -682,6 +779,8 @@ async fn trait_driver_matches_a_db_loaded_trait(mut conn: PoolConnection<Sqlite>
 
     let result = evaluator::evaluate(
         &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
         &environment,
         &evaluator::IdentityContext {
             value: &identity.value,
@@ -694,3 +793,385 @@ async fn trait_driver_matches_a_db_loaded_trait(mut conn: PoolConnection<Sqlite>
 
     assert!(result.is_some());
 }
+
+#[sqlx::test]
+async fn segment_referencing_another_matches_its_identities(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    let staff = segment::create(&mut conn, &project, "staff".to_owned(), None)
+        .await
+        .unwrap();
+    apply(
+        &mut conn,
+        &project,
+        staff,
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Identity,
+                Comparator::MatchesRegex,
+                "@corp\\.com$",
+            ),
+        ],
+    )
+    .await;
+
+    // Only the referencing segment overrides the feature.
+    let beta = segment::create(&mut conn, &project, "staff_beta".to_owned(), None)
+        .await
+        .unwrap();
+    apply(
+        &mut conn,
+        &project,
+        beta.clone(),
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Segment("staff".to_owned()),
+                Comparator::ExactlyMatches,
+                "true",
+            ),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 30,
+                }],
+            },
+        ],
+    )
+    .await;
+
+    for (value, expected) in [("jane@corp.com", Some(beta.id)), ("jane@else.com", None)] {
+        let identity = identity::create(&mut conn, &environment, value.to_owned(), vec![])
+            .await
+            .unwrap();
+        let result = evaluator::evaluate(
+            &mut conn,
+            &SegmentCache::default(),
+            &mut References::default(),
+            &environment,
+            &evaluator::IdentityContext {
+                value: &identity.value,
+                traits: &identity.traits,
+            },
+            feature.id,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, expected, "identity {value}");
+    }
+}
+
+#[sqlx::test]
+async fn referenced_segment_is_evaluated_once_per_references(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let staff = segment::create(&mut conn, &project, "staff".to_owned(), None)
+        .await
+        .unwrap();
+    let staff = apply(
+        &mut conn,
+        &project,
+        staff,
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Identity,
+                Comparator::MatchesRegex,
+                "@corp\\.com$",
+            ),
+        ],
+    )
+    .await;
+
+    let beta = segment::create(&mut conn, &project, "staff_beta".to_owned(), None)
+        .await
+        .unwrap();
+    let mut ops = vec![
+        add_group(None),
+        add_rule(
+            "group-1",
+            SegmentDriver::Segment("staff".to_owned()),
+            Comparator::ExactlyMatches,
+            "true",
+        ),
+    ];
+    let mut features = Vec::new();
+    for _ in 0..2 {
+        let feature = create_feature(&mut conn, &environment, "control").await;
+        let alt = variant::create(
+            &mut conn,
+            &environment,
+            &feature,
+            FeatureValue::build("alt"),
+            40,
+        )
+        .await
+        .unwrap();
+        ops.push(SegmentPatchOp::SetFeatureOverride {
+            feature_id: feature.id,
+            environment_id: environment.id,
+            variant_weights: vec![SegmentVariantWeight {
+                variant_id: alt.id,
+                weight: 30,
+            }],
+        });
+        features.push(feature);
+    }
+    let beta = apply(&mut conn, &project, beta, ops).await;
+
+    let identity = identity::create(&mut conn, &environment, "jane@corp.com".to_owned(), vec![])
+        .await
+        .unwrap();
+    let ctx = evaluator::IdentityContext {
+        value: &identity.value,
+        traits: &identity.traits,
+    };
+    let mut references = References::default();
+    let result = evaluator::evaluate(
+        &mut conn,
+        &SegmentCache::default(),
+        &mut references,
+        &environment,
+        &ctx,
+        features[0].id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(result, Some(beta.id));
+
+    // Referenced segment stops matching meanwhile - evaluation of the other feature sticks
+    // to the outcome referenced segment had once evaluated.
+    apply(
+        &mut conn,
+        &project,
+        staff,
+        vec![SegmentPatchOp::DeleteGroup {
+            label: "group-1".to_owned(),
+        }],
+    )
+    .await;
+
+    let result = evaluator::evaluate(
+        &mut conn,
+        &SegmentCache::default(),
+        &mut references,
+        &environment,
+        &ctx,
+        features[1].id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(result, Some(beta.id));
+
+    let result = evaluator::evaluate(
+        &mut conn,
+        &SegmentCache::default(),
+        &mut References::default(),
+        &environment,
+        &ctx,
+        features[1].id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(result, None);
+}
+
+#[sqlx::test]
+async fn or_connector_matches_identities_of_either_term(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    // (identity = user-pro AND environment = none) OR identity = user-staff, where no
+    // environment is named "none"
+    let segment = segment::create(&mut conn, &project, "pro_or_staff".to_owned(), None)
+        .await
+        .unwrap();
+    apply(
+        &mut conn,
+        &project,
+        segment.clone(),
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Identity,
+                Comparator::ExactlyMatches,
+                "user-pro",
+            ),
+            add_group(Some(GroupConnector::And)),
+            add_rule(
+                "group-2",
+                SegmentDriver::Environment,
+                Comparator::ExactlyMatches,
+                "none",
+            ),
+            add_group(Some(GroupConnector::Or)),
+            add_rule(
+                "group-3",
+                SegmentDriver::Identity,
+                Comparator::ExactlyMatches,
+                "user-staff",
+            ),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 30,
+                }],
+            },
+        ],
+    )
+    .await;
+
+    for (value, expected) in [
+        ("user-staff", Some(segment.id)),
+        ("user-pro", None),
+        ("user-other", None),
+    ] {
+        let identity = identity::create(&mut conn, &environment, value.to_owned(), vec![])
+            .await
+            .unwrap();
+        let result = evaluator::evaluate(
+            &mut conn,
+            &SegmentCache::default(),
+            &mut References::default(),
+            &environment,
+            &evaluator::IdentityContext {
+                value: &identity.value,
+                traits: &identity.traits,
+            },
+            feature.id,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, expected, "identity {value}");
+    }
+}
+
+#[sqlx::test]
+async fn cached_segments_stay_until_invalidated(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    let segment = segment::create(&mut conn, &project, "vip".to_owned(), None)
+        .await
+        .unwrap();
+
+    apply(
+        &mut conn,
+        &project,
+        segment.clone(),
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Identity,
+                Comparator::ExactlyMatches,
+                "user-vip",
+            ),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 30,
+                }],
+            },
+        ],
+    )
+    .await;
+
+    let identity = identity::create(&mut conn, &environment, "user-vip".to_owned(), vec![])
+        .await
+        .unwrap();
+    let ctx = evaluator::IdentityContext {
+        value: &identity.value,
+        traits: &identity.traits,
+    };
+    let segments = SegmentCache::default();
+
+    let result = evaluator::evaluate(
+        &mut conn,
+        &segments,
+        &mut References::default(),
+        &environment,
+        &ctx,
+        feature.id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(result, Some(segment.id));
+
+    apply(
+        &mut conn,
+        &project,
+        segment.clone(),
+        vec![SegmentPatchOp::UnsetFeatureOverride {
+            feature_id: feature.id,
+            environment_id: environment.id,
+        }],
+    )
+    .await;
+
+    // Override is still cached, it's up to the cache holder to drop it.
+    let result = evaluator::evaluate(
+        &mut conn,
+        &segments,
+        &mut References::default(),
+        &environment,
+        &ctx,
+        feature.id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(result, Some(segment.id));
+
+    segments.invalidate();
+    let result = evaluator::evaluate(
+        &mut conn,
+        &segments,
+        &mut References::default(),
+        &environment,
+        &ctx,
+        feature.id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(result, None);
+}
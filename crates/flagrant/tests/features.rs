@@ -1,9 +1,9 @@
 use common::{create_context, create_environment, random_string};
 use flagrant::errors::FlagrantError;
-use flagrant::models::{environment, feature, project, variant};
+use flagrant::models::{environment, feature, identity, project, revision, variant};
 use flagrant_types::{
-    FeatureValue,
-    payload::{FeaturePatch, TagPatchOp, VariantPatchOp},
+    FeatureValue, FeatureVisibility, RevisionEntity,
+    payload::{FeaturePatch, PrerequisitePatchOp, TagPatchOp, VariantPatchOp},
 };
 use smallvec::smallvec;
 use sqlx::{Sqlite, pool::PoolConnection};
@@ -772,7 +772,7 @@ async fn patch_control_variant_weight_is_rejected(mut conn: PoolConnection<Sqlit
         ..Default::default()
     };
     assert!(
-        feature::patch(&mut conn, &environment, &feature, patch)
+        feature::patch(&mut conn, &environment, &feature, patch, None)
             .await
             .is_err()
     );
@@ -792,7 +792,7 @@ async fn patch_control_variant_value_is_accepted(mut conn: PoolConnection<Sqlite
         ..Default::default()
     };
     assert!(
-        feature::patch(&mut conn, &environment, &feature, patch)
+        feature::patch(&mut conn, &environment, &feature, patch, None)
             .await
             .is_ok()
     );
@@ -1043,7 +1043,7 @@ async fn patch_tags_adds_without_clearing_existing(mut conn: PoolConnection<Sqli
         tags: vec![TagPatchOp::Add("beta".to_owned())],
         ..Default::default()
     };
-    feature::patch(&mut conn, &environment, &feature, patch)
+    feature::patch(&mut conn, &environment, &feature, patch, None)
         .await
         .unwrap();
 
@@ -1056,7 +1056,7 @@ async fn patch_tags_adds_without_clearing_existing(mut conn: PoolConnection<Sqli
         tags: vec![TagPatchOp::Add("experimental".to_owned())],
         ..Default::default()
     };
-    feature::patch(&mut conn, &environment, &feature, patch)
+    feature::patch(&mut conn, &environment, &feature, patch, None)
         .await
         .unwrap();
 
@@ -1078,7 +1078,7 @@ async fn patch_tags_add_is_idempotent(mut conn: PoolConnection<Sqlite>) {
             tags: vec![TagPatchOp::Add("beta".to_owned())],
             ..Default::default()
         };
-        feature::patch(&mut conn, &environment, &feature, patch)
+        feature::patch(&mut conn, &environment, &feature, patch, None)
             .await
             .unwrap();
     }
@@ -1102,7 +1102,7 @@ async fn patch_tags_removes_only_given_tag(mut conn: PoolConnection<Sqlite>) {
         ],
         ..Default::default()
     };
-    feature::patch(&mut conn, &environment, &feature, patch)
+    feature::patch(&mut conn, &environment, &feature, patch, None)
         .await
         .unwrap();
     let feature = feature::get_by_id(&mut conn, &environment, feature.id)
@@ -1113,7 +1113,7 @@ async fn patch_tags_removes_only_given_tag(mut conn: PoolConnection<Sqlite>) {
         tags: vec![TagPatchOp::Remove("beta".to_owned())],
         ..Default::default()
     };
-    feature::patch(&mut conn, &environment, &feature, patch)
+    feature::patch(&mut conn, &environment, &feature, patch, None)
         .await
         .unwrap();
 
@@ -1128,7 +1128,7 @@ async fn patch_tags_removes_only_given_tag(mut conn: PoolConnection<Sqlite>) {
         ..Default::default()
     };
     assert!(
-        feature::patch(&mut conn, &environment, &feature, patch)
+        feature::patch(&mut conn, &environment, &feature, patch, None)
             .await
             .is_ok()
     );
@@ -1146,7 +1146,7 @@ async fn get_all_filters_by_included_and_excluded_tags(mut conn: PoolConnection<
         ],
         ..Default::default()
     };
-    feature::patch(&mut conn, &environment, &ui_beta, patch)
+    feature::patch(&mut conn, &environment, &ui_beta, patch, None)
         .await
         .unwrap();
 
@@ -1155,7 +1155,7 @@ async fn get_all_filters_by_included_and_excluded_tags(mut conn: PoolConnection<
         tags: vec![TagPatchOp::Add("ui".to_owned())],
         ..Default::default()
     };
-    feature::patch(&mut conn, &environment, &ui_only, patch)
+    feature::patch(&mut conn, &environment, &ui_only, patch, None)
         .await
         .unwrap();
 
@@ -1195,3 +1195,505 @@ async fn get_all_filters_by_included_and_excluded_tags(mut conn: PoolConnection<
     assert!(names.contains(&ui_only.name));
     assert!(!names.contains(&untagged.name));
 }
+
+#[sqlx::test]
+async fn patch_status_is_scoped_to_environment(mut conn: PoolConnection<Sqlite>) {
+    let (project, staging) = create_context(&mut conn).await;
+    let prod = create_environment(&mut conn, &project).await;
+    let feature = create_feature(&mut conn, &staging, "foo").await;
+
+    // Created enabled in staging only - every other environment starts disabled.
+    assert!(feature.is_enabled);
+    let feature_prod = feature::get_by_id(&mut conn, &prod, feature.id)
+        .await
+        .unwrap();
+    assert!(!feature_prod.is_enabled);
+
+    // Switching the feature off and archiving it in staging leaves prod untouched.
+    let patch = FeaturePatch {
+        is_enabled: Some(false),
+        is_archived: Some(true),
+        ..Default::default()
+    };
+    let feature = feature::patch(&mut conn, &staging, &feature, patch, None)
+        .await
+        .unwrap();
+    assert!(!feature.is_enabled);
+    assert!(feature.is_archived);
+
+    let patch = FeaturePatch {
+        is_enabled: Some(true),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &prod, &feature_prod, patch, None)
+        .await
+        .unwrap();
+
+    let feature_prod = feature::get_by_id(&mut conn, &prod, feature.id)
+        .await
+        .unwrap();
+    assert!(feature_prod.is_enabled);
+    assert!(!feature_prod.is_archived);
+
+    // Status filters are evaluated against the environment being listed.
+    let archived_staging =
+        feature::get_all(&mut conn, &staging, Some(true), None, None, None, None)
+            .await
+            .unwrap();
+    let archived_prod = feature::get_all(&mut conn, &prod, Some(true), None, None, None, None)
+        .await
+        .unwrap();
+    let enabled_prod =
+        feature::get_all(&mut conn, &prod, Some(false), Some(true), None, None, None)
+            .await
+            .unwrap();
+
+    assert_eq!(archived_staging.len(), 1);
+    assert!(archived_prod.is_empty());
+    assert_eq!(enabled_prod.len(), 1);
+
+    // Identities get the feature only where it is enabled.
+    let alice_staging = identity::get_or_create_by_value(&mut conn, &staging, "alice".to_owned())
+        .await
+        .unwrap();
+    let alice_prod = identity::get_or_create_by_value(&mut conn, &prod, "alice".to_owned())
+        .await
+        .unwrap();
+
+    assert!(
+        identity::get_identity_variants(&mut conn, &staging, &alice_staging)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        identity::get_identity_variants(&mut conn, &prod, &alice_prod)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[sqlx::test]
+async fn disabled_features_are_returned_to_identities(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let patch = FeaturePatch {
+        is_enabled: Some(false),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &feature, patch, None)
+        .await
+        .unwrap();
+
+    let alice = identity::get_or_create_by_value(&mut conn, &environment, "alice".to_owned())
+        .await
+        .unwrap();
+    let variants = identity::get_identity_variants(&mut conn, &environment, &alice)
+        .await
+        .unwrap();
+
+    assert_eq!(variants.len(), 1);
+    assert!(!variants[0].is_enabled);
+    assert_eq!(variants[0].feature_value, Some(FeatureValue::build("foo")));
+}
+
+#[sqlx::test]
+async fn patch_records_feature_revision(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let patch = FeaturePatch {
+        is_enabled: Some(false),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &feature, patch, Some("alice"))
+        .await
+        .unwrap();
+
+    // Applying the same state once again changes nothing, hence no revision.
+    let feature = feature::get_by_id(&mut conn, &environment, feature.id)
+        .await
+        .unwrap();
+    let patch = FeaturePatch {
+        is_enabled: Some(false),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &feature, patch, Some("alice"))
+        .await
+        .unwrap();
+
+    let revisions = revision::list_for_entity(
+        &mut conn,
+        RevisionEntity::Feature,
+        feature.id,
+        Some(environment.id),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].author.as_deref(), Some("alice"));
+    assert_eq!(revisions[0].before["is_enabled"], true);
+    assert_eq!(revisions[0].after["is_enabled"], false);
+}
+
+#[sqlx::test]
+async fn revert_restores_feature_state(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let patch = FeaturePatch {
+        is_enabled: Some(false),
+        description: Some("changed".to_owned()),
+        tags: vec![TagPatchOp::Add("beta".to_owned())],
+        variants: vec![VariantPatchOp::Add {
+            value: FeatureValue::build("bar"),
+            weight: 30,
+        }],
+        ..Default::default()
+    };
+    let patched = feature::patch(&mut conn, &environment, &feature, patch, None)
+        .await
+        .unwrap();
+    assert_eq!(patched.variants.len(), 2);
+
+    let revisions = revision::list_for_entity(
+        &mut conn,
+        RevisionEntity::Feature,
+        feature.id,
+        Some(environment.id),
+    )
+    .await
+    .unwrap();
+    let reverted = feature::revert(&mut conn, &environment, &patched, revisions[0].id, None)
+        .await
+        .unwrap();
+
+    assert!(reverted.is_enabled);
+    assert_eq!(reverted.description, feature.description);
+    assert!(reverted.tags.0.is_empty());
+    assert_eq!(reverted.variants.len(), 1);
+    assert_eq!(reverted.get_default_variant().weight, 100);
+
+    // Revert is a change on its own, recorded as a new revision.
+    let revisions = revision::list_for_entity(
+        &mut conn,
+        RevisionEntity::Feature,
+        feature.id,
+        Some(environment.id),
+    )
+    .await
+    .unwrap();
+    assert_eq!(revisions.len(), 2);
+}
+
+#[sqlx::test]
+async fn revert_rejects_revision_of_other_environment(mut conn: PoolConnection<Sqlite>) {
+    let (project, staging) = create_context(&mut conn).await;
+    let prod = create_environment(&mut conn, &project).await;
+    let feature = create_feature(&mut conn, &staging, "foo").await;
+
+    let patch = FeaturePatch {
+        is_archived: Some(true),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &staging, &feature, patch, None)
+        .await
+        .unwrap();
+
+    let revisions = revision::list_for_entity(
+        &mut conn,
+        RevisionEntity::Feature,
+        feature.id,
+        Some(staging.id),
+    )
+    .await
+    .unwrap();
+    let feature_prod = feature::get_by_id(&mut conn, &prod, feature.id)
+        .await
+        .unwrap();
+    let result = feature::revert(&mut conn, &prod, &feature_prod, revisions[0].id, None).await;
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::NotFound(_))
+    ));
+}
+
+#[sqlx::test]
+async fn visibility_is_shared_by_all_environments(mut conn: PoolConnection<Sqlite>) {
+    let (project, staging) = create_context(&mut conn).await;
+    let prod = create_environment(&mut conn, &project).await;
+    let feature = feature::create_with_visibility(
+        &mut conn,
+        &staging,
+        "foo".to_owned(),
+        None,
+        FeatureValue::build("bar"),
+        true,
+        FeatureVisibility::Backend,
+    )
+    .await
+    .unwrap();
+    assert_eq!(feature.visibility, FeatureVisibility::Backend);
+
+    let feature_prod = feature::get_by_id(&mut conn, &prod, feature.id)
+        .await
+        .unwrap();
+    assert_eq!(feature_prod.visibility, FeatureVisibility::Backend);
+
+    let patch = FeaturePatch {
+        visibility: Some(FeatureVisibility::All),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &prod, &feature_prod, patch, None)
+        .await
+        .unwrap();
+
+    let feature = feature::get_by_id(&mut conn, &staging, feature.id)
+        .await
+        .unwrap();
+    assert_eq!(feature.visibility, FeatureVisibility::All);
+}
+
+#[sqlx::test]
+async fn identity_variants_carry_feature_visibility(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let public = create_feature(&mut conn, &environment, "public").await;
+    let internal = create_feature(&mut conn, &environment, "internal").await;
+    let patch = FeaturePatch {
+        visibility: Some(FeatureVisibility::Backend),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &internal, patch, None)
+        .await
+        .unwrap();
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, "alice".to_owned())
+        .await
+        .unwrap();
+    let variants = identity::get_identity_variants(&mut conn, &environment, &ident)
+        .await
+        .unwrap();
+    let visibility_of = |feature_id| {
+        variants
+            .iter()
+            .find(|v| v.feature_id == feature_id)
+            .map(|v| v.feature_visibility)
+    };
+
+    assert_eq!(visibility_of(public.id), Some(FeatureVisibility::All));
+    assert_eq!(visibility_of(internal.id), Some(FeatureVisibility::Backend));
+}
+
+#[sqlx::test]
+async fn revert_restores_feature_visibility(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let patch = FeaturePatch {
+        visibility: Some(FeatureVisibility::Backend),
+        ..Default::default()
+    };
+    let patched = feature::patch(&mut conn, &environment, &feature, patch, None)
+        .await
+        .unwrap();
+    assert_eq!(patched.visibility, FeatureVisibility::Backend);
+
+    let revisions = revision::list_for_entity(
+        &mut conn,
+        RevisionEntity::Feature,
+        feature.id,
+        Some(environment.id),
+    )
+    .await
+    .unwrap();
+    let reverted = feature::revert(&mut conn, &environment, &patched, revisions[0].id, None)
+        .await
+        .unwrap();
+
+    assert_eq!(reverted.visibility, FeatureVisibility::All);
+}
+
+fn requires(feature_id: i32, variant_id: i32) -> FeaturePatch {
+    FeaturePatch {
+        prerequisites: vec![PrerequisitePatchOp::Set {
+            feature_id,
+            variant_id,
+        }],
+        ..Default::default()
+    }
+}
+
+#[sqlx::test]
+async fn identities_failing_prerequisite_get_control_value(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let payments = create_feature(&mut conn, &environment, "off").await;
+    let payments_on = variant::create(
+        &mut conn,
+        &environment,
+        &payments,
+        FeatureValue::build("on"),
+        100,
+    )
+    .await
+    .unwrap();
+    let checkout = create_feature(&mut conn, &environment, "old").await;
+    variant::create(
+        &mut conn,
+        &environment,
+        &checkout,
+        FeatureValue::build("new"),
+        100,
+    )
+    .await
+    .unwrap();
+
+    let patched = feature::patch(
+        &mut conn,
+        &environment,
+        &checkout,
+        requires(payments.id, payments_on.id),
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(patched.prerequisites.len(), 1);
+    assert_eq!(patched.prerequisites[0].feature_name, payments.name);
+    assert_eq!(patched.prerequisites[0].value, FeatureValue::build("on"));
+
+    // Alice gets payments on, hence passes the prerequisite.
+    let alice = identity::get_or_create_by_value(&mut conn, &environment, "alice".to_owned())
+        .await
+        .unwrap();
+    let variants = identity::get_identity_variants(&mut conn, &environment, &alice)
+        .await
+        .unwrap();
+    let value_of = |variants: &[flagrant_types::IdentityVariant], feature_id| {
+        variants
+            .iter()
+            .find(|v| v.feature_id == feature_id)
+            .and_then(|v| v.feature_value.clone())
+    };
+    assert_eq!(
+        value_of(&variants, checkout.id),
+        Some(FeatureValue::build("new"))
+    );
+
+    // Bob is pinned to payments off, hence fails it.
+    let bob = identity::get_or_create_by_value(&mut conn, &environment, "bob".to_owned())
+        .await
+        .unwrap();
+    identity::override_variant(
+        &mut conn,
+        &environment,
+        &bob,
+        payments.id,
+        payments.get_default_variant().id,
+    )
+    .await
+    .unwrap();
+    let variants = identity::get_identity_variants(&mut conn, &environment, &bob)
+        .await
+        .unwrap();
+    assert_eq!(
+        value_of(&variants, checkout.id),
+        Some(FeatureValue::build("old"))
+    );
+
+    // ...and doesn't get distributed into the weighted pool.
+    let assignments = identity::list_variant_assignments(&mut conn, &environment, &bob)
+        .await
+        .unwrap();
+    assert!(
+        assignments
+            .iter()
+            .any(|a| a.feature_id == checkout.id && a.identity_id.is_none())
+    );
+}
+
+#[sqlx::test]
+async fn prerequisite_on_disabled_feature_is_not_met(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let payments = create_feature(&mut conn, &environment, "on").await;
+    let checkout = create_feature(&mut conn, &environment, "old").await;
+    variant::create(
+        &mut conn,
+        &environment,
+        &checkout,
+        FeatureValue::build("new"),
+        100,
+    )
+    .await
+    .unwrap();
+
+    let required = requires(payments.id, payments.get_default_variant().id);
+    feature::patch(&mut conn, &environment, &checkout, required, None)
+        .await
+        .unwrap();
+    let disable = FeaturePatch {
+        is_enabled: Some(false),
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &payments, disable, None)
+        .await
+        .unwrap();
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, "alice".to_owned())
+        .await
+        .unwrap();
+    let variants = identity::get_identity_variants(&mut conn, &environment, &ident)
+        .await
+        .unwrap();
+    let checkout_value = variants
+        .iter()
+        .find(|v| v.feature_id == checkout.id)
+        .and_then(|v| v.feature_value.clone());
+
+    assert_eq!(checkout_value, Some(FeatureValue::build("old")));
+}
+
+#[sqlx::test]
+async fn prerequisite_cycles_are_rejected(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let first = create_feature(&mut conn, &environment, "a").await;
+    let second = create_feature(&mut conn, &environment, "b").await;
+    let third = create_feature(&mut conn, &environment, "c").await;
+
+    let itself = requires(first.id, first.get_default_variant().id);
+    let res = feature::patch(&mut conn, &environment, &first, itself, None).await;
+    assert!(res.is_err());
+
+    let second_required = requires(second.id, second.get_default_variant().id);
+    feature::patch(&mut conn, &environment, &first, second_required, None)
+        .await
+        .unwrap();
+    let third_required = requires(third.id, third.get_default_variant().id);
+    feature::patch(&mut conn, &environment, &second, third_required, None)
+        .await
+        .unwrap();
+
+    // first → second → third → first
+    let first_required = requires(first.id, first.get_default_variant().id);
+    let res = feature::patch(&mut conn, &environment, &third, first_required, None).await;
+    assert!(matches!(
+        res.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+}
+
+#[sqlx::test]
+async fn prerequisite_requires_variant_of_required_feature(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let first = create_feature(&mut conn, &environment, "a").await;
+    let second = create_feature(&mut conn, &environment, "b").await;
+
+    // Variant of the feature itself, rather than of the required one.
+    let mismatched = requires(second.id, first.get_default_variant().id);
+    let res = feature::patch(&mut conn, &environment, &first, mismatched, None).await;
+
+    assert!(res.is_err());
+}
@@ -1,16 +1,27 @@
-use flagrant::models::{
-    feature,
-    identity::{self, HugSql, SQLIdentities, TraitCondition},
-    project, traits, variant,
+use flagrant::{
+    evaluator::SegmentCache,
+    models::{
+        feature,
+        identity::{self, HugSql, SQLIdentities, TraitCondition},
+        project, segment, traits, variant,
+    },
 };
 use flagrant_types::{
-    Environment, Feature, FeatureValue, TraitValue, Variant, payload::IdentityTraitPayload,
+    Comparator, Distribution, Environment, Feature, FeatureValue, SegmentDriver, TraitPersistence,
+    TraitValue, Variant,
+    explain::Resolution,
+    payload::{
+        FeaturePatch, IdentityTraitPayload, PrerequisitePatchOp, ProjectPatch, SegmentPatchOp,
+        SegmentVariantWeight,
+    },
 };
 use hugsqlx::params;
 use smallvec::smallvec;
 use sqlx::{Sqlite, SqliteConnection, pool::PoolConnection};
 
-use crate::common::create_context;
+use crate::common::{
+    add_group, add_rule, apply, create_context, create_environment, create_feature,
+};
 
 mod common;
 
@@ -1113,3 +1124,834 @@ async fn trait_value_with_unsafe_characters_is_rejected(mut conn: PoolConnection
 
     assert!(result.is_err());
 }
+
+#[sqlx::test]
+async fn transient_traits_match_segment_without_being_stored(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    let segment = segment::create(&mut conn, &project, "premium".to_owned(), None)
+        .await
+        .unwrap();
+
+    apply(
+        &mut conn,
+        &project,
+        segment.clone(),
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Trait("plan".to_owned()),
+                Comparator::ExactlyMatches,
+                "premium",
+            ),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 20,
+                }],
+            },
+        ],
+    )
+    .await;
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, "user-1".to_owned())
+        .await
+        .unwrap();
+
+    let variants = identity::get_identity_variants_with_traits(
+        &mut conn,
+        &SegmentCache::default(),
+        &environment,
+        &ident,
+        &[IdentityTraitPayload {
+            name: "plan".to_owned(),
+            value: Some(TraitValue::Str("premium".to_owned())),
+        }],
+    )
+    .await
+    .unwrap();
+
+    let variant = variants
+        .iter()
+        .find(|v| v.feature_id == feature.id)
+        .unwrap();
+    assert_eq!(variant.segment_id, Some(segment.id));
+
+    let stored = identity::get_by_value_with_traits(&mut conn, &environment, "user-1".to_owned())
+        .await
+        .unwrap();
+    assert!(stored.traits.is_empty());
+}
+
+#[sqlx::test]
+async fn upserting_traits_keeps_the_other_ones(mut conn: PoolConnection<Sqlite>) {
+    let (_project, environment) = create_context(&mut conn).await;
+    let ident = identity::create(
+        &mut conn,
+        &environment,
+        "user-1".to_owned(),
+        vec![IdentityTraitPayload {
+            name: "country".to_owned(),
+            value: Some(TraitValue::Str("PL".to_owned())),
+        }],
+    )
+    .await
+    .unwrap();
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, ident.value)
+        .await
+        .unwrap();
+
+    identity::upsert_traits(
+        &mut conn,
+        &environment,
+        &ident,
+        vec![IdentityTraitPayload {
+            name: "plan".to_owned(),
+            value: Some(TraitValue::Str("premium".to_owned())),
+        }],
+    )
+    .await
+    .unwrap();
+
+    let stored = identity::get_by_value_with_traits(&mut conn, &environment, "user-1".to_owned())
+        .await
+        .unwrap();
+    let mut names = stored
+        .traits
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["country", "plan"]);
+}
+
+fn request_traits() -> Vec<IdentityTraitPayload> {
+    vec![
+        IdentityTraitPayload {
+            name: "country".to_owned(),
+            value: Some(TraitValue::Str("PL".to_owned())),
+        },
+        IdentityTraitPayload {
+            name: "platform".to_owned(),
+            value: Some(TraitValue::Str("ios".to_owned())),
+        },
+    ]
+}
+
+fn names(traits: &[IdentityTraitPayload]) -> Vec<&str> {
+    traits.iter().map(|t| t.name.as_str()).collect()
+}
+
+#[sqlx::test]
+async fn request_traits_are_not_persisted_by_default(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+    traits::upsert(&mut conn, project.id, "country".to_owned())
+        .await
+        .unwrap();
+
+    assert_eq!(project.trait_persistence, TraitPersistence::Never);
+
+    let (persisted, transient) =
+        traits::split_by_persistence(&mut conn, &project, request_traits())
+            .await
+            .unwrap();
+
+    assert!(persisted.is_empty());
+    assert_eq!(names(&transient), vec!["country", "platform"]);
+}
+
+#[sqlx::test]
+async fn request_traits_known_to_project_are_persisted(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+    traits::upsert(&mut conn, project.id, "country".to_owned())
+        .await
+        .unwrap();
+
+    let project = project::patch(
+        &mut conn,
+        project,
+        ProjectPatch {
+            trait_persistence: Some(TraitPersistence::Known),
+        },
+    )
+    .await
+    .unwrap();
+
+    let (persisted, transient) =
+        traits::split_by_persistence(&mut conn, &project, request_traits())
+            .await
+            .unwrap();
+
+    assert_eq!(names(&persisted), vec!["country"]);
+    assert_eq!(names(&transient), vec!["platform"]);
+
+    let project = project::patch(
+        &mut conn,
+        project,
+        ProjectPatch {
+            trait_persistence: Some(TraitPersistence::Always),
+        },
+    )
+    .await
+    .unwrap();
+
+    let (persisted, transient) =
+        traits::split_by_persistence(&mut conn, &project, request_traits())
+            .await
+            .unwrap();
+
+    assert_eq!(names(&persisted), vec!["country", "platform"]);
+    assert!(transient.is_empty());
+
+    // Policy sticks to the project.
+    let fetched = project::get_by_name(&mut conn, project.name.clone())
+        .await
+        .unwrap();
+    assert_eq!(fetched.trait_persistence, TraitPersistence::Always);
+}
+
+#[sqlx::test]
+async fn request_traits_with_unsafe_characters_are_rejected(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+
+    let result = traits::split_by_persistence(
+        &mut conn,
+        &project,
+        vec![IdentityTraitPayload {
+            name: "nope\",null],[\"vip".to_owned(),
+            value: Some(TraitValue::Bool(true)),
+        }],
+    )
+    .await;
+
+    assert!(result.is_err());
+}
+
+#[sqlx::test]
+async fn explain_reports_matching_segment_without_distributing(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    let segment = segment::create(&mut conn, &project, "premium".to_owned(), None)
+        .await
+        .unwrap();
+
+    apply(
+        &mut conn,
+        &project,
+        segment.clone(),
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Trait("plan".to_owned()),
+                Comparator::ExactlyMatches,
+                "premium",
+            ),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 20,
+                }],
+            },
+        ],
+    )
+    .await;
+
+    let created = identity::create(
+        &mut conn,
+        &environment,
+        "user-1".to_owned(),
+        vec![IdentityTraitPayload {
+            name: "plan".to_owned(),
+            value: Some(TraitValue::Str("premium".to_owned())),
+        }],
+    )
+    .await
+    .unwrap();
+    let ident = identity::get_by_value(&mut conn, &environment, created.value)
+        .await
+        .unwrap();
+
+    let explanations = identity::explain(&mut conn, &environment, &ident, None)
+        .await
+        .unwrap();
+    let explanation = explanations
+        .iter()
+        .find(|e| e.feature_id == feature.id)
+        .unwrap();
+
+    assert!(explanation.pending);
+    assert!(matches!(
+        explanation.resolution,
+        Resolution::Segment { segment_id, .. } if segment_id == segment.id
+    ));
+    assert_eq!(explanation.segments.len(), 1);
+    assert!(explanation.segments[0].matched);
+
+    let rule = &explanation.segments[0].groups[0].rules[0];
+    assert!(rule.matched);
+    assert_eq!(rule.actual.as_deref(), Some("premium"));
+
+    // Explaining doesn't distribute the identity.
+    let assignments = identity::list_variant_assignments(&mut conn, &environment, &ident)
+        .await
+        .unwrap();
+    assert!(assignments.iter().all(|a| a.identity_id.is_none()));
+}
+
+#[sqlx::test]
+async fn explain_reports_pinned_identity(mut conn: PoolConnection<Sqlite>) {
+    let (_project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, "user-1".to_owned())
+        .await
+        .unwrap();
+    identity::override_variant(&mut conn, &environment, &ident, feature.id, alt.id)
+        .await
+        .unwrap();
+
+    let explanations = identity::explain(&mut conn, &environment, &ident, Some(&feature.name))
+        .await
+        .unwrap();
+
+    assert_eq!(explanations.len(), 1);
+    assert!(matches!(
+        explanations[0].resolution,
+        Resolution::Pinned { .. }
+    ));
+    assert!(!explanations[0].pending);
+    assert!(explanations[0].segments.is_empty());
+    assert_eq!(explanations[0].value, Some(FeatureValue::build("alt")));
+
+    let missing = identity::explain(&mut conn, &environment, &ident, Some("missing")).await;
+    assert!(missing.is_err());
+}
+
+#[sqlx::test]
+async fn explain_reports_failed_prerequisite(mut conn: PoolConnection<Sqlite>) {
+    let (_project, environment) = create_context(&mut conn).await;
+    let payments = create_feature(&mut conn, &environment, "off").await;
+    let payments_on = variant::create(
+        &mut conn,
+        &environment,
+        &payments,
+        FeatureValue::build("on"),
+        50,
+    )
+    .await
+    .unwrap();
+    let checkout = create_feature(&mut conn, &environment, "old").await;
+    let patch = FeaturePatch {
+        prerequisites: vec![PrerequisitePatchOp::Set {
+            feature_id: payments.id,
+            variant_id: payments_on.id,
+        }],
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &checkout, patch, None)
+        .await
+        .unwrap();
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, "user-1".to_owned())
+        .await
+        .unwrap();
+    identity::override_variant(
+        &mut conn,
+        &environment,
+        &ident,
+        payments.id,
+        payments.get_default_variant().id,
+    )
+    .await
+    .unwrap();
+
+    let explanations = identity::explain(&mut conn, &environment, &ident, Some(&checkout.name))
+        .await
+        .unwrap();
+
+    assert_eq!(explanations.len(), 1);
+    assert!(matches!(
+        &explanations[0].resolution,
+        Resolution::Prerequisite { feature_id, .. } if *feature_id == payments.id
+    ));
+    assert!(!explanations[0].pending);
+    assert_eq!(explanations[0].value, Some(FeatureValue::build("old")));
+}
+
+/// Variants `n` identities resolve to for `feature`, in order of identity values.
+async fn hashed_variants(
+    conn: &mut PoolConnection<Sqlite>,
+    environment: &Environment,
+    feature: &Feature,
+    n: usize,
+) -> Vec<Option<i32>> {
+    let mut variants = Vec::with_capacity(n);
+    for i in 0..n {
+        let ident = identity::get_or_create_by_value(conn, environment, format!("user-{i}"))
+            .await
+            .unwrap();
+        let resolved = identity::get_identity_variants(conn, environment, &ident)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|iv| iv.feature_id == feature.id)
+            .unwrap();
+        variants.push(resolved.variant_id);
+    }
+    variants
+}
+
+async fn hash_feature(
+    conn: &mut PoolConnection<Sqlite>,
+    environment: &Environment,
+    feature: &Feature,
+) -> Feature {
+    let patch = FeaturePatch {
+        distribution: Some(Distribution::Hash {
+            salt: "experiment".to_owned(),
+        }),
+        ..Default::default()
+    };
+    feature::patch(conn, environment, feature, patch, None)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn hash_distribution_is_stable_without_storing_assignments(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    let variant = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("on"),
+        50,
+    )
+    .await
+    .unwrap();
+    let feature = hash_feature(&mut conn, &environment, &feature).await;
+    assert_eq!(
+        feature.distribution,
+        Distribution::Hash {
+            salt: "experiment".to_owned()
+        }
+    );
+
+    let first = hashed_variants(&mut conn, &environment, &feature, 200).await;
+    let second = hashed_variants(&mut conn, &environment, &feature, 200).await;
+    assert_eq!(first, second);
+    assert!(first.contains(&Some(variant.id)));
+    assert!(first.contains(&Some(feature.get_default_variant().id)));
+
+    let stored = get_test_migrations(&mut conn, &environment, &feature)
+        .await
+        .unwrap();
+    assert!(stored.is_empty());
+}
+
+#[sqlx::test]
+async fn hash_distribution_moves_only_weight_delta(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    let variant = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("on"),
+        20,
+    )
+    .await
+    .unwrap();
+    let feature = hash_feature(&mut conn, &environment, &feature).await;
+    let at_20 = hashed_variants(&mut conn, &environment, &feature, 300).await;
+
+    variant::update_one(
+        &mut conn,
+        &environment,
+        &variant,
+        FeatureValue::build("on"),
+        50,
+    )
+    .await
+    .unwrap();
+    let at_50 = hashed_variants(&mut conn, &environment, &feature, 300).await;
+
+    // growing a variant only pulls identities in, never pushes any out
+    for (before, after) in at_20.iter().zip(&at_50) {
+        if *before == Some(variant.id) {
+            assert_eq!(*after, Some(variant.id));
+        }
+    }
+    let count =
+        |variants: &[Option<i32>]| variants.iter().filter(|v| **v == Some(variant.id)).count();
+    assert!(count(&at_50) > count(&at_20));
+
+    let variant = variant::get_by_id(&mut conn, &environment, variant.id, None)
+        .await
+        .unwrap();
+    variant::update_one(
+        &mut conn,
+        &environment,
+        &variant,
+        FeatureValue::build("on"),
+        20,
+    )
+    .await
+    .unwrap();
+    let back_at_20 = hashed_variants(&mut conn, &environment, &feature, 300).await;
+
+    // shrinking releases the buckets claimed last, bringing back the former split
+    assert_eq!(at_20, back_at_20);
+}
+
+#[sqlx::test]
+async fn hash_distribution_keeps_segment_variants_when_override_weight_changes(
+    mut conn: PoolConnection<Sqlite>,
+) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    let mut variants = Vec::new();
+    for value in ["a", "b"] {
+        let variant = variant::create(
+            &mut conn,
+            &environment,
+            &feature,
+            FeatureValue::build(value),
+            10,
+        )
+        .await
+        .unwrap();
+        variants.push(variant);
+    }
+    let (a, b) = (variants[0].id, variants[1].id);
+    let feature = hash_feature(&mut conn, &environment, &feature).await;
+
+    let override_weights = |a_weight, b_weight| SegmentPatchOp::SetFeatureOverride {
+        feature_id: feature.id,
+        environment_id: environment.id,
+        variant_weights: vec![
+            SegmentVariantWeight {
+                variant_id: a,
+                weight: a_weight,
+            },
+            SegmentVariantWeight {
+                variant_id: b,
+                weight: b_weight,
+            },
+        ],
+    };
+    let seg = segment::create(&mut conn, &project, "everyone".to_owned(), None)
+        .await
+        .unwrap();
+    let seg = apply(
+        &mut conn,
+        &project,
+        seg,
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Environment,
+                Comparator::ExactlyMatches,
+                &environment.name,
+            ),
+            override_weights(20, 30),
+        ],
+    )
+    .await;
+    let before = hashed_variants(&mut conn, &environment, &feature, 300).await;
+
+    apply(&mut conn, &project, seg, vec![override_weights(10, 30)]).await;
+    let after = hashed_variants(&mut conn, &environment, &feature, 300).await;
+
+    // only the shrunk variant gives identities away, the rest stay where they were
+    for (before, after) in before.iter().zip(&after) {
+        if *before == Some(b) {
+            assert_eq!(*after, Some(b));
+        }
+        if *after == Some(a) {
+            assert_eq!(*before, Some(a));
+        }
+    }
+    let count = |variants: &[Option<i32>]| variants.iter().filter(|v| **v == Some(a)).count();
+    assert!(count(&after) < count(&before));
+}
+
+#[sqlx::test]
+async fn hash_distribution_requires_salt(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    let patch = FeaturePatch {
+        distribution: Some(Distribution::Hash {
+            salt: String::new(),
+        }),
+        ..Default::default()
+    };
+    let result = feature::patch(&mut conn, &environment, &feature, patch, None).await;
+    assert!(result.is_err());
+}
+
+/// Variant `user` carrying `account_id` trait of given value resolves to for `feature`.
+async fn keyed_variant(
+    conn: &mut PoolConnection<Sqlite>,
+    environment: &Environment,
+    feature: &Feature,
+    user: &str,
+    account_id: Option<&str>,
+) -> Option<i32> {
+    let ident = identity::get_or_create_by_value(conn, environment, user.to_owned())
+        .await
+        .unwrap();
+    let traits: Vec<IdentityTraitPayload> = account_id
+        .map(|id| IdentityTraitPayload {
+            name: "account_id".to_owned(),
+            value: Some(TraitValue::Str(id.to_owned())),
+        })
+        .into_iter()
+        .collect();
+
+    identity::get_identity_variants_with_traits(
+        conn,
+        &SegmentCache::default(),
+        environment,
+        &ident,
+        &traits,
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .find(|iv| iv.feature_id == feature.id)
+    .unwrap()
+    .variant_id
+}
+
+async fn key_feature(
+    conn: &mut PoolConnection<Sqlite>,
+    environment: &Environment,
+    feature: &Feature,
+) -> Feature {
+    let patch = FeaturePatch {
+        distribution_key: Some("account_id".to_owned()),
+        ..Default::default()
+    };
+    feature::patch(conn, environment, feature, patch, None)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn identities_sharing_key_value_get_the_same_variant(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    let variant = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("on"),
+        50,
+    )
+    .await
+    .unwrap();
+    let feature = key_feature(&mut conn, &environment, &feature).await;
+    assert_eq!(feature.distribution_key.as_deref(), Some("account_id"));
+
+    let mut accounts = Vec::new();
+    for account in 0..10 {
+        let account_id = format!("account-{account}");
+        let expected = keyed_variant(
+            &mut conn,
+            &environment,
+            &feature,
+            &format!("{account_id}-admin"),
+            Some(&account_id),
+        )
+        .await;
+        for user in 0..5 {
+            let user = format!("{account_id}-user-{user}");
+            let variant_id =
+                keyed_variant(&mut conn, &environment, &feature, &user, Some(&account_id)).await;
+            assert_eq!(variant_id, expected);
+        }
+        accounts.push(expected);
+    }
+    assert!(accounts.contains(&Some(variant.id)));
+    assert!(accounts.contains(&Some(feature.get_default_variant().id)));
+
+    // assignments are kept per key value, not per identity
+    let stored = get_test_migrations(&mut conn, &environment, &feature)
+        .await
+        .unwrap();
+    assert!(stored.is_empty());
+}
+
+#[sqlx::test]
+async fn identities_lacking_key_get_control_value(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("on"),
+        100,
+    )
+    .await
+    .unwrap();
+    let feature = key_feature(&mut conn, &environment, &feature).await;
+
+    let keyed = keyed_variant(&mut conn, &environment, &feature, "user-1", Some("acme")).await;
+    assert_ne!(keyed, Some(feature.get_default_variant().id));
+
+    let unkeyed = keyed_variant(&mut conn, &environment, &feature, "user-2", None).await;
+    assert_eq!(unkeyed, Some(feature.get_default_variant().id));
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, "user-2".to_owned())
+        .await
+        .unwrap();
+    let explanations = identity::explain(&mut conn, &environment, &ident, Some(&feature.name))
+        .await
+        .unwrap();
+    assert!(matches!(
+        &explanations[0].resolution,
+        Resolution::MissingKey { key } if key == "account_id"
+    ));
+}
+
+#[sqlx::test]
+async fn key_values_migrate_when_weights_change(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    let variant = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("on"),
+        0,
+    )
+    .await
+    .unwrap();
+    let feature = key_feature(&mut conn, &environment, &feature).await;
+    let control = feature.get_default_variant().id;
+
+    let keyed = keyed_variant(&mut conn, &environment, &feature, "user-1", Some("acme")).await;
+    assert_eq!(keyed, Some(control));
+
+    variant::update_one(
+        &mut conn,
+        &environment,
+        &variant,
+        FeatureValue::build("on"),
+        100,
+    )
+    .await
+    .unwrap();
+
+    let keyed = keyed_variant(&mut conn, &environment, &feature, "user-2", Some("acme")).await;
+    assert_eq!(keyed, Some(variant.id));
+}
+
+#[sqlx::test]
+async fn key_values_get_re_evaluated_when_segments_change(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    let variant = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("on"),
+        0,
+    )
+    .await
+    .unwrap();
+    let feature = key_feature(&mut conn, &environment, &feature).await;
+
+    let keyed = keyed_variant(&mut conn, &environment, &feature, "user-1", Some("acme")).await;
+    assert_eq!(keyed, Some(feature.get_default_variant().id));
+
+    let seg = segment::create(&mut conn, &project, "everyone".to_owned(), None)
+        .await
+        .unwrap();
+    apply(
+        &mut conn,
+        &project,
+        seg,
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Environment,
+                Comparator::ExactlyMatches,
+                &environment.name,
+            ),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: variant.id,
+                    weight: 100,
+                }],
+            },
+        ],
+    )
+    .await;
+
+    let keyed = keyed_variant(&mut conn, &environment, &feature, "user-1", Some("acme")).await;
+    assert_eq!(keyed, Some(variant.id));
+}
+
+#[sqlx::test]
+async fn distribution_key_is_scoped_to_environment(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let other = create_environment(&mut conn, &project).await;
+    let feature = create_feature(&mut conn, &environment, "off").await;
+    key_feature(&mut conn, &environment, &feature).await;
+
+    let in_other = feature::get_by_id(&mut conn, &other, feature.id)
+        .await
+        .unwrap();
+    assert_eq!(in_other.distribution_key, None);
+
+    let patch = FeaturePatch {
+        distribution_key: Some("not a trait".to_owned()),
+        ..Default::default()
+    };
+    let result = feature::patch(&mut conn, &environment, &feature, patch, None).await;
+    assert!(result.is_err());
+}
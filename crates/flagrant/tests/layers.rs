@@ -0,0 +1,185 @@
+use flagrant::models::{identity, layer, variant};
+use flagrant_types::{Environment, Feature, FeatureValue, Layer, explain::Resolution};
+use sqlx::{Sqlite, pool::PoolConnection};
+
+use crate::common::{create_context, create_feature};
+
+mod common;
+
+/// Creates a feature with an "on" variant taking all the weight, so that any identity
+/// not excluded by a layer gets a non-control value.
+async fn experiment(conn: &mut PoolConnection<Sqlite>, environment: &Environment) -> Feature {
+    let feature = create_feature(conn, environment, "off").await;
+    variant::create(conn, environment, &feature, FeatureValue::build("on"), 100)
+        .await
+        .unwrap();
+    feature
+}
+
+async fn layer_with(
+    conn: &mut PoolConnection<Sqlite>,
+    environment: &Environment,
+    shares: &[(&Feature, u8)],
+) -> Layer {
+    let mut layer = layer::create(conn, environment, "checkout".to_owned(), None)
+        .await
+        .unwrap();
+    for (feature, share) in shares {
+        layer = layer::set_share(conn, environment, &layer, feature.id, *share)
+            .await
+            .unwrap();
+    }
+    layer
+}
+
+/// Returns number of `features` identity of given `value` gets a non-control value of.
+async fn enabled_count(
+    conn: &mut PoolConnection<Sqlite>,
+    environment: &Environment,
+    value: String,
+    features: &[&Feature],
+) -> usize {
+    let ident = identity::get_or_create_by_value(conn, environment, value)
+        .await
+        .unwrap();
+    identity::get_identity_variants(conn, environment, &ident)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|iv| features.iter().any(|f| f.id == iv.feature_id))
+        .filter(|iv| iv.feature_value != Some(FeatureValue::build("off")))
+        .count()
+}
+
+#[sqlx::test]
+async fn identity_gets_at_most_one_feature_of_layer(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let first = experiment(&mut conn, &environment).await;
+    let second = experiment(&mut conn, &environment).await;
+    let layer = layer_with(&mut conn, &environment, &[(&first, 50), (&second, 30)]).await;
+
+    assert_eq!(layer.features.len(), 2);
+    assert_eq!(layer.features[0].first_bucket, 0);
+    assert_eq!(layer.features[1].first_bucket, 50);
+
+    let mut counts = [0; 2];
+    for i in 0..300 {
+        let count = enabled_count(
+            &mut conn,
+            &environment,
+            format!("user-{i}"),
+            &[&first, &second],
+        )
+        .await;
+        assert!(count <= 1);
+        counts[count] += 1;
+    }
+    // 20% of traffic falls into no share at all.
+    assert!(counts[0] > 0);
+    assert!(counts[1] > 0);
+}
+
+#[sqlx::test]
+async fn layer_shares_cannot_exceed_traffic(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let first = experiment(&mut conn, &environment).await;
+    let second = experiment(&mut conn, &environment).await;
+    let layer = layer_with(&mut conn, &environment, &[(&first, 70)]).await;
+
+    let overflow = layer::set_share(&mut conn, &environment, &layer, second.id, 40).await;
+    assert!(overflow.is_err());
+
+    let layer = layer::set_share(&mut conn, &environment, &layer, second.id, 30)
+        .await
+        .unwrap();
+    assert_eq!(layer.features.len(), 2);
+
+    // Releasing a share makes room for the others.
+    let layer = layer::unset_share(&mut conn, &environment, &layer, first.id)
+        .await
+        .unwrap();
+    let layer = layer::set_share(&mut conn, &environment, &layer, second.id, 100)
+        .await
+        .unwrap();
+    assert_eq!(layer.features[0].first_bucket, 0);
+}
+
+#[sqlx::test]
+async fn feature_keeps_its_buckets_when_share_grows(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let first = experiment(&mut conn, &environment).await;
+    let second = experiment(&mut conn, &environment).await;
+    let layer = layer_with(&mut conn, &environment, &[(&first, 20), (&second, 20)]).await;
+    let layer = layer::unset_share(&mut conn, &environment, &layer, first.id)
+        .await
+        .unwrap();
+
+    let layer = layer::set_share(&mut conn, &environment, &layer, second.id, 50)
+        .await
+        .unwrap();
+    assert_eq!(layer.features[0].first_bucket, 20);
+}
+
+#[sqlx::test]
+async fn feature_cannot_join_two_layers(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = experiment(&mut conn, &environment).await;
+    layer_with(&mut conn, &environment, &[(&feature, 50)]).await;
+
+    let other = layer::create(&mut conn, &environment, "search".to_owned(), None)
+        .await
+        .unwrap();
+    let result = layer::set_share(&mut conn, &environment, &other, feature.id, 10).await;
+    assert!(result.is_err());
+}
+
+#[sqlx::test]
+async fn explain_reports_exclusion_by_layer(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = experiment(&mut conn, &environment).await;
+    let layer = layer_with(&mut conn, &environment, &[(&feature, 1)]).await;
+
+    let mut excluded = None;
+    for i in 0..50 {
+        let ident = identity::get_or_create_by_value(&mut conn, &environment, format!("user-{i}"))
+            .await
+            .unwrap();
+        let explanations = identity::explain(&mut conn, &environment, &ident, Some(&feature.name))
+            .await
+            .unwrap();
+        if let Resolution::Excluded { layer_id, .. } = explanations[0].resolution {
+            excluded = Some((layer_id, explanations[0].value.clone()));
+            break;
+        }
+    }
+    let (layer_id, value) = excluded.unwrap();
+    assert_eq!(layer_id, layer.id);
+    assert_eq!(value, Some(FeatureValue::build("off")));
+}
+
+#[sqlx::test]
+async fn deleting_layer_stops_exclusion(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let first = experiment(&mut conn, &environment).await;
+    let second = experiment(&mut conn, &environment).await;
+    let layer = layer_with(&mut conn, &environment, &[(&first, 50), (&second, 50)]).await;
+
+    layer::delete(&mut conn, &environment, layer.id)
+        .await
+        .unwrap();
+    assert!(
+        layer::list(&mut conn, &environment)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    let count = enabled_count(
+        &mut conn,
+        &environment,
+        "user-1".to_owned(),
+        &[&first, &second],
+    )
+    .await;
+    assert_eq!(count, 2);
+}
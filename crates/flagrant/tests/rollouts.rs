@@ -0,0 +1,257 @@
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use common::{create_context, create_feature};
+use flagrant::errors::FlagrantError;
+use flagrant::models::{revision, rollout, variant};
+use flagrant_types::{
+    Environment, FeatureValue, RevisionEntity, RolloutStatus, payload::NewRolloutPlanPayload,
+};
+use sqlx::{Sqlite, SqliteConnection, pool::PoolConnection};
+
+mod common;
+
+fn in_hours(hours: i64) -> NaiveDateTime {
+    Utc::now().naive_utc() + TimeDelta::hours(hours)
+}
+
+fn plan(start_weight: u8, step: u8, target_weight: u8) -> NewRolloutPlanPayload {
+    NewRolloutPlanPayload {
+        start_weight,
+        step,
+        target_weight,
+        interval_minutes: 60,
+    }
+}
+
+async fn weight_of(conn: &mut SqliteConnection, environment: &Environment, variant_id: i32) -> u8 {
+    variant::get_by_id(conn, environment, variant_id, None)
+        .await
+        .unwrap()
+        .weight
+}
+
+#[sqlx::test]
+async fn rollout_advances_step_by_step_until_target(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        0,
+    )
+    .await
+    .unwrap();
+
+    let created = rollout::create(
+        &mut conn,
+        &environment,
+        alt.id,
+        plan(5, 10, 20),
+        Some("alice"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(created.status, RolloutStatus::Active);
+    assert!(created.next_step_at.is_some());
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 5);
+
+    // Not yet due.
+    let advanced = rollout::advance_due(&mut conn, Utc::now().naive_utc())
+        .await
+        .unwrap();
+    assert_eq!(advanced, 0);
+
+    let advanced = rollout::advance_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(advanced, 1);
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 15);
+
+    // Last step stops at the target weight.
+    let advanced = rollout::advance_due(&mut conn, in_hours(4)).await.unwrap();
+    assert_eq!(advanced, 1);
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 20);
+
+    let completed = rollout::get_for_variant(&mut conn, &environment, alt.id)
+        .await
+        .unwrap();
+    assert_eq!(completed.status, RolloutStatus::Completed);
+    assert_eq!(completed.current_weight, 20);
+    assert!(completed.next_step_at.is_none());
+
+    // Each step is recorded as a revision of whoever started the rollout.
+    let revisions = revision::list_for_entity(
+        &mut conn,
+        RevisionEntity::Feature,
+        feature.id,
+        Some(environment.id),
+    )
+    .await
+    .unwrap();
+    assert_eq!(revisions.len(), 3);
+    assert!(
+        revisions
+            .iter()
+            .all(|r| r.author.as_deref() == Some("alice"))
+    );
+
+    let advanced = rollout::advance_due(&mut conn, in_hours(6)).await.unwrap();
+    assert_eq!(advanced, 0);
+}
+
+#[sqlx::test]
+async fn paused_rollout_does_not_advance_until_resumed(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        0,
+    )
+    .await
+    .unwrap();
+
+    rollout::create(&mut conn, &environment, alt.id, plan(10, 10, 50), None)
+        .await
+        .unwrap();
+    let paused = rollout::pause(&mut conn, &environment, alt.id)
+        .await
+        .unwrap();
+    assert_eq!(paused.status, RolloutStatus::Paused);
+    assert!(paused.next_step_at.is_none());
+
+    let advanced = rollout::advance_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(advanced, 0);
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 10);
+
+    // Paused rollout can't be paused again.
+    let result = rollout::pause(&mut conn, &environment, alt.id).await;
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+
+    let resumed = rollout::resume(&mut conn, &environment, alt.id)
+        .await
+        .unwrap();
+    assert_eq!(resumed.status, RolloutStatus::Active);
+
+    let advanced = rollout::advance_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(advanced, 1);
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 20);
+}
+
+#[sqlx::test]
+async fn aborted_rollout_rolls_back_to_start_weight(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        0,
+    )
+    .await
+    .unwrap();
+
+    rollout::create(&mut conn, &environment, alt.id, plan(5, 20, 45), None)
+        .await
+        .unwrap();
+    rollout::advance_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 25);
+
+    let aborted = rollout::abort(&mut conn, &environment, alt.id, None)
+        .await
+        .unwrap();
+    assert_eq!(aborted.status, RolloutStatus::Aborted);
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 5);
+
+    let advanced = rollout::advance_due(&mut conn, in_hours(4)).await.unwrap();
+    assert_eq!(advanced, 0);
+
+    // Once aborted, variant can be rolled out again.
+    rollout::create(&mut conn, &environment, alt.id, plan(5, 20, 45), None)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn rollout_step_not_fitting_other_variants_fails(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        0,
+    )
+    .await
+    .unwrap();
+
+    rollout::create(&mut conn, &environment, alt.id, plan(10, 30, 70), None)
+        .await
+        .unwrap();
+
+    // Another variant takes most of the weight over in the meantime.
+    variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("other"),
+        80,
+    )
+    .await
+    .unwrap();
+
+    let advanced = rollout::advance_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(advanced, 0);
+
+    let failed = rollout::get_for_variant(&mut conn, &environment, alt.id)
+        .await
+        .unwrap();
+    assert_eq!(failed.status, RolloutStatus::Failed);
+    assert!(failed.error.is_some());
+    assert_eq!(weight_of(&mut conn, &environment, alt.id).await, 10);
+}
+
+#[sqlx::test]
+async fn create_rejects_invalid_plans(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        0,
+    )
+    .await
+    .unwrap();
+    let control_id = feature.get_default_variant().id;
+
+    for (variant_id, payload) in [
+        (alt.id, plan(10, 0, 50)),
+        (alt.id, plan(50, 10, 10)),
+        (alt.id, plan(10, 10, 110)),
+        (control_id, plan(10, 10, 50)),
+    ] {
+        let result = rollout::create(&mut conn, &environment, variant_id, payload, None).await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref::<FlagrantError>(),
+            Some(FlagrantError::BadRequest(_))
+        ));
+    }
+
+    // Only one rollout of a variant can be in progress.
+    rollout::create(&mut conn, &environment, alt.id, plan(10, 10, 50), None)
+        .await
+        .unwrap();
+    let result = rollout::create(&mut conn, &environment, alt.id, plan(10, 10, 50), None).await;
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+}
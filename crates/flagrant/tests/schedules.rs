@@ -0,0 +1,304 @@
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use common::{create_context, create_feature};
+use flagrant::errors::FlagrantError;
+use flagrant::models::{feature, revision, schedule, segment, variant};
+use flagrant_types::{
+    FeatureValue, RevisionEntity, ScheduleStatus, ScheduledPatch,
+    payload::{FeaturePatch, SegmentPatch, SegmentPatchOp, SegmentVariantWeight, VariantPatchOp},
+};
+use sqlx::{Sqlite, pool::PoolConnection};
+
+mod common;
+
+fn in_hours(hours: i64) -> NaiveDateTime {
+    Utc::now().naive_utc() + TimeDelta::hours(hours)
+}
+
+#[sqlx::test]
+async fn apply_due_applies_only_due_changes(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let change = ScheduledPatch::Feature {
+        feature_id: feature.id,
+        patch: FeaturePatch {
+            is_enabled: Some(false),
+            ..Default::default()
+        },
+    };
+    let scheduled = schedule::create(&mut conn, &environment, change, in_hours(1), Some("alice"))
+        .await
+        .unwrap();
+    assert_eq!(scheduled.status, ScheduleStatus::Pending);
+
+    // Not yet due.
+    let applied = schedule::apply_due(&mut conn, Utc::now().naive_utc())
+        .await
+        .unwrap();
+    assert_eq!(applied, 0);
+    assert!(
+        feature::get_by_id(&mut conn, &environment, feature.id)
+            .await
+            .unwrap()
+            .is_enabled
+    );
+
+    let applied = schedule::apply_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(applied, 1);
+    assert!(
+        !feature::get_by_id(&mut conn, &environment, feature.id)
+            .await
+            .unwrap()
+            .is_enabled
+    );
+
+    let scheduled = schedule::get_by_id(&mut conn, &environment, scheduled.id)
+        .await
+        .unwrap();
+    assert_eq!(scheduled.status, ScheduleStatus::Applied);
+    assert!(scheduled.applied_at.is_some());
+
+    // Applied change is recorded as a revision of whoever scheduled it.
+    let revisions = revision::list_for_entity(
+        &mut conn,
+        RevisionEntity::Feature,
+        feature.id,
+        Some(environment.id),
+    )
+    .await
+    .unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].author.as_deref(), Some("alice"));
+
+    // Once applied, change is not picked up again.
+    let applied = schedule::apply_due(&mut conn, in_hours(3)).await.unwrap();
+    assert_eq!(applied, 0);
+}
+
+#[sqlx::test]
+async fn cancelled_change_is_not_applied(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let change = ScheduledPatch::Feature {
+        feature_id: feature.id,
+        patch: FeaturePatch {
+            is_archived: Some(true),
+            ..Default::default()
+        },
+    };
+    let scheduled = schedule::create(&mut conn, &environment, change, in_hours(1), None)
+        .await
+        .unwrap();
+    let cancelled = schedule::cancel(&mut conn, &environment, scheduled.id)
+        .await
+        .unwrap();
+    assert_eq!(cancelled.status, ScheduleStatus::Cancelled);
+
+    let applied = schedule::apply_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(applied, 0);
+    assert!(
+        !feature::get_by_id(&mut conn, &environment, feature.id)
+            .await
+            .unwrap()
+            .is_archived
+    );
+
+    // Cancelled change can't be cancelled again.
+    let result = schedule::cancel(&mut conn, &environment, scheduled.id).await;
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+}
+
+#[sqlx::test]
+async fn change_failing_to_apply_is_marked_as_failed(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let change = ScheduledPatch::Feature {
+        feature_id: feature.id,
+        patch: FeaturePatch {
+            is_enabled: Some(false),
+            variants: vec![VariantPatchOp::Add {
+                value: FeatureValue::build("bar"),
+                weight: 10,
+            }],
+            ..Default::default()
+        },
+    };
+    let scheduled = schedule::create(&mut conn, &environment, change, in_hours(1), None)
+        .await
+        .unwrap();
+
+    // Variant of the same value gets created before the scheduled change is due.
+    variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("bar"),
+        20,
+    )
+    .await
+    .unwrap();
+
+    let applied = schedule::apply_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(applied, 0);
+
+    let scheduled = schedule::get_by_id(&mut conn, &environment, scheduled.id)
+        .await
+        .unwrap();
+    assert_eq!(scheduled.status, ScheduleStatus::Failed);
+    assert!(scheduled.error.is_some());
+
+    // Failed change is applied as a whole or not at all.
+    assert!(
+        feature::get_by_id(&mut conn, &environment, feature.id)
+            .await
+            .unwrap()
+            .is_enabled
+    );
+}
+
+#[sqlx::test]
+async fn change_holding_undecodable_patch_is_marked_as_failed(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let change = ScheduledPatch::Feature {
+        feature_id: feature.id,
+        patch: FeaturePatch {
+            is_enabled: Some(false),
+            ..Default::default()
+        },
+    };
+    let scheduled = schedule::create(&mut conn, &environment, change, in_hours(1), None)
+        .await
+        .unwrap();
+
+    // Patch stored in a format which is no longer known.
+    sqlx::query(
+        "UPDATE scheduled_changes SET patch = '{\"feature\": 1}' WHERE scheduled_change_id = ?",
+    )
+    .bind(scheduled.id)
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+
+    let applied = schedule::apply_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(applied, 0);
+
+    let (status, error): (String, Option<String>) =
+        sqlx::query_as("SELECT status, error FROM scheduled_changes WHERE scheduled_change_id = ?")
+            .bind(scheduled.id)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+    assert_eq!(status, "failed");
+    assert!(error.is_some());
+    assert!(
+        feature::get_by_id(&mut conn, &environment, feature.id)
+            .await
+            .unwrap()
+            .is_enabled
+    );
+
+    // Another run doesn't pick the change up again.
+    let applied = schedule::apply_due(&mut conn, in_hours(3)).await.unwrap();
+    assert_eq!(applied, 0);
+}
+
+#[sqlx::test]
+async fn create_rejects_changes_in_the_past(mut conn: PoolConnection<Sqlite>) {
+    let (_, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "foo").await;
+
+    let change = ScheduledPatch::Feature {
+        feature_id: feature.id,
+        patch: FeaturePatch {
+            is_enabled: Some(false),
+            ..Default::default()
+        },
+    };
+    let result = schedule::create(&mut conn, &environment, change, in_hours(-1), None).await;
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+}
+
+#[sqlx::test]
+async fn segment_override_gets_applied_when_due(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        10,
+    )
+    .await
+    .unwrap();
+    let seg = segment::create(&mut conn, &project, "vip".to_owned(), None)
+        .await
+        .unwrap();
+
+    let change = ScheduledPatch::Segment {
+        segment_id: seg.id,
+        patch: SegmentPatch {
+            ops: vec![SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 90,
+                }],
+            }],
+        },
+    };
+    schedule::create(&mut conn, &environment, change, in_hours(1), None)
+        .await
+        .unwrap();
+
+    let pending = schedule::list(
+        &mut conn,
+        &environment,
+        ScheduleStatus::Pending,
+        Some((RevisionEntity::Segment, seg.id)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(pending.len(), 1);
+
+    let applied = schedule::apply_due(&mut conn, in_hours(2)).await.unwrap();
+    assert_eq!(applied, 1);
+
+    let overrides = segment::list_overridden_features(&mut conn, environment.id, seg.id)
+        .await
+        .unwrap();
+    assert_eq!(overrides.len(), 1);
+}
+
+#[sqlx::test]
+async fn create_rejects_segment_changes_other_than_overrides(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let seg = segment::create(&mut conn, &project, "vip".to_owned(), None)
+        .await
+        .unwrap();
+
+    let change = ScheduledPatch::Segment {
+        segment_id: seg.id,
+        patch: SegmentPatch {
+            ops: vec![SegmentPatchOp::SetName("vips".to_owned())],
+        },
+    };
+    let result = schedule::create(&mut conn, &environment, change, in_hours(1), None).await;
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+}
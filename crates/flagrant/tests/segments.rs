@@ -4,11 +4,12 @@ use flagrant::{
     distributor,
     models::{
         identity::{self, HugSql, SQLIdentities},
-        rule, segment, variant,
+        revision, rule, segment, variant,
     },
 };
 use flagrant_types::{
-    Comparator, Environment, Feature, FeatureValue, Identity, SegmentDriver,
+    Comparator, Environment, Feature, FeatureValue, Identity, RevisionEntity, Segment,
+    SegmentDriver, SegmentRule,
     payload::{SegmentPatch, SegmentPatchOp, SegmentVariantWeight},
 };
 use hugsqlx::params;
@@ -84,7 +85,7 @@ async fn segment_override_writes_into_variant_weights_alongside_organic_weights(
             }],
         }],
     };
-    segment::patch(&mut conn, &project, segment.clone(), patch)
+    segment::patch(&mut conn, &project, segment.clone(), patch, None)
         .await
         .unwrap();
 
@@ -175,7 +176,7 @@ async fn list_overridden_features_groups_by_feature(mut conn: PoolConnection<Sql
                 }],
             }],
         };
-        segment::patch(&mut conn, &project, segment.clone(), patch)
+        segment::patch(&mut conn, &project, segment.clone(), patch, None)
             .await
             .unwrap();
     }
@@ -231,7 +232,7 @@ async fn distributor_scopes_by_segment_id(mut conn: PoolConnection<Sqlite>) {
             }],
         }],
     };
-    segment::patch(&mut conn, &project, segment.clone(), patch)
+    segment::patch(&mut conn, &project, segment.clone(), patch, None)
         .await
         .unwrap();
 
@@ -774,3 +775,448 @@ async fn higher_priority_segment_keeps_identity_when_a_later_one_also_matches(
     let after = attribution_for(&mut conn, &environment, &feature, ident.id).await;
     assert_eq!(after.segment_id, Some(older.id));
 }
+
+/// Raising priority of a newer segment's override flags identities of the feature dirty,
+/// and hands the identity over to the newer segment once re-evaluated.
+#[sqlx::test]
+async fn raising_priority_hands_identity_over_to_another_segment(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+
+    let mut segments = Vec::new();
+    for name in ["older", "newer"] {
+        let seg = segment::create(&mut conn, &project, name.to_owned(), None)
+            .await
+            .unwrap();
+        let seg = apply(
+            &mut conn,
+            &project,
+            seg,
+            vec![
+                add_group(None),
+                add_rule(
+                    "group-1",
+                    SegmentDriver::Environment,
+                    Comparator::ExactlyMatches,
+                    &environment.name,
+                ),
+                SegmentPatchOp::SetFeatureOverride {
+                    feature_id: feature.id,
+                    environment_id: environment.id,
+                    variant_weights: vec![SegmentVariantWeight {
+                        variant_id: alt.id,
+                        weight: 15,
+                    }],
+                },
+            ],
+        )
+        .await;
+        segments.push(seg);
+    }
+    let (older, newer) = (segments[0].clone(), segments[1].clone());
+
+    let ident = identity::get_or_create_by_value(&mut conn, &environment, "any-user".to_owned())
+        .await
+        .unwrap();
+    resolve(&mut conn, &environment, &ident).await;
+    assert_eq!(
+        attribution_for(&mut conn, &environment, &feature, ident.id)
+            .await
+            .segment_id,
+        Some(older.id)
+    );
+
+    let newer = apply(
+        &mut conn,
+        &project,
+        newer,
+        vec![SegmentPatchOp::SetFeaturePriority {
+            feature_id: feature.id,
+            environment_id: environment.id,
+            priority: 10,
+        }],
+    )
+    .await;
+
+    let flagged = attribution_for(&mut conn, &environment, &feature, ident.id).await;
+    assert!(flagged.segment_dirty);
+
+    let overrides = segment::list_overridden_features(&mut conn, environment.id, newer.id)
+        .await
+        .unwrap();
+    assert_eq!(overrides[0].priority, 10);
+
+    resolve(&mut conn, &environment, &ident).await;
+
+    let after = attribution_for(&mut conn, &environment, &feature, ident.id).await;
+    assert_eq!(after.segment_id, Some(newer.id));
+    assert!(!after.segment_dirty);
+}
+
+#[sqlx::test]
+async fn priority_of_missing_override_is_rejected(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let seg = segment::create(&mut conn, &project, "vip".to_owned(), None)
+        .await
+        .unwrap();
+
+    let patch = SegmentPatch {
+        ops: vec![SegmentPatchOp::SetFeaturePriority {
+            feature_id: feature.id,
+            environment_id: environment.id,
+            priority: 1,
+        }],
+    };
+    let result = segment::patch(&mut conn, &project, seg, patch, None).await;
+    assert!(result.is_err());
+}
+
+#[sqlx::test]
+async fn deleting_segment_removes_its_priorities(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+    let seg = segment::create(&mut conn, &project, "vip".to_owned(), None)
+        .await
+        .unwrap();
+    let seg = apply(
+        &mut conn,
+        &project,
+        seg,
+        vec![
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 15,
+                }],
+            },
+            SegmentPatchOp::SetFeaturePriority {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                priority: 5,
+            },
+        ],
+    )
+    .await;
+
+    segment::delete(&mut conn, &seg).await.unwrap();
+
+    let (remaining,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM segment_priorities WHERE segment_id = $1")
+            .bind(seg.id)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+    assert_eq!(remaining, 0);
+}
+
+#[sqlx::test]
+async fn patch_records_segment_revision(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+    let segment = segment::create(&mut conn, &project, "vip".to_owned(), None)
+        .await
+        .unwrap();
+
+    let patch = SegmentPatch {
+        ops: vec![
+            add_group(None),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 30,
+                }],
+            },
+        ],
+    };
+    segment::patch(&mut conn, &project, segment.clone(), patch, Some("bob"))
+        .await
+        .unwrap();
+
+    let revisions = revision::list_for_entity(&mut conn, RevisionEntity::Segment, segment.id, None)
+        .await
+        .unwrap();
+
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0].author.as_deref(), Some("bob"));
+
+    // Overrides within the touched environment are captured along with the segment itself.
+    let env_key = environment.id.to_string();
+    let before = &revisions[0].before;
+    let after = &revisions[0].after;
+
+    assert_eq!(
+        before["segment"]["groups"].as_array().map(Vec::len),
+        Some(0)
+    );
+    assert_eq!(after["segment"]["groups"].as_array().map(Vec::len), Some(1));
+    assert_eq!(
+        before["overrides"][&env_key].as_array().map(Vec::len),
+        Some(0)
+    );
+    assert_eq!(
+        after["overrides"][&env_key].as_array().map(Vec::len),
+        Some(1)
+    );
+}
+
+/// Regex patterns are validated when a rule gets stored, so an invalid one never makes
+/// it into a segment.
+#[sqlx::test]
+async fn rule_with_invalid_regex_is_rejected(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+    let segment = segment::create(&mut conn, &project, "staff".to_owned(), None)
+        .await
+        .unwrap();
+    let segment = apply(&mut conn, &project, segment, vec![add_group(None)]).await;
+    let group_id = segment.groups[0].id;
+
+    let result = rule::add(
+        &mut conn,
+        segment.id,
+        group_id,
+        SegmentDriver::Identity,
+        Comparator::MatchesRegex,
+        "(@ourcompany\\.com".to_owned(),
+    )
+    .await;
+    assert!(result.is_err());
+
+    rule::add(
+        &mut conn,
+        segment.id,
+        group_id,
+        SegmentDriver::Identity,
+        Comparator::MatchesRegex,
+        "@ourcompany\\.com$".to_owned(),
+    )
+    .await
+    .unwrap();
+
+    let segment = segment::get_by_id(&mut conn, &project, segment.id)
+        .await
+        .unwrap();
+    assert_eq!(segment.groups[0].rules.len(), 1);
+}
+
+#[sqlx::test]
+async fn rule_with_invalid_version_is_rejected(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+    let segment = segment::create(&mut conn, &project, "mobile".to_owned(), None)
+        .await
+        .unwrap();
+    let segment = apply(&mut conn, &project, segment, vec![add_group(None)]).await;
+    let group_id = segment.groups[0].id;
+
+    for (value, valid) in [("5.2.0", true), ("5.2", true), ("5.x", false), ("", false)] {
+        let result = rule::add(
+            &mut conn,
+            segment.id,
+            group_id,
+            SegmentDriver::Trait("app_version".to_owned()),
+            Comparator::VersionGreaterEqualThan,
+            value.to_owned(),
+        )
+        .await;
+        assert_eq!(result.is_ok(), valid, "version {value:?}");
+    }
+}
+
+#[sqlx::test]
+async fn rule_with_invalid_date_or_duration_is_rejected(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+    let segment = segment::create(&mut conn, &project, "newcomers".to_owned(), None)
+        .await
+        .unwrap();
+    let segment = apply(&mut conn, &project, segment, vec![add_group(None)]).await;
+    let group_id = segment.groups[0].id;
+
+    for (comparator, value, valid) in [
+        (Comparator::After, "2026-01-01", true),
+        (Comparator::After, "2026-01-01T10:00:00+01:00", true),
+        (Comparator::Before, "yesterday", false),
+        (Comparator::NewerThan, "30d", true),
+        (Comparator::OlderThan, "12h", true),
+        (Comparator::OlderThan, "30", false),
+        (Comparator::NewerThan, "1y", false),
+    ] {
+        let result = rule::add(
+            &mut conn,
+            segment.id,
+            group_id,
+            SegmentDriver::Trait("signed_up_at".to_owned()),
+            comparator.clone(),
+            value.to_owned(),
+        )
+        .await;
+        assert_eq!(result.is_ok(), valid, "{comparator:?} {value:?}");
+    }
+}
+
+/// Bucket rules compare a number, so anything else than a numeric comparator with
+/// a bucket in range is rejected. Accepted rules keep their salt once stored.
+#[sqlx::test]
+async fn bucket_rule_takes_numeric_comparators_only(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+    let segment = segment::create(&mut conn, &project, "beta_10".to_owned(), None)
+        .await
+        .unwrap();
+    let segment = apply(&mut conn, &project, segment, vec![add_group(None)]).await;
+    let group_id = segment.groups[0].id;
+    let driver = SegmentDriver::Bucket {
+        salt: "beta".to_owned(),
+    };
+
+    for (comparator, value, valid) in [
+        (Comparator::LowerThan, "1000", true),
+        (Comparator::GreaterEqualThan, "10000", true),
+        (Comparator::LowerThan, "10001", false),
+        (Comparator::LowerThan, "-1", false),
+        (Comparator::ExactlyMatches, "1000", false),
+        (Comparator::Contains, "1", false),
+    ] {
+        let result = rule::add(
+            &mut conn,
+            segment.id,
+            group_id,
+            driver.clone(),
+            comparator.clone(),
+            value.to_owned(),
+        )
+        .await;
+        assert_eq!(result.is_ok(), valid, "{comparator:?} {value:?}");
+    }
+
+    let segment = segment::get_by_id(&mut conn, &project, segment.id)
+        .await
+        .unwrap();
+    assert_eq!(segment.groups[0].rules.len(), 2);
+    assert!(
+        segment.groups[0]
+            .rules
+            .iter()
+            .all(|r| matches!(&r.driver, SegmentDriver::Bucket { salt } if salt == "beta"))
+    );
+}
+
+/// Segments may reference other segments of the project, as long as no segment ends up
+/// referencing itself - directly or through others.
+#[sqlx::test]
+async fn segment_reference_cycle_is_rejected(mut conn: PoolConnection<Sqlite>) {
+    let (project, _environment) = create_context(&mut conn).await;
+    let mut segments = Vec::new();
+    for name in ["staff", "beta", "early"] {
+        let segment = segment::create(&mut conn, &project, name.to_owned(), None)
+            .await
+            .unwrap();
+        segments.push(apply(&mut conn, &project, segment, vec![add_group(None)]).await);
+    }
+    let [staff, beta, early] = &segments[..] else {
+        unreachable!()
+    };
+
+    // early -> beta -> staff
+    assert!(add_reference(&mut conn, beta, "staff").await.is_ok());
+    assert!(add_reference(&mut conn, early, "beta").await.is_ok());
+
+    assert!(add_reference(&mut conn, staff, "staff").await.is_err());
+    assert!(add_reference(&mut conn, staff, "beta").await.is_err());
+    assert!(add_reference(&mut conn, staff, "early").await.is_err());
+    assert!(add_reference(&mut conn, staff, "missing").await.is_err());
+}
+
+async fn add_reference(
+    conn: &mut SqliteConnection,
+    segment: &Segment,
+    referenced: &str,
+) -> anyhow::Result<SegmentRule> {
+    rule::add(
+        conn,
+        segment.id,
+        segment.groups[0].id,
+        SegmentDriver::Segment(referenced.to_owned()),
+        Comparator::ExactlyMatches,
+        "true".to_owned(),
+    )
+    .await
+}
+
+/// Rules reference segments by name, so renaming a referenced segment updates the rules,
+/// while deleting it is refused for as long as it's referenced.
+#[sqlx::test]
+async fn referenced_segment_follows_renames_and_cannot_be_deleted(
+    mut conn: PoolConnection<Sqlite>,
+) {
+    let (project, _environment) = create_context(&mut conn).await;
+    let staff = segment::create(&mut conn, &project, "staff".to_owned(), None)
+        .await
+        .unwrap();
+    let beta = segment::create(&mut conn, &project, "beta".to_owned(), None)
+        .await
+        .unwrap();
+    let beta = apply(
+        &mut conn,
+        &project,
+        beta,
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Segment("staff".to_owned()),
+                Comparator::ExactlyMatches,
+                "true",
+            ),
+        ],
+    )
+    .await;
+
+    let staff = apply(
+        &mut conn,
+        &project,
+        staff,
+        vec![SegmentPatchOp::SetName("employees".to_owned())],
+    )
+    .await;
+    let beta = segment::get_by_id(&mut conn, &project, beta.id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        &beta.groups[0].rules[0].driver,
+        SegmentDriver::Segment(name) if name == "employees"
+    ));
+
+    assert!(segment::delete(&mut conn, &staff).await.is_err());
+    segment::delete(&mut conn, &beta).await.unwrap();
+    segment::delete(&mut conn, &staff).await.unwrap();
+}
@@ -0,0 +1,176 @@
+use common::{add_group, add_rule, apply, create_context, create_environment, create_feature};
+use flagrant::errors::FlagrantError;
+use flagrant::models::{feature, project, segment, snapshot, variant};
+use flagrant_types::{
+    Comparator, FeatureValue, SegmentDriver,
+    payload::{FeaturePatch, SegmentPatchOp, SegmentVariantWeight, TagPatchOp, VariantPatchOp},
+    snapshot::SNAPSHOT_VERSION,
+};
+use sqlx::{Sqlite, pool::PoolConnection};
+
+mod common;
+
+#[sqlx::test]
+async fn import_restores_snapshot_into_other_project(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    let alt = variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        40,
+    )
+    .await
+    .unwrap();
+    let patch = FeaturePatch {
+        is_enabled: Some(false),
+        tags: vec![TagPatchOp::Add("beta".to_owned())],
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &feature, patch, None)
+        .await
+        .unwrap();
+
+    let seg = segment::create(&mut conn, &project, "vip".to_owned(), None)
+        .await
+        .unwrap();
+    apply(
+        &mut conn,
+        &project,
+        seg,
+        vec![
+            add_group(None),
+            add_rule(
+                "group-1",
+                SegmentDriver::Trait("plan".to_owned()),
+                Comparator::ExactlyMatches,
+                "pro",
+            ),
+            SegmentPatchOp::SetFeatureOverride {
+                feature_id: feature.id,
+                environment_id: environment.id,
+                variant_weights: vec![SegmentVariantWeight {
+                    variant_id: alt.id,
+                    weight: 70,
+                }],
+            },
+        ],
+    )
+    .await;
+
+    let snap = snapshot::export(&mut conn, &project, &environment, false)
+        .await
+        .unwrap();
+    assert_eq!(snap.version, SNAPSHOT_VERSION);
+    assert!(snap.identities.is_none());
+
+    let other = project::create(&mut conn, "other_project".to_owned())
+        .await
+        .unwrap();
+    let other_env = create_environment(&mut conn, &other).await;
+    let summary = snapshot::import(&mut conn, &other_env, snap, None)
+        .await
+        .unwrap();
+    assert_eq!(summary.features_created, 1);
+    assert_eq!(summary.segments_created, 1);
+
+    let restored = feature::get_by_name(&mut conn, &other_env, feature.name.clone())
+        .await
+        .unwrap();
+    assert!(!restored.is_enabled);
+    assert_eq!(restored.tags.0[0].name, "beta");
+    assert_eq!(restored.get_default_value(), feature.get_default_value());
+    assert_eq!(restored.get_default_variant().weight, 60);
+    let restored_alt = restored.variants.iter().find(|v| !v.is_control()).unwrap();
+    assert_eq!(restored_alt.value, FeatureValue::build("alt"));
+    assert_eq!(restored_alt.weight, 40);
+
+    let restored_seg = segment::get_by_name(&mut conn, &other, "vip".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(restored_seg.groups.len(), 1);
+    assert_eq!(restored_seg.groups[0].rules[0].value, "pro");
+
+    let overrides = segment::list_overridden_features(&mut conn, other_env.id, restored_seg.id)
+        .await
+        .unwrap();
+    assert_eq!(overrides.len(), 1);
+    let weight = overrides[0]
+        .weights
+        .iter()
+        .find(|w| w.value == FeatureValue::build("alt"))
+        .unwrap();
+    assert_eq!(weight.weight, 70);
+}
+
+/// Importing into the environment a snapshot was taken from should bring back
+/// the captured state, leaving entities absent in snapshot intact.
+#[sqlx::test]
+async fn import_restores_snapshot_into_same_environment(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let feature = create_feature(&mut conn, &environment, "control").await;
+    variant::create(
+        &mut conn,
+        &environment,
+        &feature,
+        FeatureValue::build("alt"),
+        20,
+    )
+    .await
+    .unwrap();
+    let feature = feature::get_by_id(&mut conn, &environment, feature.id)
+        .await
+        .unwrap();
+
+    let snap = snapshot::export(&mut conn, &project, &environment, false)
+        .await
+        .unwrap();
+
+    let alt = feature.variants.iter().find(|v| !v.is_control()).unwrap();
+    let patch = FeaturePatch {
+        description: Some("changed".to_owned()),
+        variants: vec![VariantPatchOp::SetWeight {
+            id: alt.id,
+            weight: 90,
+        }],
+        ..Default::default()
+    };
+    feature::patch(&mut conn, &environment, &feature, patch, None)
+        .await
+        .unwrap();
+    let untouched = create_feature(&mut conn, &environment, "other").await;
+
+    let summary = snapshot::import(&mut conn, &environment, snap, None)
+        .await
+        .unwrap();
+    assert_eq!(summary.features_updated, 1);
+    assert_eq!(summary.features_created, 0);
+
+    let restored = feature::get_by_id(&mut conn, &environment, feature.id)
+        .await
+        .unwrap();
+    assert_eq!(restored.description, feature.description);
+    assert_eq!(restored.get_default_variant().weight, 80);
+    assert!(
+        feature::get_by_id(&mut conn, &environment, untouched.id)
+            .await
+            .is_ok()
+    );
+}
+
+#[sqlx::test]
+async fn import_rejects_unsupported_version(mut conn: PoolConnection<Sqlite>) {
+    let (project, environment) = create_context(&mut conn).await;
+    let mut snap = snapshot::export(&mut conn, &project, &environment, true)
+        .await
+        .unwrap();
+    snap.version = SNAPSHOT_VERSION + 1;
+
+    let result = snapshot::import(&mut conn, &environment, snap, None).await;
+
+    assert!(matches!(
+        result.unwrap_err().downcast_ref::<FlagrantError>(),
+        Some(FlagrantError::BadRequest(_))
+    ));
+}